@@ -9,5 +9,3 @@ fud exec top.futil --to dat --through icarus-verilog -s verilog.data top.json \
   --from futil
 
 rm frisc.futil
-
-rm model.smt
\ No newline at end of file
@@ -50,6 +50,10 @@ def construct_transaction_fsm(interface, randomize):
 
     assert len(interface["interfaces"]) > 0, "No interfaces defined"
     assert len(interface["interfaces"]) == 1, "Unsupported: multiple interfaces"
+    for port in interface["inputs"] + interface["outputs"]:
+        assert (
+            port.get("bundle") is None
+        ), f"Unsupported: bundle port `{port['name']}'. Split it into separate ports before simulating."
 
     # Track which inputs have been written to
     inp_write_idx = [0 for _ in interface["inputs"]]
@@ -8,11 +8,67 @@ module NegConst #(
 endmodule
 
 module SAdd #(
-  parameter WIDTH = 32
+  parameter WIDTH = 32,
+  parameter FRAC = 0
 ) (
   input wire logic signed [WIDTH-1:0] left,
   input wire logic signed [WIDTH-1:0] right,
   output wire logic signed [WIDTH-1:0] out
 );
   assign out = left + right;
-endmodule
\ No newline at end of file
+endmodule
+
+module SSub #(
+  parameter WIDTH = 32,
+  parameter FRAC = 0
+) (
+  input wire logic signed [WIDTH-1:0] left,
+  input wire logic signed [WIDTH-1:0] right,
+  output wire logic signed [WIDTH-1:0] out
+);
+  assign out = left - right;
+endmodule
+
+module SGt #(
+  parameter WIDTH = 32,
+  parameter FRAC = 0
+) (
+  input wire logic signed [WIDTH-1:0] left,
+  input wire logic signed [WIDTH-1:0] right,
+  output wire logic out
+);
+  assign out = left > right;
+endmodule
+
+module SLt #(
+  parameter WIDTH = 32,
+  parameter FRAC = 0
+) (
+  input wire logic signed [WIDTH-1:0] left,
+  input wire logic signed [WIDTH-1:0] right,
+  output wire logic out
+);
+  assign out = left < right;
+endmodule
+
+module SGte #(
+  parameter WIDTH = 32,
+  parameter FRAC = 0
+) (
+  input wire logic signed [WIDTH-1:0] left,
+  input wire logic signed [WIDTH-1:0] right,
+  output wire logic out
+);
+  assign out = left >= right;
+endmodule
+
+module SLte #(
+  parameter WIDTH = 32,
+  parameter FRAC = 0
+) (
+  input wire logic signed [WIDTH-1:0] left,
+  input wire logic signed [WIDTH-1:0] right,
+  output wire logic out
+);
+  assign out = left <= right;
+endmodule
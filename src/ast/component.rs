@@ -1,8 +1,13 @@
-use super::{Command, Fsm, Id, Invoke, Signature};
+use super::{
+    Command, ConstDef, Expr, FnDef, Fsm, Functions, Id, Invoke, Loc, PortDef,
+    Signature, StructDef,
+};
 use crate::errors::{Error, FilamentResult};
+use crate::utils::Binding;
 use std::fmt::Display;
 
 /// A component in Filament
+#[derive(Clone)]
 pub struct Component {
     // Signature of this component
     pub sig: Signature,
@@ -40,6 +45,7 @@ impl Component {
                 | Command::Fact(_)
                 | Command::Bundle(_)
                 | Command::If(_)
+                | Command::Let(_)
                 | Command::ForLoop(_) => (),
             }
         }
@@ -70,12 +76,21 @@ impl Display for Component {
 
 #[derive(Default)]
 pub struct Namespace {
-    /// Imported files
-    pub imports: Vec<String>,
+    /// Imported files and the alias used to qualify the names they define
+    pub imports: Vec<(String, Option<Loc<Id>>)>,
     /// Define externals and their files
     pub externs: Vec<(String, Vec<Signature>)>,
     /// Components defined in this file
     pub components: Vec<Component>,
+    /// Functions in the parameter language defined in this file
+    pub fns: Vec<FnDef>,
+    /// Table of functions shared by all files of the program. Applications
+    /// of user-defined functions refer to this table.
+    pub funcs: Functions,
+    /// Struct types defined in this file
+    pub structs: Vec<StructDef>,
+    /// Constants defined in this file
+    pub consts: Vec<ConstDef>,
     /// Top-level component id
     pub toplevel: String,
 }
@@ -97,6 +112,74 @@ impl Namespace {
         )
     }
 
+    /// Replace the uses of constants with their values. Parameters bound by
+    /// components, structs, loops, bundles, and `let` bindings shadow the
+    /// constants with the same name.
+    pub fn resolve_consts(&mut self) -> FilamentResult<()> {
+        let consts = ConstDef::binding(&self.consts)?;
+        if consts.is_empty() {
+            return Ok(());
+        }
+        // Constants not shadowed by the given parameters
+        let visible = |bound: Vec<Id>| -> Binding<Expr> {
+            Binding::new(
+                consts
+                    .iter()
+                    .filter(|(n, _)| !bound.contains(n))
+                    .map(|(n, v)| (*n, v.clone())),
+            )
+        };
+        // Parameters bound by a signature
+        let sig_params = |sig: &Signature| -> Vec<Id> {
+            sig.params()
+                .map(|p| p.take())
+                .chain(sig.ports().iter().flat_map(|pd| match pd.inner() {
+                    PortDef::Bundle(b) => {
+                        b.typ.idxs.iter().map(|i| i.copy()).collect()
+                    }
+                    _ => vec![],
+                }))
+                .collect()
+        };
+
+        for (_, sigs) in &mut self.externs {
+            *sigs = std::mem::take(sigs)
+                .into_iter()
+                .map(|sig| {
+                    let bind = visible(sig_params(&sig));
+                    sig.resolve_binding(&bind)
+                })
+                .collect();
+        }
+        self.components = std::mem::take(&mut self.components)
+            .into_iter()
+            .map(|comp| {
+                let mut bound = sig_params(&comp.sig);
+                bound.extend(comp.body.iter().flat_map(|c| c.params()));
+                let bind = visible(bound);
+                Component {
+                    sig: comp.sig.resolve_binding(&bind),
+                    body: comp
+                        .body
+                        .into_iter()
+                        .map(|c| c.resolve_exprs(&bind))
+                        .collect(),
+                    ..comp
+                }
+            })
+            .collect();
+        for def in &mut self.structs {
+            let bind = visible(def.params.iter().map(|p| p.copy()).collect());
+            for field in &mut def.fields {
+                field.bitwidth =
+                    field.bitwidth.clone().map(|w| w.resolve(&bind));
+                field.offset =
+                    field.offset.take().map(|o| o.map(|o| o.resolve(&bind)));
+            }
+        }
+        Ok(())
+    }
+
     /// Get the index to the top-level component.
     /// Currently, this is the distinguished "main" component
     pub fn main_idx(&self) -> Option<usize> {
@@ -108,8 +191,11 @@ impl Namespace {
 
 impl Display for Namespace {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for imp in &self.imports {
-            writeln!(f, "import \"{}\";", imp)?;
+        for (imp, alias) in &self.imports {
+            match alias {
+                Some(alias) => writeln!(f, "import \"{imp}\" as {alias};")?,
+                None => writeln!(f, "import \"{imp}\";")?,
+            }
         }
         for (path, sigs) in &self.externs {
             writeln!(f, "extern \"{}\" {{", path)?;
@@ -118,6 +204,15 @@ impl Display for Namespace {
             }
             writeln!(f, "}}")?;
         }
+        for def in &self.consts {
+            writeln!(f, "{}", def)?;
+        }
+        for def in &self.fns {
+            writeln!(f, "{}", def)?;
+        }
+        for def in &self.structs {
+            writeln!(f, "{}", def)?;
+        }
         for comp in &self.components {
             writeln!(f, "{}", comp)?;
         }
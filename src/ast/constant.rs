@@ -0,0 +1,84 @@
+use super::{Expr, Id, Loc};
+use crate::{
+    errors::{Error, FilamentResult},
+    utils::Binding,
+};
+use std::{collections::HashMap, fmt::Display};
+
+#[derive(Clone)]
+/// A parameter defined at the top-level of a file using `const #XLEN = 32;`.
+/// Constants are visible in every file that imports the file defining them.
+pub struct ConstDef {
+    pub name: Loc<Id>,
+    pub value: Loc<Expr>,
+}
+
+impl ConstDef {
+    pub fn new(name: Loc<Id>, value: Loc<Expr>) -> Self {
+        Self { name, value }
+    }
+
+    /// Compute the value of each constant. Constants may be defined using
+    /// other constants regardless of the order of their definitions.
+    /// Reports constants that are defined multiple times, defined
+    /// recursively, or whose value is not concrete.
+    pub fn binding(defs: &[ConstDef]) -> FilamentResult<Binding<Expr>> {
+        let mut by_name: HashMap<Id, &ConstDef> = HashMap::new();
+        for def in defs {
+            let name = *def.name.inner();
+            if by_name.insert(name, def).is_some() {
+                return Err(Error::already_bound(name, "constant"));
+            }
+        }
+
+        fn visit(
+            def: &ConstDef,
+            defs: &HashMap<Id, &ConstDef>,
+            stack: &mut Vec<Id>,
+            values: &mut Binding<Expr>,
+        ) -> FilamentResult<()> {
+            let name = *def.name.inner();
+            if values.find(&name).is_some() {
+                return Ok(());
+            }
+            if stack.contains(&name) {
+                return Err(Error::malformed(format!(
+                    "constant `#{name}' is defined recursively"
+                )));
+            }
+            stack.push(name);
+            for used in def.value.inner().exprs() {
+                match defs.get(used) {
+                    Some(d) => visit(d, defs, stack, values)?,
+                    None => {
+                        return Err(Error::malformed(format!(
+                            "parameter `#{used}' is not bound in the definition of constant `#{name}'"
+                        )))
+                    }
+                }
+            }
+            stack.pop();
+            let value = def.value.inner().clone().resolve(values);
+            let value: u64 = value.try_into().map_err(|_| {
+                Error::malformed(format!(
+                    "constant `#{name}' must be concrete but was {}",
+                    def.value
+                ))
+            })?;
+            values.insert(name, Expr::concrete(value));
+            Ok(())
+        }
+
+        let mut values = Binding::default();
+        for def in defs {
+            visit(def, &by_name, &mut vec![], &mut values)?;
+        }
+        Ok(values)
+    }
+}
+
+impl Display for ConstDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "const #{} = {};", self.name, self.value)
+    }
+}
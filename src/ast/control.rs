@@ -1,6 +1,6 @@
 use super::expr::FnAssume;
 use super::{
-    Expr, Id, Implication, Loc, OrderConstraint, PortDef, Range, Time,
+    Expr, Id, Implication, Loc, NumRepr, OrderConstraint, PortDef, Range, Time,
 };
 use crate::utils::{self, Binding};
 use crate::{
@@ -8,7 +8,7 @@ use crate::{
     utils::GPosIdx,
 };
 use itertools::Itertools;
-use std::fmt::Display;
+use std::{fmt::Display, iter};
 
 #[derive(Clone)]
 /// Access into a bundle
@@ -60,13 +60,17 @@ pub enum Port {
     /// A port on an invoke
     InvPort { invoke: Loc<Id>, name: Loc<Id> },
 
-    /// A port represented by an index into a bundle
-    Bundle { name: Loc<Id>, access: Loc<Access> },
+    /// A port represented by an access into a bundle with one access per
+    /// dimension of the bundle
+    Bundle {
+        name: Loc<Id>,
+        access: Vec<Loc<Access>>,
+    },
     /// A bundle port on an invocation
     InvBundle {
         invoke: Loc<Id>,
         port: Loc<Id>,
-        access: Loc<Access>,
+        access: Vec<Loc<Access>>,
     },
 }
 
@@ -83,14 +87,14 @@ impl Port {
         Port::Constant(v)
     }
 
-    pub fn bundle(name: Loc<Id>, access: Loc<Access>) -> Self {
+    pub fn bundle(name: Loc<Id>, access: Vec<Loc<Access>>) -> Self {
         Port::Bundle { name, access }
     }
 
     pub fn inv_bundle(
         invoke: Loc<Id>,
         port: Loc<Id>,
-        access: Loc<Access>,
+        access: Vec<Loc<Access>>,
     ) -> Self {
         Port::InvBundle {
             invoke,
@@ -99,11 +103,14 @@ impl Port {
         }
     }
 
-    /// Returns true iff this port represents a ranged access on a bundle
+    /// Returns true iff this port represents a ranged access on some
+    /// dimension of a bundle
     pub fn range_access(&self) -> bool {
         match self {
             Port::Bundle { access, .. } | Port::InvBundle { access, .. } => {
-                matches!(access.inner(), Access::Range { .. })
+                access
+                    .iter()
+                    .any(|a| matches!(a.inner(), Access::Range { .. }))
             }
             _ => false,
         }
@@ -113,7 +120,10 @@ impl Port {
         match self {
             Port::Bundle { name, access } => Port::Bundle {
                 name,
-                access: access.map(|i| i.resolve(bindings)),
+                access: access
+                    .into_iter()
+                    .map(|a| a.map(|i| i.resolve(bindings)))
+                    .collect(),
             },
             Port::InvBundle {
                 invoke,
@@ -122,7 +132,10 @@ impl Port {
             } => Port::InvBundle {
                 invoke,
                 port,
-                access: access.map(|a| a.resolve(bindings)),
+                access: access
+                    .into_iter()
+                    .map(|a| a.map(|a| a.resolve(bindings)))
+                    .collect(),
             },
             _ => self,
         }
@@ -138,14 +151,16 @@ impl std::fmt::Display for Port {
             }
             Port::Constant(n) => write!(f, "{}", n),
             Port::Bundle { name, access } => {
-                write!(f, "{name}{{{access}}}")
+                write!(f, "{name}")?;
+                access.iter().try_for_each(|a| write!(f, "{{{a}}}"))
             }
             Port::InvBundle {
                 invoke,
                 port,
                 access,
             } => {
-                write!(f, "{invoke}.{port}{{{access}}}")
+                write!(f, "{invoke}.{port}")?;
+                access.iter().try_for_each(|a| write!(f, "{{{a}}}"))
             }
         }
     }
@@ -161,6 +176,7 @@ pub enum Command {
     ForLoop(ForLoop),
     If(If),
     Bundle(Bundle),
+    Let(ParamLet),
 }
 
 impl From<Connect> for Command {
@@ -202,6 +218,64 @@ impl Display for Command {
             Command::If(l) => write!(f, "{}", l),
             Command::Bundle(b) => write!(f, "{b}"),
             Command::Fact(a) => write!(f, "{a}"),
+            Command::Let(l) => write!(f, "{l}"),
+        }
+    }
+}
+
+impl Command {
+    /// Resolve the expressions in this command and the commands nested in it
+    pub fn resolve_exprs(self, bind: &Binding<Expr>) -> Self {
+        match self {
+            Command::Invoke(inv) => inv.resolve_exprs(bind).into(),
+            Command::Instance(inst) => inst.resolve_exprs(bind).into(),
+            Command::Fact(fact) => fact.resolve(bind).into(),
+            Command::Connect(con) => con.resolve_exprs(bind).into(),
+            Command::ForLoop(ForLoop {
+                idx,
+                start,
+                end,
+                body,
+            }) => ForLoop::new(
+                idx,
+                start.resolve(bind),
+                end.resolve(bind),
+                body.into_iter().map(|c| c.resolve_exprs(bind)).collect(),
+            )
+            .into(),
+            Command::If(If { cond, then, alt }) => If::new(
+                cond.resolve_expr(bind),
+                then.into_iter().map(|c| c.resolve_exprs(bind)).collect(),
+                alt.into_iter().map(|c| c.resolve_exprs(bind)).collect(),
+            )
+            .into(),
+            Command::Bundle(bl) => bl.resolve_exprs(bind).into(),
+            Command::Let(ParamLet { name, expr }) => {
+                ParamLet::new(name, expr.map(|e| e.resolve(bind))).into()
+            }
+        }
+    }
+
+    /// Parameters bound by this command and the commands nested in it
+    pub fn params(&self) -> Vec<Id> {
+        match self {
+            Command::ForLoop(l) => iter::once(l.idx.copy())
+                .chain(l.body.iter().flat_map(|c| c.params()))
+                .collect(),
+            Command::If(i) => i
+                .then
+                .iter()
+                .chain(&i.alt)
+                .flat_map(|c| c.params())
+                .collect(),
+            Command::Bundle(bl) => {
+                bl.typ.idxs.iter().map(|i| i.copy()).collect()
+            }
+            Command::Let(l) => vec![l.name.copy()],
+            Command::Invoke(_)
+            | Command::Instance(_)
+            | Command::Fact(_)
+            | Command::Connect(_) => vec![],
         }
     }
 }
@@ -228,6 +302,18 @@ impl Instance {
             bindings,
         }
     }
+
+    /// Resolve the expressions in the parameters of the instance
+    pub fn resolve_exprs(self, bind: &Binding<Expr>) -> Self {
+        Instance {
+            bindings: self
+                .bindings
+                .into_iter()
+                .map(|e| e.map(|e| e.resolve(bind)))
+                .collect(),
+            ..self
+        }
+    }
 }
 impl std::fmt::Display for Instance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -268,6 +354,23 @@ impl Invoke {
         }
     }
 
+    /// Resolve the expressions in the events and ports of the invocation
+    pub fn resolve_exprs(self, bind: &Binding<Expr>) -> Self {
+        Self {
+            abstract_vars: self
+                .abstract_vars
+                .into_iter()
+                .map(|t| t.map(|t| t.resolve_expr(bind)))
+                .collect(),
+            ports: self.ports.map(|ps| {
+                ps.into_iter()
+                    .map(|p| p.map(|p| p.resolve_exprs(bind)))
+                    .collect()
+            }),
+            ..self
+        }
+    }
+
     // XXX: This can probably be removed
     pub fn bindings<I>(&self, abstract_vars: I) -> Binding<Time>
     where
@@ -366,28 +469,104 @@ impl std::fmt::Display for Fact {
 }
 
 #[derive(Clone)]
-/// A Guard expression
+/// A Guard expression over 1-bit ports
 pub enum Guard {
     Or(Box<Guard>, Box<Guard>, GPosIdx),
-    Port(Port),
+    And(Box<Guard>, Box<Guard>, GPosIdx),
+    Not(Box<Guard>, GPosIdx),
+    Port(Loc<Port>),
 }
 
+impl From<Loc<Port>> for Guard {
+    fn from(v: Loc<Port>) -> Self {
+        Self::Port(v)
+    }
+}
 impl From<Port> for Guard {
     fn from(v: Port) -> Self {
-        Self::Port(v)
+        Self::Port(Loc::unknown(v))
     }
 }
 impl Guard {
     pub fn or(g1: Guard, g2: Guard) -> Self {
         Guard::Or(Box::new(g1), Box::new(g2), GPosIdx::UNKNOWN)
     }
+
+    pub fn and(g1: Guard, g2: Guard) -> Self {
+        Guard::And(Box::new(g1), Box::new(g2), GPosIdx::UNKNOWN)
+    }
+
+    /// Resolve the expressions in the ports used by the guard
+    pub fn resolve_exprs(self, bind: &Binding<Expr>) -> Self {
+        match self {
+            Guard::Or(g1, g2, pos) => Guard::Or(
+                Box::new(g1.resolve_exprs(bind)),
+                Box::new(g2.resolve_exprs(bind)),
+                pos,
+            ),
+            Guard::And(g1, g2, pos) => Guard::And(
+                Box::new(g1.resolve_exprs(bind)),
+                Box::new(g2.resolve_exprs(bind)),
+                pos,
+            ),
+            Guard::Not(g, pos) => {
+                Guard::Not(Box::new(g.resolve_exprs(bind)), pos)
+            }
+            Guard::Port(p) => Guard::Port(p.map(|p| p.resolve_exprs(bind))),
+        }
+    }
+
+    /// Ports used in the guard
+    pub fn ports(&self) -> Vec<&Loc<Port>> {
+        match self {
+            Guard::Or(g1, g2, _) | Guard::And(g1, g2, _) => {
+                let mut ports = g1.ports();
+                ports.extend(g2.ports());
+                ports
+            }
+            Guard::Not(g, _) => g.ports(),
+            Guard::Port(p) => vec![p],
+        }
+    }
+
+    /// Display the guard and add parentheses if it binds more loosely than
+    /// an operator with the given precedence.
+    fn fmt_prec(
+        &self,
+        prec: u8,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        let (own, parens) = match self {
+            Guard::Or(..) => (0, prec > 0),
+            Guard::And(..) => (1, prec > 1),
+            Guard::Not(..) | Guard::Port(_) => (2, false),
+        };
+        if parens {
+            write!(f, "(")?;
+        }
+        match self {
+            Guard::Or(g1, g2, _) | Guard::And(g1, g2, _) => {
+                let op = if own == 0 { "|" } else { "&" };
+                g1.fmt_prec(own, f)?;
+                write!(f, " {op} ")?;
+                // Operators are left associative
+                g2.fmt_prec(own + 1, f)?;
+            }
+            Guard::Not(g, _) => {
+                write!(f, "!")?;
+                g.fmt_prec(2, f)?;
+            }
+            Guard::Port(p) => write!(f, "{}", p)?,
+        }
+        if parens {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
 }
 impl std::fmt::Display for Guard {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Guard::Or(g1, g2, _) => write!(f, "{} | {}", g1, g2),
-            Guard::Port(p) => write!(f, "{}", p),
-        }
+        self.fmt_prec(0, f)
     }
 }
 
@@ -406,6 +585,15 @@ impl Connect {
     pub fn new(dst: Loc<Port>, src: Loc<Port>, guard: Option<Guard>) -> Self {
         Self { dst, src, guard }
     }
+
+    /// Resolve the expressions in the ports and guard of the connection
+    pub fn resolve_exprs(self, bind: &Binding<Expr>) -> Self {
+        Self {
+            dst: self.dst.map(|p| p.resolve_exprs(bind)),
+            src: self.src.map(|p| p.resolve_exprs(bind)),
+            guard: self.guard.map(|g| g.resolve_exprs(bind)),
+        }
+    }
 }
 impl std::fmt::Display for Connect {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -519,6 +707,34 @@ impl From<ForLoop> for Command {
     }
 }
 
+#[derive(Clone)]
+/// A parameter defined in the body of a component using an expression over
+/// other parameters: `let #K = #N * #B;`
+pub struct ParamLet {
+    /// Name of the parameter
+    pub name: Loc<Id>,
+    /// Expression the parameter is bound to
+    pub expr: Loc<Expr>,
+}
+
+impl ParamLet {
+    pub fn new(name: Loc<Id>, expr: Loc<Expr>) -> Self {
+        Self { name, expr }
+    }
+}
+
+impl Display for ParamLet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "let #{} = {}", self.name, self.expr)
+    }
+}
+
+impl From<ParamLet> for Command {
+    fn from(v: ParamLet) -> Self {
+        Self::Let(v)
+    }
+}
+
 #[derive(Clone)]
 /// A conditional statement:
 /// The `then` branch is checked assuming that the condition is true and the `else` branch is checked
@@ -562,40 +778,56 @@ impl From<If> for Command {
 #[derive(Clone)]
 /// The type of the bundle:
 /// ```
-/// for<#i> @[G+#i, G+#i+1] #W
+/// for<#i, #j> @[G+#i+#j, G+#i+#j+1] #W
 /// ```
 pub struct BundleType {
-    /// The name of the parameter for the bundle type
-    pub idx: Loc<Id>,
-    /// Length of the bundle. The index parameter ranges over [0, len)
-    pub len: Loc<Expr>,
+    /// The names of the index parameters, one for each dimension
+    pub idxs: Vec<Loc<Id>>,
+    /// Length of each dimension. The i-th index parameter ranges over
+    /// [0, lens[i])
+    pub lens: Vec<Loc<Expr>>,
     /// Availability interval for the bundle
     pub liveness: Loc<Range>,
     /// Bitwidth of the bundle
     pub bitwidth: Loc<Expr>,
+    /// Numeric representation of the bundle's ports
+    pub repr: NumRepr,
 }
 
 impl BundleType {
     pub fn new(
-        idx: Loc<Id>,
-        len: Loc<Expr>,
+        idxs: Vec<Loc<Id>>,
+        lens: Vec<Loc<Expr>>,
         liveness: Loc<Range>,
         bitwidth: Loc<Expr>,
+        repr: NumRepr,
     ) -> Self {
+        debug_assert_eq!(idxs.len(), lens.len());
         Self {
-            idx,
-            len,
+            idxs,
+            lens,
             liveness,
             bitwidth,
+            repr,
         }
     }
 
+    /// Number of dimensions of the bundle
+    pub fn dims(&self) -> usize {
+        self.idxs.len()
+    }
+
     pub fn resolve_exprs(self, binding: &Binding<Expr>) -> Self {
         Self {
-            idx: self.idx,
-            len: self.len.map(|e| e.resolve(binding)),
+            idxs: self.idxs,
+            lens: self
+                .lens
+                .into_iter()
+                .map(|e| e.map(|e| e.resolve(binding)))
+                .collect(),
             liveness: self.liveness.map(|e| e.resolve_exprs(binding)),
             bitwidth: self.bitwidth.map(|e| e.resolve(binding)),
+            repr: self.repr.resolve(binding),
         }
     }
 
@@ -614,31 +846,13 @@ impl BundleType {
         // let resolved = other.resolve_exprs(&binding);
         todo!()
     }
-
-    /// Return the type for a bundle that has been offset by the given expression.
-    /// For example:
-    /// ```
-    /// for<#i> @[G+#i, G+#i+1] #W
-    /// ```
-    /// offset by `#K` becomes
-    /// ```
-    /// for<#i> @[G+#i+#K, G+#i+1+#K] #W
-    /// ```
-    pub fn shrink(self, start: Expr, end: Expr) -> Self {
-        // Generate the offset by resolving the index of the bundle type with index+offset
-        let len = end - start.clone();
-        let idx = self.idx.copy();
-        let binding = Binding::new(Some((idx, Expr::abs(idx) + start)));
-        let mut resolved = self.resolve_exprs(&binding);
-        *resolved.len.inner_mut() = len;
-        resolved
-    }
 }
 
 #[derive(Clone)]
 /// Represents a bundle of wires with timing guarantees
 /// ```
 /// bundle f[10]: for<#i> @[G+#i, G+#i+1] #W;
+/// bundle m[#N][#M]: for<#i, #j> @[G+#i+#j, G+#i+#j+1] #W;
 /// ```
 pub struct Bundle {
     /// Name of the bundle
@@ -652,16 +866,54 @@ impl Bundle {
         Self { name, typ }
     }
 
-    /// Generate a port definition corresponding to a given index
-    pub fn index(&self, idx: Expr) -> PortDef {
+    /// The port definition for an access into the bundle with one access per
+    /// dimension. Indexed dimensions are removed while ranges become
+    /// dimensions of a smaller bundle. For example, accessing
+    /// ```
+    /// for<#i, #j> @[G+#i+#j, G+#i+#j+1] #W
+    /// ```
+    /// with `{#K}{1..3}` produces the bundle type
+    /// ```
+    /// for<#j> @[G+#K+#j+1, G+#K+#j+2] #W
+    /// ```
+    /// If every dimension is indexed, the result is a port.
+    pub fn access(&self, access: &[Access]) -> PortDef {
+        debug_assert_eq!(access.len(), self.typ.dims());
         let mut bind = Binding::default();
-        bind.insert(*self.typ.idx.inner(), idx);
-        let liveness = self.typ.liveness.clone();
-        PortDef::port(
-            Loc::unknown(Id::from("__FAKE_NAME_SHOULD_NOT_BE_USED")),
-            liveness.map(|r| r.resolve_exprs(&bind)),
-            self.typ.bitwidth.clone(),
-        )
+        let mut idxs = Vec::new();
+        let mut lens = Vec::new();
+        for ((idx, len), acc) in
+            self.typ.idxs.iter().zip(&self.typ.lens).zip(access)
+        {
+            match acc {
+                Access::Index(e) => bind.insert(idx.copy(), e.clone()),
+                Access::Range { start, end } => {
+                    bind.insert(
+                        idx.copy(),
+                        Expr::abs(idx.copy()) + start.clone(),
+                    );
+                    idxs.push(idx.clone());
+                    lens.push(Loc::new(end.clone() - start.clone(), len.pos()));
+                }
+            }
+        }
+        let liveness =
+            self.typ.liveness.clone().map(|r| r.resolve_exprs(&bind));
+        let bitwidth = self.typ.bitwidth.clone().map(|w| w.resolve(&bind));
+        let repr = self.typ.repr.clone().resolve(&bind);
+        if idxs.is_empty() {
+            PortDef::port(
+                Loc::unknown(Id::from("__FAKE_NAME_SHOULD_NOT_BE_USED")),
+                liveness,
+                bitwidth,
+                repr,
+            )
+        } else {
+            PortDef::Bundle(Bundle::new(
+                self.name.clone(),
+                BundleType::new(idxs, lens, liveness, bitwidth, repr),
+            ))
+        }
     }
 
     /// Resolve expressions in the Bundle
@@ -675,11 +927,16 @@ impl Bundle {
 
 impl Display for Bundle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "bundle {}[{}]: ", self.name, self.typ.len)?;
+        write!(f, "bundle {}", self.name)?;
+        for len in &self.typ.lens {
+            write!(f, "[{len}]")?;
+        }
         write!(
             f,
-            "for<#{}> {} {};",
-            self.typ.idx, self.typ.liveness, self.typ.bitwidth
+            ": for<{}> {} {};",
+            self.typ.idxs.iter().map(|i| format!("#{i}")).join(", "),
+            self.typ.liveness,
+            self.typ.repr.typ(&self.typ.bitwidth)
         )
     }
 }
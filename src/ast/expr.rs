@@ -1,4 +1,4 @@
-use super::{Fact, Id, Implication, OrderConstraint, OrderOp};
+use super::{Fact, FnRef, Id, Implication, OrderConstraint, OrderOp};
 use crate::{
     errors,
     utils::{self, Binding, SExp},
@@ -103,8 +103,12 @@ impl FnAssume {
                 left,
                 right,
             } => match (left, right) {
-                (_, Expr::App { func, arg: right }) => func.assume(left, right),
-                (Expr::App { func, arg: left }, _) => func.assume(right, left),
+                (_, Expr::App { func, args }) if args.len() == 1 => {
+                    func.clone().assume(left, &args[0])
+                }
+                (Expr::App { func, args }, _) if args.len() == 1 => {
+                    func.clone().assume(right, &args[0])
+                }
                 _ => Vec::new(),
             },
             _ => Vec::new(),
@@ -112,19 +116,22 @@ impl FnAssume {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd)]
-/// A unary uninterpreted function over integers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd)]
+/// An uninterpreted function over integers.
 pub enum UnFn {
     /// The `pow2` function
     Pow2,
     /// The `log2` function
     Log2,
+    /// A function defined using `fn`. See [super::FnDef].
+    User(FnRef),
 }
 impl std::fmt::Display for UnFn {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             UnFn::Pow2 => write!(f, "pow2"),
             UnFn::Log2 => write!(f, "log2"),
+            UnFn::User(name) => write!(f, "{name}"),
         }
     }
 }
@@ -135,18 +142,59 @@ impl UnFn {
         FnAssume::from(self).assume(left, right)
     }
 
-    pub fn apply(self, arg: Expr) -> Expr {
-        match (self, arg) {
-            (UnFn::Pow2, Expr::Concrete(n)) => {
-                Expr::Concrete(2u64.pow(n as u32))
-            }
-            (UnFn::Log2, Expr::Concrete(n)) => {
-                Expr::Concrete((n as f64).log2().ceil() as u64)
-            }
-            (func, arg) => Expr::App {
-                func,
-                arg: Box::new(arg),
-            },
+    /// The builtin function with the given name
+    pub fn builtin(name: Id) -> Option<Self> {
+        match name.as_ref() {
+            "pow2" => Some(UnFn::Pow2),
+            "log2" => Some(UnFn::Log2),
+            _ => None,
+        }
+    }
+
+    /// Is this function defined using `fn`?
+    pub fn is_user(&self) -> bool {
+        matches!(self, UnFn::User(_))
+    }
+
+    /// Number of arguments taken by the function or `None` if the function
+    /// is not defined.
+    pub fn arity(&self) -> Option<usize> {
+        match self {
+            UnFn::Pow2 | UnFn::Log2 => Some(1),
+            UnFn::User(f) => f.def().map(|def| def.arity()),
+        }
+    }
+
+    /// Name of the function in SMT queries. User-defined functions use quoted
+    /// symbols so that they cannot clash with the functions in the prelude.
+    pub fn smt_name(&self) -> String {
+        match self {
+            UnFn::User(name) => format!("|fn {name}|"),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Evaluate the function on concrete arguments
+    pub fn eval(&self, args: &[u64]) -> Option<u64> {
+        match (self, args) {
+            (UnFn::Pow2, [n]) => Some(2u64.pow(*n as u32)),
+            (UnFn::Log2, [n]) => Some((*n as f64).log2().ceil() as u64),
+            (UnFn::User(f), args) => f.def()?.eval(args),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, args: Vec<Expr>) -> Expr {
+        let concrete = args
+            .iter()
+            .map(|arg| match arg {
+                Expr::Concrete(n) => Some(*n),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        match concrete.and_then(|args| self.eval(&args)) {
+            Some(n) => Expr::Concrete(n),
+            None => Expr::App { func: self, args },
         }
     }
 }
@@ -164,11 +212,11 @@ impl From<UnFn> for FnAssume {
                             Expr::abs(FnAssume::left()),
                             Expr::concrete(2),
                         ),
-                        func.apply(Expr::op(
+                        func.clone().apply(vec![Expr::op(
                             Op::Add,
                             Expr::abs(FnAssume::right()),
                             Expr::concrete(1),
-                        )),
+                        )]),
                     )
                     .into(),
                     // assume #r >= 1 => #l == pow2(#r-1)*2;
@@ -181,11 +229,11 @@ impl From<UnFn> for FnAssume {
                             Expr::abs(FnAssume::left()),
                             Expr::op(
                                 Op::Mul,
-                                func.apply(Expr::op(
+                                func.clone().apply(vec![Expr::op(
                                     Op::Sub,
                                     Expr::abs(FnAssume::right()),
                                     Expr::concrete(1),
-                                )),
+                                )]),
                                 Expr::concrete(2),
                             ),
                         ),
@@ -216,11 +264,11 @@ impl From<UnFn> for FnAssume {
                             Expr::abs(FnAssume::left()),
                             Expr::concrete(1),
                         ),
-                        func.apply(Expr::op(
+                        func.clone().apply(vec![Expr::op(
                             Op::Mul,
                             Expr::abs(FnAssume::right()),
                             Expr::concrete(2),
-                        )),
+                        )]),
                     )
                     .into(),
                     // assume #l >= 1 => #l-1 == log2(#r/2);
@@ -235,11 +283,11 @@ impl From<UnFn> for FnAssume {
                                 Expr::abs(FnAssume::left()),
                                 Expr::concrete(1),
                             ),
-                            func.apply(Expr::op(
+                            func.clone().apply(vec![Expr::op(
                                 Op::Div,
                                 Expr::abs(FnAssume::right()),
                                 Expr::concrete(2),
-                            )),
+                            )]),
                         ),
                     ),
                     // assume #l >= 1 => #r/2*2 = #r;
@@ -278,6 +326,9 @@ impl From<UnFn> for FnAssume {
                 )
                 .collect_vec(),
             ),
+            // User-defined functions are constrained by their axioms which
+            // are given to the solver directly.
+            UnFn::User(_) => FnAssume::new(vec![]),
         }
     }
 }
@@ -290,13 +341,13 @@ pub trait EvalBool {
 }
 
 /// An expression containing integers and abstract variables
-#[derive(Clone, Hash)]
+#[derive(Clone, Hash, PartialEq, Eq)]
 pub enum Expr {
     Concrete(u64),
     Abstract(Id),
     App {
         func: UnFn,
-        arg: Box<Expr>,
+        args: Vec<Expr>,
     },
     Op {
         op: Op,
@@ -344,8 +395,8 @@ impl Expr {
     }
 
     /// Function application
-    pub fn func(func: UnFn, arg: Expr) -> Self {
-        func.apply(arg)
+    pub fn func(func: UnFn, args: Vec<Expr>) -> Self {
+        func.apply(args)
     }
 
     pub fn op(op: Op, l: Expr, r: Expr) -> Self {
@@ -371,7 +422,9 @@ impl Expr {
         match self {
             Expr::Concrete(_) => self,
             Expr::Abstract(ref id) => bind.find(id).cloned().unwrap_or(self),
-            Expr::App { func, arg } => func.apply(arg.resolve(bind)),
+            Expr::App { func, args } => {
+                func.apply(args.into_iter().map(|a| a.resolve(bind)).collect())
+            }
             Expr::Op { op, left, right } => {
                 let l = left.resolve(bind);
                 let r = right.resolve(bind);
@@ -396,12 +449,30 @@ impl Expr {
         match self {
             Expr::Concrete(_) => Box::new(std::iter::empty()),
             Expr::Abstract(id) => Box::new(std::iter::once(id)),
-            Expr::App { arg, .. } => Box::new(arg.exprs()),
+            Expr::App { args, .. } => {
+                Box::new(args.iter().flat_map(|a| a.exprs()))
+            }
             Expr::Op { left, right, .. } => {
                 Box::new(left.exprs().chain(right.exprs()))
             }
         }
     }
+
+    /// Get all the functions applied in this expression along with the
+    /// number of arguments they are applied to
+    pub fn funcs(&self) -> Vec<(UnFn, usize)> {
+        match self {
+            Expr::Concrete(_) | Expr::Abstract(_) => vec![],
+            Expr::App { func, args } => {
+                std::iter::once((func.clone(), args.len()))
+                    .chain(args.iter().flat_map(|a| a.funcs()))
+                    .collect()
+            }
+            Expr::Op { left, right, .. } => {
+                left.funcs().into_iter().chain(right.funcs()).collect()
+            }
+        }
+    }
 }
 
 impl std::ops::Add for Expr {
@@ -499,9 +570,11 @@ impl From<Expr> for utils::SExp {
         match value {
             Expr::Concrete(n) => SExp(format!("{}", n)),
             Expr::Abstract(id) => SExp(format!("{}", id)),
-            Expr::App { func, arg } => {
-                SExp(format!("({} {})", func, SExp::from(*arg)))
-            }
+            Expr::App { func, args } => SExp(format!(
+                "({} {})",
+                func.smt_name(),
+                args.into_iter().map(SExp::from).join(" ")
+            )),
             Expr::Op { op, left, right } => SExp(format!(
                 "({} {} {})",
                 SExp::from(op),
@@ -551,8 +624,12 @@ impl ECtx {
             Expr::Abstract(v) => {
                 format!("#{v}")
             }
-            Expr::App { func, arg } => {
-                format!("{}({})", func, Self::Func.print(arg))
+            Expr::App { func, args } => {
+                format!(
+                    "{}({})",
+                    func,
+                    args.iter().map(|a| Self::Func.print(a)).join(", ")
+                )
             }
             Expr::Op { op, left, right } => {
                 let inner = Self::from(*op);
@@ -0,0 +1,347 @@
+use super::{EvalBool, Expr, Id, Implication, Loc, OrderOp, UnFn};
+use crate::{
+    errors::{Error, FilamentResult},
+    utils::{Binding, SExp},
+};
+use itertools::Itertools;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    sync::{Arc, RwLock},
+};
+
+#[derive(Clone, Default)]
+/// Functions defined by a program, in an order where every function is
+/// defined after the functions it uses. The table is shared by all files of
+/// the program and every [UnFn::User] refers to the table it was parsed with
+/// so that expressions can be evaluated without threading the definitions
+/// through every pass.
+pub struct Functions(Arc<RwLock<Vec<Arc<FnDef>>>>);
+
+#[derive(Clone)]
+/// A reference to a function by name in the table of its program. References
+/// are compared using only the name of the function.
+pub struct FnRef(Arc<(Id, Functions)>);
+
+impl FnRef {
+    /// The name of the function
+    pub fn name(&self) -> Id {
+        self.0 .0
+    }
+
+    /// The definition of the function if it has been installed
+    pub fn def(&self) -> Option<Arc<FnDef>> {
+        self.0 .1.get(self.name())
+    }
+}
+
+impl PartialEq for FnRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for FnRef {}
+
+impl PartialOrd for FnRef {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.name().partial_cmp(&other.name())
+    }
+}
+
+impl Hash for FnRef {
+    /// Hashes the name instead of its interned symbol so that the hash does
+    /// not depend on the order in which names were interned.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name().as_ref().hash(state)
+    }
+}
+
+impl std::fmt::Debug for FnRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.name())
+    }
+}
+
+impl Display for FnRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone)]
+/// The definition of a user-defined function
+pub enum FnBody {
+    /// `fn f(#a) = <expr>;`
+    /// The expression is used to evaluate the function and is given to the
+    /// solver as its definition.
+    Expr(Loc<Expr>),
+    /// `fn f(#a) -> #r { assume <implication>; ... }`
+    /// The function is uninterpreted and the solver only knows the axioms
+    /// relating the parameters to the result `#r`.
+    Axioms {
+        result: Loc<Id>,
+        axioms: Vec<Loc<Implication<Expr>>>,
+    },
+}
+
+#[derive(Clone)]
+/// A function in the parameter language defined using a top-level `fn`
+pub struct FnDef {
+    pub name: Loc<Id>,
+    pub params: Vec<Loc<Id>>,
+    pub body: FnBody,
+}
+
+impl FnDef {
+    pub fn new(name: Loc<Id>, params: Vec<Loc<Id>>, body: FnBody) -> Self {
+        Self { name, params, body }
+    }
+
+    /// Does the function need quantified axioms in SMT queries?
+    pub fn quantified(&self) -> bool {
+        matches!(&self.body, FnBody::Axioms { axioms, .. } if !axioms.is_empty())
+    }
+
+    /// Number of arguments taken by the function
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+
+    /// The function applied to its own parameters
+    fn applied(&self, funcs: &Functions) -> Expr {
+        Expr::App {
+            func: funcs.call(*self.name.inner()),
+            args: self.params.iter().map(|p| Expr::abs(*p.inner())).collect(),
+        }
+    }
+
+    fn bind(&self, args: &[u64]) -> Binding<Expr> {
+        Binding::new(
+            self.params
+                .iter()
+                .map(|p| *p.inner())
+                .zip(args.iter().map(|a| Expr::concrete(*a))),
+        )
+    }
+
+    /// Evaluate the function on concrete arguments.
+    /// Functions defined using axioms can only be evaluated if, for the given
+    /// arguments, some axiom with a true guard is of the form `#r == <expr>`.
+    pub fn eval(&self, args: &[u64]) -> Option<u64> {
+        let bind = self.bind(args);
+        match &self.body {
+            FnBody::Expr(body) => {
+                body.inner().clone().resolve(&bind).try_into().ok()
+            }
+            FnBody::Axioms { result, axioms } => axioms.iter().find_map(|ax| {
+                let ax = ax.inner().clone().resolve_expr(&bind);
+                if let Some(guard) = ax.guard {
+                    if !guard.resolve_bool(&Binding::default()).ok()? {
+                        return None;
+                    }
+                }
+                if ax.cons.op != OrderOp::Eq {
+                    return None;
+                }
+                match (ax.cons.left, ax.cons.right) {
+                    (Expr::Abstract(id), e) | (e, Expr::Abstract(id))
+                        if id == *result.inner() =>
+                    {
+                        e.try_into().ok()
+                    }
+                    _ => None,
+                }
+            }),
+        }
+    }
+
+    /// Axioms of an uninterpreted function where the result is replaced by an
+    /// application of the function to its parameters.
+    pub fn axioms(&self, funcs: &Functions) -> Vec<Implication<Expr>> {
+        let FnBody::Axioms { result, axioms } = &self.body else {
+            return vec![];
+        };
+        let bind = Binding::new([(*result.inner(), self.applied(funcs))]);
+        axioms
+            .iter()
+            .map(|ax| ax.inner().clone().resolve_expr(&bind))
+            .collect()
+    }
+
+    /// The body of the function in SMT queries if it is defined by an
+    /// expression.
+    pub fn smt_body(&self) -> Option<SExp> {
+        match &self.body {
+            FnBody::Expr(body) => Some(SExp::from(body.inner().clone())),
+            FnBody::Axioms { .. } => None,
+        }
+    }
+
+    /// The axioms of the function in SMT queries, quantified over all
+    /// natural-number arguments.
+    pub fn smt_axioms(&self, funcs: &Functions) -> Vec<SExp> {
+        let vars = self.params.iter().map(|p| format!("({p} Int)")).join(" ");
+        self.axioms(funcs)
+            .into_iter()
+            .map(|ax| {
+                let body = self
+                    .params
+                    .iter()
+                    .rev()
+                    .fold(SExp::from(ax).to_string(), |body, p| {
+                        format!("(=> (>= {p} 0) {body})")
+                    });
+                SExp(format!("(forall ({vars}) {body})"))
+            })
+            .collect()
+    }
+
+    /// Functions called in the body of this function
+    fn calls(&self) -> Vec<Id> {
+        let exprs: Vec<&Expr> = match &self.body {
+            FnBody::Expr(e) => vec![e.inner()],
+            FnBody::Axioms { axioms, .. } => {
+                axioms.iter().flat_map(|ax| ax.inner().exprs()).collect()
+            }
+        };
+        exprs
+            .into_iter()
+            .flat_map(|e| e.funcs())
+            .filter_map(|(f, _)| match f {
+                UnFn::User(f) => Some(f.name()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Check that the body only uses the parameters and the result
+    fn check_body(&self) -> FilamentResult<()> {
+        let mut bound = self.params.iter().map(|p| *p.inner()).collect_vec();
+        let exprs: Vec<&Expr> = match &self.body {
+            FnBody::Expr(e) => vec![e.inner()],
+            FnBody::Axioms { result, axioms } => {
+                bound.push(*result.inner());
+                axioms.iter().flat_map(|ax| ax.inner().exprs()).collect()
+            }
+        };
+        if let Some(p) = self.params.iter().duplicates_by(|p| p.inner()).next()
+        {
+            return Err(Error::malformed(format!(
+                "function `{}' has multiple parameters named `#{p}'",
+                self.name
+            )));
+        }
+        for e in exprs {
+            if let Some(v) = e.exprs().find(|v| !bound.contains(v)) {
+                return Err(Error::malformed(format!(
+                    "parameter `#{v}' is not bound in the definition of function `{}'",
+                    self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Functions {
+    /// A reference to the function with the given name in this table
+    pub fn call(&self, name: Id) -> UnFn {
+        UnFn::User(FnRef(Arc::new((name, self.clone()))))
+    }
+
+    /// Set the functions defined by the program, replacing previously
+    /// installed functions. Reports functions that are defined multiple times,
+    /// shadow a builtin function, or are recursive.
+    pub fn install(&self, defs: &[FnDef]) -> FilamentResult<()> {
+        for (idx, def) in defs.iter().enumerate() {
+            let name = *def.name.inner();
+            if UnFn::builtin(name).is_some() {
+                return Err(Error::malformed(format!(
+                    "function `{name}' shadows a builtin function"
+                )));
+            }
+            if defs[..idx].iter().any(|d| *d.name.inner() == name) {
+                return Err(Error::already_bound(name, "function"));
+            }
+            def.check_body()?;
+        }
+
+        // Order the definitions so that callees are defined first.
+        let mut order: Vec<Arc<FnDef>> = Vec::with_capacity(defs.len());
+        let mut done: HashSet<Id> = HashSet::new();
+        fn visit(
+            def: &FnDef,
+            defs: &[FnDef],
+            stack: &mut Vec<Id>,
+            done: &mut HashSet<Id>,
+            order: &mut Vec<Arc<FnDef>>,
+        ) -> FilamentResult<()> {
+            let name = *def.name.inner();
+            if done.contains(&name) {
+                return Ok(());
+            }
+            if stack.contains(&name) {
+                return Err(Error::malformed(format!(
+                    "function `{name}' is defined recursively"
+                )));
+            }
+            stack.push(name);
+            for callee in def.calls() {
+                if let Some(d) = defs.iter().find(|d| *d.name.inner() == callee)
+                {
+                    visit(d, defs, stack, done, order)?;
+                }
+            }
+            stack.pop();
+            done.insert(name);
+            order.push(Arc::new(def.clone()));
+            Ok(())
+        }
+        for def in defs {
+            visit(def, defs, &mut vec![], &mut done, &mut order)?;
+        }
+
+        *self.0.write().unwrap() = order;
+        Ok(())
+    }
+
+    /// The installed function with the given name
+    pub fn get(&self, name: Id) -> Option<Arc<FnDef>> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .find(|d| *d.name.inner() == name)
+            .cloned()
+    }
+
+    /// All installed functions. Functions are defined after the functions
+    /// they use.
+    pub fn all(&self) -> Vec<Arc<FnDef>> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Do the installed functions need quantified axioms in SMT queries?
+    pub fn quantified(&self) -> bool {
+        self.0.read().unwrap().iter().any(|def| def.quantified())
+    }
+}
+
+impl Display for FnDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = self.params.iter().map(|p| format!("#{p}")).join(", ");
+        write!(f, "fn {}({params})", self.name)?;
+        match &self.body {
+            FnBody::Expr(e) => write!(f, " = {e};"),
+            FnBody::Axioms { result, axioms } => {
+                writeln!(f, " -> #{result} {{")?;
+                for ax in axioms {
+                    writeln!(f, "  assume {ax};")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -12,6 +12,18 @@ impl Id {
             id: id.to_string().into(),
         }
     }
+
+    /// The name of `name` when it is defined in a file imported as `alias`:
+    /// `alias::name`
+    pub fn qualified(alias: Id, name: Id) -> Self {
+        Id::new(format!("{alias}::{name}"))
+    }
+
+    /// Name where the qualifier separator is replaced so that it can be used
+    /// as an identifier in the generated code.
+    pub fn mangle(&self) -> Self {
+        Id::new(self.id.as_str().replace("::", "__"))
+    }
 }
 
 /* =================== Impls for Id to make them easier to use ============== */
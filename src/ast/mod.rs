@@ -1,26 +1,32 @@
 mod component;
+mod constant;
 mod constraint;
 mod control;
 mod expr;
+mod func;
 mod id;
 mod implication;
 mod interval;
 mod loc;
 mod port;
 mod signature;
+mod struct_def;
 mod time;
 
 pub use component::{Component, Namespace};
+pub use constant::ConstDef;
 pub use constraint::{Constraint, OrderConstraint, OrderOp};
 pub use control::{
     Access, Bundle, BundleType, Command, Connect, Fact, ForLoop, Fsm, Guard,
-    If, Instance, Invoke, Port,
+    If, Instance, Invoke, ParamLet, Port,
 };
 pub use expr::{EvalBool, Expr, FnAssume, Op, UnFn};
+pub use func::{FnBody, FnDef, FnRef, Functions};
 pub use id::Id;
 pub use implication::Implication;
 pub use interval::Range;
 pub use loc::Loc;
-pub use port::{InterfaceDef, PortDef};
+pub use port::{InterfaceDef, NumRepr, PortDef};
 pub use signature::{EventBind, ParamBind, Signature};
+pub use struct_def::{StructDef, StructField, StructType};
 pub use time::{Time, TimeSub};
@@ -1,7 +1,77 @@
-use super::{Bundle, Expr, Id, Loc, Range, Time};
+use super::{Bundle, Expr, Id, Loc, Range, StructType, Time};
 use crate::utils::Binding;
 use std::fmt::Display;
 
+#[derive(Clone, Default)]
+/// Numeric interpretation of the bits carried by a port.
+pub enum NumRepr {
+    /// Uninterpreted bits. Can be connected to ports of any representation.
+    #[default]
+    Bits,
+    /// An integer. Written as `signed #W` or `unsigned #W`.
+    Int { signed: bool },
+    /// A fixed-point number with `frac` fractional bits. Written as
+    /// `Q<#F> #W` or `UQ<#F> #W` where `#W` is the bitwidth of the port.
+    Fixed { signed: bool, frac: Box<Expr> },
+}
+
+impl NumRepr {
+    pub fn resolve(self, bindings: &Binding<Expr>) -> Self {
+        match self {
+            NumRepr::Fixed { signed, frac } => NumRepr::Fixed {
+                signed,
+                frac: Box::new(frac.resolve(bindings)),
+            },
+            r => r,
+        }
+    }
+
+    /// Is this a signed representation?
+    pub fn is_signed(&self) -> bool {
+        match self {
+            NumRepr::Bits => false,
+            NumRepr::Int { signed } | NumRepr::Fixed { signed, .. } => *signed,
+        }
+    }
+
+    /// Number of fractional bits in the representation
+    pub fn frac(&self) -> Expr {
+        match self {
+            NumRepr::Bits | NumRepr::Int { .. } => Expr::concrete(0),
+            NumRepr::Fixed { frac, .. } => frac.as_ref().clone(),
+        }
+    }
+
+    /// Surface-level description of the representation used in errors
+    pub fn describe(&self) -> String {
+        let sign = if self.is_signed() {
+            "signed"
+        } else {
+            "unsigned"
+        };
+        match self {
+            NumRepr::Bits => "bits".to_string(),
+            NumRepr::Int { .. } => sign.to_string(),
+            NumRepr::Fixed { frac, .. } => {
+                format!("{sign} fixed-point with {frac} fractional bits")
+            }
+        }
+    }
+
+    /// Format a port type with this representation and the given bitwidth
+    pub fn typ(&self, bitwidth: &Expr) -> String {
+        match self {
+            NumRepr::Bits => format!("{bitwidth}"),
+            NumRepr::Int { signed: true } => format!("signed {bitwidth}"),
+            NumRepr::Int { signed: false } => format!("unsigned {bitwidth}"),
+            NumRepr::Fixed { signed, frac } => {
+                let q = if *signed { "Q" } else { "UQ" };
+                format!("{q}<{frac}> {bitwidth}")
+            }
+        }
+    }
+}
+
 /// A port definition in a [super::Signature].
 #[derive(Clone)]
 pub enum PortDef {
@@ -11,8 +81,17 @@ pub enum PortDef {
         liveness: Loc<Range>,
         /// Bitwidth of the port
         bitwidth: Loc<Expr>,
+        /// Numeric representation of the port
+        repr: NumRepr,
     },
     Bundle(Bundle),
+    /// A port with a struct type. Eliminated by [crate::passes::StructElim]
+    /// before any other pass runs.
+    Struct {
+        name: Loc<Id>,
+        liveness: Loc<Range>,
+        typ: StructType,
+    },
 }
 impl From<Bundle> for PortDef {
     fn from(b: Bundle) -> Self {
@@ -25,11 +104,13 @@ impl PortDef {
         name: Loc<Id>,
         liveness: Loc<Range>,
         bitwidth: Loc<Expr>,
+        repr: NumRepr,
     ) -> Self {
         Self::Port {
             name,
             liveness,
             bitwidth,
+            repr,
         }
     }
 
@@ -42,6 +123,7 @@ impl PortDef {
         match &self {
             PortDef::Port { name, .. } => name,
             PortDef::Bundle(b) => &b.name,
+            PortDef::Struct { name, .. } => name,
         }
     }
 
@@ -49,6 +131,22 @@ impl PortDef {
         match &self {
             PortDef::Port { bitwidth, .. } => bitwidth,
             PortDef::Bundle(b) => &b.typ.bitwidth,
+            PortDef::Struct { name, .. } => {
+                unreachable!("struct port `{name}' does not have a bitwidth")
+            }
+        }
+    }
+
+    /// Numeric representation of the port
+    pub fn repr(&self) -> &NumRepr {
+        match &self {
+            PortDef::Port { repr, .. } => repr,
+            PortDef::Bundle(b) => &b.typ.repr,
+            PortDef::Struct { name, .. } => {
+                unreachable!(
+                    "struct port `{name}' does not have a representation"
+                )
+            }
         }
     }
 
@@ -56,6 +154,7 @@ impl PortDef {
         match &self {
             PortDef::Port { liveness, .. } => liveness,
             PortDef::Bundle(b) => &b.typ.liveness,
+            PortDef::Struct { liveness, .. } => liveness,
         }
     }
 }
@@ -66,10 +165,16 @@ impl Display for PortDef {
                 name,
                 liveness,
                 bitwidth,
+                repr,
             } => {
-                write!(f, "{} {}: {}", *liveness, name, *bitwidth,)
+                write!(f, "{} {}: {}", *liveness, name, repr.typ(bitwidth))
             }
             PortDef::Bundle(b) => write!(f, "{b}"),
+            PortDef::Struct {
+                name,
+                liveness,
+                typ,
+            } => write!(f, "{} {name}: {typ}", *liveness),
         }
     }
 }
@@ -81,16 +186,27 @@ impl PortDef {
                 name,
                 liveness,
                 bitwidth,
+                repr,
             } => PortDef::Port {
                 name,
                 liveness: liveness.map(|l| l.resolve_event(bindings)),
                 bitwidth,
+                repr,
             },
             PortDef::Bundle(b) => {
                 let t = b.typ.resolve_event(bindings);
                 let bun = Bundle { typ: t, ..b };
                 PortDef::Bundle(bun)
             }
+            PortDef::Struct {
+                name,
+                liveness,
+                typ,
+            } => PortDef::Struct {
+                name,
+                liveness: liveness.map(|l| l.resolve_event(bindings)),
+                typ,
+            },
         }
     }
 
@@ -98,18 +214,30 @@ impl PortDef {
     /// Specifically:
     /// - The bitwidth of the port
     /// - The liveness condition
+    /// - The fixed-point format of the port
     pub fn resolve_exprs(self, bindings: &Binding<Expr>) -> Self {
         match self {
             PortDef::Port {
                 name,
                 liveness,
                 bitwidth,
+                repr,
             } => PortDef::Port {
                 name,
                 liveness: liveness.map(|l| l.resolve_exprs(bindings)),
                 bitwidth: bitwidth.map(|b| b.resolve(bindings)),
+                repr: repr.resolve(bindings),
             },
             PortDef::Bundle(b) => PortDef::Bundle(b.resolve_exprs(bindings)),
+            PortDef::Struct {
+                name,
+                liveness,
+                typ,
+            } => PortDef::Struct {
+                name,
+                liveness: liveness.map(|l| l.resolve_exprs(bindings)),
+                typ: typ.resolve_exprs(bindings),
+            },
         }
     }
 }
@@ -141,6 +269,7 @@ impl From<InterfaceDef> for PortDef {
             id.name,
             Loc::unknown(Range::new(start, end)),
             Loc::unknown(Expr::from(1)),
+            NumRepr::Bits,
         )
     }
 }
@@ -433,28 +433,45 @@ impl Signature {
 
     pub fn resolve_exprs(self, args: Vec<Expr>) -> Signature {
         let binding: Binding<Expr> = self.param_binding(args);
-
         Signature {
             params: vec![],
+            ..self.resolve_binding(&binding)
+        }
+    }
+
+    /// Resolve the expressions in the signature using the binding while
+    /// keeping the parameters of the signature.
+    pub fn resolve_binding(self, binding: &Binding<Expr>) -> Signature {
+        Signature {
+            params: self
+                .params
+                .into_iter()
+                .map(|pb| {
+                    pb.map(|pb| ParamBind {
+                        default: pb.default.map(|d| d.resolve(binding)),
+                        ..pb
+                    })
+                })
+                .collect_vec(),
             ports: self
                 .ports
                 .into_iter()
-                .map(|pd| pd.map(|p| p.resolve_exprs(&binding)))
+                .map(|pd| pd.map(|p| p.resolve_exprs(binding)))
                 .collect_vec(),
             events: self
                 .events
                 .into_iter()
-                .map(|eb| eb.map(|e| e.resolve_exprs(&binding)))
+                .map(|eb| eb.map(|e| e.resolve_exprs(binding)))
                 .collect_vec(),
             param_constraints: self
                 .param_constraints
                 .into_iter()
-                .map(|c| c.map(|c| c.resolve_expr(&binding)))
+                .map(|c| c.map(|c| c.resolve_expr(binding)))
                 .collect_vec(),
             event_constraints: self
                 .event_constraints
                 .into_iter()
-                .map(|c| c.map(|c| c.resolve_expr(&binding)))
+                .map(|c| c.map(|c| c.resolve_expr(binding)))
                 .collect_vec(),
             ..self
         }
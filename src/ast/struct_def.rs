@@ -0,0 +1,140 @@
+use super::{Expr, Id, Loc, Range};
+use crate::utils::Binding;
+use itertools::Itertools;
+use std::fmt::Display;
+
+#[derive(Clone)]
+/// A field of a struct
+pub struct StructField {
+    pub name: Loc<Id>,
+    /// Number of cycles the field is live after the start of the port's
+    /// interval. Fields without an offset are live for the whole interval.
+    pub offset: Option<Loc<Expr>>,
+    pub bitwidth: Loc<Expr>,
+}
+
+impl Display for StructField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(offset) = &self.offset {
+            write!(f, "@+{offset} ")?;
+        }
+        write!(f, "{}: {}", self.name, self.bitwidth)
+    }
+}
+
+#[derive(Clone)]
+/// A struct type that groups ports with different widths and intervals:
+/// ```text
+/// struct Stream[#W] {
+///   data: #W,
+///   @+1 last: 1,
+/// }
+/// ```
+pub struct StructDef {
+    pub name: Loc<Id>,
+    pub params: Vec<Loc<Id>>,
+    pub fields: Vec<StructField>,
+}
+
+impl StructDef {
+    pub fn new(
+        name: Loc<Id>,
+        params: Vec<Loc<Id>>,
+        fields: Vec<StructField>,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            fields,
+        }
+    }
+
+    /// Get the field with the given name
+    pub fn field(&self, name: &Id) -> Option<&StructField> {
+        self.fields.iter().find(|f| f.name.inner() == name)
+    }
+
+    /// The liveness and width of each field for a port of this struct type
+    /// with the given liveness and parameters.
+    pub fn instantiate(
+        &self,
+        liveness: &Range,
+        args: &[Loc<Expr>],
+    ) -> Vec<(Loc<Id>, Range, Expr)> {
+        let bind = Binding::new(
+            self.params
+                .iter()
+                .map(|p| p.copy())
+                .zip(args.iter().map(|a| a.inner().clone())),
+        );
+        self.fields
+            .iter()
+            .map(|f| {
+                let live = match &f.offset {
+                    Some(off) => {
+                        let off = off.inner().clone().resolve(&bind);
+                        Range::new(
+                            liveness.start.clone().increment(off.clone()),
+                            liveness.end.clone().increment(off),
+                        )
+                    }
+                    None => liveness.clone(),
+                };
+                let width = f.bitwidth.inner().clone().resolve(&bind);
+                (f.name.clone(), live, width)
+            })
+            .collect()
+    }
+}
+
+impl Display for StructDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "struct {}", self.name)?;
+        if !self.params.is_empty() {
+            write!(
+                f,
+                "[{}]",
+                self.params.iter().map(|p| format!("#{p}")).join(", ")
+            )?;
+        }
+        writeln!(f, " {{")?;
+        for field in &self.fields {
+            writeln!(f, "  {field},")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[derive(Clone)]
+/// The type of a port with a struct type: `Name[args]`
+pub struct StructType {
+    pub name: Loc<Id>,
+    pub args: Vec<Loc<Expr>>,
+}
+
+impl StructType {
+    pub fn new(name: Loc<Id>, args: Vec<Loc<Expr>>) -> Self {
+        Self { name, args }
+    }
+
+    pub fn resolve_exprs(self, bindings: &Binding<Expr>) -> Self {
+        Self {
+            args: self
+                .args
+                .into_iter()
+                .map(|a| a.map(|e| e.resolve(bindings)))
+                .collect(),
+            ..self
+        }
+    }
+}
+
+impl Display for StructType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.args.is_empty() {
+            write!(f, "[{}]", self.args.iter().join(", "))?;
+        }
+        Ok(())
+    }
+}
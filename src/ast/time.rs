@@ -37,6 +37,7 @@ impl Time {
 
     /// Attempt to add a [TimeSub] expression to this time expression.
     /// Only possible when the [TimeSub::Unit] expression is a concrete value.
+    #[allow(clippy::result_large_err)]
     pub fn try_increment(self, sub: TimeSub) -> Result<Self, (Self, TimeSub)> {
         match sub {
             TimeSub::Unit(n) => Ok(self.increment(n)),
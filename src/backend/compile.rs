@@ -9,7 +9,7 @@ use std::iter;
 use std::path::PathBuf;
 use std::rc::Rc;
 
-const INTERFACE_PORTS: [(ir::BoolAttr, (&str, u64, ir::Direction)); 2] = [
+pub(crate) const INTERFACE_PORTS: [(ir::BoolAttr, (&str, u64, ir::Direction)); 2] = [
     (ir::BoolAttr::Clk, ("clk", 1, ir::Direction::Input)),
     (ir::BoolAttr::Reset, ("reset", 1, ir::Direction::Input)),
 ];
@@ -134,7 +134,13 @@ fn compile_guard(
             let c2 = compile_guard(*g2, ctx);
             c1 | c2
         }
-        ast::Guard::Port(p) => match &p {
+        ast::Guard::And(g1, g2, _) => {
+            let c1 = compile_guard(*g1, ctx);
+            let c2 = compile_guard(*g2, ctx);
+            c1 & c2
+        }
+        ast::Guard::Not(g, _) => !compile_guard(*g, ctx),
+        ast::Guard::Port(p) => match p.inner() {
             ast::Port::This(p) => {
                 let this = ctx.builder.component.signature.borrow();
                 this.get(p.as_ref()).into()
@@ -163,7 +169,10 @@ fn compile_guard(
 
 /// Construct a new component that represents an FSM with `states`.
 /// component fsm_<states>(go: 1) -> (_0: 1, ..., <states-1>: 1) { ... }
-fn define_fsm_component(states: u64, ctx: &mut Context) {
+pub(crate) fn define_fsm_component(
+    states: u64,
+    lib: &ir::LibrarySignatures,
+) -> ir::Component {
     let ports: Vec<ir::PortDef<u64>> = (0..states)
         .map(|n| {
             (ir::Id::from(format!("_{n}")), 1, ir::Direction::Output).into()
@@ -183,7 +192,7 @@ fn define_fsm_component(states: u64, ctx: &mut Context) {
         false,
     );
     comp.attributes.insert(ir::BoolAttr::NoInterface, 1);
-    let mut builder = ir::Builder::new(&mut comp, ctx.lib).not_generated();
+    let mut builder = ir::Builder::new(&mut comp, lib).not_generated();
 
     // Add n-1 registers
     let regs = (0..states - 1)
@@ -242,7 +251,7 @@ fn define_fsm_component(states: u64, ctx: &mut Context) {
         ]);
     }
     drop(this);
-    ctx.binding.fsm_comps.insert(states, comp);
+    comp
 }
 
 fn compile_connect(con: ast::Connect, ctx: &mut Context) {
@@ -299,6 +308,19 @@ where
         )
         .collect_vec();
 
+    add_interface_attrs(&mut ports, concrete_transform, is_comp);
+    ports
+}
+
+/// Add the attributes for the `clk` and `reset` ports. If `is_comp` is true,
+/// the ports are added to the signature if they are missing.
+pub(crate) fn add_interface_attrs<CW, F>(
+    ports: &mut Vec<ir::PortDef<CW>>,
+    concrete_transform: F,
+    is_comp: bool,
+) where
+    F: Fn(&ast::Id, u64) -> ir::PortDef<CW>,
+{
     // Add annotations for interface ports
     let mut interface_ports = INTERFACE_PORTS
         .iter()
@@ -307,7 +329,7 @@ where
 
     // Walk over the ports and if they match the known port name, add the attribute.
     // The name of the `clk` and `reset` ports are currently hardcoded.
-    for pd in &mut ports {
+    for pd in ports.iter_mut() {
         if let Some((a, n)) = interface_ports
             .iter()
             .find(|(_, n)| *n == pd.name.as_ref())
@@ -325,8 +347,6 @@ where
             ports.push(pd);
         }
     }
-
-    ports
 }
 
 fn compile_component(
@@ -362,7 +382,8 @@ fn compile_component(
     for fsm in &comp.fsms {
         // If FSM with required number of states has not been constructed, define a new component for it
         if !ctx.binding.fsm_comps.contains_key(&fsm.states) {
-            define_fsm_component(fsm.states, &mut ctx);
+            let comp = define_fsm_component(fsm.states, ctx.lib);
+            ctx.binding.fsm_comps.insert(fsm.states, comp);
         }
         // Construct the FSM
         let name = fsm.name;
@@ -423,6 +444,9 @@ fn compile_component(
             ast::Command::If(_) => {
                 unreachable!("If should have been compiled away.")
             }
+            ast::Command::Let(_) => {
+                unreachable!("Let should have been compiled away.")
+            }
             ast::Command::Fact(a) => {
                 unreachable!("Assumption `{a}' should have been compiled away.")
             }
@@ -480,18 +504,17 @@ fn compile_signature(sig: &ast::Signature) -> ir::Primitive {
     }
 }
 
-#[allow(clippy::type_complexity)]
-fn init_calyx(
-    externs: &[(String, Vec<ast::Signature>)],
+/// Construct a Calyx context with the given external primitives
+pub(crate) fn init_calyx(
+    externs: Vec<(String, Vec<ir::Primitive>)>,
 ) -> CalyxResult<ir::Context> {
     let mut ws = frontend::Workspace::from_compile_lib()?;
     // Add externals
-    ws.externs.extend(externs.iter().map(|(file, sigs)| {
-        (
-            Some(PathBuf::from(file)),
-            sigs.iter().map(compile_signature).collect(),
-        )
-    }));
+    ws.externs.extend(
+        externs
+            .into_iter()
+            .map(|(file, prims)| (Some(PathBuf::from(file)), prims)),
+    );
 
     // define a fake main component
     let main = frontend::ast::ComponentDef::new("main", false, vec![]);
@@ -502,7 +525,14 @@ fn init_calyx(
 }
 
 pub fn compile(ns: ast::Namespace) {
-    let mut calyx_ctx = init_calyx(&ns.externs).unwrap_or_else(|e| {
+    let externs = ns
+        .externs
+        .iter()
+        .map(|(file, sigs)| {
+            (file.clone(), sigs.iter().map(compile_signature).collect())
+        })
+        .collect();
+    let mut calyx_ctx = init_calyx(externs).unwrap_or_else(|e| {
         panic!("Error initializing calyx context: {:?}", e);
     });
 
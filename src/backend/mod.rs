@@ -1,7 +1,12 @@
 mod compile;
 mod fsm_gen;
+pub mod verilog;
 
 pub(super) use compile::{Binding, Context};
 pub(super) use fsm_gen::Fsm;
 
+pub(crate) use compile::{
+    add_interface_attrs, define_fsm_component, init_calyx,
+};
+
 pub use compile::compile;
@@ -0,0 +1,536 @@
+use super::compile::INTERFACE_PORTS;
+use crate::ast;
+use crate::cmdline::DiagnosticsFormat;
+use crate::diagnostics::Diagnostics;
+use crate::errors::Error;
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
+
+/// Direction of a port in the generated module
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Input,
+    Output,
+}
+
+/// A concrete port in the generated SystemVerilog
+struct SvPort {
+    name: ast::Id,
+    width: u64,
+    dir: Dir,
+}
+
+impl SvPort {
+    fn new(name: ast::Id, width: u64, dir: Dir) -> Self {
+        Self { name, width, dir }
+    }
+
+    fn is_clk_or_reset(&self) -> bool {
+        INTERFACE_PORTS
+            .iter()
+            .any(|(_, (n, _, _))| *n == self.name.as_ref())
+    }
+}
+
+/// Compute the ports of a signature in the same order as the Calyx backend:
+/// inputs, interface ports, outputs, and unannotated ports. If `is_comp` is
+/// true, the `clk` and `reset` ports are added when missing.
+fn signature_ports(sig: &ast::Signature, is_comp: bool) -> Vec<SvPort> {
+    let width = |pd: &ast::PortDef| -> u64 {
+        pd.bitwidth().inner().try_into().unwrap_or_else(|_| {
+            panic!(
+                "Width of port `{}' in `{}' is not concrete: {}",
+                pd.name(),
+                sig.name,
+                pd.bitwidth()
+            )
+        })
+    };
+    let mut ports =
+        sig.inputs()
+            .map(|pd| SvPort::new(*pd.name().inner(), width(pd), Dir::Input))
+            .chain(
+                sig.interface_signals
+                    .iter()
+                    .map(|id| SvPort::new(*id.name.inner(), 1, Dir::Input)),
+            )
+            .chain(sig.outputs().map(|pd| {
+                SvPort::new(*pd.name().inner(), width(pd), Dir::Output)
+            }))
+            .chain(
+                sig.unannotated_ports
+                    .iter()
+                    .map(|(n, bw)| SvPort::new(*n, *bw, Dir::Input)),
+            )
+            .collect_vec();
+
+    if is_comp {
+        for (_, (name, width, _)) in INTERFACE_PORTS.iter() {
+            if !ports.iter().any(|p| p.name.as_ref() == *name) {
+                ports.push(SvPort::new((*name).into(), *width, Dir::Input));
+            }
+        }
+    }
+    ports
+}
+
+/// Declaration of a bitwidth: `[W-1:0] `
+fn range(width: u64) -> String {
+    if width == 1 {
+        String::new()
+    } else {
+        format!("[{}:0] ", width - 1)
+    }
+}
+
+/// Emit the module for the FSM with `states` states. The FSM is a shift
+/// register where state `_0` is the trigger and state `_n` is the trigger
+/// delayed by `n` cycles.
+fn emit_fsm(states: u64, out: &mut impl Write) -> io::Result<()> {
+    let outputs = (0..states)
+        .map(|n| format!("  output logic _{n}"))
+        .join(",\n");
+    writeln!(out, "module fsm_{states} (")?;
+    writeln!(out, "  input wire logic clk,")?;
+    writeln!(out, "  input wire logic reset,")?;
+    writeln!(out, "  input wire logic go,")?;
+    writeln!(out, "{outputs}")?;
+    writeln!(out, ");")?;
+    writeln!(out, "  assign _0 = go;")?;
+    if states > 1 {
+        writeln!(out, "  always_ff @(posedge clk) begin")?;
+        writeln!(out, "    if (reset) begin")?;
+        for n in 1..states {
+            writeln!(out, "      _{n} <= 1'b0;")?;
+        }
+        writeln!(out, "    end else begin")?;
+        for n in 1..states {
+            writeln!(out, "      _{n} <= _{};", n - 1)?;
+        }
+        writeln!(out, "    end")?;
+        writeln!(out, "  end")?;
+    }
+    writeln!(out, "endmodule")
+}
+
+/// Guarded drivers for a destination: `(guard, source)`
+type Drivers = Vec<(Option<String>, String)>;
+
+/// Context for emitting a single component
+struct CompCtx<'a> {
+    /// Signatures of all components and externals and whether they are
+    /// components
+    sigs: &'a HashMap<ast::Id, (&'a ast::Signature, bool)>,
+    /// Mapping from FSM names to the FSMs
+    fsms: HashMap<ast::Id, &'a ast::Fsm>,
+    /// Mapping from invocations to instances
+    invokes: HashMap<ast::Id, ast::Id>,
+    /// Names defined in the module
+    names: HashSet<String>,
+    /// Wires connected to the ports of instances and the states of FSMs
+    wires: HashMap<(ast::Id, ast::Id), String>,
+    /// Input wires of instances
+    inst_inputs: Vec<String>,
+    /// Drivers for each destination in the order they were defined
+    drivers: Vec<(String, Drivers)>,
+    /// Errors found while compiling the component
+    diag: &'a mut Diagnostics,
+}
+
+impl<'a> CompCtx<'a> {
+    fn new(
+        sigs: &'a HashMap<ast::Id, (&'a ast::Signature, bool)>,
+        diag: &'a mut Diagnostics,
+    ) -> Self {
+        Self {
+            sigs,
+            fsms: HashMap::new(),
+            invokes: HashMap::new(),
+            names: HashSet::new(),
+            wires: HashMap::new(),
+            inst_inputs: Vec::new(),
+            drivers: Vec::new(),
+            diag,
+        }
+    }
+
+    /// Define a wire for the port of an instance or the state of an FSM
+    /// named `{owner}_{port}`. If the name is already defined in the module,
+    /// the first free name `{owner}_{port}_{n}` is used instead.
+    fn define_wire(&mut self, owner: ast::Id, port: ast::Id) -> String {
+        let base = format!("{owner}_{port}");
+        let mut name = base.clone();
+        let mut n = 0;
+        while self.names.contains(&name) {
+            name = format!("{base}_{n}");
+            n += 1;
+        }
+        self.names.insert(name.clone());
+        self.wires.insert((owner, port), name.clone());
+        name
+    }
+
+    /// Name of the wire defined for the port of an instance or the state of
+    /// an FSM
+    fn wire(&self, owner: &ast::Id, port: &ast::Id) -> String {
+        self.wires[&(*owner, *port)].clone()
+    }
+
+    /// Name of the wire for a port of an invocation. Reports an error if the
+    /// invocation is not defined.
+    fn inv_port(
+        &mut self,
+        invoke: &ast::Loc<ast::Id>,
+        port: &ast::Id,
+    ) -> String {
+        if let Some(inst) = self.invokes.get(invoke.inner()) {
+            self.wire(inst, port)
+        } else {
+            let err = Error::undefined(*invoke.inner(), "invocation").add_note(
+                self.diag
+                    .add_info("invocation is not defined", invoke.pos()),
+            );
+            self.diag.add_error(err);
+            "'x".to_string()
+        }
+    }
+
+    /// Compile a port into an expression. If the port is a state of an FSM,
+    /// returns the guard implied by it.
+    fn port(&mut self, port: &ast::Port) -> (String, Option<String>) {
+        match port {
+            ast::Port::This(p) => (p.to_string(), None),
+            ast::Port::InvPort { invoke, name } => {
+                if let Some(fsm) = self.fsms.get(invoke.inner()) {
+                    ("1'b1".to_string(), Some(self.wire(&fsm.name, name)))
+                } else {
+                    (self.inv_port(invoke, name), None)
+                }
+            }
+            ast::Port::Constant(c) => (c.to_string(), None),
+            ast::Port::Bundle { .. } | ast::Port::InvBundle { .. } => {
+                unreachable!("Bundles should be compiled away")
+            }
+        }
+    }
+
+    /// Compile a guard operand. Disjunctions bind more loosely than the
+    /// other operators so they need to be parenthesized.
+    fn guard_operand(&mut self, guard: &ast::Guard, not: bool) -> String {
+        match guard {
+            ast::Guard::Port(_) => self.guard(guard),
+            ast::Guard::And(..) | ast::Guard::Not(..) if !not => {
+                self.guard(guard)
+            }
+            _ => format!("({})", self.guard(guard)),
+        }
+    }
+
+    fn guard(&mut self, guard: &ast::Guard) -> String {
+        match guard {
+            ast::Guard::Or(g1, g2, _) => {
+                format!("{} | {}", self.guard(g1), self.guard(g2))
+            }
+            ast::Guard::And(g1, g2, _) => format!(
+                "{} & {}",
+                self.guard_operand(g1, false),
+                self.guard_operand(g2, false)
+            ),
+            ast::Guard::Not(g, _) => {
+                format!("!{}", self.guard_operand(g, true))
+            }
+            ast::Guard::Port(p) => match p.inner() {
+                ast::Port::This(p) => p.to_string(),
+                ast::Port::InvPort { invoke, name } => {
+                    if let Some(fsm) = self.fsms.get(invoke.inner()) {
+                        self.wire(&fsm.name, name)
+                    } else {
+                        self.inv_port(invoke, name)
+                    }
+                }
+                ast::Port::Constant(_) => {
+                    unreachable!("Constants cannot be in guards")
+                }
+                ast::Port::Bundle { .. } | ast::Port::InvBundle { .. } => {
+                    unreachable!("Bundles should be compiled away")
+                }
+            },
+        }
+    }
+
+    fn connect(&mut self, con: &ast::Connect) {
+        let ast::Connect {
+            dst, src, guard, ..
+        } = con;
+        let (dst, g) = self.port(dst);
+        assert!(g.is_none(), "Destination has a guard");
+        let (src_wire, g) = self.port(src);
+        let guard = match (guard, g) {
+            (None, g) => g,
+            (Some(g), None) => Some(self.guard(g)),
+            (Some(_), Some(_)) => {
+                let msg = "guarded connection from the state of an FSM";
+                let err =
+                    Error::malformed(msg).add_note(self.diag.add_info(
+                        "FSM state already implies a guard",
+                        src.pos(),
+                    ));
+                self.diag.add_error(err);
+                None
+            }
+        };
+        if let Some((_, drivers)) =
+            self.drivers.iter_mut().find(|(d, _)| *d == dst)
+        {
+            drivers.push((guard, src_wire));
+        } else {
+            self.drivers.push((dst, vec![(guard, src_wire)]));
+        }
+    }
+
+    fn emit_instance(
+        &mut self,
+        inst: &ast::Instance,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let ast::Instance {
+            name,
+            component,
+            bindings,
+        } = inst;
+        let (sig, is_comp) = self
+            .sigs
+            .get(component.inner())
+            .unwrap_or_else(|| panic!("Unknown component: {component}"));
+        let args = bindings.iter().map(|b| b.inner().clone()).collect_vec();
+        let binding = sig.param_binding(args.clone());
+        let params = sig
+            .params()
+            .map(|p| format!(".{p}({})", binding.get(p.inner())))
+            .collect_vec();
+        let sig = (*sig).clone().resolve_exprs(args);
+        let ports = signature_ports(&sig, *is_comp);
+
+        // Wires for each port of the instance
+        for port in ports.iter().filter(|p| !p.is_clk_or_reset()) {
+            let wire = self.define_wire(*name.inner(), port.name);
+            writeln!(out, "  logic {}{wire};", range(port.width))?;
+            if port.dir == Dir::Input {
+                self.inst_inputs.push(wire);
+            }
+        }
+
+        let conns = ports
+            .iter()
+            .map(|p| {
+                if p.is_clk_or_reset() {
+                    format!("    .{}({})", p.name, p.name)
+                } else {
+                    format!("    .{}({})", p.name, self.wire(name, &p.name))
+                }
+            })
+            .join(",\n");
+        if params.is_empty() {
+            writeln!(out, "  {component} {name} (")?;
+        } else {
+            writeln!(out, "  {component} #({}) {name} (", params.join(", "))?;
+        }
+        writeln!(out, "{conns}")?;
+        writeln!(out, "  );")
+    }
+
+    fn emit_fsm(
+        &mut self,
+        fsm: &ast::Fsm,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let ast::Fsm {
+            name,
+            states,
+            trigger,
+        } = fsm;
+        let (trigger, g) = self.port(trigger);
+        assert!(g.is_none(), "Trigger port implies guard");
+        let states = (0..*states)
+            .map(|n| self.define_wire(*name, format!("_{n}").into()))
+            .collect_vec();
+        writeln!(out, "  logic {};", states.join(", "))?;
+        let conns = states
+            .iter()
+            .enumerate()
+            .map(|(n, st)| format!("    ._{n}({st})"))
+            .join(",\n");
+        writeln!(out, "  fsm_{} {name} (", fsm.states)?;
+        writeln!(out, "    .clk(clk),")?;
+        writeln!(out, "    .reset(reset),")?;
+        writeln!(out, "    .go({trigger}),")?;
+        writeln!(out, "{conns}")?;
+        writeln!(out, "  );")
+    }
+
+    /// Emit the assignments. Destinations with a single unguarded driver
+    /// become continuous assignments while the rest become `always_comb`
+    /// blocks that default to zero.
+    fn emit_drivers(&self, out: &mut impl Write) -> io::Result<()> {
+        for (dst, drivers) in &self.drivers {
+            if let [(None, src)] = drivers.as_slice() {
+                writeln!(out, "  assign {dst} = {src};")?;
+                continue;
+            }
+            writeln!(out, "  always_comb begin")?;
+            writeln!(out, "    {dst} = '0;")?;
+            for (idx, (guard, src)) in drivers.iter().enumerate() {
+                let kw = if idx == 0 { "if" } else { "else if" };
+                let guard = guard.as_deref().unwrap_or("1'b1");
+                writeln!(out, "    {kw} ({guard}) {dst} = {src};")?;
+            }
+            writeln!(out, "  end")?;
+        }
+        Ok(())
+    }
+}
+
+fn emit_component(
+    comp: &ast::Component,
+    sigs: &HashMap<ast::Id, (&ast::Signature, bool)>,
+    diag: &mut Diagnostics,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let sig_ports = signature_ports(&comp.sig, true);
+    let ports = sig_ports
+        .iter()
+        .map(|p| {
+            let dir = match p.dir {
+                Dir::Input => "input wire logic",
+                Dir::Output => "output logic",
+            };
+            format!("  {dir} {}{}", range(p.width), p.name)
+        })
+        .join(",\n");
+    writeln!(out, "module {} (", comp.sig.name)?;
+    writeln!(out, "{ports}")?;
+    writeln!(out, ");")?;
+
+    // Wires are named after the instances and FSMs they belong to so the
+    // names of the ports, instances, and FSMs must be known up front.
+    let mut ctx = CompCtx::new(sigs, diag);
+    ctx.names
+        .extend(sig_ports.iter().map(|p| p.name.to_string()));
+    ctx.names
+        .extend(comp.fsms.iter().map(|f| f.name.to_string()));
+    ctx.names
+        .extend(comp.body.iter().filter_map(|cmd| match cmd {
+            ast::Command::Instance(inst) => Some(inst.name.to_string()),
+            _ => None,
+        }));
+    for fsm in &comp.fsms {
+        ctx.fsms.insert(fsm.name, fsm);
+        ctx.emit_fsm(fsm, out)?;
+    }
+
+    let mut cons = vec![];
+    for cmd in &comp.body {
+        match cmd {
+            ast::Command::Invoke(ast::Invoke {
+                name,
+                instance,
+                ports,
+                ..
+            }) => {
+                assert!(
+                    ports.is_none(),
+                    "Cannot compile high-level invoke statements"
+                );
+                ctx.invokes.insert(*name.inner(), *instance.inner());
+            }
+            ast::Command::Instance(inst) => ctx.emit_instance(inst, out)?,
+            ast::Command::Connect(con) => cons.push(con),
+            ast::Command::ForLoop(_) => {
+                unreachable!("Loop should have been compiled away.")
+            }
+            ast::Command::Bundle(_) => {
+                unreachable!("Bundles should have been compiled away.")
+            }
+            ast::Command::If(_) => {
+                unreachable!("If should have been compiled away.")
+            }
+            ast::Command::Let(_) => {
+                unreachable!("Let should have been compiled away.")
+            }
+            ast::Command::Fact(a) => {
+                unreachable!("Assumption `{a}' should have been compiled away.")
+            }
+        }
+    }
+    // Compile connects once all invocations are known
+    cons.into_iter().for_each(|con| ctx.connect(con));
+    ctx.emit_drivers(out)?;
+
+    // Drive undriven inputs of instances and outputs of the component to zero
+    let driven: HashSet<_> = ctx.drivers.iter().map(|(d, _)| d).collect();
+    let undriven = ctx
+        .inst_inputs
+        .iter()
+        .cloned()
+        .chain(comp.sig.outputs().map(|pd| pd.name().to_string()));
+    for wire in undriven {
+        if !driven.contains(&wire) {
+            writeln!(out, "  assign {wire} = '0;")?;
+        }
+    }
+    writeln!(out, "endmodule")
+}
+
+/// Emit SystemVerilog for a lowered namespace. The extern modules are
+/// included from the files they are defined in. Nothing is emitted if the
+/// program cannot be compiled.
+pub fn compile(
+    ns: ast::Namespace,
+    format: DiagnosticsFormat,
+) -> Result<(), u64> {
+    let mut diag = Diagnostics::new(format);
+    let mut out = Vec::new();
+    emit(&ns, &mut diag, &mut out).unwrap();
+    if let Some(errs) = diag.report_all() {
+        return Err(errs);
+    }
+    io::stdout().write_all(&out).unwrap();
+    Ok(())
+}
+
+fn emit(
+    ns: &ast::Namespace,
+    diag: &mut Diagnostics,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for file in ns.externs.iter().map(|(file, _)| file).unique() {
+        writeln!(out, "`include \"{file}\"")?;
+    }
+    writeln!(out)?;
+
+    let sigs: HashMap<_, _> = ns
+        .externals()
+        .map(|(name, sig)| (name, (sig, false)))
+        .chain(
+            ns.components
+                .iter()
+                .map(|c| (*c.sig.name.inner(), (&c.sig, true))),
+        )
+        .collect();
+    for comp in &ns.components {
+        emit_component(comp, &sigs, diag, out)?;
+        writeln!(out)?;
+    }
+
+    let states: BTreeSet<_> = ns
+        .components
+        .iter()
+        .flat_map(|c| c.fsms.iter().map(|f| f.states))
+        .collect();
+    for n in states {
+        emit_fsm(n, out)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
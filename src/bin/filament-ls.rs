@@ -0,0 +1,15 @@
+use argh::FromArgs;
+use std::path::PathBuf;
+
+#[derive(FromArgs)]
+/// Language server for Filament. Communicates over stdin and stdout.
+struct Opts {
+    /// path to search for imports
+    #[argh(option, long = "library", short = 'l', default = "\".\".into()")]
+    library: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let opts: Opts = argh::from_env();
+    filament::lsp::run(opts.library)
+}
@@ -190,7 +190,9 @@ impl BoundComponent {
                     self.process_cmds(prog, &if_.then);
                     self.process_cmds(prog, &if_.alt);
                 }
-                ast::Command::Connect(_) | ast::Command::Fact(_) => (),
+                ast::Command::Connect(_)
+                | ast::Command::Fact(_)
+                | ast::Command::Let(_) => (),
             }
         }
     }
@@ -328,7 +330,7 @@ impl BoundComponent {
                 ast::Command::Bundle(bl) => {
                     self.add_bundle(bl.clone());
                 }
-                ast::Command::Fact(_) => (),
+                ast::Command::Fact(_) | ast::Command::Let(_) => (),
             }
         }
     }
@@ -437,14 +439,12 @@ impl<'c, 'p> CompBinding<'c, 'p> {
         })
     }
 
-    fn bundle_access(mut bun: ast::Bundle, acc: &ast::Access) -> ast::PortDef {
-        match acc {
-            ast::Access::Index(idx) => bun.index(idx.clone()),
-            ast::Access::Range { start, end } => {
-                bun.typ = bun.typ.shrink(start.clone(), end.clone());
-                bun.into()
-            }
-        }
+    fn bundle_access(
+        bun: &ast::Bundle,
+        acc: &[Loc<ast::Access>],
+    ) -> ast::PortDef {
+        let acc = acc.iter().map(|a| a.inner().clone()).collect_vec();
+        bun.access(&acc)
     }
 
     /// Returns a resolved port definition for the given port.
@@ -457,7 +457,7 @@ impl<'c, 'p> CompBinding<'c, 'p> {
             }
             ast::Port::Bundle { name, access, .. } => {
                 let bi = self.get_bundle_idx(name);
-                Some(Self::bundle_access(self[bi].clone(), access.inner()))
+                Some(Self::bundle_access(&self[bi], access))
             }
             ast::Port::Constant(_) => None,
             ast::Port::InvBundle {
@@ -470,7 +470,7 @@ impl<'c, 'p> CompBinding<'c, 'p> {
                 let ast::PortDef::Bundle(bun) = port else {
                     unreachable!("Expected bundle port, received: `{port}'")
                 };
-                Some(Self::bundle_access(bun, access))
+                Some(Self::bundle_access(&bun, access))
             }
         }
     }
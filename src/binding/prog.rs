@@ -1,5 +1,6 @@
 use crate::{
     ast::{self, Id},
+    cmdline::DiagnosticsFormat,
     diagnostics,
     errors::Error,
     idx,
@@ -62,13 +63,13 @@ impl<'a> ProgBinding<'a> {
         diag: &mut diagnostics::Diagnostics,
     ) -> SigIdx {
         if let Some(old_sig) = self.is_bound(&sig.name) {
-            let err = Error::already_bound(*sig.name.inner(), "signature")
+            let err = Error::already_bound(*sig.name.inner(), "component")
                 .add_note(diag.add_info(
-                    "signature with same name is already bound",
+                    "component with the same name is already defined",
                     sig.name.pos(),
                 ))
                 .add_note(
-                    diag.add_info("previous definition", old_sig.name.pos()),
+                    diag.add_info("conflicting definition", old_sig.name.pos()),
                 );
             diag.add_error(err);
             self.get_sig_idx(&sig.name)
@@ -102,12 +103,23 @@ impl<'a> TryFrom<&'a ast::Namespace> for ProgBinding<'a> {
     type Error = u64;
 
     fn try_from(ns: &'a ast::Namespace) -> Result<Self, Self::Error> {
+        Self::new(ns, DiagnosticsFormat::default())
+    }
+}
+
+impl<'a> ProgBinding<'a> {
+    /// Construct a binding for the namespace. Ambiguous component names are
+    /// reported in the given format.
+    pub fn new(
+        ns: &'a ast::Namespace,
+        format: DiagnosticsFormat,
+    ) -> Result<Self, u64> {
         let mut ctx = ProgBinding {
             signatures: Vec::new(),
             comps: Vec::with_capacity(ns.components.len()),
             name_map: HashMap::new(),
         };
-        let mut diag = diagnostics::Diagnostics::default();
+        let mut diag = diagnostics::Diagnostics::new(format);
         // Add component signatures first so that we can index comps vector using the same
         // index as the signature.
         ns.components.iter().for_each(|c| {
@@ -119,6 +131,10 @@ impl<'a> TryFrom<&'a ast::Namespace> for ProgBinding<'a> {
             .for_each(|c| {
                 ctx.add_sig(c, &mut diag);
             });
+        // Components cannot be bound if their names are ambiguous
+        if let Some(errs) = diag.report_all() {
+            return Err(errs);
+        }
 
         // Build and add bindings for components
         ns.components.iter().for_each(|c| {
@@ -1,5 +1,92 @@
 use argh::FromArgs;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Backend used to generate hardware from the program
+pub enum Backend {
+    /// Generate Calyx
+    Calyx,
+    /// Generate SystemVerilog
+    Verilog,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "calyx" => Ok(Backend::Calyx),
+            "verilog" => Ok(Backend::Verilog),
+            _ => Err(format!(
+                "unknown backend `{s}', expected `calyx' or `verilog'"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// SMT solver used to discharge constraints
+pub enum Solver {
+    /// Z3
+    Z3,
+    /// cvc5
+    Cvc5,
+    /// Yices 2
+    Yices,
+}
+
+impl FromStr for Solver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "z3" => Ok(Solver::Z3),
+            "cvc5" => Ok(Solver::Cvc5),
+            "yices" | "yices2" => Ok(Solver::Yices),
+            "bitwuzla" => Err(
+                "bitwuzla does not support integer arithmetic which is required to encode Filament constraints".to_string(),
+            ),
+            _ => Err(format!(
+                "unknown solver `{s}', expected `z3', `cvc5', or `yices'"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Solver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Solver::Z3 => write!(f, "z3"),
+            Solver::Cvc5 => write!(f, "cvc5"),
+            Solver::Yices => write!(f, "yices"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Format used to report diagnostics
+pub enum DiagnosticsFormat {
+    /// Human-readable text
+    #[default]
+    Human,
+    /// One JSON object per diagnostic
+    Json,
+}
+
+impl FromStr for DiagnosticsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(DiagnosticsFormat::Human),
+            "json" => Ok(DiagnosticsFormat::Json),
+            _ => Err(format!(
+                "unknown diagnostics format `{s}', expected `human' or `json'"
+            )),
+        }
+    }
+}
 
 #[derive(FromArgs, Debug)]
 /// The Filament pipeline verifier
@@ -16,7 +103,7 @@ pub struct Opts {
     #[argh(switch, long = "show-ir")]
     pub show_ir: bool,
 
-    /// print out assignments that falisfy the constraints
+    /// print out assignments that falsify the constraints and explain them
     #[argh(switch, long = "show-models")]
     pub show_models: bool,
 
@@ -24,19 +111,91 @@ pub struct Opts {
     #[argh(option, long = "library", short = 'l', default = "\".\".into()")]
     pub library: PathBuf,
 
+    /// number of solver processes used to discharge constraints. Defaults
+    /// to the number of available cores.
+    #[argh(option, long = "solver-jobs")]
+    pub solver_jobs: Option<usize>,
+
+    /// file used to cache proven constraints across runs
+    #[argh(option, long = "solver-cache")]
+    pub solver_cache: Option<PathBuf>,
+
+    /// directory used to cache the interfaces of imported files that have
+    /// been checked. Only used with --check
+    #[argh(option, long = "interface-cache")]
+    pub interface_cache: Option<PathBuf>,
+
+    /// SMT solver used to discharge constraints: z3, cvc5, or yices
+    #[argh(option, long = "solver", default = "Solver::Z3")]
+    pub solver: Solver,
+
+    /// additional command-line option passed to the solver. Can be repeated.
+    #[argh(option, long = "solver-opt")]
+    pub solver_opts: Vec<String>,
+
+    /// timeout for each solver query in milliseconds
+    #[argh(option, long = "solver-timeout", default = "30000")]
+    pub solver_timeout: u64,
+
+    /// skip proving the timing constraints of the program. The generated
+    /// design may read ports outside their availability.
+    #[argh(switch, long = "unsafe-skip-discharge")]
+    pub unsafe_skip_discharge: bool,
+
+    /// directory to write the queries sent to the solver for each component
+    #[argh(option, long = "dump-smt")]
+    pub dump_smt: Option<PathBuf>,
+
     /// only check the program without compilation.
     #[argh(switch, short = 'c', long = "check")]
     pub check: bool,
 
-    /// output the interface.json for the input program
+    /// output the interface.json for the top-level component
     #[argh(switch, long = "dump-interface")]
     pub dump_interface: bool,
 
+    /// output the interfaces of all components as a JSON array
+    #[argh(switch, long = "dump-all-interfaces")]
+    pub dump_all_interfaces: bool,
+
+    /// format used to report diagnostics: human or json
+    #[argh(
+        option,
+        long = "diagnostics-format",
+        default = "DiagnosticsFormat::Human"
+    )]
+    pub diagnostics_format: DiagnosticsFormat,
+
     /// set log level
     #[argh(option, long = "log", default = "log::LevelFilter::Warn")]
     pub log_level: log::LevelFilter,
 
+    /// backend used for code generation: calyx or verilog
+    #[argh(option, long = "backend", default = "Backend::Calyx")]
+    pub backend: Backend,
+
+    /// simulate the program using the inputs in the given data file
+    #[argh(option, long = "simulate")]
+    pub simulate: Option<PathBuf>,
+
+    /// write a waveform of the simulation to the given VCD file
+    #[argh(option, long = "vcd")]
+    pub vcd: Option<PathBuf>,
+
     /// set toplevel
     #[argh(option, long = "toplevel", default = "\"main\".into()")]
     pub toplevel: String,
 }
+
+#[derive(FromArgs, Debug)]
+/// Format Filament programs in the canonical style. Reads the program from
+/// stdin and prints the formatted program if no files are given.
+pub struct FmtOpts {
+    /// files to format in place
+    #[argh(positional)]
+    pub files: Vec<PathBuf>,
+
+    /// check that the files are formatted instead of formatting them
+    #[argh(switch, long = "check")]
+    pub check: bool,
+}
@@ -0,0 +1,30 @@
+//! Capturing diagnostics instead of reporting them.
+use codespan_reporting::diagnostic::Diagnostic;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Diagnostics captured on this thread, if capturing is enabled
+    static CAPTURED: RefCell<Option<Vec<Diagnostic<usize>>>> =
+        const { RefCell::new(None) };
+}
+
+/// Run `f` and return the diagnostics reported while it runs instead of
+/// printing them.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<Diagnostic<usize>>) {
+    let prev = CAPTURED.with(|c| c.replace(Some(vec![])));
+    let out = f();
+    let diags = CAPTURED.with(|c| c.replace(prev)).unwrap_or_default();
+    (out, diags)
+}
+
+/// Record the diagnostic if diagnostics are being captured.
+/// Returns `false` if the diagnostic should be reported instead.
+pub fn record(diag: &Diagnostic<usize>) -> bool {
+    CAPTURED.with(|c| match &mut *c.borrow_mut() {
+        Some(diags) => {
+            diags.push(diag.clone());
+            true
+        }
+        None => false,
+    })
+}
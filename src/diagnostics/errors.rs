@@ -5,6 +5,8 @@ use itertools::Itertools;
 
 #[derive(PartialEq, Eq, Hash)]
 pub struct Error {
+    /// Stable code identifying the kind of error
+    pub code: &'static str,
     pub kind: String,
     pub notes: Vec<InfoIdx>,
 }
@@ -32,6 +34,7 @@ impl Error {
 
     pub fn parse_error(err: pest_consume::Error<frontend::Rule>) -> Self {
         Self {
+            code: "parse",
             kind: format!("error while parsing: {}", err),
             notes: vec![],
         }
@@ -39,6 +42,7 @@ impl Error {
 
     pub fn invalid_file(f: String) -> Self {
         Self {
+            code: "invalid-file",
             kind: format!("invalid file: {}", f),
             notes: vec![],
         }
@@ -46,6 +50,7 @@ impl Error {
 
     pub fn write_error(e: String) -> Self {
         Self {
+            code: "write",
             kind: format!("failed to write output: {}", e),
             notes: vec![],
         }
@@ -53,6 +58,7 @@ impl Error {
 
     pub fn malformed<S: ToString>(msg: S) -> Self {
         Self {
+            code: "malformed",
             kind: msg.to_string(),
             notes: vec![],
         }
@@ -60,6 +66,7 @@ impl Error {
 
     pub fn undefined<S: ToString>(name: Id, kind: S) -> Self {
         Self {
+            code: "undefined",
             kind: format!(
                 "undefined {kind} name: {name}",
                 kind = kind.to_string()
@@ -70,6 +77,7 @@ impl Error {
 
     pub fn already_bound<S: ToString>(name: Id, kind: S) -> Self {
         Self {
+            code: "already-bound",
             kind: format!(
                 "name `{name}' is already bound by {}",
                 kind.to_string()
@@ -80,10 +88,20 @@ impl Error {
 
     pub fn misc(msg: String) -> Self {
         Self {
+            code: "misc",
             kind: msg,
             notes: vec![],
         }
     }
+
+    /// A constraint that could not be proven
+    pub fn constraint<S: ToString>(msg: S) -> Self {
+        Self {
+            code: "constraint",
+            kind: msg.to_string(),
+            notes: vec![],
+        }
+    }
 }
 
 /// Convience wrapper to represent success or meaningul compiler error.
@@ -0,0 +1,51 @@
+//! Machine-readable reporting of diagnostics.
+use crate::utils::GlobalPositionTable;
+use codespan_reporting::diagnostic::{Diagnostic, Label, LabelStyle};
+use codespan_reporting::files::Files;
+use serde_json::{json, Value};
+
+/// A label as a JSON object containing the file, the line and column of the
+/// start and end of the span, and the message.
+fn label(label: &Label<usize>) -> Value {
+    let files = GlobalPositionTable::as_ref().files();
+    let loc = |idx: usize| {
+        files
+            .location(label.file_id, idx)
+            .map(|l| json!({"line": l.line_number, "column": l.column_number}))
+            .unwrap_or(Value::Null)
+    };
+    json!({
+        "file": files.name(label.file_id).ok(),
+        "start": loc(label.range.start),
+        "end": loc(label.range.end),
+        "message": label.message,
+    })
+}
+
+/// Emit a diagnostic as a single line of JSON on stderr:
+/// ```json
+/// {"kind":"...","message":"...","model":"...","notes":[...],"primary":{...},
+///  "secondary":[...],"severity":"error"}
+/// ```
+/// `kind` is a stable code identifying the kind of diagnostic. The first
+/// primary label is reported as the primary span and all other labels as
+/// secondary spans. `model` is the counterexample for the error, if any.
+pub fn emit(diag: &Diagnostic<usize>, kind: &str, model: Option<&str>) {
+    let (primary, secondary): (Vec<_>, Vec<_>) = diag
+        .labels
+        .iter()
+        .partition(|l| l.style == LabelStyle::Primary);
+    let mut primary = primary.into_iter();
+    let first = primary.next().map(label);
+    let secondary: Vec<_> = primary.chain(secondary).map(label).collect();
+    let out = json!({
+        "severity": format!("{:?}", diag.severity).to_lowercase(),
+        "kind": kind,
+        "message": diag.message,
+        "primary": first,
+        "secondary": secondary,
+        "notes": diag.notes,
+        "model": model,
+    });
+    eprintln!("{out}");
+}
@@ -1,4 +1,8 @@
+mod capture;
 pub mod errors;
+mod json;
 mod reporter;
 
+pub use capture::capture;
+pub use json::emit as emit_json;
 pub use reporter::{Diagnostics, InfoIdx};
@@ -1,4 +1,6 @@
+use super::{capture, json};
 use crate::{
+    cmdline::DiagnosticsFormat,
     errors::Error,
     utils::{GPosIdx, GlobalPositionTable},
 };
@@ -21,11 +23,17 @@ pub struct InfoIdx(usize);
 struct Information {
     message: String,
     pos: GPosIdx,
+    /// Is this a counterexample generated by the solver?
+    model: bool,
 }
 
 impl Information {
     fn new(message: String, pos: GPosIdx) -> Self {
-        Self { message, pos }
+        Self {
+            message,
+            pos,
+            model: false,
+        }
     }
 }
 
@@ -36,9 +44,25 @@ pub struct Diagnostics {
     infos: Vec<Information>,
     /// Errors that have been reported.
     errors: Vec<Error>,
+    /// Format used to report the errors.
+    format: DiagnosticsFormat,
 }
 
 impl Diagnostics {
+    /// Track diagnostics that are reported in the given format
+    pub fn new(format: DiagnosticsFormat) -> Self {
+        Self {
+            infos: Vec::new(),
+            errors: Vec::new(),
+            format,
+        }
+    }
+
+    /// Are the errors reported as JSON?
+    pub fn is_json(&self) -> bool {
+        self.format == DiagnosticsFormat::Json
+    }
+
     fn add_info_help(&mut self, info: Information) -> InfoIdx {
         if let Some(idx) = self.infos.iter().position(|i| *i == info) {
             InfoIdx(idx)
@@ -72,6 +96,14 @@ impl Diagnostics {
         ))
     }
 
+    /// Add a counterexample generated by the solver.
+    pub fn add_model<S: ToString>(&mut self, model: S) -> InfoIdx {
+        self.add_info_help(Information {
+            model: true,
+            ..Information::new(model.to_string(), GPosIdx::UNKNOWN)
+        })
+    }
+
     /// Add an error to the diagnostics instance.
     // XXX: Make this add a new information object so that its easy to express
     // the "create error and add info" pattern.
@@ -82,15 +114,40 @@ impl Diagnostics {
         }
     }
 
-    /// Report all errors and return the number of errors.
-    /// Returns None if there are no errors.
-    pub fn report_all(&mut self) -> Option<u64> {
+    /// Report a single diagnostic identified by the stable `code`. `model` is
+    /// the counterexample for the diagnostic which is only reported on its
+    /// own in JSON; human-readable diagnostics should mention it in a note.
+    pub fn emit(
+        &self,
+        diag: &Diagnostic<usize>,
+        code: &str,
+        model: Option<&str>,
+    ) {
+        if capture::record(diag) {
+            return;
+        }
+        if self.is_json() {
+            json::emit(diag, code, model);
+            return;
+        }
         let is_tty = atty::is(atty::Stream::Stderr);
         let writer = StandardStream::stderr(if is_tty {
             ColorChoice::Always
         } else {
             ColorChoice::Never
         });
+        term::emit(
+            &mut writer.lock(),
+            &term::Config::default(),
+            GlobalPositionTable::as_ref().files(),
+            diag,
+        )
+        .unwrap();
+    }
+
+    /// Report all errors and return the number of errors.
+    /// Returns None if there are no errors.
+    pub fn report_all(&mut self) -> Option<u64> {
         if self.errors.is_empty() {
             return None;
         }
@@ -114,16 +171,19 @@ impl Diagnostics {
             error_map
                 .entry(error.notes)
                 .or_insert_with(Vec::new)
-                .push(error.kind);
+                .push((error.code, error.kind));
         }
 
         let table = GlobalPositionTable::as_ref();
         for (all_notes, errors) in error_map {
             let mut labels = vec![];
             let mut notes = vec![];
+            let mut models = vec![];
             for (idx, info) in all_notes.iter().enumerate() {
                 let info = &self.infos[info.0];
-                if let Some(p) = info.pos.into_option() {
+                if info.model && self.is_json() {
+                    models.push(info.message.clone());
+                } else if let Some(p) = info.pos.into_option() {
                     let pos = table.get_pos(p.0);
                     let l = Label::new(
                         if idx == 0 {
@@ -140,24 +200,20 @@ impl Diagnostics {
                 }
             }
 
-            let msg = if errors.len() > 1 {
-                notes.extend(errors.iter().map(|e| e.to_string()));
-                "Multiple errors encountered".to_string()
+            let (code, msg) = if errors.len() > 1 {
+                notes.extend(errors.iter().map(|(_, e)| e.to_string()));
+                ("multiple", "Multiple errors encountered".to_string())
             } else {
-                errors[0].to_string()
+                (errors[0].0, errors[0].1.to_string())
             };
 
             total += 1;
-            term::emit(
-                &mut writer.lock(),
-                &term::Config::default(),
-                table.files(),
-                &Diagnostic::error()
-                    .with_message(msg)
-                    .with_labels(labels)
-                    .with_notes(notes),
-            )
-            .unwrap();
+            let diag = Diagnostic::error()
+                .with_message(msg)
+                .with_labels(labels)
+                .with_notes(notes);
+            let model = models.join("\n");
+            self.emit(&diag, code, (!models.is_empty()).then_some(&model));
         }
 
         Some(total)
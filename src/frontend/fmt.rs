@@ -0,0 +1,755 @@
+//! Canonical formatting of Filament programs.
+//!
+//! The formatter prints the parse tree of the program instead of the AST so
+//! that the program is reproduced exactly as written. Comments are not part of
+//! the parse tree and are instead attached to the closest line in the output:
+//! a comment on the same line as the end of a command or port stays on that
+//! line while all other comments are printed on their own line before the next
+//! command, port, or closing brace. A single blank line between commands is
+//! preserved.
+use super::{FilamentParser, Rule};
+use itertools::Itertools;
+use pest::iterators::Pair;
+use pest_consume::{Error, Parser};
+use std::path::Path;
+
+/// Indentation used for each level of nesting
+const INDENT: &str = "    ";
+
+/// Format the program in `source` in the canonical style. `path` is only used
+/// for reporting errors.
+#[allow(clippy::result_large_err)]
+pub fn format(path: &Path, source: &str) -> Result<String, Error<Rule>> {
+    let file = <FilamentParser as Parser>::parse(Rule::file, source)
+        .map_err(|e| e.with_path(&path.to_string_lossy()))?
+        .single()?
+        .into_pair();
+    let mut printer = Printer::new(source);
+    printer.file(file);
+    Ok(printer.out)
+}
+
+/// A comment in the source
+struct Comment {
+    start: usize,
+    end: usize,
+}
+
+/// Find all the comments in the source
+fn comments(src: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut idx = 0;
+    while idx < src.len() {
+        let rest = &src[idx..];
+        if let Some(lit) = rest.strip_prefix('"') {
+            // Skip string literals
+            idx += lit.find('"').map(|e| e + 2).unwrap_or(rest.len());
+        } else if rest.starts_with("//") {
+            let end = idx + rest.find('\n').unwrap_or(rest.len());
+            comments.push(Comment { start: idx, end });
+            idx = end;
+        } else if rest.starts_with("/*") {
+            let end =
+                idx + rest.find("*/").map(|e| e + 2).unwrap_or(rest.len());
+            comments.push(Comment { start: idx, end });
+            idx = end;
+        } else {
+            idx += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    comments
+}
+
+struct Printer<'a> {
+    src: &'a str,
+    comments: Vec<Comment>,
+    /// Index of the next comment to be printed
+    next: usize,
+    /// End of the source of the last printed line
+    last: usize,
+    /// Current indentation level
+    indent: usize,
+    /// Suppress blank lines before the next line. Set at the start of blocks.
+    no_blank: bool,
+    /// Print a blank line before the next line. Set between top-level
+    /// definitions.
+    blank: bool,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            comments: comments(src),
+            next: 0,
+            last: 0,
+            indent: 0,
+            no_blank: true,
+            blank: false,
+            out: String::new(),
+        }
+    }
+
+    /// Start a new line in the output for source starting at `start`,
+    /// adding a blank line if required.
+    fn start_line(&mut self, start: usize) {
+        let gap = &self.src[self.last.min(start)..start];
+        if !self.no_blank && (self.blank || gap.matches('\n').count() > 1) {
+            self.out.push('\n');
+        }
+        self.blank = false;
+        self.no_blank = false;
+        self.out.push_str(&INDENT.repeat(self.indent));
+    }
+
+    /// Print all comments that start before `pos` on their own lines
+    fn comments_before(&mut self, pos: usize) {
+        while let Some(c) = self.comments.get(self.next) {
+            if c.start >= pos {
+                break;
+            }
+            let (start, end) = (c.start, c.end);
+            self.next += 1;
+            self.start_line(start);
+            self.out.push_str(&self.src[start..end]);
+            self.out.push('\n');
+            self.last = end;
+        }
+    }
+
+    /// Print a line for the source between `start` and `end`
+    fn line(&mut self, start: usize, end: usize, text: &str) {
+        self.comments_before(start);
+        self.start_line(start);
+        self.out.push_str(text);
+        self.last = end;
+        // Keep comments that follow the line in the source on the same line
+        if let Some(c) = self.comments.get(self.next) {
+            let gap = &self.src[end.min(c.start)..c.start];
+            if c.start >= end && gap.chars().all(|c| " \t,;".contains(c)) {
+                self.out.push(' ');
+                self.out.push_str(&self.src[c.start..c.end]);
+                self.last = c.end;
+                self.next += 1;
+            }
+        }
+        self.out.push('\n');
+    }
+
+    /// Print a line that opens a block
+    fn open(&mut self, start: usize, end: usize, text: &str) {
+        self.line(start, end, text);
+        self.indent += 1;
+        self.no_blank = true;
+    }
+
+    /// Print a line closing the current block. `start` is the position of the
+    /// closing delimiter.
+    fn close(&mut self, start: usize, end: usize, text: &str) {
+        self.comments_before(start);
+        self.indent -= 1;
+        self.no_blank = true;
+        self.line(start, end, text);
+    }
+
+    /// Position of the token `tok` after `pos`, skipping whitespace and
+    /// comments.
+    fn find(&self, mut pos: usize, tok: &str) -> usize {
+        loop {
+            let rest = &self.src[pos..];
+            let trimmed = rest.trim_start();
+            pos += rest.len() - trimmed.len();
+            if trimmed.starts_with(tok) {
+                return pos;
+            }
+            match self.comments.iter().find(|c| c.start == pos) {
+                Some(c) => pos = c.end,
+                None => unreachable!("expected `{tok}' at {pos}"),
+            }
+        }
+    }
+
+    /// Position of the keyword `kw` immediately before `pos`
+    fn keyword(&self, pos: usize, kw: &str) -> usize {
+        let before = self.src[..pos].trim_end();
+        if before.ends_with(kw) {
+            before.len() - kw.len()
+        } else {
+            pos
+        }
+    }
+
+    fn file(&mut self, file: Pair<Rule>) {
+        let mut prev_const = false;
+        for item in file.into_inner() {
+            match item.as_rule() {
+                Rule::imports => {
+                    for imp in item.into_inner() {
+                        let (start, end) =
+                            (imp.as_span().start(), imp.as_span().end());
+                        let mut inner = imp.into_inner();
+                        let path = inner.next().unwrap().as_str();
+                        let text = match inner.next() {
+                            Some(alias) => {
+                                format!("import {path} as {};", alias.as_str())
+                            }
+                            None => format!("import {path};"),
+                        };
+                        self.line(start, end, &text);
+                    }
+                }
+                Rule::comp_or_ext => {
+                    let item = item.into_inner().next().unwrap();
+                    let is_const = item.as_rule() == Rule::const_def;
+                    // Consecutive constants are grouped like commands
+                    let grouped = is_const && prev_const;
+                    self.blank = !self.out.is_empty() && !grouped;
+                    prev_const = is_const;
+                    match item.as_rule() {
+                        Rule::component => self.component(item),
+                        Rule::external => self.external(item),
+                        Rule::fn_def => self.fn_def(item),
+                        Rule::struct_def => self.struct_def(item),
+                        Rule::const_def => self.const_def(item),
+                        r => unreachable!("unexpected rule {r:?}"),
+                    }
+                }
+                Rule::EOI => self.comments_before(self.src.len()),
+                r => unreachable!("unexpected rule {r:?}"),
+            }
+        }
+    }
+
+    fn component(&mut self, comp: Pair<Rule>) {
+        let (start, end) = (comp.as_span().start(), comp.as_span().end());
+        let mut inner = comp.into_inner();
+        let sig = inner.next().unwrap();
+        let sig_end = sig.as_span().end();
+        let body = self.find(sig_end, "{") + 1;
+        self.signature(start, sig, " {", body);
+        self.indent += 1;
+        self.no_blank = true;
+        for cmd in inner {
+            self.command(cmd);
+        }
+        self.close(end - 1, end, "}");
+    }
+
+    fn external(&mut self, ext: Pair<Rule>) {
+        let (start, end) = (ext.as_span().start(), ext.as_span().end());
+        let mut inner = ext.into_inner();
+        let file = inner.next().unwrap();
+        let open = self.find(file.as_span().end(), "{") + 1;
+        self.open(start, open, &format!("extern {} {{", file.as_str()));
+        for sig in inner {
+            let sig_start = self.keyword(sig.as_span().start(), "comp");
+            let sig_end = self.find(sig.as_span().end(), ";") + 1;
+            self.signature(sig_start, sig, ";", sig_end);
+        }
+        self.close(end - 1, end, "}");
+    }
+
+    fn fn_def(&mut self, def: Pair<Rule>) {
+        let (start, end) = (def.as_span().start(), def.as_span().end());
+        let mut inner = def.into_inner();
+        let name = inner.next().unwrap().as_str();
+        let params = inner
+            .next()
+            .unwrap()
+            .into_inner()
+            .map(|p| p.as_str())
+            .join(", ");
+        let head = format!("fn {name}({params})");
+        let body = inner.next().unwrap();
+        if body.as_rule() == Rule::expr {
+            self.line(start, end, &format!("{head} = {};", expr(body)));
+            return;
+        }
+        let open = self.find(body.as_span().end(), "{") + 1;
+        self.open(start, open, &format!("{head} -> {} {{", body.as_str()));
+        for axiom in inner {
+            let (ax_start, ax_end) =
+                (axiom.as_span().start(), axiom.as_span().end());
+            let imp = axiom
+                .into_inner()
+                .next()
+                .unwrap()
+                .into_inner()
+                .map(expr_cmp)
+                .join(" => ");
+            self.line(ax_start, ax_end, &format!("assume {imp};"));
+        }
+        self.close(end - 1, end, "}");
+    }
+
+    fn const_def(&mut self, def: Pair<Rule>) {
+        let (start, end) = (def.as_span().start(), def.as_span().end());
+        let mut inner = def.into_inner();
+        let name = inner.next().unwrap().as_str();
+        let value = expr(inner.next().unwrap());
+        self.line(start, end, &format!("const {name} = {value};"));
+    }
+
+    fn struct_def(&mut self, def: Pair<Rule>) {
+        let (start, end) = (def.as_span().start(), def.as_span().end());
+        let mut inner = def.into_inner();
+        let name = inner.next().unwrap().as_str();
+        let params = inner.next().unwrap();
+        let open = self.find(params.as_span().end(), "{") + 1;
+        let params = list("[", params, ", ", "]", |p| p.as_str().to_string());
+        self.open(start, open, &format!("struct {name}{params} {{"));
+        for field in inner {
+            let (f_start, f_end) =
+                (field.as_span().start(), field.as_span().end());
+            self.line(f_start, f_end, &format!("{},", struct_field(field)));
+        }
+        self.close(end - 1, end, "}");
+    }
+
+    /// Print a signature starting at `start` followed by `term` which ends at
+    /// `end` in the source.
+    fn signature(
+        &mut self,
+        start: usize,
+        sig: Pair<Rule>,
+        term: &str,
+        end: usize,
+    ) {
+        let mut head = String::from("comp ");
+        let (mut io, mut cons) = (None, None);
+        for p in sig.into_inner() {
+            match p.as_rule() {
+                Rule::identifier => head.push_str(p.as_str()),
+                Rule::params => {
+                    head.push_str(&list("[", p, ", ", "]", param_bind))
+                }
+                Rule::abstract_var => {
+                    head.push_str(&list("<", p, ", ", ">", event_bind))
+                }
+                Rule::io => io = Some(p),
+                Rule::constraints => cons = Some(p),
+                r => unreachable!("unexpected rule {r:?}"),
+            }
+        }
+        let cons = list(" where ", cons.unwrap(), ", ", "", constraint);
+        let io = io.unwrap();
+        let io_span = io.as_span();
+        let (mut inputs, mut arrow, mut outputs) = (None, None, None);
+        for p in io.into_inner() {
+            match p.as_rule() {
+                Rule::arrow => arrow = Some(p.as_span()),
+                Rule::ports if arrow.is_none() => inputs = Some(p),
+                Rule::ports => outputs = Some(p),
+                r => unreachable!("unexpected rule {r:?}"),
+            }
+        }
+        let arrow = arrow.unwrap();
+        let tail = format!("){cons}{term}");
+
+        match (inputs, outputs) {
+            (None, None) => {
+                self.line(start, end, &format!("{head}() -> ({tail}"));
+            }
+            (inputs, outputs) => {
+                if let Some(inputs) = inputs {
+                    self.open(start, io_span.start() + 1, &format!("{head}("));
+                    self.ports(inputs);
+                    match &outputs {
+                        Some(_) => {
+                            let open = self.find(arrow.end(), "(") + 1;
+                            self.close(arrow.start(), open, ") -> (");
+                            self.indent += 1;
+                            self.no_blank = true;
+                        }
+                        None => self.close(
+                            arrow.start(),
+                            end,
+                            &format!(") -> ({tail}"),
+                        ),
+                    }
+                } else {
+                    let open = self.find(arrow.end(), "(") + 1;
+                    self.open(start, open, &format!("{head}() -> ("));
+                }
+                if let Some(outputs) = outputs {
+                    self.ports(outputs);
+                    self.close(io_span.end() - 1, end, &tail);
+                }
+            }
+        }
+    }
+
+    fn ports(&mut self, ports: Pair<Rule>) {
+        for pd in ports.into_inner() {
+            let (start, end) = (pd.as_span().start(), pd.as_span().end());
+            self.line(start, end, &format!("{},", port_def(pd)));
+        }
+    }
+
+    fn command(&mut self, cmd: Pair<Rule>) {
+        let (start, end) = (cmd.as_span().start(), cmd.as_span().end());
+        let cmd = cmd.into_inner().next().unwrap();
+        let text = match cmd.as_rule() {
+            Rule::instance => {
+                let mut inner = cmd.into_inner();
+                let name = inner.next().unwrap().as_str();
+                let comp = inner.next().unwrap().as_str();
+                let params = list("[", inner.next().unwrap(), ", ", "]", expr);
+                let args = inner.next().map(invoke_args).unwrap_or_default();
+                format!("{name} := new {comp}{params}{args};")
+            }
+            Rule::invocation => {
+                let mut inner = cmd.into_inner();
+                let name = inner.next().unwrap().as_str();
+                let inst = inner.next().unwrap().as_str();
+                let args = invoke_args(inner.next().unwrap());
+                format!("{name} := {inst}{args};")
+            }
+            Rule::connect => {
+                let parts = cmd.into_inner().collect_vec();
+                match parts.as_slice() {
+                    [dst, src] => format!(
+                        "{} = {};",
+                        port(dst.clone()),
+                        port(src.clone())
+                    ),
+                    [dst, g, src] => format!(
+                        "{} = {} ? {};",
+                        port(dst.clone()),
+                        guard(g.clone()),
+                        port(src.clone())
+                    ),
+                    _ => unreachable!("malformed connect"),
+                }
+            }
+            Rule::bundle => {
+                let parts = cmd.into_inner().collect_vec();
+                let [name, lens @ .., typ] = parts.as_slice() else {
+                    unreachable!("malformed bundle")
+                };
+                let lens = lens
+                    .iter()
+                    .map(|l| format!("[{}]", expr(l.clone())))
+                    .join("");
+                format!(
+                    "bundle {}{lens}: {};",
+                    name.as_str(),
+                    bundle_typ(typ.clone())
+                )
+            }
+            Rule::fact => {
+                let mut inner = cmd.into_inner();
+                let kw = inner.next().unwrap().as_str();
+                let imp = inner
+                    .next()
+                    .unwrap()
+                    .into_inner()
+                    .map(expr_cmp)
+                    .join(" => ");
+                format!("{kw} {imp};")
+            }
+            Rule::param_let => {
+                let mut inner = cmd.into_inner();
+                let name = inner.next().unwrap().as_str();
+                let e = expr(inner.next().unwrap());
+                format!("let {name} = {e};")
+            }
+            Rule::for_loop => return self.for_loop(start, end, cmd),
+            Rule::if_stmt => return self.if_stmt(start, end, cmd),
+            r => unreachable!("unexpected rule {r:?}"),
+        };
+        self.line(start, end, &text);
+    }
+
+    fn commands(&mut self, cmds: Pair<Rule>) {
+        for cmd in cmds.into_inner() {
+            self.command(cmd);
+        }
+    }
+
+    fn for_loop(&mut self, start: usize, end: usize, l: Pair<Rule>) {
+        let mut inner = l.into_inner();
+        let idx = inner.next().unwrap().as_str();
+        let lo = expr(inner.next().unwrap());
+        let hi = inner.next().unwrap();
+        let open = self.find(hi.as_span().end(), "{") + 1;
+        let hi = expr(hi);
+        self.open(start, open, &format!("for {idx} in {lo}..{hi} {{"));
+        self.commands(inner.next().unwrap());
+        self.close(end - 1, end, "}");
+    }
+
+    fn if_stmt(&mut self, start: usize, end: usize, i: Pair<Rule>) {
+        let mut inner = i.into_inner();
+        let cond = inner.next().unwrap();
+        let open = self.find(cond.as_span().end(), "{") + 1;
+        self.open(start, open, &format!("if {} {{", expr_cmp(cond)));
+        let then = inner.next().unwrap();
+        let then_end = self.find(then.as_span().end(), "}");
+        self.commands(then);
+        if let Some(alt) = inner.next() {
+            let else_kw = self.find(then_end + 1, "else");
+            let open = self.find(else_kw + 4, "{") + 1;
+            self.close(then_end, open, "} else {");
+            self.indent += 1;
+            self.no_blank = true;
+            self.commands(alt);
+        }
+        self.close(end - 1, end, "}");
+    }
+}
+
+/// Print the children of `p` separated by `sep` and surrounded by `open` and
+/// `close`. Prints nothing if there are no children.
+fn list(
+    open: &str,
+    p: Pair<Rule>,
+    sep: &str,
+    close: &str,
+    f: fn(Pair<Rule>) -> String,
+) -> String {
+    let items = p.into_inner().map(f).collect_vec();
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!("{open}{}{close}", items.join(sep))
+    }
+}
+
+/// Does the source of this pair start with a `?`?
+fn is_optional(p: &Pair<Rule>) -> bool {
+    p.as_str().starts_with('?')
+}
+
+fn param_bind(p: Pair<Rule>) -> String {
+    let opt = is_optional(&p);
+    let mut inner = p.into_inner();
+    let var = inner.next().unwrap().as_str();
+    match inner.next() {
+        Some(default) if opt => format!("?{var}={}", expr(default)),
+        _ => var.to_string(),
+    }
+}
+
+fn event_bind(p: Pair<Rule>) -> String {
+    let opt = is_optional(&p);
+    let mut inner = p.into_inner();
+    let event = inner.next().unwrap().as_str();
+    let delay = delay(inner.next().unwrap());
+    match inner.next() {
+        Some(default) if opt => format!("?{event}: {delay}={}", time(default)),
+        _ => format!("{event}: {delay}"),
+    }
+}
+
+fn delay(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [e] => expr(e.clone()),
+        [l, r] => format!("{}-({})", time(l.clone()), time(r.clone())),
+        _ => unreachable!("malformed delay"),
+    }
+}
+
+fn constraint(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [l, op, r] => format!(
+            "{} {} {}",
+            time_or_expr(l.clone()),
+            op.as_str(),
+            time_or_expr(r.clone())
+        ),
+        _ => unreachable!("malformed constraint"),
+    }
+}
+
+fn time_or_expr(p: Pair<Rule>) -> String {
+    match p.as_rule() {
+        Rule::time => time(p),
+        _ => expr(p),
+    }
+}
+
+fn expr_cmp(p: Pair<Rule>) -> String {
+    constraint(p)
+}
+
+fn expr(p: Pair<Rule>) -> String {
+    p.into_inner()
+        .map(|p| match p.as_rule() {
+            Rule::expr_base => expr_base(p),
+            _ => p.as_str().to_string(),
+        })
+        .collect()
+}
+
+fn expr_base(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [f, args @ ..] if f.as_rule() == Rule::un_fn => format!(
+            "{}({})",
+            f.as_str(),
+            args.iter().cloned().map(expr).join(", ")
+        ),
+        [e] if e.as_rule() == Rule::expr => format!("({})", expr(e.clone())),
+        [e] => e.as_str().to_string(),
+        _ => unreachable!("malformed expression"),
+    }
+}
+
+fn time(p: Pair<Rule>) -> String {
+    p.into_inner()
+        .map(|p| match p.as_rule() {
+            Rule::expr => expr(p),
+            _ => p.as_str().to_string(),
+        })
+        .join("+")
+}
+
+fn interval(p: Pair<Rule>) -> String {
+    match p.as_rule() {
+        Rule::interval_range => {
+            format!("@[{}]", p.into_inner().map(time).join(", "))
+        }
+        Rule::interface => {
+            format!("@interface[{}]", p.into_inner().next().unwrap().as_str())
+        }
+        r => unreachable!("unexpected rule {r:?}"),
+    }
+}
+
+fn port_def(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [name, typ] => {
+            format!("{}: {}", name.as_str(), port_typ(typ.clone()))
+        }
+        [name, lens @ .., typ] if typ.as_rule() == Rule::bundle_typ => {
+            format!(
+                "{}{}: {}",
+                name.as_str(),
+                lens.iter()
+                    .map(|l| format!("[{}]", expr(l.clone())))
+                    .join(""),
+                bundle_typ(typ.clone())
+            )
+        }
+        [live, name, typ] => format!(
+            "{} {}: {}",
+            interval(live.clone()),
+            name.as_str(),
+            port_typ(typ.clone())
+        ),
+        _ => unreachable!("malformed port definition"),
+    }
+}
+
+/// The type of a scalar port is its bitwidth, optionally with a numeric
+/// representation, or a struct
+fn port_typ(p: Pair<Rule>) -> String {
+    match p.as_rule() {
+        Rule::struct_typ => {
+            let mut inner = p.into_inner();
+            let name = inner.next().unwrap().as_str();
+            let args = list("[", inner.next().unwrap(), ", ", "]", expr);
+            format!("{name}{args}")
+        }
+        Rule::num_typ => num_typ(p),
+        _ => expr(p),
+    }
+}
+
+/// A numeric representation followed by its bitwidth
+fn num_typ(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [sign, width] => format!("{} {}", sign.as_str(), expr(width.clone())),
+        [q, frac, width] => format!(
+            "{}<{}> {}",
+            q.as_str(),
+            expr(frac.clone()),
+            expr(width.clone())
+        ),
+        _ => unreachable!("malformed numeric type"),
+    }
+}
+
+fn struct_field(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [offset, name, width] => format!(
+            "@+{} {}: {}",
+            expr(offset.clone()),
+            name.as_str(),
+            expr(width.clone())
+        ),
+        [name, width] => format!("{}: {}", name.as_str(), expr(width.clone())),
+        _ => unreachable!("malformed struct field"),
+    }
+}
+
+fn bundle_typ(p: Pair<Rule>) -> String {
+    let mut inner = p.into_inner();
+    let idxs = inner
+        .next()
+        .unwrap()
+        .into_inner()
+        .map(|i| i.as_str())
+        .join(", ");
+    let live = interval(inner.next().unwrap());
+    let width = port_typ(inner.next().unwrap());
+    format!("for<{idxs}> {live} {width}")
+}
+
+fn port(p: Pair<Rule>) -> String {
+    let mut out = String::new();
+    for (idx, p) in p.into_inner().enumerate() {
+        match p.as_rule() {
+            Rule::access => out.push_str(&access(p)),
+            _ if idx > 0 => {
+                out.push('.');
+                out.push_str(p.as_str())
+            }
+            _ => out.push_str(p.as_str()),
+        }
+    }
+    out
+}
+
+fn access(p: Pair<Rule>) -> String {
+    let parts = p.into_inner().collect_vec();
+    match parts.as_slice() {
+        [e] => format!("{{{}}}", expr(e.clone())),
+        [s, _, e] => format!("{{{}..{}}}", expr(s.clone()), expr(e.clone())),
+        _ => unreachable!("malformed access"),
+    }
+}
+
+fn guard(p: Pair<Rule>) -> String {
+    p.into_inner()
+        .map(|p| match p.as_rule() {
+            Rule::guard_base => guard_base(p),
+            _ => p.as_str().to_string(),
+        })
+        .join(" ")
+}
+
+fn guard_base(p: Pair<Rule>) -> String {
+    let p = p.into_inner().next().unwrap();
+    match p.as_rule() {
+        Rule::guard_not => {
+            format!("!{}", guard_base(p.into_inner().next().unwrap()))
+        }
+        Rule::guard => format!("({})", guard(p)),
+        _ => port(p),
+    }
+}
+
+fn invoke_args(p: Pair<Rule>) -> String {
+    let mut inner = p.into_inner();
+    let times = list("<", inner.next().unwrap(), ", ", ">", time);
+    let args = inner.next().unwrap().into_inner().map(port).join(", ");
+    format!("{times}({args})")
+}
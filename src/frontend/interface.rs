@@ -0,0 +1,249 @@
+//! Interfaces of files that have already been checked.
+//!
+//! The interface of a file is its source where the bodies of all components
+//! have been blanked out. Since the blanked out text is replaced with spaces,
+//! positions in the interface are the same as in the source and errors
+//! reported on the signatures in the interface point to the source.
+//!
+//! Interfaces are kept as source instead of a serialized [ast::Signature] so
+//! that they are read by the same parser as the source and keep the positions
+//! of the signatures for error reporting. The signatures, constraints, and
+//! event delays of every component are part of its header and are kept.
+//!
+//! An interface is reused when the signatures of the file and of the files it
+//! transitively imports have not changed since the interface was written.
+//! Changing the body of a component does not invalidate the interface: the
+//! files that import it only depend on its signature and the body is checked
+//! when the file itself is checked.
+//!
+//! [ast::Signature]: crate::ast::Signature
+use super::{FilamentParser, Rule};
+use crate::utils::Fnv;
+use itertools::Itertools;
+use pest_consume::Parser;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+/// The interface of a file with the given source: the source where the
+/// commands of every component are replaced with whitespace.
+#[allow(clippy::result_large_err)]
+pub fn interface(source: &str) -> Result<String, pest_consume::Error<Rule>> {
+    let file = <FilamentParser as Parser>::parse(Rule::file, source)?
+        .single()?
+        .into_pair();
+    let mut out = source.as_bytes().to_vec();
+    let comps = file
+        .into_inner()
+        .filter(|item| item.as_rule() == Rule::comp_or_ext)
+        .filter_map(|item| item.into_inner().next())
+        .filter(|item| item.as_rule() == Rule::component);
+    for comp in comps {
+        for cmd in comp.into_inner().skip(1) {
+            let span = cmd.as_span();
+            for b in &mut out[span.start()..span.end()] {
+                if !b.is_ascii_whitespace() {
+                    *b = b' ';
+                }
+            }
+        }
+    }
+    // Every byte of a multi-byte character is replaced so the interface is
+    // still valid UTF-8.
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Interfaces of checked files stored in a directory
+pub struct InterfaceCache {
+    /// Directory containing the interfaces
+    dir: PathBuf,
+    /// Source of each file read so far
+    sources: HashMap<PathBuf, String>,
+    /// Hash of the signatures of each file read so far
+    signatures: HashMap<PathBuf, u64>,
+    /// Files imported by each file
+    imports: HashMap<PathBuf, Vec<PathBuf>>,
+    /// Files parsed from their source along with the source. Their
+    /// interfaces are written once the program has been checked.
+    parsed: Vec<(PathBuf, String)>,
+}
+
+impl InterfaceCache {
+    /// First line of the metadata at the end of an interface. Changing the
+    /// format of interfaces requires changing this line.
+    const HEADER: &'static str = "// filament interface v2 ";
+    /// Prefix of the lines listing the files an interface depends on
+    const DEPENDS: &'static str = "// depends ";
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            sources: HashMap::new(),
+            signatures: HashMap::new(),
+            imports: HashMap::new(),
+            parsed: Vec::new(),
+        }
+    }
+
+    fn canonical(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// Location of the interface of a file
+    fn location(&self, path: &Path) -> PathBuf {
+        let mut hasher = Fnv::default();
+        hasher.write(path.as_os_str().as_encoded_bytes());
+        self.dir.join(format!("{:016x}.fil", hasher.finish()))
+    }
+
+    fn hash_source(source: &str) -> u64 {
+        let mut hasher = Fnv::default();
+        hasher.write(source.as_bytes());
+        hasher.finish()
+    }
+
+    /// Hash of the signatures in a source. Whitespace is ignored so that
+    /// changing the length of a body does not change the hash.
+    fn hash_signatures(source: &str) -> Option<u64> {
+        let mut hasher = Fnv::default();
+        for tok in interface(source).ok()?.split_whitespace() {
+            hasher.write(tok.as_bytes());
+            hasher.write_u8(b' ');
+        }
+        Some(hasher.finish())
+    }
+
+    /// Current source of a file
+    fn source(&mut self, path: &Path) -> Option<&str> {
+        if !self.sources.contains_key(path) {
+            let source = fs::read_to_string(path).ok()?;
+            self.sources.insert(path.to_path_buf(), source);
+        }
+        self.sources.get(path).map(String::as_str)
+    }
+
+    /// Hash of the signatures in the current source of a file
+    fn signatures(&mut self, path: &Path) -> Option<u64> {
+        if let Some(h) = self.signatures.get(path) {
+            return Some(*h);
+        }
+        let h = Self::hash_signatures(self.source(path)?)?;
+        self.signatures.insert(path.to_path_buf(), h);
+        Some(h)
+    }
+
+    /// The interface of the file at `path` if neither the signatures of the
+    /// file nor those of its dependencies have changed since the interface
+    /// was written.
+    pub fn lookup(&mut self, path: &Path) -> Option<String> {
+        let path = Self::canonical(path);
+        let cached = fs::read_to_string(self.location(&path)).ok()?;
+        let (_, meta) = cached.rsplit_once(Self::HEADER)?;
+        let mut lines = meta.lines();
+        let (source, sigs) = lines.next()?.split_once(' ')?;
+        let source = u64::from_str_radix(source, 16).ok()?;
+        let sigs = u64::from_str_radix(sigs, 16).ok()?;
+        for line in lines {
+            let (hash, dep) =
+                line.strip_prefix(Self::DEPENDS)?.split_once(' ')?;
+            let hash = u64::from_str_radix(hash, 16).ok()?;
+            if self.signatures(Path::new(dep))? != hash {
+                return None;
+            }
+        }
+        if self.signatures(&path)? != sigs {
+            return None;
+        }
+        log::info!("Using the cached interface of `{}`", path.display());
+        // Positions in the cached interface are only valid if the source is
+        // unchanged.
+        let current = self.source(&path)?;
+        if Self::hash_source(current) == source {
+            Some(cached)
+        } else {
+            interface(current).ok()
+        }
+    }
+
+    /// Record a file that was parsed from its source
+    pub fn add_source(&mut self, path: &Path, source: String) {
+        let path = Self::canonical(path);
+        self.sources.insert(path.clone(), source.clone());
+        self.parsed.push((path, source));
+    }
+
+    /// Record the files imported by a file
+    pub fn add_imports(&mut self, path: &Path, imports: &[PathBuf]) {
+        self.imports.insert(
+            Self::canonical(path),
+            imports.iter().map(|p| Self::canonical(p)).collect(),
+        );
+    }
+
+    /// Files transitively imported by a file
+    fn dependencies(&self, path: &PathBuf) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        let mut todo = vec![path];
+        while let Some(p) = todo.pop() {
+            for imp in self.imports.get(p).into_iter().flatten() {
+                if imp != path && seen.insert(imp.clone()) {
+                    todo.push(imp);
+                }
+            }
+        }
+        seen.into_iter().sorted().collect()
+    }
+
+    /// Write the interfaces of the files parsed from their source
+    pub fn save(&mut self) {
+        if self.parsed.is_empty() {
+            return;
+        }
+        if let Err(e) = fs::create_dir_all(&self.dir) {
+            log::warn!(
+                "failed to create interface cache `{}': {e}",
+                self.dir.display()
+            );
+            return;
+        }
+        for (path, source) in std::mem::take(&mut self.parsed) {
+            let Ok(mut out) = interface(&source) else {
+                continue;
+            };
+            let Some(sigs) = self.signatures(&path) else {
+                continue;
+            };
+            out.push_str(&format!(
+                "\n{}{:016x} {sigs:016x}\n",
+                Self::HEADER,
+                Self::hash_source(&source)
+            ));
+            // Interfaces whose dependencies cannot be read are not written
+            let Some(deps) = self
+                .dependencies(&path)
+                .into_iter()
+                .map(|dep| Some((self.signatures(&dep)?, dep)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            for (hash, dep) in deps {
+                out.push_str(&format!(
+                    "{}{hash:016x} {}\n",
+                    Self::DEPENDS,
+                    dep.display()
+                ));
+            }
+            let file = self.location(&path);
+            if let Err(e) = fs::write(&file, out) {
+                log::warn!(
+                    "failed to write interface `{}': {e}",
+                    file.display()
+                );
+            }
+        }
+    }
+}
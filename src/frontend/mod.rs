@@ -1,3 +1,6 @@
+pub mod fmt;
+mod interface;
 mod parser;
 
+pub use interface::{interface, InterfaceCache};
 pub use parser::{FilamentParser, Rule};
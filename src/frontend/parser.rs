@@ -15,6 +15,8 @@ use std::path::Path;
 struct UserData {
     /// Index to the current file
     pub file: FileIdx,
+    /// Functions defined by the program
+    pub funcs: ast::Functions,
 }
 
 type ParseResult<T> = Result<T, Error<Rule>>;
@@ -23,6 +25,13 @@ type ParseResult<T> = Result<T, Error<Rule>>;
 type Node<'i> = pest_consume::Node<'i, Rule, UserData>;
 
 type Ports = Vec<Loc<ast::PortDef>>;
+/// Index parameters, liveness, bitwidth, and representation of a bundle type
+type BundleTyp = (
+    Vec<Loc<ast::Id>>,
+    Loc<ast::Range>,
+    Loc<ast::Expr>,
+    ast::NumRepr,
+);
 
 // include the grammar file so that Cargo knows to rebuild this file on grammar changes
 const _GRAMMAR: &str = include_str!("syntax.pest");
@@ -34,11 +43,18 @@ lazy_static::lazy_static! {
     PrattParser::new()
         .op(Op::infix(Rule::op_add, Assoc::Left) | Op::infix(Rule::op_sub, Assoc::Left))
         .op(Op::infix(Rule::op_mul, Assoc::Left) | Op::infix(Rule::op_div, Assoc::Left) | Op::infix(Rule::op_mod, Assoc::Left));
+    static ref GUARD_PRATT: PrattParser<Rule> =
+    PrattParser::new()
+        .op(Op::infix(Rule::op_or, Assoc::Left))
+        .op(Op::infix(Rule::op_and, Assoc::Left));
 }
 
 pub enum ExtOrComp {
     Ext((String, Vec<ast::Signature>)),
     Comp(ast::Component),
+    Fn(ast::FnDef),
+    Struct(ast::StructDef),
+    Const(ast::ConstDef),
 }
 
 #[derive(Clone)]
@@ -47,6 +63,7 @@ pub enum FCons {
     TimeC(ast::OrderConstraint<ast::Time>),
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum Port {
     Pd(Loc<ast::PortDef>),
     Int(ast::InterfaceDef),
@@ -58,19 +75,38 @@ pub enum Port {
 pub struct FilamentParser;
 
 impl FilamentParser {
-    pub fn parse_file(path: &Path) -> FilamentResult<ast::Namespace> {
-        let time = std::time::Instant::now();
+    /// Parse the file at `path`. Applications of user-defined functions refer
+    /// to the definitions in `funcs`.
+    pub fn parse_file(
+        path: &Path,
+        funcs: &ast::Functions,
+    ) -> FilamentResult<ast::Namespace> {
         let content = &fs::read(path).map_err(|err| {
             errors::Error::invalid_file(format!(
                 "Failed to read {}: {err}",
                 path.to_string_lossy(),
             ))
         })?;
-        // Add a new file to the position table
         let string_content = std::str::from_utf8(content)?.to_string();
+        Ok(Self::parse_source(path, string_content, funcs)?)
+    }
+
+    /// Parse the contents of the file at `path` from `source` instead of
+    /// reading it from disk.
+    #[allow(clippy::result_large_err)]
+    pub fn parse_source(
+        path: &Path,
+        source: String,
+        funcs: &ast::Functions,
+    ) -> Result<ast::Namespace, Error<Rule>> {
+        let time = std::time::Instant::now();
+        // Add a new file to the position table
         let file = GlobalPositionTable::as_mut()
-            .add_file(path.to_string_lossy().to_string(), string_content);
-        let user_data = UserData { file };
+            .add_file(path.to_string_lossy().to_string(), source);
+        let user_data = UserData {
+            file,
+            funcs: funcs.clone(),
+        };
         let (_, content) = GlobalPositionTable::as_ref().get_file_data(file);
         // Parse the file
         let inputs =
@@ -97,6 +133,27 @@ impl FilamentParser {
         GPosIdx(pos)
     }
 
+    /// Construct the type of a bundle and check that it has an index
+    /// parameter for every dimension.
+    #[allow(clippy::result_large_err)]
+    fn bundle_type(
+        input: &Node,
+        idxs: Vec<Loc<ast::Id>>,
+        lens: Vec<Loc<ast::Expr>>,
+        liveness: Loc<ast::Range>,
+        bitwidth: Loc<ast::Expr>,
+        repr: ast::NumRepr,
+    ) -> ParseResult<ast::BundleType> {
+        if idxs.len() != lens.len() {
+            return Err(input.error(format!(
+                "bundle has {} dimensions but {} index parameters",
+                lens.len(),
+                idxs.len()
+            )));
+        }
+        Ok(ast::BundleType::new(idxs, lens, liveness, bitwidth, repr))
+    }
+
     #[allow(clippy::result_large_err)]
     fn expr_helper(
         ud: UserData,
@@ -122,6 +179,28 @@ impl FilamentParser {
             })
             .parse(pairs)
     }
+
+    fn guard_helper(
+        ud: UserData,
+        pairs: pest::iterators::Pairs<Rule>,
+    ) -> ParseResult<ast::Guard> {
+        GUARD_PRATT
+            .map_primary(|primary| match primary.as_rule() {
+                Rule::guard_base => Self::guard_base(Node::new_with_user_data(
+                    primary,
+                    ud.clone(),
+                )),
+                x => unreachable!("Unexpected rule `{:?}' for guard_helper", x),
+            })
+            .map_infix(|lhs, op, rhs| {
+                Ok(match op.as_rule() {
+                    Rule::op_or => ast::Guard::or(lhs?, rhs?),
+                    Rule::op_and => ast::Guard::and(lhs?, rhs?),
+                    _ => unreachable!("Unknown guard operator"),
+                })
+            })
+            .parse(pairs)
+    }
 }
 
 #[pest_consume::parser]
@@ -143,6 +222,12 @@ impl FilamentParser {
         Ok(Loc::new(id, sp))
     }
 
+    fn comp_name(input: Node) -> ParseResult<Loc<ast::Id>> {
+        let sp = Self::get_span(&input);
+        let id = ast::Id::from(input.as_str());
+        Ok(Loc::new(id, sp))
+    }
+
     fn param_var(input: Node) -> ParseResult<Loc<ast::Id>> {
         Ok(match_nodes!(
             input.into_children();
@@ -208,21 +293,14 @@ impl FilamentParser {
         ))
     }
 
-    fn pow2(input: Node) -> ParseResult<ast::UnFn> {
-        Ok(ast::UnFn::Pow2)
-    }
-    fn log2(input: Node) -> ParseResult<ast::UnFn> {
-        Ok(ast::UnFn::Log2)
-    }
-    fn unknown_fn(input: Node) -> ParseResult<ast::UnFn> {
-        Err(input.error("Unknown function"))
-    }
     fn un_fn(input: Node) -> ParseResult<ast::UnFn> {
+        let funcs = input.user_data().funcs.clone();
         Ok(match_nodes!(
             input.into_children();
-            [pow2(_)] => ast::UnFn::Pow2,
-            [log2(_)] => ast::UnFn::Log2,
-            [unknown_fn(_)] => unreachable!(),
+            [identifier(id)] => {
+                let id = id.take();
+                ast::UnFn::builtin(id).unwrap_or_else(|| funcs.call(id))
+            }
         ))
     }
 
@@ -231,7 +309,10 @@ impl FilamentParser {
             input.into_children();
             [param_var(id)] => id.take().into(),
             [bitwidth(c)] => c.into(),
-            [un_fn(f), expr(e)] => ast::Expr::func(f, e.take()),
+            [un_fn(f), expr(e), expr(es)..] => ast::Expr::func(
+                f,
+                std::iter::once(e).chain(es).map(|e| e.take()).collect(),
+            ),
             [expr(e)] => e.take(),
         ))
     }
@@ -257,14 +338,64 @@ impl FilamentParser {
                 }
             },
             [interval_range(range), identifier(name), expr(bitwidth)] => {
-                Ok(Port::Pd(Loc::new(ast::PortDef::port(name, range, bitwidth), sp)))
+                Ok(Port::Pd(Loc::new(ast::PortDef::port(name, range, bitwidth, ast::NumRepr::Bits), sp)))
+            },
+            [interval_range(range), identifier(name), num_typ((repr, bitwidth))] => {
+                Ok(Port::Pd(Loc::new(ast::PortDef::port(name, range, bitwidth, repr), sp)))
+            },
+            [interface(_), identifier(_), num_typ(_)] => {
+                Err(input.error("interface ports cannot have a numeric representation"))
+            },
+            [identifier(_), num_typ(_)] => {
+                Err(input.error("ports with a numeric representation must have an interval"))
+            },
+            [identifier(name), expr(lens).., bundle_typ((idxs, live, width, repr))] => {
+                let typ = Self::bundle_type(&input, idxs, lens.collect(), live, width, repr)?;
+                Ok(Port::Pd(Loc::new(ast::Bundle::new(name, typ).into(), sp)))
+            },
+            [interval_range(liveness), identifier(name), struct_typ(typ)] => {
+                Ok(Port::Pd(Loc::new(ast::PortDef::Struct { name, liveness, typ }, sp)))
+            },
+            [interface(_), identifier(_), struct_typ(_)] => {
+                Err(input.error("interface ports cannot have a struct type"))
+            },
+            [identifier(_), struct_typ(_)] => {
+                Err(input.error("ports with a struct type must have an interval"))
             },
-            [identifier(name), expr(len), bundle_typ((idx, live, width))] => {
-                Ok(Port::Pd(Loc::new(ast::Bundle::new(name, ast::BundleType::new(idx, len, live, width)).into(), sp)))
-            }
         )
     }
 
+    fn struct_typ(input: Node) -> ParseResult<ast::StructType> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(name), conc_params(args)] => ast::StructType::new(name, args),
+        ))
+    }
+
+    fn signed_w(input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+    fn unsigned_w(input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+    fn fixed_w(input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+    fn ufixed_w(input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    /// A numeric representation along with the bitwidth of the port
+    fn num_typ(input: Node) -> ParseResult<(ast::NumRepr, Loc<ast::Expr>)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [signed_w(_), expr(width)] => (ast::NumRepr::Int { signed: true }, width),
+            [unsigned_w(_), expr(width)] => (ast::NumRepr::Int { signed: false }, width),
+            [fixed_w(_), expr(frac), expr(width)] => (ast::NumRepr::Fixed { signed: true, frac: Box::new(frac.take()) }, width),
+            [ufixed_w(_), expr(frac), expr(width)] => (ast::NumRepr::Fixed { signed: false, frac: Box::new(frac.take()) }, width),
+        ))
+    }
+
     fn delay(input: Node) -> ParseResult<Loc<TimeSub>> {
         let sp = Self::get_span(&input);
         let out = match_nodes!(
@@ -369,10 +500,10 @@ impl FilamentParser {
     fn instance(input: Node) -> ParseResult<Vec<ast::Command>> {
         Ok(match_nodes!(
             input.clone().into_children();
-            [identifier(name), identifier(component), conc_params(params)] => vec![
+            [identifier(name), comp_name(component), conc_params(params)] => vec![
                 ast::Instance::new(name, component, params).into()
             ],
-            [identifier(name), identifier(component), conc_params(params), invoke_args((abstract_vars, ports))] => {
+            [identifier(name), comp_name(component), conc_params(params), invoke_args((abstract_vars, ports))] => {
                 // Upper case the first letter of name
                 let mut iname = name.as_ref().to_string();
                 iname.make_ascii_uppercase();
@@ -409,10 +540,16 @@ impl FilamentParser {
             input.into_children();
             [bitwidth(constant)] => ast::Port::constant(constant),
             [identifier(name)] => ast::Port::this(name),
-            [identifier(name), access(range)] => ast::Port::bundle(name, range),
+            [identifier(name), access(access)..] => ast::Port::bundle(name, access.collect()),
             [identifier(comp), identifier(name)] => ast::Port::inv_port(comp, name),
-            [identifier(invoke), identifier(port), access(access)] => ast::Port::inv_bundle(invoke, port, access),
-            [identifier(name), expr(idx)] => ast::Port::bundle(name, idx.map(|x| x.into())),
+            // Field of a struct port on an invocation. The field is eliminated
+            // along with the struct by StructElim.
+            [identifier(invoke), identifier(port), identifier(field)] => {
+                let name = ast::Id::from(format!("{port}.{field}"));
+                ast::Port::inv_port(invoke, Loc::new(name, sp))
+            },
+            [identifier(invoke), identifier(port), access(access)..] => ast::Port::inv_bundle(invoke, port, access.collect()),
+            [identifier(name), expr(idx)] => ast::Port::bundle(name, vec![idx.map(|x| x.into())]),
         );
         Ok(Loc::new(n, sp))
     }
@@ -591,16 +728,28 @@ impl FilamentParser {
         ))
     }
 
-    fn guard(input: Node) -> ParseResult<ast::Guard> {
+    fn guard_not(input: Node) -> ParseResult<ast::Guard> {
+        let sp = Self::get_span(&input);
         Ok(match_nodes!(
             input.into_children();
-            [port(p)] => p.take().into(),
-            [port(p), guard(g)] => {
-                ast::Guard::or(p.take().into(), g)
-            }
+            [guard_base(g)] => ast::Guard::Not(Box::new(g), sp),
         ))
     }
 
+    fn guard_base(input: Node) -> ParseResult<ast::Guard> {
+        Ok(match_nodes!(
+            input.into_children();
+            [guard_not(g)] => g,
+            [guard(g)] => g,
+            [port(p)] => p.into(),
+        ))
+    }
+
+    fn guard(input: Node) -> ParseResult<ast::Guard> {
+        let ud = input.user_data().clone();
+        Self::guard_helper(ud, input.into_pair().into_inner())
+    }
+
     fn connect(input: Node) -> ParseResult<ast::Connect> {
         Ok(match_nodes!(
             input.into_children();
@@ -642,22 +791,38 @@ impl FilamentParser {
         ))
     }
 
-    fn bundle_typ(
-        input: Node,
-    ) -> ParseResult<(Loc<ast::Id>, Loc<ast::Range>, Loc<ast::Expr>)> {
+    fn param_let(input: Node) -> ParseResult<ast::ParamLet> {
         Ok(match_nodes!(
             input.into_children();
-            [param_var(param), interval_range(range), expr(width)] => (param, range, width),
+            [param_var(name), expr(e)] => ast::ParamLet::new(name, e),
         ))
     }
 
-    fn bundle(input: Node) -> ParseResult<ast::Bundle> {
+    fn bundle_typ(input: Node) -> ParseResult<BundleTyp> {
         Ok(match_nodes!(
             input.into_children();
-            [identifier(name), expr(size), bundle_typ((param, range, width))] => ast::Bundle::new(name, ast::BundleType::new(param, size, range, width)),
+            [bundle_params(params), interval_range(range), expr(width)] => (params, range, width, ast::NumRepr::Bits),
+            [bundle_params(params), interval_range(range), num_typ((repr, width))] => (params, range, width, repr),
         ))
     }
 
+    fn bundle_params(input: Node) -> ParseResult<Vec<Loc<ast::Id>>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [param_var(params)..] => params.collect(),
+        ))
+    }
+
+    fn bundle(input: Node) -> ParseResult<ast::Bundle> {
+        match_nodes!(
+            input.clone().into_children();
+            [identifier(name), expr(sizes).., bundle_typ((params, range, width, repr))] => {
+                let typ = Self::bundle_type(&input, params, sizes.collect(), range, width, repr)?;
+                Ok(ast::Bundle::new(name, typ))
+            },
+        )
+    }
+
     fn implication(input: Node) -> ParseResult<ast::Implication<ast::Expr>> {
         Ok(match_nodes!(
             input.into_children();
@@ -691,6 +856,7 @@ impl FilamentParser {
             [for_loop(l)] => vec![ast::Command::ForLoop(l)],
             [bundle(bl)] => vec![bl.into()],
             [if_stmt(if_)] => vec![if_.into()],
+            [param_let(l)] => vec![l.into()],
             [fact(a)] => vec![a.into()]
         ))
     }
@@ -726,17 +892,94 @@ impl FilamentParser {
             input.into_children();
             [external(sig)] => ExtOrComp::Ext(sig),
             [component(comp)] => ExtOrComp::Comp(comp),
+            [fn_def(def)] => ExtOrComp::Fn(def),
+            [struct_def(def)] => ExtOrComp::Struct(def),
+            [const_def(def)] => ExtOrComp::Const(def),
+        ))
+    }
+
+    fn const_def(input: Node) -> ParseResult<ast::ConstDef> {
+        Ok(match_nodes!(
+            input.into_children();
+            [param_var(name), expr(value)] => ast::ConstDef::new(name, value),
+        ))
+    }
+
+    fn struct_params(input: Node) -> ParseResult<Vec<Loc<ast::Id>>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [param_var(params)..] => params.collect(),
+        ))
+    }
+
+    fn struct_field(input: Node) -> ParseResult<ast::StructField> {
+        Ok(match_nodes!(
+            input.into_children();
+            [expr(offset), identifier(name), expr(bitwidth)] => {
+                ast::StructField { name, offset: Some(offset), bitwidth }
+            },
+            [identifier(name), expr(bitwidth)] => {
+                ast::StructField { name, offset: None, bitwidth }
+            },
+        ))
+    }
+
+    fn struct_def(input: Node) -> ParseResult<ast::StructDef> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(name), struct_params(params), struct_field(fields)..] => {
+                ast::StructDef::new(name, params, fields.collect())
+            },
+        ))
+    }
+
+    fn fn_params(input: Node) -> ParseResult<Vec<Loc<ast::Id>>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [param_var(params)..] => params.collect(),
+        ))
+    }
+
+    fn fn_axiom(input: Node) -> ParseResult<Loc<ast::Implication<ast::Expr>>> {
+        let sp = Self::get_span(&input);
+        Ok(match_nodes!(
+            input.into_children();
+            [implication(imp)] => Loc::new(imp, sp),
         ))
     }
 
-    fn imports(input: Node) -> ParseResult<Vec<String>> {
+    fn fn_def(input: Node) -> ParseResult<ast::FnDef> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(name), fn_params(params), expr(body)] => {
+                ast::FnDef::new(name, params, ast::FnBody::Expr(body))
+            },
+            [identifier(name), fn_params(params), param_var(result), fn_axiom(axioms)..] => {
+                let body = ast::FnBody::Axioms { result, axioms: axioms.collect() };
+                ast::FnDef::new(name, params, body)
+            },
+        ))
+    }
+
+    fn import(input: Node) -> ParseResult<(String, Option<Loc<ast::Id>>)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [string_lit(path)] => (path, None),
+            [string_lit(path), identifier(alias)] => (path, Some(alias)),
+        ))
+    }
+
+    fn imports(
+        input: Node,
+    ) -> ParseResult<Vec<(String, Option<Loc<ast::Id>>)>> {
         Ok(match_nodes!(
             input.into_children();
-            [string_lit(path)..] => path.collect()
+            [import(imps)..] => imps.collect()
         ))
     }
 
     fn file(input: Node) -> ParseResult<ast::Namespace> {
+        let funcs = input.user_data().funcs.clone();
         Ok(match_nodes!(
             input.into_children();
             [imports(imps), comp_or_ext(mixed).., _EOI] => {
@@ -744,12 +987,19 @@ impl FilamentParser {
                     imports: imps,
                     externs: vec![],
                     components: vec![],
+                    fns: vec![],
+                    funcs,
+                    structs: vec![],
+                    consts: vec![],
                     toplevel: "main".to_string(),
                 };
                 for m in mixed {
                     match m {
                         ExtOrComp::Ext(sig) => namespace.externs.push(sig),
                         ExtOrComp::Comp(comp) => namespace.components.push(comp),
+                        ExtOrComp::Fn(def) => namespace.fns.push(def),
+                        ExtOrComp::Struct(def) => namespace.structs.push(def),
+                        ExtOrComp::Const(def) => namespace.consts.push(def),
                     }
                 }
                 namespace
@@ -0,0 +1,207 @@
+//! Canonical form of expressions.
+//!
+//! Expressions built from additions, subtractions and multiplications are
+//! normalized into a sum of products. The products are ordered by the atoms
+//! they contain and the constant is added last. Terms with negative
+//! coefficients are subtracted from the sum of the positive terms:
+//! ```text
+//! (#N + 1) * 2 - #M - 3  ==>  2*#N - (#M + 1)
+//! ```
+//! Atoms are parameters and the expressions that are not normalized:
+//! divisions, remainders, and function applications that cannot be
+//! evaluated.
+//!
+//! Since every expression in a [super::Component] is interned in its
+//! canonical form, two expressions with the same normal form have the same
+//! index.
+use super::{Expr, ExprIdx, Interned};
+use crate::ast;
+use std::collections::BTreeMap;
+
+/// Maximum number of products in a canonical expression. Larger expressions
+/// are kept as-is.
+const MAX_TERMS: usize = 32;
+
+/// A sum of products of atoms with integer coefficients. The product of no
+/// atoms is the constant term.
+#[derive(Default)]
+struct Poly(BTreeMap<Vec<ExprIdx>, i128>);
+
+impl Poly {
+    fn constant(n: u64) -> Self {
+        let mut p = Poly::default();
+        if n != 0 {
+            p.0.insert(vec![], n as i128);
+        }
+        p
+    }
+
+    fn atom(e: ExprIdx) -> Self {
+        Poly(BTreeMap::from([(vec![e], 1)]))
+    }
+
+    fn insert(&mut self, mono: Vec<ExprIdx>, coeff: i128) -> Option<()> {
+        let c = self.0.get(&mono).copied().unwrap_or(0).checked_add(coeff)?;
+        if c == 0 {
+            self.0.remove(&mono);
+        } else {
+            self.0.insert(mono, c);
+        }
+        Some(())
+    }
+
+    /// `self + sign * other`
+    fn add(mut self, other: Poly, sign: i128) -> Option<Poly> {
+        for (mono, c) in other.0 {
+            self.insert(mono, c.checked_mul(sign)?)?;
+        }
+        Some(self)
+    }
+
+    fn mul(&self, other: &Poly) -> Option<Poly> {
+        let mut out = Poly::default();
+        for (lm, lc) in &self.0 {
+            for (rm, rc) in &other.0 {
+                let mut mono = lm.iter().chain(rm).copied().collect::<Vec<_>>();
+                mono.sort();
+                out.insert(mono, lc.checked_mul(*rc)?)?;
+            }
+        }
+        Some(out)
+    }
+}
+
+/// The polynomial represented by a canonical expression
+fn poly(e: ExprIdx, exprs: &Interned<Expr>) -> Option<Poly> {
+    match exprs.get(e) {
+        Expr::Concrete(n) => Some(Poly::constant(*n)),
+        Expr::Bin { op, lhs, rhs } => {
+            let (l, r) = (poly(*lhs, exprs)?, poly(*rhs, exprs)?);
+            match op {
+                ast::Op::Add => l.add(r, 1),
+                ast::Op::Sub => l.add(r, -1),
+                ast::Op::Mul => l.mul(&r),
+                ast::Op::Div | ast::Op::Mod => Some(Poly::atom(e)),
+            }
+        }
+        Expr::Param(_) | Expr::Fn { .. } => Some(Poly::atom(e)),
+    }
+}
+
+fn num(n: u64, exprs: &mut Interned<Expr>) -> ExprIdx {
+    exprs.intern(Expr::Concrete(n))
+}
+
+fn bin(
+    op: ast::Op,
+    lhs: ExprIdx,
+    rhs: ExprIdx,
+    exprs: &mut Interned<Expr>,
+) -> ExprIdx {
+    exprs.intern(Expr::Bin { op, lhs, rhs })
+}
+
+/// Build the canonical expression for a polynomial. Fails if a coefficient
+/// does not fit in an expression.
+fn build(p: &Poly, exprs: &mut Interned<Expr>) -> Option<ExprIdx> {
+    let mut pos = Vec::new();
+    let mut neg = Vec::new();
+    for (mono, c) in p.0.iter().filter(|(m, _)| !m.is_empty()) {
+        let prod = mono
+            .iter()
+            .copied()
+            .reduce(|l, r| bin(ast::Op::Mul, l, r, exprs))?;
+        let coeff = u64::try_from(c.unsigned_abs()).ok()?;
+        let term = if coeff == 1 {
+            prod
+        } else {
+            let coeff = num(coeff, exprs);
+            bin(ast::Op::Mul, coeff, prod, exprs)
+        };
+        if *c > 0 {
+            pos.push(term);
+        } else {
+            neg.push(term);
+        }
+    }
+    // The constant is added last
+    if let Some(c) = p.0.get(&vec![]) {
+        let n = num(u64::try_from(c.unsigned_abs()).ok()?, exprs);
+        if *c > 0 {
+            pos.push(n);
+        } else {
+            neg.push(n);
+        }
+    }
+    let mut sum = |terms: Vec<ExprIdx>| {
+        terms
+            .into_iter()
+            .reduce(|l, r| bin(ast::Op::Add, l, r, exprs))
+    };
+    Some(match (sum(pos), sum(neg)) {
+        (Some(p), None) => p,
+        (None, None) => num(0, exprs),
+        (p, Some(n)) => {
+            let p = p.unwrap_or_else(|| num(0, exprs));
+            bin(ast::Op::Sub, p, n, exprs)
+        }
+    })
+}
+
+/// Intern the canonical form of an expression. The subexpressions of the
+/// expression must already be canonical.
+pub(super) fn intern(expr: Expr, exprs: &mut Interned<Expr>) -> ExprIdx {
+    let concrete = |e: &ExprIdx, exprs: &Interned<Expr>| match exprs.get(*e) {
+        Expr::Concrete(n) => Some(*n),
+        _ => None,
+    };
+    match &expr {
+        Expr::Param(_) | Expr::Concrete(_) => exprs.intern(expr),
+        Expr::Bin { op, lhs, rhs } => {
+            let (op, lhs, rhs) = (*op, *lhs, *rhs);
+            let (l, r) = (concrete(&lhs, exprs), concrete(&rhs, exprs));
+            match op {
+                ast::Op::Add | ast::Op::Sub | ast::Op::Mul => {
+                    let sign = if op == ast::Op::Sub { -1 } else { 1 };
+                    let p = poly(lhs, exprs).zip(poly(rhs, exprs)).and_then(
+                        |(l, r)| match op {
+                            ast::Op::Mul => l.mul(&r),
+                            _ => l.add(r, sign),
+                        },
+                    );
+                    p.filter(|p| p.0.len() <= MAX_TERMS)
+                        .and_then(|p| build(&p, exprs))
+                        .unwrap_or_else(|| exprs.intern(expr))
+                }
+                ast::Op::Div => match (l, r) {
+                    (Some(l), Some(r)) if r != 0 => num(l / r, exprs),
+                    (_, Some(1)) => lhs,
+                    _ if lhs == rhs => num(1, exprs),
+                    _ => exprs.intern(expr),
+                },
+                ast::Op::Mod => match (l, r) {
+                    (Some(l), Some(r)) if r != 0 => num(l % r, exprs),
+                    (_, Some(1)) => num(0, exprs),
+                    _ if lhs == rhs => num(0, exprs),
+                    _ => exprs.intern(expr),
+                },
+            }
+        }
+        Expr::Fn { op, args } => {
+            let args = args
+                .iter()
+                .map(|a| concrete(a, exprs))
+                .collect::<Option<Vec<_>>>();
+            let val = match (op, args.as_deref()) {
+                // Avoid overflowing when evaluating large powers
+                (ast::UnFn::Pow2, Some([n])) if *n >= 64 => None,
+                (_, Some(args)) => op.eval(args),
+                (_, None) => None,
+            };
+            match val {
+                Some(n) => num(n, exprs),
+                None => exprs.intern(expr),
+            }
+        }
+    }
+}
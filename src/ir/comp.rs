@@ -1,14 +1,50 @@
 use super::{
-    CmpOp, Command, CompIdx, Ctx, Event, EventIdx, Expr, ExprIdx, Fact,
-    IndexStore, Info, InfoIdx, InstIdx, Instance, Interned, InvIdx, Invoke,
-    MutCtx, Param, ParamIdx, Port, PortIdx, Prop, PropIdx, Time, TimeIdx,
-    TimeSub,
+    canonical, CmpOp, Command, CompIdx, Ctx, Event, EventIdx, Expr, ExprIdx,
+    Fact, IndexStore, Info, InfoIdx, InstIdx, Instance, Interned, InvIdx,
+    Invoke, MutCtx, Param, ParamIdx, ParamOwner, Port, PortIdx, Prop, PropIdx,
+    Time, TimeIdx, TimeSub,
 };
-use crate::utils::Idx;
+use crate::{ast, utils::Idx};
+use std::collections::HashMap;
 
 #[derive(Default)]
 pub struct Context {
     pub comps: IndexStore<Component>,
+    /// The top-level component of the program
+    pub entrypoint: Option<CompIdx>,
+    /// External components grouped by the file that implements them
+    pub externals: Vec<(String, Vec<CompIdx>)>,
+    /// Functions defined by the program
+    pub funcs: ast::Functions,
+}
+
+impl Context {
+    /// Get the component with the given index
+    pub fn get(&self, idx: CompIdx) -> &Component {
+        self.comps.get(idx)
+    }
+}
+
+/// Source-level information about the interface of a component. The IR
+/// does not need this information to check the program but it is required to
+/// generate the external interface of a component during lowering.
+pub struct InterfaceSrc {
+    /// Name of the component
+    pub name: ast::Id,
+    /// Mapping from events to the name of the interface port that triggers them
+    pub interface_ports: HashMap<EventIdx, ast::Id>,
+    /// Ports without any liveness information (like `clk` and `reset`)
+    pub unannotated_ports: Vec<(ast::Id, u64)>,
+}
+
+impl InterfaceSrc {
+    pub fn new(name: ast::Id) -> Self {
+        Self {
+            name,
+            interface_ports: HashMap::default(),
+            unannotated_ports: Vec::default(),
+        }
+    }
 }
 
 /// A IR component. If `is_ext` is true then this is an external component.
@@ -46,6 +82,11 @@ pub struct Component {
     info: IndexStore<Info>,
     /// Is this an external component
     pub is_ext: bool,
+    /// Source-level information about the interface of the component
+    pub src_info: Option<InterfaceSrc>,
+    /// Reasons for the assumptions that make up the path conditions of
+    /// hoisted assertions. Used to explain assertions that cannot be proven.
+    pub assumptions: HashMap<PropIdx, InfoIdx>,
 }
 
 impl Component {
@@ -63,6 +104,8 @@ impl Component {
             times: Interned::default(),
             props: Interned::default(),
             cmds: Vec::default(),
+            src_info: None,
+            assumptions: HashMap::default(),
         };
         // Allocate numbers and props now so we get reasonable indices.
         comp.num(0);
@@ -220,6 +263,19 @@ impl Component {
         self.prop_params_acc(prop, &mut acc);
         acc
     }
+
+    /// The port whose generated index parameter is `param`. Ports that are
+    /// not bundles are represented as bundles with a single element and an
+    /// index that is always 0.
+    pub fn index_of(&self, param: ParamIdx) -> Option<PortIdx> {
+        let ParamOwner::Bundle(port) = self.get(param).owner else {
+            return None;
+        };
+        match self.get(self.get(port).info) {
+            Info::Port { bundle: false, .. } => Some(port),
+            _ => None,
+        }
+    }
 }
 
 // =========== Context accessors for each type ===========
@@ -275,8 +331,9 @@ impl Ctx<Invoke> for Component {
 }
 
 impl Ctx<Expr> for Component {
+    /// Expressions are interned in their canonical form
     fn add(&mut self, val: Expr) -> ExprIdx {
-        self.exprs.intern(val)
+        canonical::intern(val, &mut self.exprs)
     }
 
     fn get(&self, idx: ExprIdx) -> &Expr {
@@ -318,28 +375,48 @@ impl MutCtx<Port> for Component {
     fn get_mut(&mut self, idx: PortIdx) -> &mut Port {
         self.ports.get_mut(idx)
     }
+
+    fn delete(&mut self, idx: PortIdx) {
+        self.ports.delete(idx)
+    }
 }
 
 impl MutCtx<Event> for Component {
     fn get_mut(&mut self, idx: EventIdx) -> &mut Event {
         self.events.get_mut(idx)
     }
+
+    fn delete(&mut self, idx: EventIdx) {
+        self.events.delete(idx)
+    }
 }
 
 impl MutCtx<Param> for Component {
     fn get_mut(&mut self, idx: ParamIdx) -> &mut Param {
         self.params.get_mut(idx)
     }
+
+    fn delete(&mut self, idx: ParamIdx) {
+        self.params.delete(idx)
+    }
 }
 
 impl MutCtx<Invoke> for Component {
     fn get_mut(&mut self, idx: InvIdx) -> &mut Invoke {
         self.invocations.get_mut(idx)
     }
+
+    fn delete(&mut self, idx: InvIdx) {
+        self.invocations.delete(idx)
+    }
 }
 
 impl MutCtx<Instance> for Component {
     fn get_mut(&mut self, idx: InstIdx) -> &mut Instance {
         self.instances.get_mut(idx)
     }
+
+    fn delete(&mut self, idx: InstIdx) {
+        self.instances.delete(idx)
+    }
 }
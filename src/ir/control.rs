@@ -6,10 +6,12 @@ use std::fmt;
 
 #[derive(Clone, PartialEq, Eq)]
 /// A flattened and minimized representation of the control flow graph.
-/// Bundle definitions and facts are removed during the process of compilation to the IR.
 pub enum Command {
     Instance(InstIdx),
     Invoke(InvIdx),
+    /// Definition of a local bundle. Tracks the scope in which the bundle is
+    /// defined so that bundles defined in loop bodies are unique per iteration.
+    BundleDef(PortIdx),
     Connect(Connect),
     ForLoop(Loop),
     If(If),
@@ -26,6 +28,11 @@ impl From<InvIdx> for Command {
         Command::Invoke(idx)
     }
 }
+impl From<PortIdx> for Command {
+    fn from(idx: PortIdx) -> Self {
+        Command::BundleDef(idx)
+    }
+}
 impl From<Connect> for Command {
     fn from(con: Connect) -> Self {
         Command::Connect(con)
@@ -59,6 +66,7 @@ pub struct Instance {
     pub comp: CompIdx,
     /// The parameters used in the binding of this instance
     pub params: Box<[ExprIdx]>,
+    pub info: InfoIdx,
 }
 
 impl fmt::Display for Instance {
@@ -79,11 +87,66 @@ impl fmt::Display for Instance {
 pub struct Connect {
     pub src: Access,
     pub dst: Access,
+    /// The connection is only active when the guard is true
+    pub guard: Option<Guard>,
     pub info: InfoIdx,
 }
 impl fmt::Display for Connect {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} = {}", self.dst, self.src)
+        match &self.guard {
+            Some(g) => write!(f, "{} = {} ? {}", self.dst, g, self.src),
+            None => write!(f, "{} = {}", self.dst, self.src),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+/// A guard on a connection built from 1-bit ports
+pub enum Guard {
+    Or(Box<Guard>, Box<Guard>),
+    And(Box<Guard>, Box<Guard>),
+    Not(Box<Guard>),
+    Port(Access),
+}
+impl Guard {
+    /// The ports read by the guard
+    pub fn ports(&self) -> Vec<&Access> {
+        match self {
+            Guard::Or(l, r) | Guard::And(l, r) => {
+                let mut ports = l.ports();
+                ports.extend(r.ports());
+                ports
+            }
+            Guard::Not(g) => g.ports(),
+            Guard::Port(p) => vec![p],
+        }
+    }
+
+    /// Transform the ports read by the guard
+    pub fn map_ports<F>(self, f: &mut F) -> Self
+    where
+        F: FnMut(Access) -> Access,
+    {
+        match self {
+            Guard::Or(l, r) => {
+                Guard::Or(Box::new(l.map_ports(f)), Box::new(r.map_ports(f)))
+            }
+            Guard::And(l, r) => {
+                Guard::And(Box::new(l.map_ports(f)), Box::new(r.map_ports(f)))
+            }
+            Guard::Not(g) => Guard::Not(Box::new(g.map_ports(f))),
+            Guard::Port(p) => Guard::Port(f(p)),
+        }
+    }
+}
+impl fmt::Display for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Guard::Or(l, r) => write!(f, "({l} | {r})"),
+            Guard::And(l, r) => write!(f, "({l} & {r})"),
+            Guard::Not(g) => write!(f, "!{g}"),
+            Guard::Port(p) => write!(f, "{p}"),
+        }
     }
 }
 
@@ -97,6 +160,7 @@ pub struct Invoke {
     pub inst: InstIdx,
     // The ports defined by this invocation
     pub ports: Vec<PortIdx>,
+    pub info: InfoIdx,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -122,10 +186,23 @@ pub struct EventBind {
     pub event: EventIdx,
     pub arg: TimeIdx,
     pub info: InfoIdx,
+    /// The event in the signature of the invoked component that is bound by
+    /// this command
+    pub base: EventIdx,
 }
 
 impl EventBind {
-    pub fn new(event: EventIdx, arg: TimeIdx, info: InfoIdx) -> Self {
-        Self { event, arg, info }
+    pub fn new(
+        event: EventIdx,
+        arg: TimeIdx,
+        info: InfoIdx,
+        base: EventIdx,
+    ) -> Self {
+        Self {
+            event,
+            arg,
+            info,
+            base,
+        }
     }
 }
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use super::{Cmp, CmpOp, Ctx, ExprIdx, ParamIdx, Prop, PropIdx};
 use crate::ast;
@@ -54,6 +54,47 @@ impl ExprIdx {
         self.as_concrete(ctx).map(|c| c == n).unwrap_or(false)
     }
 
+    /// Evaluate the expression using a binding for the parameters.
+    /// Returns `None` if the evaluation underflows, overflows, or divides by
+    /// zero. Panics if the expression mentions a parameter not in the binding.
+    pub fn eval(
+        &self,
+        ctx: &impl Ctx<Expr>,
+        bind: &HashMap<ParamIdx, u64>,
+    ) -> Option<u64> {
+        match ctx.get(*self) {
+            Expr::Param(p) => {
+                Some(*bind.get(p).unwrap_or_else(|| {
+                    unreachable!("Parameter {p} is not bound")
+                }))
+            }
+            Expr::Concrete(n) => Some(*n),
+            Expr::Bin { op, lhs, rhs } => {
+                let l = lhs.eval(ctx, bind)?;
+                let r = rhs.eval(ctx, bind)?;
+                match op {
+                    ast::Op::Add => l.checked_add(r),
+                    ast::Op::Sub => l.checked_sub(r),
+                    ast::Op::Mul => l.checked_mul(r),
+                    ast::Op::Div => l.checked_div(r),
+                    ast::Op::Mod => l.checked_rem(r),
+                }
+            }
+            Expr::Fn { op, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(ctx, bind))
+                    .collect::<Option<Vec<_>>>()?;
+                match (op, args.as_slice()) {
+                    (ast::UnFn::Pow2, [arg]) => {
+                        1u64.checked_shl(u32::try_from(*arg).ok()?)
+                    }
+                    _ => op.eval(&args),
+                }
+            }
+        }
+    }
+
     /// Returns true of the expression is equal to the given parameter.
     pub fn is_param(&self, ctx: &impl Ctx<Expr>, param: ParamIdx) -> bool {
         if let Expr::Param(p) = ctx.get(*self) {
@@ -169,27 +210,46 @@ impl ExprIdx {
         }
     }
 
+    /// `2^self`. Like other expressions, it is evaluated by the component
+    /// when the argument is concrete.
     pub fn pow2(self, ctx: &mut impl Ctx<Expr>) -> Self {
-        let l = self.as_concrete(ctx);
-
-        match l {
-            Some(l) => ctx.add(Expr::Concrete(1 << l)),
-            _ => ctx.add(Expr::Fn {
-                op: ast::UnFn::Pow2,
-                args: Box::new([self]),
-            }),
-        }
+        ctx.add(Expr::Fn {
+            op: ast::UnFn::Pow2,
+            args: Box::new([self]),
+        })
     }
 
+    /// Ceiling of the base-2 logarithm of the expression.
     pub fn log2(self, ctx: &mut impl Ctx<Expr>) -> Self {
-        let l = self.as_concrete(ctx);
+        ctx.add(Expr::Fn {
+            op: ast::UnFn::Log2,
+            args: Box::new([self]),
+        })
+    }
 
-        match l {
-            Some(l) => ctx.add(Expr::Concrete(l.trailing_zeros() as u64)),
-            _ => ctx.add(Expr::Fn {
-                op: ast::UnFn::Log2,
-                args: Box::new([self]),
-            }),
+    /// Apply the function to the arguments. User-defined functions are
+    /// evaluated if all the arguments are concrete.
+    pub fn func(
+        op: ast::UnFn,
+        args: Vec<ExprIdx>,
+        ctx: &mut impl Ctx<Expr>,
+    ) -> Self {
+        match (&op, args.as_slice()) {
+            (ast::UnFn::Pow2, [arg]) => arg.pow2(ctx),
+            (ast::UnFn::Log2, [arg]) => arg.log2(ctx),
+            _ => {
+                let concrete = args
+                    .iter()
+                    .map(|arg| arg.as_concrete(ctx))
+                    .collect::<Option<Vec<_>>>();
+                match concrete.and_then(|args| op.eval(&args)) {
+                    Some(n) => ctx.add(Expr::Concrete(n)),
+                    None => ctx.add(Expr::Fn {
+                        op,
+                        args: args.into(),
+                    }),
+                }
+            }
         }
     }
 
@@ -7,7 +7,7 @@ use crate::ir::{
 use crate::utils::GPosIdx;
 use crate::{ast, ir, utils::Binding};
 use itertools::Itertools;
-use std::{iter, rc::Rc};
+use std::{collections::HashMap, iter, rc::Rc};
 
 /// # Declare phase
 /// This is the first pass over the AST and responsible for forward declaring names defined by invocations.
@@ -36,6 +36,12 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             comp.params.clone(),
             bindings.iter().map(|e| e.inner()).cloned().collect_vec(),
         );
+        let info = self.comp.add(ir::Info::instance(
+            name.copy(),
+            name.pos(),
+            component.pos(),
+            bindings.first().map_or(GPosIdx::UNKNOWN, |b| b.pos()),
+        ));
         let inst = ir::Instance {
             comp: comp.idx,
             params: bindings
@@ -43,6 +49,7 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 .map(|b| self.expr(b.clone().take()))
                 .collect_vec()
                 .into_boxed_slice(),
+            info,
         };
         let idx = self.comp.add(inst);
         self.inst_map.insert(name.copy(), idx);
@@ -62,9 +69,15 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
         } = inv;
 
         let inst = *self.inst_map.get(instance).unwrap();
+        let info = self.comp.add(ir::Info::invoke(
+            name.copy(),
+            name.pos(),
+            instance.pos(),
+        ));
         let inv = self.comp.add(ir::Invoke {
             inst,
             ports: vec![], // Filled in later
+            info,
         });
         self.add_inv(name.copy(), inv);
 
@@ -107,6 +120,15 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             ast::Command::Invoke(inv) => {
                 self.declare_inv(inv);
             }
+            // Parameters defined by `let` may be used by the instances and
+            // invocations declared after them.
+            ast::Command::Let(ast::ParamLet { name, expr }) => {
+                let bind = self.expr(expr.inner().clone());
+                self.param(
+                    &ast::ParamBind::from(name.clone()),
+                    ir::ParamOwner::Let { bind },
+                );
+            }
             ast::Command::ForLoop(_)
             | ast::Command::If(_)
             | ast::Command::Fact(_)
@@ -146,12 +168,9 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                     ast::Op::Mod => l.rem(r, self.comp),
                 }
             }
-            ast::Expr::App { func, arg } => {
-                let arg = self.expr(*arg);
-                match func {
-                    ast::UnFn::Pow2 => arg.pow2(self.comp),
-                    ast::UnFn::Log2 => arg.log2(self.comp),
-                }
+            ast::Expr::App { func, args } => {
+                let args = args.into_iter().map(|a| self.expr(a)).collect();
+                ExprIdx::func(func, args, self.comp)
             }
         }
     }
@@ -265,12 +284,27 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
         ir::Range { start, end }
     }
 
+    fn repr(&mut self, repr: ast::NumRepr) -> ir::NumRepr {
+        match repr {
+            ast::NumRepr::Bits => ir::NumRepr::Bits,
+            ast::NumRepr::Int { signed } => ir::NumRepr::Num {
+                signed,
+                frac: self.comp.num(0),
+            },
+            ast::NumRepr::Fixed { signed, frac, .. } => ir::NumRepr::Num {
+                signed,
+                frac: self.expr(*frac),
+            },
+        }
+    }
+
     fn port(&mut self, pd: ast::PortDef, owner: ir::PortOwner) -> PortIdx {
         let (name, p) = match pd {
             ast::PortDef::Port {
                 name,
                 liveness,
                 bitwidth,
+                repr,
             } => {
                 let info = self.comp.add(ir::Info::port(
                     name.copy(),
@@ -284,16 +318,17 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 // bind any new parameters.
                 let p_name = self.gen_name();
                 let live = self.with_scope(|ctx| ir::Liveness {
-                    idx: ctx.param(
+                    idxs: vec![ctx.param(
                         &ast::ParamBind::from(p_name),
                         // Updated after the port is constructed
                         ir::ParamOwner::bundle(ir::PortIdx::UNKNOWN),
-                    ), // This parameter is unused
-                    len: ctx.comp.num(1),
+                    )], // This parameter is unused
+                    lens: vec![ctx.comp.num(1)],
                     range: ctx.range(liveness.take()),
                 });
                 let p = ir::Port {
                     width: self.expr(bitwidth.take()),
+                    repr: self.repr(repr),
                     owner,
                     live,
                     info,
@@ -304,13 +339,14 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 name,
                 typ:
                     ast::BundleType {
-                        idx,
-                        len,
+                        idxs,
+                        lens,
                         liveness,
                         bitwidth,
+                        repr,
                     },
             }) => {
-                let info = self.comp.add(ir::Info::port(
+                let info = self.comp.add(ir::Info::bundle(
                     name.copy(),
                     name.pos(),
                     bitwidth.pos(),
@@ -318,45 +354,64 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 ));
                 // Construct the bundle type in a new scope.
                 let live = self.with_scope(|ctx| ir::Liveness {
-                    idx: ctx.param(
-                        // Updated after the port is constructed
-                        &ast::ParamBind::from(idx),
-                        ir::ParamOwner::bundle(PortIdx::UNKNOWN),
-                    ),
-                    len: ctx.expr(len.take()),
+                    idxs: idxs
+                        .into_iter()
+                        .map(|idx| {
+                            ctx.param(
+                                // Updated after the port is constructed
+                                &ast::ParamBind::from(idx),
+                                ir::ParamOwner::bundle(PortIdx::UNKNOWN),
+                            )
+                        })
+                        .collect(),
+                    lens: lens
+                        .into_iter()
+                        .map(|l| ctx.expr(l.take()))
+                        .collect(),
                     range: ctx.range(liveness.take()),
                 });
                 let p = ir::Port {
                     width: self.expr(bitwidth.take()),
+                    repr: self.repr(repr),
                     owner,
                     live,
                     info,
                 };
                 (name, p)
             }
+            ast::PortDef::Struct { .. } => {
+                unreachable!("struct ports should have been eliminated")
+            }
         };
         let idx = self.comp.add(p);
-        // Fixup the liveness index parameter's owner
-        let p = self.comp.get(idx);
-        let param = self.comp.get_mut(p.live.idx);
-        param.owner = ir::ParamOwner::bundle(idx);
+        // Fixup the owners of the liveness index parameters
+        let params = self.comp.get(idx).live.idxs.clone();
+        for param in params {
+            self.comp.get_mut(param).owner = ir::ParamOwner::bundle(idx);
+        }
 
         // Add the port to the current scope
         self.add_port(*name, idx);
         idx
     }
 
-    /// Transforms an access into (start, end)
-    fn access(&mut self, access: ast::Access) -> (ir::ExprIdx, ir::ExprIdx) {
-        match access {
-            ast::Access::Index(n) => {
-                let n = self.expr(n);
-                (n, n.add(self.comp.num(1), self.comp))
-            }
-            ast::Access::Range { start, end } => {
-                (self.expr(start), self.expr(end))
-            }
-        }
+    /// Transforms the access on each dimension into (start, end)
+    fn access(
+        &mut self,
+        access: Vec<ast::Loc<ast::Access>>,
+    ) -> Vec<(ir::ExprIdx, ir::ExprIdx)> {
+        access
+            .into_iter()
+            .map(|a| match a.take() {
+                ast::Access::Index(n) => {
+                    let n = self.expr(n);
+                    (n, n.add(self.comp.num(1), self.comp))
+                }
+                ast::Access::Range { start, end } => {
+                    (self.expr(start), self.expr(end))
+                }
+            })
+            .collect()
     }
 
     /// Get the index associated with an AST port. The port must have been
@@ -369,7 +424,13 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
         match port {
             ast::Port::This(n) => {
                 let owner = ir::PortOwner::Sig { dir };
-                ir::Access::port(self.get_port(n.copy(), owner), self.comp)
+                let port = match self.find_port(n.copy(), owner.clone()) {
+                    Some(port) => port,
+                    None => self
+                        .interface_port(n.copy(), &owner)
+                        .unwrap_or_else(|| self.get_port(n.copy(), owner)),
+                };
+                ir::Access::port(port, self.comp)
             }
             ast::Port::InvPort { invoke, name } => {
                 let inv = self.get_inv(invoke.copy());
@@ -387,8 +448,8 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                     let owner = ir::PortOwner::Local;
                     self.get_port(name.copy(), owner)
                 };
-                let (start, end) = self.access(access.take());
-                ir::Access { port, start, end }
+                let ranges = self.access(access);
+                ir::Access { port, ranges }
             }
             ast::Port::InvBundle {
                 invoke,
@@ -398,10 +459,99 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 let inv = self.get_inv(invoke.copy());
                 let owner = ir::PortOwner::Inv { inv, dir };
                 let port = self.get_port(port.copy(), owner);
-                let (start, end) = self.access(access.take());
-                ir::Access { port, start, end }
+                let ranges = self.access(access);
+                ir::Access { port, ranges }
+            }
+            ast::Port::Constant(_) => {
+                unreachable!("Constants are converted using their destination")
+            }
+        }
+    }
+
+    /// Get the port for the interface signal `name` when it is read as data.
+    /// Returns `None` if `name` is not an interface signal.
+    fn interface_port(
+        &mut self,
+        name: ast::Id,
+        owner: &ir::PortOwner,
+    ) -> Option<PortIdx> {
+        if *owner != ir::PortOwner::sig_out() {
+            return None;
+        }
+        if let Some(port) = self.interface_ports.get(&name) {
+            return Some(*port);
+        }
+        let src = self.comp.src_info.as_ref()?;
+        let ev = src
+            .interface_ports
+            .iter()
+            .find_map(|(ev, n)| (*n == name).then_some(*ev))?;
+        let ir::Info::Event { name: event, .. } =
+            self.comp.get(self.comp.get(ev).info)
+        else {
+            unreachable!("Expected event info")
+        };
+        let def = ast::InterfaceDef::new(ast::Loc::unknown(name), *event);
+        let port = self.port(def.into(), owner.clone());
+        self.interface_ports.insert(name, port);
+        Some(port)
+    }
+
+    /// Define a port for the constant `value` connected to the port `dst`.
+    /// The constant has the width of `dst` and is available whenever `dst`
+    /// requires it.
+    fn constant(
+        &mut self,
+        value: u64,
+        pos: GPosIdx,
+        dst: PortIdx,
+    ) -> ir::Access {
+        let ir::Port { width, live, .. } = self.comp.get(dst).clone();
+        let info = self.comp.add(ir::Info::port(
+            value.to_string().into(),
+            pos,
+            GPosIdx::UNKNOWN,
+            GPosIdx::UNKNOWN,
+        ));
+        let p_name = self.gen_name();
+        let live = self.with_scope(|ctx| ir::Liveness {
+            idxs: vec![ctx.param(
+                &ast::ParamBind::from(p_name),
+                // Updated after the port is constructed
+                ir::ParamOwner::bundle(ir::PortIdx::UNKNOWN),
+            )], // This parameter is unused
+            lens: vec![ctx.comp.num(1)],
+            range: live.range,
+        });
+        let p = ir::Port {
+            owner: ir::PortOwner::Const { value },
+            width,
+            repr: ir::NumRepr::Bits,
+            live,
+            info,
+        };
+        let idx = self.comp.add(p);
+        let params = self.comp.get(idx).live.idxs.clone();
+        for param in params {
+            self.comp.get_mut(param).owner = ir::ParamOwner::bundle(idx);
+        }
+        ir::Access::port(idx, self.comp)
+    }
+
+    fn guard(&mut self, guard: ast::Guard) -> ir::Guard {
+        match guard {
+            ast::Guard::Or(l, r, _) => ir::Guard::Or(
+                Box::new(self.guard(*l)),
+                Box::new(self.guard(*r)),
+            ),
+            ast::Guard::And(l, r, _) => ir::Guard::And(
+                Box::new(self.guard(*l)),
+                Box::new(self.guard(*r)),
+            ),
+            ast::Guard::Not(g, _) => ir::Guard::Not(Box::new(self.guard(*g))),
+            ast::Guard::Port(p) => {
+                ir::Guard::Port(self.get_access(p.take(), ir::Direction::Out))
             }
-            ast::Port::Constant(_) => todo!("Constant ports"),
         }
     }
 
@@ -419,6 +569,15 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             let idx = self.event_map.get(&event.inner().event).unwrap();
             self.comp.get_mut(*idx).delay = delay;
         }
+        // Track the source-level interface of the component
+        let mut src_info = ir::InterfaceSrc::new(sig.name.copy());
+        for interface in &sig.interface_signals {
+            let ev = *self.event_map.get(&interface.event).unwrap();
+            src_info.interface_ports.insert(ev, interface.name.copy());
+        }
+        src_info.unannotated_ports = sig.unannotated_ports.clone();
+        self.comp.src_info = Some(src_info);
+
         for port in sig.inputs() {
             // XXX(rachit): Unnecessary clone.
             self.port(port.inner().clone(), ir::PortOwner::sig_out());
@@ -583,9 +742,16 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             abstract_vars.iter().map(|v| v.inner().clone()),
         );
 
+        // Constants take the type of the port they are connected to so they
+        // are defined along with the invocation's ports.
         let srcs = ports
             .into_iter()
-            .map(|p| p.map(|p| self.get_access(p, ir::Direction::Out)))
+            .map(|p| {
+                p.map(|p| match p {
+                    ast::Port::Constant(c) => Err(c),
+                    p => Ok(self.get_access(p, ir::Direction::Out)),
+                })
+            })
             .collect_vec();
         assert!(
             sig.inputs.len() == srcs.len(),
@@ -628,16 +794,26 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             let pidx = self.port(resolved.take(), owner);
             self.comp.get_mut(inv).ports.push(pidx);
 
-            let end = self.comp[pidx].live.len;
+            let zero = self.comp.num(0);
             let dst = ir::Access {
                 port: pidx,
-                start: self.comp.num(0),
-                end,
+                ranges: self.comp[pidx]
+                    .live
+                    .lens
+                    .iter()
+                    .map(|&len| (zero, len))
+                    .collect(),
+            };
+            let pos = src.pos();
+            let src = match src.take() {
+                Ok(src) => src,
+                Err(c) => self.constant(c, pos, pidx),
             };
             connects.push(
                 ir::Connect {
-                    src: src.take(),
+                    src,
                     dst,
+                    guard: None,
                     info,
                 }
                 .into(),
@@ -649,7 +825,8 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             .events
             .iter()
             .zip(abstract_vars.iter())
-            .map(|(event, arg)| {
+            .enumerate()
+            .map(|(i, (event, arg))| {
                 let resolved = event
                     .clone()
                     .resolve_exprs(&param_binding)
@@ -660,7 +837,11 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
 
                 let arg = self.time(arg.inner().clone());
                 let event = self.event(resolved, ir::EventOwner::Inv { inv });
-                ir::EventBind::new(event, arg, info).into()
+                // The invoked component may not have been built yet so we
+                // track the position of the event in its signature and
+                // resolve it once all components are built.
+                let base = EventIdx::new(i);
+                ir::EventBind::new(event, arg, info, base).into()
             })
             .collect();
 
@@ -694,12 +875,28 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 fact.into_iter().collect()
             }
             ast::Command::Connect(ast::Connect { src, dst, guard }) => {
-                assert!(guard.is_none(), "Guards are not supported");
                 let info =
                     self.comp.add(ir::Info::connect(dst.pos(), src.pos()));
-                let src = self.get_access(src.take(), ir::Direction::Out);
-                let dst = self.get_access(dst.take(), ir::Direction::In);
-                vec![ir::Connect { src, dst, info }.into()]
+                let guard = guard.map(|g| self.guard(g));
+                let pos = src.pos();
+                let (src, dst) = match src.take() {
+                    ast::Port::Constant(c) => {
+                        let dst =
+                            self.get_access(dst.take(), ir::Direction::In);
+                        (self.constant(c, pos, dst.port), dst)
+                    }
+                    src => (
+                        self.get_access(src, ir::Direction::Out),
+                        self.get_access(dst.take(), ir::Direction::In),
+                    ),
+                };
+                vec![ir::Connect {
+                    src,
+                    dst,
+                    guard,
+                    info,
+                }
+                .into()]
             }
             ast::Command::ForLoop(ast::ForLoop {
                 idx,
@@ -744,10 +941,26 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
                 let alt = self.commands(alt);
                 vec![ir::If { cond, then, alt }.into()]
             }
+            ast::Command::Let(ast::ParamLet { name, .. }) => {
+                let Some(param) = self.get_param(name.inner()) else {
+                    unreachable!("Parameter {name} not found")
+                };
+                let ir::ParamOwner::Let { bind } = self.comp.get(param).owner
+                else {
+                    unreachable!("Parameter {name} is not defined by a let")
+                };
+                let reason = self.comp.add(
+                    ir::Reason::misc("parameter defined by let", name.pos())
+                        .into(),
+                );
+                let prop = param.expr(self.comp).equal(bind, self.comp);
+                self.comp.assume(prop, reason).into_iter().collect()
+            }
             ast::Command::Bundle(bun) => {
                 // Add the bundle to the current scope
-                self.port(ast::PortDef::Bundle(bun), ir::PortOwner::Local);
-                vec![]
+                let idx =
+                    self.port(ast::PortDef::Bundle(bun), ir::PortOwner::Local);
+                vec![idx.into()]
             }
         }
     }
@@ -759,7 +972,9 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             .comp
             .ports()
             .iter()
-            .map(|(_, p)| (p.live.idx, p.live.len))
+            .flat_map(|(_, p)| {
+                p.live.idxs.iter().copied().zip(p.live.lens.iter().copied())
+            })
             .collect_vec();
         // Add assumptions for range of bundle-bound indices
         let reason = self.comp.add(
@@ -777,7 +992,7 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
     }
 
     fn external(idx: CompIdx, sig: ast::Signature) -> ir::Component {
-        let mut ir_comp = ir::Component::new(idx, false);
+        let mut ir_comp = ir::Component::new(idx, true);
         let binding = SigMap::default();
         let mut builder = BuildCtx::new(&mut ir_comp, &binding);
 
@@ -812,13 +1027,20 @@ pub fn transform(ns: ast::Namespace) -> ir::Context {
         sig_map.insert(sig.name.copy(), Sig::from((sig, idx)));
     }
 
-    let mut ctx = ir::Context::default();
-    for (_, exts) in ns.externs {
+    let mut ctx = ir::Context {
+        entrypoint: sig_map.get(&ns.toplevel.as_str().into()).map(|s| s.idx),
+        funcs: ns.funcs.clone(),
+        ..Default::default()
+    };
+    for (file, exts) in ns.externs {
+        let mut idxs = Vec::with_capacity(exts.len());
         for ext in exts {
             let idx = sig_map.get(&ext.name).unwrap().idx;
             let ir_ext = BuildCtx::external(idx, ext);
             ctx.comps.checked_add(idx, ir_ext);
+            idxs.push(idx);
         }
+        ctx.externals.push((file, idxs));
     }
 
     for comp in ns.components {
@@ -826,5 +1048,55 @@ pub fn transform(ns: ast::Namespace) -> ir::Context {
         let ir_comp = BuildCtx::comp(comp, idx, &sig_map);
         ctx.comps.checked_add(idx, ir_comp);
     }
+    resolve_event_binds(&mut ctx);
     ctx
 }
+
+/// Replace the signature positions stored in [ir::EventBind::base] with the
+/// corresponding events of the invoked components.
+fn resolve_event_binds(ctx: &mut ir::Context) {
+    let sig_events: HashMap<CompIdx, Vec<EventIdx>> = ctx
+        .comps
+        .iter()
+        .map(|(idx, comp)| {
+            let evs = comp
+                .events()
+                .iter()
+                .filter(|(_, ev)| ev.owner.is_sig())
+                .map(|(ev, _)| ev)
+                .collect_vec();
+            (idx, evs)
+        })
+        .collect();
+
+    fn resolve(
+        cmds: &mut [ir::Command],
+        comp: &ir::Component,
+        ctx: &HashMap<CompIdx, Vec<EventIdx>>,
+    ) {
+        for cmd in cmds {
+            match cmd {
+                ir::Command::EventBind(eb) => {
+                    let ir::EventOwner::Inv { inv } = comp.get(eb.event).owner
+                    else {
+                        unreachable!("Event binding for a non-invocation event")
+                    };
+                    let callee = comp.get(comp.get(inv).inst).comp;
+                    eb.base = ctx[&callee][eb.base.get()];
+                }
+                ir::Command::ForLoop(l) => resolve(&mut l.body, comp, ctx),
+                ir::Command::If(i) => {
+                    resolve(&mut i.then, comp, ctx);
+                    resolve(&mut i.alt, comp, ctx);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    for (_, comp) in ctx.comps.iter_mut() {
+        let mut cmds = std::mem::take(&mut comp.cmds);
+        resolve(&mut cmds, comp, &sig_events);
+        comp.cmds = cmds;
+    }
+}
@@ -80,6 +80,9 @@ pub(super) struct BuildCtx<'ctx, 'prog> {
     inv_map: ScopeMap<ir::Invoke>,
     port_map: ScopeMap<ir::Port, InvPort>,
     param_map: ScopeMap<ir::Param>,
+    /// Ports for the interface signals that are read as data. They are defined
+    /// on their first use.
+    pub interface_ports: HashMap<Id, PortIdx>,
 
     /// Index for generating unique names
     name_idx: u32,
@@ -96,6 +99,7 @@ impl<'ctx, 'prog> BuildCtx<'ctx, 'prog> {
             inst_map: ScopeMap::new(),
             inv_map: ScopeMap::new(),
             inst_to_sig: DenseIndexInfo::default(),
+            interface_ports: HashMap::new(),
         }
     }
 
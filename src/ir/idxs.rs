@@ -26,7 +26,7 @@ impl PortIdx {
         C: Ctx<Port> + Ctx<Expr>,
     {
         let port = ctx.get(*self);
-        port.live.len.is_const(ctx, 1)
+        port.live.lens.iter().all(|len| len.is_const(ctx, 1))
     }
 }
 
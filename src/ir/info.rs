@@ -1,8 +1,8 @@
-use super::{Component, DisplayCtx, ExprIdx, Range, TimeIdx, TimeSub};
+use super::{Component, DisplayCtx, ExprIdx, NumRepr, Range, TimeIdx, TimeSub};
 use crate::{ast, utils::GPosIdx};
 use codespan_reporting::diagnostic::Diagnostic;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 /// Information associated with the IR.
 pub enum Info {
     #[default]
@@ -27,15 +27,18 @@ pub enum Info {
     EventBind { bind_loc: GPosIdx },
     /// For [super::Instance]
     Instance {
+        /// Surface-level name of the instance
+        name: ast::Id,
         name_loc: GPosIdx,
         comp_loc: GPosIdx,
         bind_loc: GPosIdx,
     },
     /// For [super::Invoke]
     Invoke {
+        /// Surface-level name of the invocation
+        name: ast::Id,
         name_loc: GPosIdx,
         inst_loc: GPosIdx,
-        bind_loc: GPosIdx,
     },
     /// For [super::Connect]
     Connect { dst_loc: GPosIdx, src_loc: GPosIdx },
@@ -46,6 +49,8 @@ pub enum Info {
         bind_loc: GPosIdx,
         width_loc: GPosIdx,
         live_loc: GPosIdx,
+        /// The port was declared as a bundle
+        bundle: bool,
     },
 }
 
@@ -71,17 +76,27 @@ impl Info {
     }
 
     pub fn instance(
+        name: ast::Id,
         name_loc: GPosIdx,
         comp_loc: GPosIdx,
         bind_loc: GPosIdx,
     ) -> Self {
         Self::Instance {
+            name,
             name_loc,
             comp_loc,
             bind_loc,
         }
     }
 
+    pub fn invoke(name: ast::Id, name_loc: GPosIdx, inst_loc: GPosIdx) -> Self {
+        Self::Invoke {
+            name,
+            name_loc,
+            inst_loc,
+        }
+    }
+
     pub fn connect(dst_loc: GPosIdx, src_loc: GPosIdx) -> Self {
         Self::Connect { dst_loc, src_loc }
     }
@@ -97,6 +112,22 @@ impl Info {
             bind_loc,
             width_loc,
             live_loc,
+            bundle: false,
+        }
+    }
+
+    pub fn bundle(
+        name: ast::Id,
+        bind_loc: GPosIdx,
+        width_loc: GPosIdx,
+        live_loc: GPosIdx,
+    ) -> Self {
+        Self::Port {
+            name,
+            bind_loc,
+            width_loc,
+            live_loc,
+            bundle: true,
         }
     }
 }
@@ -133,6 +164,13 @@ pub enum Reason {
         dst_width: ExprIdx,
         src_width: ExprIdx,
     },
+    /// Require that the numeric representations of connected ports match
+    ReprMatch {
+        dst_loc: GPosIdx,
+        src_loc: GPosIdx,
+        dst_repr: NumRepr,
+        src_repr: NumRepr,
+    },
     /// An access is within bounds
     InBoundsAccess {
         // Defining location for the port
@@ -157,10 +195,9 @@ pub enum Reason {
         event_delay_loc: GPosIdx,
         bundle_range_loc: GPosIdx,
         bundle_live: TimeSub,
-        /// The bundle paramemter's binding location
-        param_loc: GPosIdx,
-        /// The start and end of index's range
-        param_range: (ExprIdx, ExprIdx),
+        /// The binding location of each bundle parameter along with the
+        /// start and end of its range
+        params: Vec<(GPosIdx, (ExprIdx, ExprIdx))>,
     },
     /// Well formed time interval
     WellFormedInterval {
@@ -196,15 +233,13 @@ impl Reason {
         event_delay_loc: GPosIdx,
         bundle_range_loc: GPosIdx,
         bundle_live: TimeSub,
-        param_loc: GPosIdx,
-        param_range: (ExprIdx, ExprIdx),
+        params: Vec<(GPosIdx, (ExprIdx, ExprIdx))>,
     ) -> Self {
         Self::BundleDelay {
             event_delay_loc,
             bundle_range_loc,
             bundle_live,
-            param_loc,
-            param_range,
+            params,
         }
     }
 
@@ -292,6 +327,20 @@ impl Reason {
         }
     }
 
+    pub fn repr_match(
+        dst_loc: GPosIdx,
+        src_loc: GPosIdx,
+        dst_repr: NumRepr,
+        src_repr: NumRepr,
+    ) -> Self {
+        Self::ReprMatch {
+            dst_loc,
+            src_loc,
+            dst_repr,
+            src_repr,
+        }
+    }
+
     pub fn well_formed_interval(
         range_loc: GPosIdx,
         range: (TimeIdx, TimeIdx),
@@ -307,6 +356,23 @@ impl From<Reason> for Info {
 }
 
 impl Reason {
+    /// Stable code identifying the kind of this reason in diagnostics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Reason::ParamConstraint { .. } => "param-constraint",
+            Reason::EventConstraint { .. } => "event-constraint",
+            Reason::BundleLenMatch { .. } => "bundle-len-match",
+            Reason::BundleWidthMatch { .. } => "bundle-width-match",
+            Reason::ReprMatch { .. } => "repr-match",
+            Reason::InBoundsAccess { .. } => "in-bounds-access",
+            Reason::Liveness { .. } => "liveness",
+            Reason::BundleDelay { .. } => "bundle-delay",
+            Reason::WellFormedInterval { .. } => "well-formed-interval",
+            Reason::EventTrig { .. } => "event-trig",
+            Reason::Misc { .. } => "misc",
+        }
+    }
+
     /// Convert this reason into a diagnostic message
     pub fn diag(&self, ctx: &Component) -> Diagnostic<usize> {
         match self {
@@ -397,6 +463,25 @@ impl Reason {
                     .with_message(format!("required bundle of width `{dw}' but found bundle of width `{sw}'"))
                     .with_labels(vec![src, dst])
             }
+            Reason::ReprMatch {
+                dst_loc,
+                src_loc,
+                dst_repr,
+                src_repr,
+            } => {
+                let sr = ctx.display_repr(src_repr);
+                let dr = ctx.display_repr(dst_repr);
+                let src =
+                    src_loc.primary().with_message(format!("source is {sr}"));
+                let dst = dst_loc
+                    .secondary()
+                    .with_message(format!("destination is {dr}"));
+                Diagnostic::error()
+                    .with_message(format!(
+                        "required `{dr}' value but found `{sr}' value"
+                    ))
+                    .with_labels(vec![src, dst])
+            }
             Reason::Liveness {
                 dst_loc,
                 src_loc,
@@ -419,8 +504,7 @@ impl Reason {
                 event_delay_loc,
                 bundle_range_loc,
                 bundle_live,
-                param_loc,
-                param_range,
+                params,
             } => {
                 let wire = bundle_range_loc.primary().with_message(format!(
                     "available for {} cycles",
@@ -430,14 +514,16 @@ impl Reason {
                     event_delay_loc.secondary().with_message("event's delay");
                 let mut labels = vec![wire, event];
 
-                // If the parameter location is not defined, we do not report its location
-                if let Some(loc) = param_loc.into_option() {
-                    let param = loc.secondary().with_message(format!(
-                        "takes values in [{}, {})",
-                        ctx.display(param_range.0),
-                        ctx.display(param_range.1)
-                    ));
-                    labels.push(param);
+                // If a parameter location is not defined, we do not report its location
+                for (param_loc, param_range) in params {
+                    if let Some(loc) = param_loc.into_option() {
+                        let param = loc.secondary().with_message(format!(
+                            "takes values in [{}, {})",
+                            ctx.display(param_range.0),
+                            ctx.display(param_range.1)
+                        ));
+                        labels.push(param);
+                    }
                 }
 
                 Diagnostic::error()
@@ -1,3 +1,4 @@
+mod canonical;
 mod comp;
 mod control;
 mod ctx;
@@ -12,8 +13,10 @@ mod subst;
 mod time;
 mod utils;
 
-pub use comp::{Component, Context};
-pub use control::{Command, Connect, EventBind, If, Instance, Invoke, Loop};
+pub use comp::{Component, Context, InterfaceSrc};
+pub use control::{
+    Command, Connect, EventBind, Guard, If, Instance, Invoke, Loop,
+};
 pub use ctx::{Ctx, MutCtx};
 pub use expr::Expr;
 pub use fact::{Cmp, CmpOp, Fact, Prop};
@@ -25,8 +28,8 @@ pub use idxs::{
 pub use info::{Info, Reason};
 pub use printer::{DisplayCtx, Printer};
 pub use structure::{
-    Access, Direction, Event, EventOwner, Liveness, Param, ParamOwner, Port,
-    PortOwner, Range,
+    Access, Direction, Event, EventOwner, Liveness, NumRepr, Param, ParamOwner,
+    Port, PortOwner, Range,
 };
 pub use subst::{Bind, Foldable, Subst};
 pub use time::{Time, TimeSub};
@@ -36,20 +36,23 @@ impl DisplayCtx<ir::Time> for ir::Component {
     }
 }
 
+/// Events and parameters are displayed using their surface-level names so
+/// that diagnostics read like the source program.
 impl DisplayCtx<ir::Event> for ir::Component {
     fn display(&self, idx: Idx<ir::Event>) -> String {
-        if log::log_enabled!(log::Level::Debug) {
-            format!("{idx}")
-        } else {
-            let ev = self.get(idx);
-            let ir::Info::Event { name, .. } = self.get(ev.info) else {
-                unreachable!("Expccted event info")
-            };
-            match ev.owner {
-                ir::EventOwner::Sig => name.to_string(),
-                ir::EventOwner::Inv { inv } => {
-                    format!("{}.{name}", inv)
-                }
+        let ev = self.get(idx);
+        let ir::Info::Event { name, .. } = self.get(ev.info) else {
+            unreachable!("Expected event info")
+        };
+        match ev.owner {
+            ir::EventOwner::Sig => name.to_string(),
+            ir::EventOwner::Inv { inv } => {
+                let ir::Info::Invoke { name: inv, .. } =
+                    self.get(self.get(inv).info)
+                else {
+                    unreachable!("Expected invoke info")
+                };
+                format!("{inv}.{name}")
             }
         }
     }
@@ -57,15 +60,15 @@ impl DisplayCtx<ir::Event> for ir::Component {
 
 impl DisplayCtx<ir::Param> for ir::Component {
     fn display(&self, idx: ir::ParamIdx) -> String {
-        if log::log_enabled!(log::Level::Debug) {
-            format!("{idx}")
-        } else {
-            let param = self.get(idx);
-            let ir::Info::Param { name, .. } = self.get(param.info) else {
-                unreachable!("Expected param info");
-            };
-            format!("#{name}")
+        let param = self.get(idx);
+        // Generated index parameters are named after their port
+        if let Some(port) = self.index_of(idx) {
+            return format!("#{}.idx", self.display(port));
         }
+        let ir::Info::Param { name, .. } = self.get(param.info) else {
+            unreachable!("Expected param info");
+        };
+        format!("#{name}")
     }
 }
 
@@ -105,6 +108,25 @@ impl Printer<'_> {
         self.ctx.display(e)
     }
 
+    /// The type of a port: its numeric representation and bitwidth
+    fn typ(&self, port: &ir::Port) -> String {
+        match &port.repr {
+            ir::NumRepr::Bits => self.expr(port.width),
+            ir::NumRepr::Num { signed, frac } => {
+                let sign = if *signed { "signed" } else { "unsigned" };
+                if frac.is_const(self.ctx, 0) {
+                    format!("{sign} {}", self.expr(port.width))
+                } else {
+                    format!(
+                        "{sign}<{}> {}",
+                        self.expr(*frac),
+                        self.expr(port.width)
+                    )
+                }
+            }
+        }
+    }
+
     fn time(&self, t: ir::TimeIdx) -> String {
         self.ctx.display(t)
     }
@@ -115,8 +137,13 @@ impl Printer<'_> {
     }
 
     fn liveness(&self, l: &ir::Liveness) -> String {
-        let ir::Liveness { idx, len, range } = l;
-        format!("for<{idx}: {}> {}", self.expr(*len), self.range(range))
+        let ir::Liveness { idxs, lens, range } = l;
+        let params = idxs
+            .iter()
+            .zip(lens)
+            .map(|(idx, len)| format!("{idx}: {}", self.expr(*len)))
+            .join(", ");
+        format!("for<{params}> {}", self.range(range))
     }
 
     fn commands(
@@ -133,8 +160,17 @@ impl Printer<'_> {
     }
 
     fn access(&self, a: &ir::Access) -> String {
-        let &ir::Access { port, start, end } = a;
-        format!("{port}[{}..{})", self.expr(start), self.expr(end))
+        let ir::Access { port, ranges } = a;
+        if let ir::PortOwner::Const { value } = self.ctx.get(*port).owner {
+            return value.to_string();
+        }
+        let ranges = ranges
+            .iter()
+            .map(|(start, end)| {
+                format!("[{}..{})", self.expr(*start), self.expr(*end))
+            })
+            .join("");
+        format!("{port}{ranges}")
     }
 
     fn connect(
@@ -143,16 +179,35 @@ impl Printer<'_> {
         indent: usize,
         f: &mut impl io::Write,
     ) -> io::Result<()> {
-        let ir::Connect { src, dst, .. } = c;
+        let ir::Connect {
+            src, dst, guard, ..
+        } = c;
+        let guard = guard
+            .as_ref()
+            .map(|g| format!("{} ? ", self.guard(g)))
+            .unwrap_or_default();
         write!(
             f,
-            "{:indent$}{} = {};",
+            "{:indent$}{} = {guard}{};",
             "",
             self.access(dst),
             self.access(src),
         )
     }
 
+    fn guard(&self, g: &ir::Guard) -> String {
+        match g {
+            ir::Guard::Or(l, r) => {
+                format!("({} | {})", self.guard(l), self.guard(r))
+            }
+            ir::Guard::And(l, r) => {
+                format!("({} & {})", self.guard(l), self.guard(r))
+            }
+            ir::Guard::Not(g) => format!("!{}", self.guard(g)),
+            ir::Guard::Port(p) => self.access(p),
+        }
+    }
+
     pub fn command(
         &self,
         c: &ir::Command,
@@ -166,6 +221,9 @@ impl Printer<'_> {
             ir::Command::Invoke(inv) => {
                 write!(f, "{:indent$}{inv};", "")
             }
+            ir::Command::BundleDef(port) => {
+                write!(f, "{:indent$}bundle {port};", "")
+            }
             ir::Command::Connect(con) => self.connect(con, indent, f),
             ir::Command::EventBind(ir::EventBind { event, arg, .. }) => {
                 write!(f, "{:indent$}bind {} to {}", "", event, self.time(*arg))
@@ -263,7 +321,7 @@ impl Printer<'_> {
                     "{:indent$}{idx}: {} {},",
                     "",
                     self.liveness(&port.live),
-                    self.expr(port.width),
+                    self.typ(port),
                     indent = indent + 2
                 )
             }
@@ -273,7 +331,7 @@ impl Printer<'_> {
                     "{:indent$}{idx}: {} {}",
                     "",
                     self.liveness(&port.live),
-                    self.expr(port.width),
+                    self.typ(port),
                     indent = indent + 2
                 )
             }
@@ -325,18 +383,16 @@ impl Printer<'_> {
         indent: usize,
         f: &mut impl io::Write,
     ) -> io::Result<()> {
-        let ir::Port {
-            owner, width, live, ..
-        } = port;
+        let ir::Port { owner, live, .. } = port;
         match &owner {
-            ir::PortOwner::Sig { .. } => Ok(()),
+            ir::PortOwner::Sig { .. } | ir::PortOwner::Const { .. } => Ok(()),
             ir::PortOwner::Inv { dir, .. } => {
                 writeln!(
                     f,
                     "{:indent$}{idx}: bundle({dir}) {} {};",
                     "",
                     self.liveness(live),
-                    self.expr(*width),
+                    self.typ(port),
                 )
             }
             ir::PortOwner::Local => {
@@ -345,7 +401,7 @@ impl Printer<'_> {
                     "{:indent$}{idx} = bundle {} {};",
                     "",
                     self.liveness(live),
-                    self.expr(*width),
+                    self.typ(port),
                 )
             }
         }
@@ -380,7 +436,7 @@ impl Printer<'_> {
         f: &mut impl io::Write,
     ) -> io::Result<()> {
         write!(f, "{:indent$}{idx} = instance ", "")?;
-        let ir::Instance { comp, params } = inst;
+        let ir::Instance { comp, params, .. } = inst;
         write!(f, "{}[", comp)?;
         for (i, param) in params.iter().enumerate() {
             if i != 0 {
@@ -397,7 +453,7 @@ impl Printer<'_> {
         indent: usize,
         f: &mut impl io::Write,
     ) -> io::Result<()> {
-        let ir::Invoke { inst, ports } = c;
+        let ir::Invoke { inst, ports, .. } = c;
 
         writeln!(
             f,
@@ -558,8 +614,23 @@ impl ir::Component {
             ir::Expr::Concrete(n) => format!("{n}"),
             ir::Expr::Bin { op, lhs, rhs } => {
                 let inner = ECtx::from(*op);
-                let left = self.display_expr_helper(*lhs, inner);
-                let right = self.display_expr_helper(*rhs, inner);
+                // The right operand of a subtraction is wrapped in parens
+                // unless it is a product.
+                let (lctx, rctx) = match op {
+                    ast::Op::Sub => (ECtx::Add, ECtx::Mul),
+                    _ => (inner, inner),
+                };
+                let left = self.display_expr_helper(*lhs, lctx);
+                let mut right = self.display_expr_helper(*rhs, rctx);
+                // Only products can be nested on the right of a product
+                if let ir::Expr::Bin { op: rop, .. } = self.get(*rhs) {
+                    if inner == ECtx::Mul
+                        && ECtx::from(*rop) == ECtx::Mul
+                        && (*op != ast::Op::Mul || *rop != ast::Op::Mul)
+                    {
+                        right = format!("({right})");
+                    }
+                }
                 // If context binds more tightly than the inner operator,
                 // wrap the inner expression in parens.
                 if ctx > inner {
@@ -569,12 +640,8 @@ impl ir::Component {
                 }
             }
             ir::Expr::Fn { op, args } => {
-                let fn_str = match op {
-                    ast::UnFn::Pow2 => "pow2",
-                    ast::UnFn::Log2 => "log2",
-                };
                 format!(
-                    "{fn_str}({args})",
+                    "{op}({args})",
                     args = args
                         .iter()
                         .map(|a| self.display_expr_helper(*a, ECtx::default()))
@@ -664,4 +731,22 @@ impl ir::Component {
     pub fn display_range(&self, r: &ir::Range) -> String {
         format!("@[{}, {}]", self.display(r.start), self.display(r.end))
     }
+
+    /// Surface-level description of a numeric representation
+    pub fn display_repr(&self, repr: &ir::NumRepr) -> String {
+        match repr {
+            ir::NumRepr::Bits => "bits".to_string(),
+            ir::NumRepr::Num { signed, frac } => {
+                let sign = if *signed { "signed" } else { "unsigned" };
+                if frac.is_const(self, 0) {
+                    sign.to_string()
+                } else {
+                    format!(
+                        "{sign} fixed-point with {} fractional bits",
+                        self.display(*frac)
+                    )
+                }
+            }
+        }
+    }
 }
@@ -1,9 +1,8 @@
-use crate::ast::Op;
-
 use super::{
     Bind, Component, Ctx, Expr, ExprIdx, Foldable, InfoIdx, InvIdx, ParamIdx,
     PortIdx, Subst, TimeIdx, TimeSub,
 };
+use itertools::Itertools;
 use std::fmt;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -43,6 +42,8 @@ pub enum PortOwner {
     /// The port is defined locally.
     /// It does not have a direction because both reading and writing to it is allowed.
     Local,
+    /// The port is a constant value which is available in every cycle
+    Const { value: u64 },
 }
 
 impl fmt::Display for PortOwner {
@@ -51,6 +52,7 @@ impl fmt::Display for PortOwner {
             Self::Sig { dir } => write!(f, "sig({})", dir),
             Self::Inv { inv, dir } => write!(f, "{}({})", inv, dir),
             Self::Local => write!(f, "local"),
+            Self::Const { value } => write!(f, "const({value})"),
         }
     }
 }
@@ -105,17 +107,24 @@ impl fmt::Display for Direction {
 /// Duration when the port caries a meaningful value.
 /// Equivalent to the bundle type:
 /// ```
-/// p[N]: for<#i> @[G, G+i+10]
+/// p[N][M]: for<#i, #j> @[G, G+i+j+10]
 /// ```
+/// Each dimension of the bundle has an index parameter and a length.
 pub struct Liveness {
-    pub idx: ParamIdx,
-    pub len: ExprIdx,
+    pub idxs: Vec<ParamIdx>,
+    pub lens: Vec<ExprIdx>,
     pub range: Range,
 }
 
 impl fmt::Display for Liveness {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "for<{}: {}> {}", self.idx, self.len, self.range)
+        let params = self
+            .idxs
+            .iter()
+            .zip(&self.lens)
+            .map(|(idx, len)| format!("{idx}: {len}"))
+            .join(", ");
+        write!(f, "for<{params}> {}", self.range)
     }
 }
 
@@ -125,6 +134,7 @@ impl fmt::Display for Liveness {
 pub struct Port {
     pub owner: PortOwner,
     pub width: ExprIdx,
+    pub repr: NumRepr,
     pub live: Liveness,
     pub info: InfoIdx,
 }
@@ -152,15 +162,23 @@ impl fmt::Display for Port {
     }
 }
 
+#[derive(PartialEq, Eq, Hash, Clone)]
+/// Numeric interpretation of the values carried by a port
+pub enum NumRepr {
+    /// Uninterpreted bits that can be connected to any representation
+    Bits,
+    /// A number with `frac` fractional bits. Integers have no fractional bits.
+    Num { signed: bool, frac: ExprIdx },
+}
+
 #[derive(PartialEq, Eq, Hash, Clone)]
 /// Represents a port access in bundle syntax since the IR desugars all ports to
 /// bundles.
 pub struct Access {
     pub port: PortIdx,
-    /// The start of the access range (inclusive)
-    pub start: ExprIdx,
-    /// The end of the access range (exclusive)
-    pub end: ExprIdx,
+    /// The range accessed in each dimension of the port. Each range is a
+    /// start (inclusive) and an end (exclusive).
+    pub ranges: Vec<(ExprIdx, ExprIdx)>,
 }
 impl Access {
     /// Construct an access on a simple port (i.e. not a bundle)
@@ -170,60 +188,70 @@ impl Access {
         let one = ctx.add(Expr::Concrete(1));
         Self {
             port,
-            start: zero,
-            end: one,
+            ranges: vec![(zero, one)],
         }
     }
 
+    /// Check if the access on the given dimension is guaranteed to be an
+    /// index, i.e., the range contains exactly one element.
+    /// Since expressions are canonicalized by the component, this holds
+    /// whenever the end of the range and the start plus one have the same
+    /// normal form.
+    pub fn is_index(&self, dim: usize, ctx: &mut impl Ctx<Expr>) -> bool {
+        let (start, end) = self.ranges[dim];
+        let one = ctx.add(Expr::Concrete(1));
+        start.add(one, ctx) == end
+    }
+
+    /// The dimensions of this access that are guaranteed to be indices
+    pub fn index_dims(&self, ctx: &mut impl Ctx<Expr>) -> Vec<bool> {
+        (0..self.ranges.len())
+            .map(|dim| self.is_index(dim, ctx))
+            .collect()
+    }
+
     /// Check if this is guaranteed a simple port access, i.e., an access that
     /// produces one port.
     /// The check is syntactic and therefore conservative.
     pub fn is_port(&self, ctx: &mut impl Ctx<Expr>) -> bool {
-        let one = ctx.add(Expr::Concrete(1));
-        match ctx.get(self.end) {
-            Expr::Bin {
-                op: Op::Add,
-                lhs,
-                rhs,
-            } => {
-                *rhs == one && self.start == *lhs
-                    || *lhs == one && self.start == *rhs
-            }
-            Expr::Concrete(e) => {
-                if let Some(s) = self.start.as_concrete(ctx) {
-                    *e == s + 1
-                } else {
-                    false
-                }
-            }
-            _ => false,
-        }
+        (0..self.ranges.len()).all(|dim| self.is_index(dim, ctx))
     }
 
-    /// Return the bundle type associated with this access
+    /// Return the bundle type associated with this access.
+    /// The bundle type has a dimension for each dimension of the access.
     pub fn bundle_typ(&self, ctx: &mut Component) -> Liveness {
         let live = ctx.get(self.port).live.clone();
-        let binding = if self.is_port(ctx) {
-            // If this access produces exactly one port, then remap `#idx` to `start`.
-            [(live.idx, self.start)]
-        } else {
-            // Remap `#idx` to `#idx+start
-            [(live.idx, live.idx.expr(ctx).add(self.start, ctx))]
-        };
+        let mut binding = Vec::with_capacity(live.idxs.len());
+        let mut lens = Vec::with_capacity(live.idxs.len());
+        for (dim, (&idx, &(start, end))) in
+            live.idxs.iter().zip(&self.ranges).enumerate()
+        {
+            if self.is_index(dim, ctx) {
+                // If this dimension is indexed, then remap `#idx` to `start`.
+                binding.push((idx, start));
+            } else {
+                // Remap `#idx` to `#idx+start
+                binding.push((idx, idx.expr(ctx).add(start, ctx)));
+            }
+            // Shrink the bundle type based on the access
+            lens.push(end.sub(start, ctx));
+        }
 
         let range = Subst::new(live.range, &Bind::new(&binding)).apply(ctx);
-        // Shrink the bundle type based on the access
-        let len = self.end.sub(self.start, ctx);
         Liveness {
-            idx: live.idx,
-            len,
+            idxs: live.idxs,
+            lens,
             range,
         }
     }
 }
 impl fmt::Display for Access {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}[{}..{})", self.port, self.start, self.end)
+        write!(f, "{}", self.port)?;
+        for (start, end) in &self.ranges {
+            write!(f, "[{start}..{end})")?;
+        }
+        Ok(())
     }
 }
 
@@ -236,6 +264,8 @@ pub enum ParamOwner {
     Bundle(PortIdx),
     /// Loop indexing parameter
     Loop,
+    /// Defined by a `let` binding in the body of the component
+    Let { bind: ExprIdx },
 }
 
 impl ParamOwner {
@@ -250,6 +280,7 @@ impl fmt::Display for ParamOwner {
             Self::Sig => write!(f, "sig"),
             Self::Bundle(p) => write!(f, "bundle({p})"),
             Self::Loop => write!(f, "loop"),
+            Self::Let { bind } => write!(f, "let({bind})"),
         }
     }
 }
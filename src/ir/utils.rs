@@ -1,5 +1,5 @@
 use crate::utils::Idx;
-use std::{collections::HashMap, fmt::Display, marker::PhantomData, rc::Rc};
+use std::{collections::HashMap, fmt::Display, marker::PhantomData, sync::Arc};
 
 /// An indexed storage for an interned type. Keeps a HashMap to provide faster reverse mapping
 /// from the value to the index.
@@ -13,8 +13,8 @@ pub struct Interned<T>
 where
     T: Eq + std::hash::Hash,
 {
-    store: Vec<Rc<T>>,
-    map: HashMap<Rc<T>, Idx<T>>,
+    store: Vec<Arc<T>>,
+    map: HashMap<Arc<T>, Idx<T>>,
 }
 
 impl<T> Default for Interned<T>
@@ -36,7 +36,7 @@ where
     /// Intern a value into the store and return the index.
     /// If the value is already in the store, return the existing index.
     pub fn intern(&mut self, val: T) -> Idx<T> {
-        let v = Rc::new(val);
+        let v = Arc::new(val);
         if let Some(idx) = self.map.get(&v) {
             return *idx;
         }
@@ -86,13 +86,19 @@ where
 }
 
 /// An indexed store for a type. Unlike [Interned], this data structure does not deduplicate values.
+/// Values can be deleted from the store but their indices are never reused.
 pub struct IndexStore<T> {
     store: Vec<T>,
+    /// Tracks whether the value at a particular index has been deleted
+    valid: Vec<bool>,
 }
 
 impl<T> Default for IndexStore<T> {
     fn default() -> Self {
-        Self { store: Vec::new() }
+        Self {
+            store: Vec::new(),
+            valid: Vec::new(),
+        }
     }
 }
 
@@ -102,20 +108,36 @@ impl<T> IndexStore<T> {
         // Add the value to the store and return index
         let idx = Idx::new(self.store.len());
         self.store.push(val);
+        self.valid.push(true);
         idx
     }
 
     /// Get the value associated with the index.
+    /// Panics if the value has been deleted.
     pub fn get(&self, idx: Idx<T>) -> &T {
+        assert!(self.valid[idx.get()], "Attempted to get deleted value");
         &self.store[idx.get()]
     }
 
     /// Get a mutable reference to the value associated with the index.
+    /// Panics if the value has been deleted.
     pub fn get_mut(&mut self, idx: Idx<T>) -> &mut T {
+        assert!(self.valid[idx.get()], "Attempted to get deleted value");
         &mut self.store[idx.get()]
     }
 
-    /// Number of elements in the store
+    /// Delete the value associated with the index.
+    /// Subsequent accesses to the index will panic.
+    pub fn delete(&mut self, idx: Idx<T>) {
+        self.valid[idx.get()] = false;
+    }
+
+    /// Check if the value associated with the index has not been deleted.
+    pub fn is_valid(&self, idx: Idx<T>) -> bool {
+        self.valid[idx.get()]
+    }
+
+    /// Number of elements in the store, including deleted ones.
     pub fn len(&self) -> usize {
         self.store.len()
     }
@@ -125,25 +147,35 @@ impl<T> IndexStore<T> {
         self.store.is_empty()
     }
 
-    /// Iterate over the indices in the store.
+    /// Iterate over the valid indices in the store.
     /// This can be useful because it allows us to mutably borrow the containing struct.
     pub fn idx_iter(&self) -> impl Iterator<Item = Idx<T>> {
-        (0..self.store.len()).map(Idx::new)
+        self.valid
+            .clone()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, valid)| valid.then_some(Idx::new(idx)))
     }
 
-    /// Iterate over the indices and the values in the store.
+    /// Iterate over the valid indices and the values in the store.
     pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &T)> {
         self.store
             .iter()
+            .zip(self.valid.iter())
             .enumerate()
-            .map(|(idx, val)| (Idx::new(idx), val))
+            .filter_map(|(idx, (val, valid))| {
+                valid.then_some((Idx::new(idx), val))
+            })
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (Idx<T>, &mut T)> {
         self.store
             .iter_mut()
+            .zip(self.valid.iter())
             .enumerate()
-            .map(|(idx, val)| (Idx::new(idx), val))
+            .filter_map(|(idx, (val, valid))| {
+                valid.then_some((Idx::new(idx), val))
+            })
     }
 
     pub(super) fn checked_add(&mut self, idx: Idx<T>, val: T) {
@@ -159,8 +191,8 @@ impl<T> IndexStore<T> {
 
 impl<T: Display> Display for IndexStore<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (idx, val) in self.store.iter().enumerate() {
-            writeln!(f, "{idx}={val}")?;
+        for (idx, val) in self.iter() {
+            writeln!(f, "{}={val}", idx.get())?;
         }
         Ok(())
     }
@@ -214,6 +246,16 @@ impl<T, V> DenseIndexInfo<T, V> {
     pub fn contains(&self, idx: Idx<T>) -> bool {
         idx.get() < self.store.len()
     }
+
+    /// Number of values in the map.
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    /// Check if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
 }
 
 impl<T, V> std::ops::Index<Idx<T>> for DenseIndexInfo<T, V> {
@@ -58,6 +58,8 @@ impl ir::Component {
                 // #r = 1 => #l = 0
                 rhs.equal(one, self).implies(lhs.equal(zero, self), self),
             ],
+            // User-defined functions are constrained by their axioms
+            ast::UnFn::User(_) => vec![],
         }
     }
 }
@@ -80,21 +82,21 @@ impl Assume {
                     comp.get(*lhs),
                     comp.get(*rhs),
                 ) {
-                    (ir::Expr::Fn { op, args }, _) => {
+                    (ir::Expr::Fn { op, args }, _) if !op.is_user() => {
                         assert!(
                             args.len() == 1,
                             "Currently Unimplemented: {} requires {} arguments, automatic assumptions only implemented for single argument functions.",
                             op, args.len()
                         );
-                        Some((*op, *rhs, args[0]))
+                        Some((op.clone(), *rhs, args[0]))
                     }
-                    (_, ir::Expr::Fn { op, args }) => {
+                    (_, ir::Expr::Fn { op, args }) if !op.is_user() => {
                         assert!(
                             args.len() == 1,
                             "Currently Unimplemented: {} requires {} arguments, automatic assumptions only implemented for single argument functions.",
                             op, args.len()
                         );
-                        Some((*op, *lhs, args[0]))
+                        Some((op.clone(), *lhs, args[0]))
                     }
                     _ => None,
                 } {
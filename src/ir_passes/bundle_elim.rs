@@ -0,0 +1,295 @@
+use crate::ir::{self, Ctx, MutCtx, PortIdx};
+use crate::ir_visitor::{Action, Construct, Visitor};
+use crate::{cmdline, diagnostics, errors::Error};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+
+/// Eliminate bundles from a monomorphized program:
+/// * Each port is split into one port for each index in the bundle. Ports
+///   generated from bundles with more than one element are named
+///   `<bundle>_<index>` or, for multi-dimensional bundles,
+///   `<bundle>_<i>_<j>...`.
+/// * Connections between bundles are split into connections between the
+///   ports generated for each index.
+/// * Local bundles are removed and their reads are replaced with the port
+///   that was written into them.
+///
+/// Run [super::Monomorphize] before this pass to ensure that all bundle
+/// lengths and accesses are concrete.
+pub struct BundleElim {
+    /// Mapping from bundles to the lengths of their dimensions and the ports
+    /// generated for each index in row-major order
+    port_map: HashMap<PortIdx, (Vec<u64>, Vec<PortIdx>)>,
+    /// Local bundles defined in the component
+    locals: HashSet<PortIdx>,
+    /// Mapping from local ports to the port written into them
+    local_map: HashMap<PortIdx, PortIdx>,
+    /// Errors in the availability of the generated ports
+    diag: diagnostics::Diagnostics,
+}
+
+impl Construct for BundleElim {
+    fn from(opts: &cmdline::Opts, _: &ir::Context) -> Self {
+        Self {
+            port_map: HashMap::new(),
+            locals: HashSet::new(),
+            local_map: HashMap::new(),
+            diag: diagnostics::Diagnostics::new(opts.diagnostics_format),
+        }
+    }
+
+    fn clear_data(&mut self) {
+        self.port_map.clear();
+        self.locals.clear();
+        self.local_map.clear();
+    }
+}
+
+impl BundleElim {
+    /// Evaluate a time expression using the bundle index. Returns `None` if
+    /// the offset cannot be computed.
+    fn time(
+        comp: &mut ir::Component,
+        time: ir::TimeIdx,
+        bind: &HashMap<ir::ParamIdx, u64>,
+    ) -> Option<ir::TimeIdx> {
+        let ir::Time { event, offset } = comp.get(time).clone();
+        let offset = offset.eval(comp, bind)?;
+        let offset = comp.num(offset);
+        Some(comp.add(ir::Time { event, offset }))
+    }
+
+    /// Split a port into a port for each index in its bundle type.
+    /// Returns the lengths of the bundle's dimensions and the generated ports.
+    fn split_port(
+        &mut self,
+        comp: &mut ir::Component,
+        pidx: PortIdx,
+    ) -> (Vec<u64>, Vec<PortIdx>) {
+        let ir::Port {
+            owner,
+            width,
+            repr,
+            live,
+            info,
+        } = comp.get(pidx).clone();
+        let ir::Liveness { idxs, lens, range } = live;
+        let lens = lens
+            .into_iter()
+            .map(|len| {
+                len.as_concrete(comp).unwrap_or_else(|| {
+                    unreachable!(
+                        "Bundle length is not concrete. Run monomorphization first"
+                    )
+                })
+            })
+            .collect_vec();
+        let ir::Info::Port {
+            name,
+            bind_loc,
+            width_loc,
+            live_loc,
+            bundle,
+        } = comp.get(info).clone()
+        else {
+            unreachable!("Expected port info")
+        };
+        let param_info = comp.get(idxs[0]).info;
+
+        let ports = lens
+            .iter()
+            .map(|len| 0..*len)
+            .multi_cartesian_product()
+            .map(|is| {
+                let bind: HashMap<_, _> =
+                    idxs.iter().copied().zip(is.iter().copied()).collect();
+                let start = Self::time(comp, range.start, &bind);
+                let end = Self::time(comp, range.end, &bind);
+                let range = match (start, end) {
+                    (Some(start), Some(end)) => ir::Range { start, end },
+                    _ => {
+                        let info = self.diag.add_info(
+                            format!(
+                                "availability of index {} is before cycle 0",
+                                is.iter().join(", ")
+                            ),
+                            live_loc,
+                        );
+                        let err = Error::malformed(format!(
+                            "cannot compute the availability of `{name}'"
+                        ))
+                        .add_note(info);
+                        self.diag.add_error(err);
+                        // Continue with the original range to report all errors
+                        range.clone()
+                    }
+                };
+                // Ports that are not bundles retain their name
+                let info = if !bundle {
+                    info
+                } else {
+                    let name = format!("{name}_{}", is.iter().join("_")).into();
+                    comp.add(ir::Info::port(
+                        name, bind_loc, width_loc, live_loc,
+                    ))
+                };
+                // The owner is updated after the port is constructed
+                let param = comp.add(ir::Param::new(
+                    ir::ParamOwner::bundle(PortIdx::UNKNOWN),
+                    param_info,
+                    None,
+                ));
+                let one = comp.num(1);
+                let port = comp.add(ir::Port {
+                    owner: owner.clone(),
+                    width,
+                    repr: repr.clone(),
+                    live: ir::Liveness {
+                        idxs: vec![param],
+                        lens: vec![one],
+                        range,
+                    },
+                    info,
+                });
+                comp.get_mut(param).owner = ir::ParamOwner::bundle(port);
+                port
+            })
+            .collect_vec();
+
+        for idx in idxs {
+            comp.delete(idx);
+        }
+        comp.delete(pidx);
+        (lens, ports)
+    }
+
+    /// The ports accessed by an access
+    fn ports(&self, access: &ir::Access, comp: &ir::Component) -> Vec<PortIdx> {
+        let ir::Access { port, ranges } = access;
+        let (lens, ports) = &self.port_map[port];
+        ranges
+            .iter()
+            .map(|(start, end)| {
+                let (Some(start), Some(end)) =
+                    (start.as_concrete(comp), end.as_concrete(comp))
+                else {
+                    unreachable!(
+                        "Bundle access is not concrete. Run monomorphization first"
+                    )
+                };
+                start..end
+            })
+            .multi_cartesian_product()
+            .map(|is| {
+                // Position of the index in row-major order
+                let flat =
+                    lens.iter().zip(is).fold(0, |acc, (len, i)| acc * len + i);
+                ports[flat as usize]
+            })
+            .collect_vec()
+    }
+
+    /// Find the port that provides the value for a port by following writes
+    /// to local ports.
+    fn resolve(&self, mut port: PortIdx) -> PortIdx {
+        while let Some(src) = self.local_map.get(&port) {
+            port = *src;
+        }
+        port
+    }
+}
+
+impl Visitor for BundleElim {
+    fn after_traversal(&mut self) -> Option<u32> {
+        self.diag.report_all().map(|n| n as u32)
+    }
+
+    fn start(&mut self, comp: &mut ir::Component) -> Action {
+        if comp.is_ext {
+            return Action::Stop;
+        }
+
+        // Split all the ports in the component
+        let ports = comp.ports().idx_iter().collect_vec();
+        for pidx in ports {
+            if matches!(comp.get(pidx).owner, ir::PortOwner::Local) {
+                self.locals.insert(pidx);
+            }
+            let split = self.split_port(comp, pidx);
+            self.port_map.insert(pidx, split);
+        }
+
+        // Update the ports defined by invocations
+        let invs = comp.invocations().idx_iter().collect_vec();
+        for inv in invs {
+            let ports = comp
+                .get(inv)
+                .ports
+                .iter()
+                .flat_map(|p| self.port_map[p].1.clone())
+                .collect_vec();
+            comp.get_mut(inv).ports = ports;
+        }
+
+        // Track the writes to local ports
+        for cmd in &comp.cmds {
+            if let ir::Command::Connect(ir::Connect { src, dst, .. }) = cmd {
+                if self.locals.contains(&dst.port) {
+                    let dsts = self.ports(dst, comp);
+                    let srcs = self.ports(src, comp);
+                    for (d, s) in dsts.into_iter().zip(srcs) {
+                        let prev = self.local_map.insert(d, s);
+                        assert!(prev.is_none(), "multiple writes to {d}");
+                    }
+                }
+            }
+        }
+
+        Action::Continue
+    }
+
+    fn bundle_def(&mut self, _: PortIdx, _: &mut ir::Component) -> Action {
+        Action::Change(vec![])
+    }
+
+    fn connect(
+        &mut self,
+        con: &mut ir::Connect,
+        comp: &mut ir::Component,
+    ) -> Action {
+        let ir::Connect {
+            src,
+            dst,
+            guard,
+            info,
+        } = con;
+        // Writes to local ports are inlined into their reads
+        if self.locals.contains(&dst.port) {
+            return Action::Change(vec![]);
+        }
+        let dsts = self.ports(dst, comp);
+        let srcs = self.ports(src, comp);
+        // Guards only use 1-bit signals which have a single element
+        let guard = guard.clone().map(|g| {
+            g.map_ports(&mut |a| {
+                let p = self.resolve(self.ports(&a, comp)[0]);
+                ir::Access::port(p, comp)
+            })
+        });
+        let cmds = dsts
+            .into_iter()
+            .zip(srcs)
+            .map(|(d, s)| {
+                let src = self.resolve(s);
+                ir::Connect {
+                    src: ir::Access::port(src, comp),
+                    dst: ir::Access::port(d, comp),
+                    guard: guard.clone(),
+                    info: *info,
+                }
+                .into()
+            })
+            .collect_vec();
+        Action::Change(cmds)
+    }
+}
@@ -1,18 +1,53 @@
+use super::linear::{Decision, Linear};
+use super::timeline::Timeline;
 use crate::ir::{Ctx, DisplayCtx};
 use crate::ir_visitor::{Action, Construct, Visitor};
-use crate::utils::GlobalPositionTable;
-use crate::{ast, cmdline, ir, utils};
-use codespan_reporting::{diagnostic as cr, term};
+use crate::{ast, cmdline, diagnostics, ir, utils};
+use codespan_reporting::diagnostic as cr;
 use easy_smt as smt;
 use itertools::Itertools;
-use std::collections::HashMap;
-use std::iter;
-use term::termcolor::{ColorChoice, StandardStream};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::{fs, io, iter, thread};
+
+/// Records the commands sent to the solver
+#[derive(Clone, Default)]
+struct Replay(Rc<RefCell<Vec<u8>>>);
+
+impl io::Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
 
 #[derive(Default)]
 pub struct Assign(Vec<(ir::ParamIdx, String)>);
 
 impl Assign {
+    /// Assignment to the parameters using values from a model. Parameters
+    /// missing from the model are 0.
+    fn from_model(
+        params: Vec<ir::ParamIdx>,
+        model: &HashMap<ir::ParamIdx, i64>,
+    ) -> Self {
+        Assign(
+            params
+                .into_iter()
+                .unique()
+                .map(|p| (p, model.get(&p).copied().unwrap_or(0).to_string()))
+                .collect(),
+        )
+    }
+
     fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
@@ -31,12 +66,184 @@ impl Assign {
     }
 }
 
+/// Values of the parameters and events in a counterexample. Parameters and
+/// events missing from the model are 0.
+#[derive(Default)]
+struct Model {
+    params: HashMap<ir::ParamIdx, i64>,
+    events: HashMap<ir::EventIdx, i64>,
+}
+
+/// Solver symbols for the parameters and events of a component. The symbols
+/// are the surface-level names so that queries read like the program. Names
+/// that are already used are disambiguated with the index of the entity.
+#[derive(Default)]
+struct Symbols(HashSet<String>);
+
+impl Symbols {
+    /// Names used by the encoding. Names starting with `_` are generated by
+    /// the compiler and are always disambiguated.
+    const RESERVED: [&'static str; 9] = [
+        "pow2", "log2", "div", "mod", "abs", "not", "and", "or", "ite",
+    ];
+
+    fn fresh(&mut self, name: String, idx: impl std::fmt::Display) -> String {
+        let name = if Self::RESERVED.contains(&name.as_str())
+            || name.starts_with('_')
+            || self.0.contains(&name)
+        {
+            format!("{name}{idx}")
+        } else {
+            name
+        };
+        self.0.insert(name.clone());
+        name
+    }
+}
+
+/// Explanation for an assertion that cannot be proven
+#[derive(Default)]
+struct Explanation {
+    /// Assumptions from the path condition needed to prove the assertion
+    relevant: Vec<ir::PropIdx>,
+    /// Constraints over the signature that make the assertion hold
+    suggestion: Vec<ir::PropIdx>,
+}
+
+/// Add the conjuncts of the proposition to `acc`
+fn conjuncts(
+    prop: ir::PropIdx,
+    comp: &ir::Component,
+    acc: &mut Vec<ir::PropIdx>,
+) {
+    match comp.get(prop) {
+        ir::Prop::And(l, r) => {
+            conjuncts(*l, comp, acc);
+            conjuncts(*r, comp, acc);
+        }
+        ir::Prop::True => (),
+        _ if acc.contains(&prop) => (),
+        _ => acc.push(prop),
+    }
+}
+
+/// Propositions proven valid in previous runs. Components are identified by a
+/// hash of their interned expressions, times, and propositions so that the
+/// propositions of a component that has not changed can be reused. Entries of
+/// components that are not in the current program are dropped when the cache
+/// is saved.
+#[derive(Default)]
+struct Cache {
+    /// Path of the file backing the cache
+    path: Option<PathBuf>,
+    /// Valid propositions for each component hash
+    proven: HashMap<u64, HashSet<ir::PropIdx>>,
+}
+
+impl Cache {
+    /// First line of the cache file. Changing the encoding of components
+    /// requires changing this line.
+    const HEADER: &'static str = "filament solver cache v2";
+
+    /// Load the cache from the given file. A missing or malformed file is
+    /// treated as an empty cache.
+    fn load(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::default();
+        };
+        let mut cache = Self {
+            path: Some(path.to_path_buf()),
+            proven: HashMap::new(),
+        };
+        let Ok(contents) = fs::read_to_string(path) else {
+            return cache;
+        };
+        if contents.is_empty() {
+            return cache;
+        }
+        let mut lines = contents.lines();
+        if lines.next() != Some(Self::HEADER) {
+            log::warn!("ignoring malformed solver cache `{}'", path.display());
+            return cache;
+        }
+        for line in lines {
+            let mut words = line.split_whitespace();
+            let Some(Ok(key)) =
+                words.next().map(|k| u64::from_str_radix(k, 16))
+            else {
+                continue;
+            };
+            let props = words
+                .filter_map(|p| p.parse().ok())
+                .map(ir::PropIdx::new)
+                .collect();
+            cache.proven.insert(key, props);
+        }
+        cache
+    }
+
+    /// Hash of a component's interned data
+    fn key(comp: &ir::Component, funcs: &ast::Functions) -> u64 {
+        let mut hasher = utils::Fnv::default();
+        comp.params().iter().count().hash(&mut hasher);
+        comp.events().iter().count().hash(&mut hasher);
+        comp.exprs().iter().for_each(|(_, e)| e.hash(&mut hasher));
+        comp.times().iter().for_each(|(_, t)| t.hash(&mut hasher));
+        comp.props().iter().for_each(|(_, p)| p.hash(&mut hasher));
+        // Proofs may depend on the definitions of user-defined functions
+        funcs
+            .all()
+            .iter()
+            .for_each(|def| def.to_string().hash(&mut hasher));
+        hasher.finish()
+    }
+
+    /// Write the entries for the components with the given keys back to the
+    /// cache file
+    fn save(mut self, keys: &HashSet<u64>) {
+        let Some(path) = &self.path else { return };
+        self.proven.retain(|k, _| keys.contains(k));
+        let mut out = format!("{}\n", Self::HEADER);
+        for (key, props) in self.proven.iter().sorted_by_key(|(k, _)| **k) {
+            let props = props.iter().map(|p| p.get()).sorted().join(" ");
+            out.push_str(&format!("{key:016x} {props}\n"));
+        }
+        if let Err(e) = fs::write(path, out) {
+            log::warn!(
+                "failed to write solver cache `{}': {e}",
+                path.display()
+            );
+        }
+    }
+}
+
 /// Pass to discharge top-level `assert` statements in the IR and turn them into
 /// `assume` if they are true. Any assertions within the body are left as-is.
 /// Run [super::HoistFacts] before this pass to ensure that all facts are
 /// top-level.
+///
+/// Propositions over linear integer arithmetic are decided by [Linear]
+/// without the solver. Only the remaining propositions are sent to the solver.
+///
+/// Components are discharged concurrently by a pool of solver processes. If a
+/// cache file is provided, propositions proven valid are recorded in it and
+/// are not checked again for components that have not changed.
+///
+/// If `--dump-smt` is provided, the queries sent to the solver for each
+/// component are written to `<dir>/<component>.smt2`. Each file starts with
+/// the definitions shared by all components and can be replayed on its own.
 pub struct Discharge {
     sol: smt::Context,
+    /// Directory to dump the queries into along with the recorded commands
+    dump: Option<(PathBuf, Replay)>,
+    /// Commands sent to the solver before any component is defined
+    prelude: Vec<u8>,
+    /// Has the current component been defined in the solver?
+    defined: bool,
+    /// Hash of the current component
+    key: u64,
+    /// Propositions of the current component proven valid by a previous run
+    cached: HashSet<ir::PropIdx>,
     /// Are we in a scoped context?
     scoped: bool,
     /// Defined functions
@@ -51,28 +258,73 @@ pub struct Discharge {
     prop_map: ir::DenseIndexInfo<ir::Prop, smt::SExpr>,
     // Propositions that have already been checked
     checked: HashMap<ir::PropIdx, Option<Assign>>,
+    // Counterexamples for the propositions that are not valid
+    models: HashMap<ir::PropIdx, Model>,
 
     /// Report the unsatisfied constraint and generate a model
     show_models: bool,
 
-    // Diagnostics to be reported
-    diagnostics: Vec<cr::Diagnostic<usize>>,
-    /// Number of errors encountered
-    error_count: u32,
+    // Diagnostics to be reported along with their counterexamples
+    diagnostics: Vec<(cr::Diagnostic<usize>, &'static str, Option<String>)>,
 }
 
 impl Construct for Discharge {
-    fn from(opts: &cmdline::Opts, _: &ir::Context) -> Self {
-        let sol = smt::ContextBuilder::new()
-            .replay_file(Some(std::fs::File::create("model.smt").unwrap()))
-            .solver("z3", ["-smt2", "-in"])
+    fn from(opts: &cmdline::Opts, ctx: &ir::Context) -> Self {
+        Self::new(opts, &ctx.funcs)
+    }
+
+    fn clear_data(&mut self) {
+        self.param_map.clear();
+        self.prop_map.clear();
+        self.time_map.clear();
+        self.ev_map.clear();
+        self.expr_map.clear();
+        self.checked.clear();
+        self.models.clear();
+        self.diagnostics.clear();
+        self.cached.clear();
+
+        // Create a new solver context
+        if self.defined {
+            self.sol.pop().unwrap();
+            self.sol.push().unwrap();
+            self.defined = false;
+        }
+        if let Some((_, replay)) = &self.dump {
+            replay.0.borrow_mut().clear();
+        }
+    }
+}
+
+impl Discharge {
+    fn new(opts: &cmdline::Opts, funcs: &ast::Functions) -> Self {
+        let dump = opts
+            .dump_smt
+            .as_ref()
+            .map(|dir| (dir.clone(), Replay::default()));
+        let conf = utils::SolverConf::from(opts);
+        let mut sol = smt::ContextBuilder::new()
+            .replay_file(dump.as_ref().map(|(_, replay)| replay.clone()))
+            .solver(conf.program(), conf.args())
             .build()
             .unwrap();
+        // Unsat cores are used to explain assertions that cannot be proven.
+        // The option can only be set before the logic.
+        if opts.show_models {
+            sol.set_option(":produce-unsat-cores", sol.true_()).unwrap();
+        }
+        if let Some(logic) = conf.logic() {
+            sol.set_logic(logic).unwrap();
+        }
 
         let mut out = Self {
             sol,
+            dump,
+            prelude: Vec::new(),
+            defined: false,
+            key: 0,
+            cached: HashSet::new(),
             scoped: false,
-            error_count: 0,
             show_models: opts.show_models,
             func_map: Default::default(),
             param_map: Default::default(),
@@ -81,52 +333,73 @@ impl Construct for Discharge {
             ev_map: Default::default(),
             expr_map: Default::default(),
             checked: Default::default(),
+            models: Default::default(),
             diagnostics: Default::default(),
         };
 
-        out.define_funcs();
+        out.define_funcs(funcs);
         out.sol.push().unwrap();
+        if let Some((_, replay)) = &out.dump {
+            out.prelude = replay.0.take();
+        }
         out
     }
 
-    fn clear_data(&mut self) {
-        self.param_map.clear();
-        self.prop_map.clear();
-        self.time_map.clear();
-        self.ev_map.clear();
-        self.expr_map.clear();
-        self.checked.clear();
-        self.diagnostics.clear();
-
-        // Create a new solver context
-        self.sol.pop().unwrap();
-        self.sol.push().unwrap();
+    /// Write the queries sent for the component to the dump directory
+    fn dump(&self, comp: &ir::Component) {
+        let Some((dir, replay)) = &self.dump else {
+            return;
+        };
+        // Nothing was sent to the solver for this component
+        if !self.defined {
+            return;
+        }
+        let name = comp.src_info.as_ref().map_or_else(
+            || format!("comp{}", comp.idx().get()),
+            |src| src.name.to_string(),
+        );
+        let path = dir.join(format!("{name}.smt2"));
+        let queries = [self.prelude.as_slice(), &replay.0.borrow()].concat();
+        if let Err(err) = fs::write(&path, queries) {
+            log::warn!("failed to write {}: {err}", path.display());
+        }
     }
-}
 
-impl Discharge {
-    fn fmt_param(param: ir::ParamIdx) -> String {
-        format!("param{}", param.get())
+    /// Parameters are named `N` for the source parameter `#N`
+    fn fmt_param(
+        param: ir::ParamIdx,
+        comp: &ir::Component,
+        syms: &mut Symbols,
+    ) -> String {
+        let name = comp.display(param);
+        syms.fresh(name.trim_start_matches('#').to_string(), param)
     }
 
-    fn fmt_event(event: ir::EventIdx) -> String {
-        format!("event{}", event.get())
+    /// Events are named `G` or `inv.G` for events of invocations
+    fn fmt_event(
+        event: ir::EventIdx,
+        comp: &ir::Component,
+        syms: &mut Symbols,
+    ) -> String {
+        syms.fresh(comp.display(event), event)
     }
 
+    /// Intermediate definitions use the IR indices which cannot conflict with
+    /// surface-level names.
     fn fmt_expr(expr: ir::ExprIdx) -> String {
-        format!("e{}", expr.get())
+        expr.to_string()
     }
 
     fn fmt_prop(prop: ir::PropIdx) -> String {
-        format!("prop{}", prop.get())
+        prop.to_string()
     }
 
     fn fmt_time(time: ir::TimeIdx) -> String {
-        format!("t{}", time.get())
+        time.to_string()
     }
 
     /// Defines primitive functions used in the encoding like `pow` and `log`
-    fn define_funcs(&mut self) {
+    fn define_funcs(&mut self, funcs: &ast::Functions) {
         let int_sort = self.sol.int_sort();
         let pow2 = self
             .sol
@@ -139,6 +412,98 @@ impl Discharge {
         self.func_map = vec![(ast::UnFn::Pow2, pow2), (ast::UnFn::Log2, log)]
             .into_iter()
             .collect();
+
+        // Functions defined by the program. They are ordered so that a
+        // function is defined after the functions it uses.
+        for def in funcs.all() {
+            let func = funcs.call(*def.name.inner());
+            let vars = def
+                .params
+                .iter()
+                .map(|p| (p.to_string(), int_sort))
+                .collect_vec();
+            let f = match &def.body {
+                ast::FnBody::Expr(body) => {
+                    let body = self.ast_expr(body.inner());
+                    self.sol.define_fun(
+                        func.smt_name(),
+                        vars.clone(),
+                        int_sort,
+                        body,
+                    )
+                }
+                ast::FnBody::Axioms { .. } => self.sol.declare_fun(
+                    func.smt_name(),
+                    vec![int_sort; def.arity()],
+                    int_sort,
+                ),
+            }
+            .unwrap();
+            self.func_map.insert(func, f);
+
+            // Axioms hold for all natural-number arguments
+            for axiom in def.axioms(funcs) {
+                let sol = &self.sol;
+                let body = def.params.iter().rev().fold(
+                    self.ast_implication(&axiom),
+                    |body, p| {
+                        sol.imp(
+                            sol.gte(sol.atom(p.to_string()), sol.numeral(0)),
+                            body,
+                        )
+                    },
+                );
+                let vars = vars
+                    .iter()
+                    .map(|(v, sort)| sol.list(vec![sol.atom(v), *sort]))
+                    .collect_vec();
+                let axiom =
+                    sol.list(vec![sol.atoms().forall, sol.list(vars), body]);
+                self.sol.assert(axiom).unwrap();
+            }
+        }
+    }
+
+    /// Convert an expression from the definition of a user-defined function.
+    /// The parameters of the function are atoms with the same name.
+    fn ast_expr(&self, expr: &ast::Expr) -> smt::SExpr {
+        let sol = &self.sol;
+        match expr {
+            ast::Expr::Concrete(n) => sol.numeral(*n),
+            ast::Expr::Abstract(id) => sol.atom(id.to_string()),
+            ast::Expr::App { func, args } => sol.list(
+                iter::once(self.func_map[func])
+                    .chain(args.iter().map(|a| self.ast_expr(a)))
+                    .collect_vec(),
+            ),
+            ast::Expr::Op { op, left, right } => {
+                let l = self.ast_expr(left);
+                let r = self.ast_expr(right);
+                match op {
+                    ast::Op::Add => sol.plus(l, r),
+                    ast::Op::Sub => sol.sub(l, r),
+                    ast::Op::Mul => sol.times(l, r),
+                    ast::Op::Div => sol.div(l, r),
+                    ast::Op::Mod => sol.modulo(l, r),
+                }
+            }
+        }
+    }
+
+    fn ast_implication(&self, imp: &ast::Implication<ast::Expr>) -> smt::SExpr {
+        let cons = |c: &ast::OrderConstraint<ast::Expr>| {
+            let l = self.ast_expr(&c.left);
+            let r = self.ast_expr(&c.right);
+            match c.op {
+                ast::OrderOp::Gt => self.sol.gt(l, r),
+                ast::OrderOp::Gte => self.sol.gte(l, r),
+                ast::OrderOp::Eq => self.sol.eq(l, r),
+            }
+        };
+        match &imp.guard {
+            Some(guard) => self.sol.imp(cons(guard), cons(&imp.cons)),
+            None => cons(&imp.cons),
+        }
     }
 
     /// Get bindings for the provided parameters in a model.
@@ -171,6 +536,93 @@ impl Discharge {
         )
     }
 
+    /// Get the values of all the parameters and events in a model. Values
+    /// that are not integers are omitted.
+    fn get_model(&mut self, comp: &ir::Component) -> Model {
+        let mut value = |sexps: Vec<smt::SExpr>| -> Vec<Option<i64>> {
+            if sexps.is_empty() {
+                return vec![];
+            }
+            self.sol
+                .get_value(sexps)
+                .unwrap()
+                .into_iter()
+                .map(|(_, v)| {
+                    let v = self.sol.display(v).to_string();
+                    // Negative values are printed as `(- n)`
+                    match v.strip_prefix("(- ") {
+                        Some(n) => n
+                            .trim_end_matches(')')
+                            .parse::<i64>()
+                            .ok()
+                            .map(|n| -n),
+                        None => v.parse().ok(),
+                    }
+                })
+                .collect()
+        };
+        let params = comp.params().idx_iter().collect_vec();
+        let events = comp.events().idx_iter().collect_vec();
+        let pv = value(params.iter().map(|p| self.param_map[*p]).collect());
+        let ev = value(events.iter().map(|e| self.ev_map[*e]).collect());
+        Model {
+            params: params
+                .into_iter()
+                .zip(pv)
+                .filter_map(|(p, v)| Some((p, v?)))
+                .collect(),
+            events: events
+                .into_iter()
+                .zip(ev)
+                .filter_map(|(e, v)| Some((e, v?)))
+                .collect(),
+        }
+    }
+
+    /// Timeline for an assertion about the liveness of a value that is not
+    /// valid. The windows of the assertion are evaluated using the
+    /// counterexample of the assertion.
+    fn timeline(
+        &self,
+        prop: ir::PropIdx,
+        reason: ir::InfoIdx,
+        comp: &ir::Component,
+    ) -> Option<Timeline> {
+        let model = self.models.get(&prop)?;
+        let ir::Info::Assert(ir::Reason::Liveness {
+            dst_liveness,
+            src_liveness,
+            ..
+        }) = comp.get(reason)
+        else {
+            return None;
+        };
+        let (dst, src) = (dst_liveness, src_liveness);
+
+        let bind = comp
+            .params()
+            .idx_iter()
+            .map(|p| {
+                let v = model.params.get(&p).copied().unwrap_or(0);
+                Some((p, u64::try_from(v).ok()?))
+            })
+            .collect::<Option<HashMap<_, _>>>()?;
+
+        let mut events = Vec::new();
+        let mut time = |t: ir::TimeIdx| {
+            let ir::Time { event, offset } = comp.get(t);
+            let v = model.events.get(event).copied().unwrap_or(0);
+            let name = comp.display(*event);
+            if !events.iter().any(|(n, _)| *n == name) {
+                events.push((name, v));
+            }
+            v.checked_add(i64::try_from(offset.eval(comp, &bind)?).ok()?)
+        };
+        let required = (time(dst.start)?, time(dst.end)?);
+        let available = (time(src.start)?, time(src.end)?);
+        Timeline::new(events, required, available)
+    }
+
     /// Check whether the proposition is valid.
     /// Returns a set of assignments if the proposition is not valid.
     fn check_valid(
@@ -178,30 +630,181 @@ impl Discharge {
         prop: ir::PropIdx,
         ctx: &ir::Component,
     ) -> &Option<Assign> {
+        if self.cached.contains(&prop) {
+            self.checked.insert(prop, None);
+        }
         #[allow(clippy::map_entry)]
         if !self.checked.contains_key(&prop) {
-            self.sol.push().unwrap();
-            self.sol.assert(self.sol.not(self.prop_map[prop])).unwrap();
-            let res = self.sol.check().unwrap();
-            let out = match res {
-                smt::Response::Sat => {
-                    if self.show_models {
-                        Some(self.get_assignments(
+            let out = match Linear::decide(prop, ctx) {
+                Decision::Valid => None,
+                Decision::Invalid(params, events) => {
+                    Some(if self.show_models {
+                        let assign = Assign::from_model(
                             ctx.prop_params(prop.consequent(ctx)),
-                        ))
+                            &params,
+                        );
+                        self.models.insert(prop, Model { params, events });
+                        assign
                     } else {
-                        Some(Assign::default())
-                    }
+                        Assign::default()
+                    })
                 }
-                smt::Response::Unsat => None,
-                smt::Response::Unknown => panic!("Solver returned unknown"),
+                Decision::Unknown => self.check_solver(prop, ctx),
             };
-            self.sol.pop().unwrap();
             self.checked.insert(prop, out);
         }
         &self.checked[&prop]
     }
 
+    /// Check whether the proposition is valid using the solver
+    fn check_solver(
+        &mut self,
+        prop: ir::PropIdx,
+        ctx: &ir::Component,
+    ) -> Option<Assign> {
+        self.define(ctx);
+        self.sol.push().unwrap();
+        self.sol.assert(self.sol.not(self.prop_map[prop])).unwrap();
+        let res = self.sol.check().unwrap();
+        let out = match res {
+            smt::Response::Sat => {
+                if self.show_models {
+                    let model = self.get_model(ctx);
+                    self.models.insert(prop, model);
+                    Some(
+                        self.get_assignments(
+                            ctx.prop_params(prop.consequent(ctx)),
+                        ),
+                    )
+                } else {
+                    Some(Assign::default())
+                }
+            }
+            smt::Response::Unsat => None,
+            // The solver gave up, usually because the query timed out
+            smt::Response::Unknown => {
+                log::warn!("solver could not decide `{}'", ctx.display(prop));
+                Some(Assign::default())
+            }
+        };
+        self.sol.pop().unwrap();
+        out
+    }
+
+    /// Check whether the conjunction of the propositions is satisfiable
+    fn query(&mut self, props: &[ir::PropIdx]) -> smt::Response {
+        self.sol.push().unwrap();
+        for p in props {
+            self.sol.assert(self.prop_map[*p]).unwrap();
+        }
+        let res = self.sol.check().unwrap();
+        self.sol.pop().unwrap();
+        res
+    }
+
+    /// Remove propositions from `props` while the conjunction of `keep`,
+    /// `props`, and `neg` remains unsatisfiable.
+    fn minimize(
+        &mut self,
+        keep: &[ir::PropIdx],
+        mut props: Vec<ir::PropIdx>,
+        neg: ir::PropIdx,
+    ) -> Vec<ir::PropIdx> {
+        let mut i = 0;
+        while i < props.len() {
+            let p = props.remove(i);
+            let query = keep.iter().chain(&props).copied().chain([neg]);
+            if self.query(&query.collect_vec()) != smt::Response::Unsat {
+                props.insert(i, p);
+                i += 1;
+            }
+        }
+        props
+    }
+
+    /// Explain why the assertion cannot be proven. Constraints over the
+    /// signature that would make the assertion hold are suggested using
+    /// [Linear::precondition]. The assumptions from the path condition that
+    /// are relevant are computed using the unsat core of the query where the
+    /// suggested constraints are assumed. Both are minimized by removing
+    /// propositions while the query remains unsatisfiable.
+    fn explain(
+        &mut self,
+        prop: ir::PropIdx,
+        comp: &mut ir::Component,
+    ) -> Explanation {
+        let (ante, cons) = match comp.get(prop) {
+            ir::Prop::Implies(a, c) => (*a, *c),
+            _ => (comp.add(ir::Prop::True), prop),
+        };
+        let mut assumes = Vec::new();
+        conjuncts(ante, comp, &mut assumes);
+        let Some(suggest) = Linear::precondition(prop, comp) else {
+            return Explanation::default();
+        };
+        let neg = cons.not(comp);
+        self.define(comp);
+
+        // Label the propositions so that they can be found in the unsat core
+        let labels = assumes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (format!("%assume{i}"), *p))
+            .chain(
+                suggest
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| (format!("%where{i}"), *p)),
+            )
+            .collect_vec();
+        self.sol.push().unwrap();
+        for (name, p) in &labels {
+            let named = self.sol.named(name, self.prop_map[*p]);
+            self.sol.assert(named).unwrap();
+        }
+        self.sol.assert(self.prop_map[neg]).unwrap();
+        let core: HashSet<String> =
+            if self.sol.check().unwrap() == smt::Response::Unsat {
+                let core = self.sol.get_unsat_core().unwrap();
+                match self.sol.get(core) {
+                    smt::SExprData::List(names) => names
+                        .iter()
+                        .map(|n| self.sol.display(*n).to_string())
+                        .collect(),
+                    smt::SExprData::Atom(_) => HashSet::new(),
+                }
+            } else {
+                HashSet::new()
+            };
+        self.sol.pop().unwrap();
+        let (core_assumes, core_suggest): (Vec<_>, Vec<_>) = labels
+            .into_iter()
+            .filter(|(name, _)| core.contains(name))
+            .partition(|(name, _)| name.starts_with("%assume"));
+        let core_assumes =
+            core_assumes.into_iter().map(|(_, p)| p).collect_vec();
+        let core_suggest =
+            core_suggest.into_iter().map(|(_, p)| p).collect_vec();
+
+        // The suggestions are minimized first so that the relevant
+        // assumptions are the ones needed by the smallest suggestion.
+        let suggestion = self.minimize(&core_assumes, core_suggest, neg);
+        let relevant = self.minimize(&suggestion, core_assumes, neg);
+
+        // Suggestions that contradict the path condition are vacuous
+        let consistent =
+            assumes.iter().chain(&suggestion).copied().collect_vec();
+        if suggestion.is_empty()
+            || self.query(&consistent) != smt::Response::Sat
+        {
+            return Explanation::default();
+        }
+        Explanation {
+            relevant,
+            suggestion,
+        }
+    }
+
     fn expr_to_sexp(&mut self, expr: &ir::Expr) -> smt::SExpr {
         let sol = &mut self.sol;
         match expr {
@@ -284,25 +887,39 @@ impl Discharge {
             }
         }
     }
-}
 
-impl Visitor for Discharge {
-    fn start(&mut self, comp: &mut ir::Component) -> Action {
-        // Declare all parameters
+    /// Define the parameters, events, and propositions of the component in the
+    /// solver. Expressions, times, and propositions added to the component
+    /// after the first call are defined by later calls.
+    fn define(&mut self, comp: &ir::Component) {
         let int = self.sol.int_sort();
-        for (idx, _) in comp.params().iter() {
-            let sexp = self.sol.declare(Self::fmt_param(idx), int).unwrap();
-            self.param_map.push(idx, sexp);
-        }
+        if !self.defined {
+            self.defined = true;
+
+            // Declare all parameters. The generated indices of ports that are
+            // not bundles are always 0.
+            let mut syms = Symbols::default();
+            for (idx, _) in comp.params().iter() {
+                if comp.index_of(idx).is_some() {
+                    let zero = self.sol.numeral(0);
+                    self.param_map.push(idx, zero);
+                    continue;
+                }
+                let name = Self::fmt_param(idx, comp, &mut syms);
+                let sexp = self.sol.declare(name, int).unwrap();
+                self.param_map.push(idx, sexp);
+            }
 
-        // Declare all events
-        for (idx, _) in comp.events().iter() {
-            let sexp = self.sol.declare(Self::fmt_event(idx), int).unwrap();
-            self.ev_map.push(idx, sexp);
+            // Declare all events
+            for (idx, _) in comp.events().iter() {
+                let name = Self::fmt_event(idx, comp, &mut syms);
+                let sexp = self.sol.declare(name, int).unwrap();
+                self.ev_map.push(idx, sexp);
+            }
         }
 
         // Declare all expressions
-        for (idx, expr) in comp.exprs().iter() {
+        for (idx, expr) in comp.exprs().iter().skip(self.expr_map.len()) {
             let assign = self.expr_to_sexp(expr);
             let sexp = self
                 .sol
@@ -312,7 +929,9 @@ impl Visitor for Discharge {
         }
 
         // Declare all time expressions
-        for (idx, ir::Time { event, offset }) in comp.times().iter() {
+        for (idx, ir::Time { event, offset }) in
+            comp.times().iter().skip(self.time_map.len())
+        {
             let assign =
                 self.sol.plus(self.ev_map[*event], self.expr_map[*offset]);
             let sexp = self
@@ -324,7 +943,7 @@ impl Visitor for Discharge {
 
         // Declare all propositions
         let bs = self.sol.bool_sort();
-        for (idx, prop) in comp.props().iter() {
+        for (idx, prop) in comp.props().iter().skip(self.prop_map.len()) {
             // Define assertion equating the proposition to its assignment
             let assign = self.prop_to_sexp(prop);
             let sexp = self
@@ -333,10 +952,94 @@ impl Visitor for Discharge {
                 .unwrap();
             self.prop_map.push(idx, sexp);
         }
-        // Pass does not need to traverse the control program.
-        Action::Continue
     }
 
+    /// Add the explanation of a failed assertion to its diagnostic. Relevant
+    /// assumptions are labelled with their reasons when they come from the
+    /// source program and listed in a note otherwise.
+    fn explain_diag(
+        &self,
+        diag: &mut cr::Diagnostic<usize>,
+        explanation: Explanation,
+        comp: &ir::Component,
+    ) {
+        // Reasons for the conjuncts of each assumption
+        let mut reasons = HashMap::new();
+        for (prop, info) in &comp.assumptions {
+            let mut conj = Vec::new();
+            conjuncts(*prop, comp, &mut conj);
+            for c in conj {
+                reasons.entry(c).or_insert(*info);
+            }
+        }
+        let mut labelled = HashSet::new();
+        let mut unlabelled = Vec::new();
+        for prop in explanation.relevant {
+            let label =
+                reasons.get(&prop).and_then(|info| match comp.get(*info) {
+                    ir::Info::Assert(ir::Reason::Misc { reason, def_loc }) => {
+                        def_loc.into_option().map(|loc| (*info, loc, reason))
+                    }
+                    _ => None,
+                });
+            match label {
+                Some((info, loc, reason)) => {
+                    if labelled.insert(info) {
+                        diag.labels
+                            .push(loc.secondary().with_message(reason.clone()));
+                    }
+                }
+                None => unlabelled.push(format!("`{}`", comp.display(prop))),
+            }
+        }
+        if !unlabelled.is_empty() {
+            diag.notes.push(format!(
+                "Relevant assumptions: {}",
+                unlabelled.join(", ")
+            ));
+        }
+        if !explanation.suggestion.is_empty() {
+            diag.notes.push(format!(
+                "Suggestion: add `where {}` to the signature",
+                explanation
+                    .suggestion
+                    .iter()
+                    .map(|p| comp.display(*p))
+                    .join(", ")
+            ));
+        }
+    }
+
+    /// Report the diagnostics and return the number of errors
+    fn report(
+        reporter: &diagnostics::Diagnostics,
+        diagnostics: &[(cr::Diagnostic<usize>, &'static str, Option<String>)],
+    ) -> u32 {
+        for (diag, kind, model) in diagnostics {
+            let mut diag = diag.clone();
+            if let Some(model) = model.as_ref().filter(|_| !reporter.is_json())
+            {
+                diag.notes.push(format!(
+                    "Counterexample: {model} (unmentioned parameters are 0)"
+                ));
+            }
+            reporter.emit(&diag, kind, model.as_deref());
+        }
+        diagnostics.len() as u32
+    }
+}
+
+/// The result of discharging a component
+struct Discharged {
+    /// Hash of the component
+    key: u64,
+    /// Propositions proven valid
+    proven: HashSet<ir::PropIdx>,
+    /// Diagnostics for assertions that could not be proven
+    diagnostics: Vec<(cr::Diagnostic<usize>, &'static str, Option<String>)>,
+}
+
+impl Visitor for Discharge {
     fn fact(&mut self, f: &mut ir::Fact, comp: &mut ir::Component) -> Action {
         if self.scoped {
             panic!("scoped facts not supported. Run `hoist-facts` before this pass");
@@ -349,7 +1052,7 @@ impl Visitor for Discharge {
         }
 
         let show_models = self.show_models;
-        match self.check_valid(f.prop, comp) {
+        let model = match self.check_valid(f.prop, comp) {
             None => {
                 let reason = comp.add(
                     ir::Reason::misc(
@@ -358,31 +1061,34 @@ impl Visitor for Discharge {
                     )
                     .into(),
                 );
-                Action::Change(
+                return Action::Change(
                     comp.assume(f.prop, reason).into_iter().collect(),
-                )
+                );
             }
-            Some(assign) => {
-                let ir::Info::Assert(reason) = comp.get(f.reason) else {
-                    unreachable!("expected assert reason")
-                };
-                let mut diag = reason.diag(comp);
-                if show_models {
-                    diag = reason.diag(comp).with_notes(vec![format!(
-                        "Cannot prove constraint: {}",
-                        comp.display(f.prop.consequent(comp))
-                    )]);
-                    if !assign.is_empty() {
-                        diag = diag.with_notes(vec![format!(
-                            "Counterexample: {} (unmentioned parameters are 0)",
-                            assign.display(comp)
-                        )]);
-                    }
-                }
-                self.diagnostics.push(diag);
-                Action::Continue
+            // Models that only assign 0 are not shown
+            Some(assign) if show_models && !assign.is_empty() => {
+                Some(assign.display(comp)).filter(|m| !m.is_empty())
+            }
+            Some(_) => None,
+        };
+        let ir::Info::Assert(reason) = comp.get(f.reason) else {
+            unreachable!("expected assert reason")
+        };
+        let mut diag = reason.diag(comp);
+        let kind = reason.kind();
+        if show_models {
+            diag.notes.push(format!(
+                "Cannot prove constraint: {}",
+                comp.display(f.prop.consequent(comp))
+            ));
+            let explanation = self.explain(f.prop, comp);
+            self.explain_diag(&mut diag, explanation, comp);
+            if let Some(timeline) = self.timeline(f.prop, f.reason, comp) {
+                diag.notes.push(timeline.to_string());
             }
         }
+        self.diagnostics.push((diag, kind, model));
+        Action::Continue
     }
 
     fn do_if(&mut self, i: &mut ir::If, comp: &mut ir::Component) -> Action {
@@ -409,33 +1115,83 @@ impl Visitor for Discharge {
         out
     }
 
-    fn end(&mut self, _: &mut ir::Component) {
+    fn end(&mut self, comp: &mut ir::Component) {
         assert!(!self.scoped, "unbalanced scopes");
-        // Report all the errors
-        let is_tty = atty::is(atty::Stream::Stderr);
-        let writer = StandardStream::stderr(if is_tty {
-            ColorChoice::Always
-        } else {
-            ColorChoice::Never
+        self.dump(comp);
+    }
+
+    fn do_pass(opts: &cmdline::Opts, ctx: &mut ir::Context) -> Result<(), u32> {
+        let mut cache = Cache::load(opts.solver_cache.as_deref());
+        if let Some(dir) = &opts.dump_smt {
+            if let Err(err) = fs::create_dir_all(dir) {
+                log::warn!("failed to create {}: {err}", dir.display());
+            }
+        }
+        let funcs = &ctx.funcs;
+        let comps = ctx.comps.iter_mut().map(|(_, c)| c).collect_vec();
+        let jobs = opts
+            .solver_jobs
+            .unwrap_or_else(|| {
+                thread::available_parallelism().map_or(1, |n| n.get())
+            })
+            .clamp(1, comps.len().max(1));
+
+        // Each worker owns a solver and discharges components from the queue
+        let queue = Mutex::new(comps.into_iter().enumerate());
+        let results = Mutex::new(Vec::new());
+        thread::scope(|s| {
+            for _ in 0..jobs {
+                let (queue, results, cache) = (&queue, &results, &cache);
+                s.spawn(move || {
+                    let mut pass = Discharge::new(opts, funcs);
+                    loop {
+                        let Some((idx, comp)) = queue.lock().unwrap().next()
+                        else {
+                            break;
+                        };
+                        pass.clear_data();
+                        pass.key = Cache::key(comp, funcs);
+                        pass.cached = cache
+                            .proven
+                            .get(&pass.key)
+                            .cloned()
+                            .unwrap_or_default();
+                        pass.visit(comp);
+                        let proven = pass
+                            .checked
+                            .iter()
+                            .filter(|(_, v)| v.is_none())
+                            .map(|(p, _)| *p)
+                            .chain(pass.cached.iter().copied())
+                            .collect();
+                        let out = Discharged {
+                            key: pass.key,
+                            proven,
+                            diagnostics: std::mem::take(&mut pass.diagnostics),
+                        };
+                        results.lock().unwrap().push((idx, out));
+                    }
+                });
+            }
         });
-        let table = GlobalPositionTable::as_ref();
-        for diag in &self.diagnostics {
-            term::emit(
-                &mut writer.lock(),
-                &term::Config::default(),
-                table.files(),
-                diag,
-            )
-            .unwrap();
-            self.error_count += 1;
+
+        // Report errors in the order of the components
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(idx, _)| *idx);
+        let reporter = diagnostics::Diagnostics::new(opts.diagnostics_format);
+        let mut errors = 0;
+        let mut keys = HashSet::new();
+        for (_, res) in results {
+            errors += Self::report(&reporter, &res.diagnostics);
+            keys.insert(res.key);
+            cache.proven.insert(res.key, res.proven);
         }
-    }
+        cache.save(&keys);
 
-    fn after_traversal(&mut self) -> Option<u32> {
-        if self.error_count > 0 {
-            Some(self.error_count)
+        if errors > 0 {
+            Err(errors)
         } else {
-            None
+            Ok(())
         }
     }
 }
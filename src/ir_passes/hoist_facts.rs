@@ -46,14 +46,16 @@ impl HoistFacts {
 impl Visitor for HoistFacts {
     /// Collect all assumptions in a given scope and add them to the path condition.
     /// We do this so that all asserts in a scope are affected by all assumes.
+    /// The reasons for the assumptions are recorded in the component.
     fn start_cmds(
         &mut self,
         cmds: &mut Vec<ir::Command>,
-        _: &mut ir::Component,
+        comp: &mut ir::Component,
     ) {
         cmds.iter().for_each(|cmd| match cmd {
             ir::Command::Fact(fact) if fact.is_assume() => {
-                self.insert(fact.prop)
+                self.insert(fact.prop);
+                comp.assumptions.entry(fact.prop).or_insert(fact.reason);
             }
             _ => (),
         })
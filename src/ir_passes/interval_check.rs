@@ -55,14 +55,40 @@ impl IntervalCheck {
         comp.assert(prop, reason)
     }
 
-    /// Proposition that ensures that the given parameter is in range
-    fn in_range(live: &ir::Liveness, comp: &mut ir::Component) -> ir::PropIdx {
-        let &ir::Liveness { idx, len, .. } = live;
+    /// Proposition that ensures that the given parameters are in range
+    fn in_range(
+        params: &[(ir::ParamIdx, ir::ExprIdx)],
+        comp: &mut ir::Component,
+    ) -> ir::PropIdx {
         let zero = comp.num(0);
-        let idx = idx.expr(comp);
-        let lo = idx.gte(zero, comp);
-        let hi = idx.lt(len, comp);
-        lo.and(hi, comp)
+        params
+            .iter()
+            .map(|&(idx, len)| {
+                let idx = idx.expr(comp);
+                let lo = idx.gte(zero, comp);
+                let hi = idx.lt(len, comp);
+                lo.and(hi, comp)
+            })
+            .collect_vec()
+            .into_iter()
+            .reduce(|acc, p| acc.and(p, comp))
+            .unwrap_or_else(|| comp.add(ir::Prop::True))
+    }
+
+    /// The index parameters and lengths of the dimensions of a bundle type
+    /// that are not syntactically indexed by the access.
+    fn ranged_dims(
+        access: &ir::Access,
+        live: &ir::Liveness,
+        comp: &mut ir::Component,
+    ) -> Vec<(ir::ParamIdx, ir::ExprIdx)> {
+        live.idxs
+            .iter()
+            .copied()
+            .zip(live.lens.iter().copied())
+            .zip(access.index_dims(comp))
+            .filter_map(|(d, is_index)| (!is_index).then_some(d))
+            .collect()
     }
 }
 
@@ -110,18 +136,26 @@ impl Visitor for IntervalCheck {
             let ir::Info::Event { delay_loc, .. } = comp[ev.info] else {
                 unreachable!("expected event info")
             };
-            let param = comp.get(live.idx);
-            let ir::Info::Param { bind_loc, .. } = comp[param.info] else {
-                unreachable!("expected param info")
-            };
             let zero = comp.num(0);
+            let params = live
+                .idxs
+                .iter()
+                .zip(&live.lens)
+                .map(|(idx, len)| {
+                    let ir::Info::Param { bind_loc, .. } =
+                        comp[comp.get(*idx).info]
+                    else {
+                        unreachable!("expected param info")
+                    };
+                    (bind_loc, (zero, *len))
+                })
+                .collect_vec();
             let reason = comp.add(
                 ir::Reason::bundle_delay(
                     delay_loc,
                     live_loc,
                     len.clone(),
-                    bind_loc,
-                    (zero, live.len),
+                    params,
                 )
                 .into(),
             );
@@ -140,7 +174,9 @@ impl Visitor for IntervalCheck {
         eb: &mut ir::EventBind,
         comp: &mut ir::Component,
     ) -> Action {
-        let ir::EventBind { event, arg, info } = &eb;
+        let ir::EventBind {
+            event, arg, info, ..
+        } = &eb;
         let ir::Info::EventBind { bind_loc } = comp[*info] else {
             unreachable!("expected event bind info")
         };
@@ -184,30 +220,84 @@ impl Visitor for IntervalCheck {
         con: &mut ir::Connect,
         comp: &mut ir::Component,
     ) -> Action {
-        let ir::Connect { src, dst, info } = con;
+        let ir::Connect { src, dst, info, .. } = con;
+        // Constants are available whenever they are required
+        if matches!(comp.get(src.port).owner, ir::PortOwner::Const { .. }) {
+            return Action::Continue;
+        }
         let src_t = src.bundle_typ(comp);
         let dst_t = dst.bundle_typ(comp);
-        let in_range = Self::in_range(&dst_t, comp)
-            .and(Self::in_range(&src_t, comp), comp);
+        // Match up the dimensions of the source and destination. Accesses
+        // with a different number of dimensions are matched up using the
+        // dimensions that are not indexed.
+        let (src_d, dst_d) = if src_t.idxs.len() == dst_t.idxs.len() {
+            let dims = |t: &ir::Liveness| {
+                t.idxs
+                    .iter()
+                    .copied()
+                    .zip(t.lens.iter().copied())
+                    .collect_vec()
+            };
+            (dims(&src_t), dims(&dst_t))
+        } else {
+            (
+                Self::ranged_dims(src, &src_t, comp),
+                Self::ranged_dims(dst, &dst_t, comp),
+            )
+        };
+        // The type checker reports accesses whose dimensions do not match up
+        if src_d.len() != dst_d.len() {
+            return Action::Continue;
+        }
+        // The index of a dimension with a single element is always 0. This
+        // keeps the generated indices of ports that are not bundles out of
+        // the constraints.
+        let zero = comp.num(0);
+        let single = src_d
+            .iter()
+            .chain(&dst_d)
+            .filter(|(_, len)| len.as_concrete(comp) == Some(1))
+            .map(|&(idx, _)| (idx, zero))
+            .collect_vec();
+        let single = ir::Bind::new(&single);
+        let ranged = |dims: &[(ir::ParamIdx, ir::ExprIdx)]| {
+            dims.iter()
+                .copied()
+                .filter(|(idx, _)| single.get(idx).is_none())
+                .collect_vec()
+        };
+        let in_range = Self::in_range(&ranged(&dst_d), comp)
+            .and(Self::in_range(&ranged(&src_d), comp), comp);
 
-        // Substitute the parameter used in source with that in dst
-        let binding = [(dst_t.idx, src_t.idx.expr(comp))];
+        // Substitute the parameters used in source with those in dst
+        let binding = dst_d
+            .iter()
+            .zip(&src_d)
+            .map(|(&(d, _), &(s, _))| (d, s.expr(comp)))
+            .collect_vec();
         let dst_range =
             ir::Subst::new(dst_t.range, &ir::Bind::new(&binding)).apply(comp);
+        let dst_range = ir::Subst::new(dst_range, &single).apply(comp);
+        let src_range = ir::Subst::new(src_t.range, &single).apply(comp);
 
         // Assuming that lengths are equal
-        let pre_req = src_t.len.equal(dst_t.len, comp).and(in_range, comp);
-        let contains = src_t
-            .range
+        let pre_req = src_d
+            .iter()
+            .zip(&dst_d)
+            .map(|(&(_, sl), &(_, dl))| sl.equal(dl, comp))
+            .collect_vec()
+            .into_iter()
+            .fold(in_range, |acc, eq| eq.and(acc, comp));
+        let contains = src_range
             .start
             .lte(dst_range.start, comp)
-            .and(src_t.range.end.gte(dst_range.end, comp), comp);
+            .and(src_range.end.gte(dst_range.end, comp), comp);
 
         let ir::Info::Connect { dst_loc, src_loc } = comp.get(*info) else {
             unreachable!("Expected connect info")
         };
         let reason = comp.add(
-            ir::Reason::liveness(*dst_loc, *src_loc, dst_range, src_t.range)
+            ir::Reason::liveness(*dst_loc, *src_loc, dst_range, src_range)
                 .into(),
         );
 
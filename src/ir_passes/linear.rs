@@ -0,0 +1,762 @@
+use crate::{
+    ast,
+    ir::{self, Ctx},
+};
+use itertools::{Either, Itertools};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Maximum number of disjuncts in the normal form of a proposition
+const MAX_CLAUSES: usize = 64;
+/// Maximum number of constraints produced while eliminating a variable
+const MAX_ATOMS: usize = 256;
+
+/// A variable in a linear term
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Var {
+    Param(ir::ParamIdx),
+    Event(ir::EventIdx),
+}
+
+/// A linear term `c + a1*x1 + ... + an*xn` over integers. Operations return
+/// `None` when they overflow.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+struct Term {
+    coeffs: BTreeMap<Var, i64>,
+    constant: i64,
+}
+
+impl Term {
+    fn constant(constant: i64) -> Self {
+        Self {
+            coeffs: BTreeMap::new(),
+            constant,
+        }
+    }
+
+    fn var(v: Var) -> Self {
+        Self {
+            coeffs: BTreeMap::from([(v, 1)]),
+            constant: 0,
+        }
+    }
+
+    fn as_constant(&self) -> Option<i64> {
+        self.coeffs.is_empty().then_some(self.constant)
+    }
+
+    fn coeff(&self, v: Var) -> i64 {
+        self.coeffs.get(&v).copied().unwrap_or(0)
+    }
+
+    fn add(&self, other: &Term) -> Option<Term> {
+        let mut out = self.clone();
+        out.constant = out.constant.checked_add(other.constant)?;
+        for (v, c) in &other.coeffs {
+            let n = out.coeff(*v).checked_add(*c)?;
+            if n == 0 {
+                out.coeffs.remove(v);
+            } else {
+                out.coeffs.insert(*v, n);
+            }
+        }
+        Some(out)
+    }
+
+    fn scale(&self, k: i64) -> Option<Term> {
+        if k == 0 {
+            return Some(Term::default());
+        }
+        let coeffs = self
+            .coeffs
+            .iter()
+            .map(|(v, c)| Some((*v, c.checked_mul(k)?)))
+            .collect::<Option<_>>()?;
+        Some(Term {
+            coeffs,
+            constant: self.constant.checked_mul(k)?,
+        })
+    }
+
+    fn sub(&self, other: &Term) -> Option<Term> {
+        self.add(&other.scale(-1)?)
+    }
+
+    /// Value of the term. Variables missing from the model are zero.
+    fn eval(&self, model: &HashMap<Var, i64>) -> Option<i64> {
+        self.coeffs.iter().try_fold(self.constant, |acc, (v, c)| {
+            let x = model.get(v).copied().unwrap_or(0);
+            acc.checked_add(c.checked_mul(x)?)
+        })
+    }
+
+    /// Divide the coefficients of the constraint `self >= 0` by their
+    /// greatest common divisor. Since variables are integers, the constant
+    /// can be rounded down.
+    fn tighten(mut self) -> Term {
+        fn gcd(a: i64, b: i64) -> i64 {
+            if b == 0 {
+                a.abs()
+            } else {
+                gcd(b, a % b)
+            }
+        }
+        let g = self.coeffs.values().fold(0, |g, c| gcd(g, *c));
+        if g > 1 {
+            self.coeffs.values_mut().for_each(|c| *c /= g);
+            self.constant = self.constant.div_euclid(g);
+        }
+        self
+    }
+}
+
+/// Disjunction of conjunctions of constraints `t >= 0`
+type Dnf = Vec<Vec<Term>>;
+/// Constraints mentioning each variable eliminated from a conjunction
+type Stages = Vec<(Var, Vec<Term>)>;
+
+/// Outcome of deciding a proposition without the solver
+pub enum Decision {
+    /// The proposition holds for all values of the parameters and events
+    Valid,
+    /// The proposition does not hold for the given values of the parameters
+    /// and events
+    Invalid(HashMap<ir::ParamIdx, i64>, HashMap<ir::EventIdx, i64>),
+    /// The proposition is not linear or could not be decided
+    Unknown,
+}
+
+/// Decides propositions over linear integer arithmetic. The negation of the
+/// proposition is converted into disjunctive normal form and each disjunct
+/// is checked using Fourier-Motzkin elimination. Propositions that use
+/// functions, multiply parameters, or divide by parameters are left to the
+/// solver.
+pub struct Linear<'a> {
+    comp: &'a ir::Component,
+    /// Linear form of the expressions seen so far
+    exprs: HashMap<ir::ExprIdx, Option<Term>>,
+}
+
+impl<'a> Linear<'a> {
+    pub fn decide(prop: ir::PropIdx, comp: &'a ir::Component) -> Decision {
+        let mut lin = Self {
+            comp,
+            exprs: HashMap::new(),
+        };
+        let Some(dnf) = lin.dnf(prop, false) else {
+            return Decision::Unknown;
+        };
+        let mut unknown = false;
+        for conj in dnf {
+            match Self::solve(conj) {
+                Some(None) => (),
+                // Models are checked against the proposition in case the
+                // elimination was wrong about them.
+                Some(Some(model)) if lin.holds(prop, &model) == Some(false) => {
+                    let (params, events) =
+                        model.into_iter().partition_map(|(v, x)| match v {
+                            Var::Param(p) => Either::Left((p, x)),
+                            Var::Event(e) => Either::Right((e, x)),
+                        });
+                    return Decision::Invalid(params, events);
+                }
+                Some(Some(_)) | None => unknown = true,
+            }
+        }
+        if unknown {
+            Decision::Unknown
+        } else {
+            Decision::Valid
+        }
+    }
+
+    /// Constraints over the parameters and events of the signature that make
+    /// the proposition valid when they are assumed. The counterexamples to the
+    /// proposition are projected onto the signature and a small set of
+    /// constraints ruling out all the projections is chosen greedily.
+    /// Returns `None` if the proposition is not linear or if some
+    /// counterexample cannot be ruled out by constraining the signature.
+    pub fn precondition(
+        prop: ir::PropIdx,
+        comp: &mut ir::Component,
+    ) -> Option<Vec<ir::PropIdx>> {
+        let cover = {
+            let comp: &ir::Component = comp;
+            let sig = |v: &Var| match v {
+                Var::Param(p) => comp.get(*p).is_sig_owned(),
+                Var::Event(e) => {
+                    matches!(comp.get(*e).owner, ir::EventOwner::Sig)
+                }
+            };
+            let mut lin = Linear {
+                comp,
+                exprs: HashMap::new(),
+            };
+            let mut shadows = Vec::new();
+            for conj in lin.dnf(prop, false)? {
+                match Self::eliminate(conj, |v| !sig(v))? {
+                    None => (),
+                    // Counterexamples exist for every value of the signature
+                    Some((rest, _)) if rest.is_empty() => return None,
+                    Some((rest, _)) => shadows.push(rest),
+                }
+            }
+            let assumes = match comp.get(prop) {
+                ir::Prop::Implies(a, _) => lin.dnf(*a, true)?,
+                _ => vec![vec![]],
+            };
+            Self::cover(shadows, &assumes)?
+        };
+        cover.iter().map(|t| Self::to_prop(t, comp)).collect()
+    }
+
+    /// Choose constraints that are each the negation of a constraint from one
+    /// of the shadows such that every shadow is unsatisfiable once they are
+    /// added. Constraints that contradict the assumptions are never chosen
+    /// and the others are chosen greedily by the number of shadows they rule
+    /// out.
+    fn cover(shadows: Vec<Vec<Term>>, assumes: &Dnf) -> Option<Vec<Term>> {
+        let unsat = |atoms: &[Term], c: &Term| {
+            let atoms = atoms.iter().chain([c]).cloned().collect();
+            matches!(Self::solve(atoms), Some(None))
+        };
+        let mut cands: Vec<Term> = Vec::new();
+        for atom in shadows.iter().flatten() {
+            let neg = atom.scale(-1)?.sub(&Term::constant(1))?.tighten();
+            if Self::expressible(&neg)
+                && !cands.contains(&neg)
+                && !assumes.iter().all(|a| unsat(a, &neg))
+            {
+                cands.push(neg);
+            }
+        }
+        let covers = cands
+            .iter()
+            .map(|c| {
+                (0..shadows.len())
+                    .filter(|i| unsat(&shadows[*i], c))
+                    .collect::<HashSet<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut uncovered = (0..shadows.len()).collect::<HashSet<_>>();
+        let mut out = Vec::new();
+        while !uncovered.is_empty() {
+            // Ties are broken in favor of the earlier candidate
+            let (best, cov) =
+                covers.iter().enumerate().rev().max_by_key(|(_, cov)| {
+                    cov.intersection(&uncovered).count()
+                })?;
+            if cov.is_disjoint(&uncovered) {
+                return None;
+            }
+            uncovered.retain(|i| !cov.contains(i));
+            out.push(cands[best].clone());
+        }
+        Some(out)
+    }
+
+    /// Events in the constraint `t >= 0`. The constraint can be written as a
+    /// comparison if it mentions no events or compares two events.
+    fn events(t: &Term) -> Option<Vec<(ir::EventIdx, i64)>> {
+        let events = t
+            .coeffs
+            .iter()
+            .filter_map(|(v, c)| match v {
+                Var::Event(e) => Some((*e, *c)),
+                Var::Param(_) => None,
+            })
+            .collect_vec();
+        match events.as_slice() {
+            [] => Some(events),
+            [(_, a), (_, b)] if a.abs() == 1 && a + b == 0 => Some(events),
+            _ => None,
+        }
+    }
+
+    fn expressible(t: &Term) -> bool {
+        Self::events(t).is_some()
+    }
+
+    /// The constraint `t >= 0` as a comparison where both sides only add
+    /// terms. A negative constant turns the comparison into `>`.
+    fn to_prop(t: &Term, comp: &mut ir::Component) -> Option<ir::PropIdx> {
+        let strict = t.constant < 0;
+        let (lk, rk) = if strict {
+            (0, -(t.constant + 1))
+        } else {
+            (t.constant, 0)
+        };
+        // Sum of the parameters with coefficients of the given sign
+        let mut side = |sign: i64, k: i64| {
+            let mut acc = None;
+            for (v, c) in &t.coeffs {
+                let (Var::Param(p), true) = (v, c.signum() == sign) else {
+                    continue;
+                };
+                let p = comp.add(ir::Expr::Param(*p));
+                let term = match c.unsigned_abs() {
+                    1 => p,
+                    c => comp.num(c).mul(p, comp),
+                };
+                acc =
+                    Some(acc.map_or(term, |a: ir::ExprIdx| a.add(term, comp)));
+            }
+            let k = comp.num(u64::try_from(k).ok()?);
+            Some(acc.map_or(k, |a| a.add(k, comp)))
+        };
+        let lhs = side(1, lk)?;
+        let rhs = side(-1, rk)?;
+        Some(match Self::events(t)?.as_slice() {
+            [] if strict => lhs.gt(rhs, comp),
+            [] => lhs.gte(rhs, comp),
+            [(a, ac), (b, _)] => {
+                let (pe, ne) = if *ac > 0 { (*a, *b) } else { (*b, *a) };
+                let l = comp.add(ir::Time {
+                    event: pe,
+                    offset: lhs,
+                });
+                let r = comp.add(ir::Time {
+                    event: ne,
+                    offset: rhs,
+                });
+                if strict {
+                    l.gt(r, comp)
+                } else {
+                    l.gte(r, comp)
+                }
+            }
+            _ => unreachable!("constraint is not expressible"),
+        })
+    }
+
+    fn expr(&mut self, e: ir::ExprIdx) -> Option<Term> {
+        if let Some(t) = self.exprs.get(&e) {
+            return t.clone();
+        }
+        let t = self.expr_uncached(e);
+        self.exprs.insert(e, t.clone());
+        t
+    }
+
+    fn expr_uncached(&mut self, e: ir::ExprIdx) -> Option<Term> {
+        match self.comp.get(e) {
+            ir::Expr::Param(p) => Some(Term::var(Var::Param(*p))),
+            ir::Expr::Concrete(n) => i64::try_from(*n).ok().map(Term::constant),
+            ir::Expr::Bin { op, lhs, rhs } => {
+                let (op, lhs, rhs) = (*op, *lhs, *rhs);
+                let l = self.expr(lhs)?;
+                let r = self.expr(rhs)?;
+                match op {
+                    ast::Op::Add => l.add(&r),
+                    ast::Op::Sub => l.sub(&r),
+                    ast::Op::Mul => match (l.as_constant(), r.as_constant()) {
+                        (Some(c), _) => r.scale(c),
+                        (_, Some(c)) => l.scale(c),
+                        (None, None) => None,
+                    },
+                    // Division by a concrete positive value rounds down like
+                    // the integer division of the solver.
+                    ast::Op::Div | ast::Op::Mod => {
+                        let (l, r) = (l.as_constant()?, r.as_constant()?);
+                        if r <= 0 {
+                            return None;
+                        }
+                        Some(Term::constant(if op == ast::Op::Div {
+                            l.div_euclid(r)
+                        } else {
+                            l.rem_euclid(r)
+                        }))
+                    }
+                }
+            }
+            ir::Expr::Fn { .. } => None,
+        }
+    }
+
+    fn time(&mut self, t: ir::TimeIdx) -> Option<Term> {
+        let ir::Time { event, offset } = self.comp.get(t).clone();
+        Term::var(Var::Event(event)).add(&self.expr(offset)?)
+    }
+
+    fn time_sub(&mut self, ts: &ir::TimeSub) -> Option<Term> {
+        match ts {
+            ir::TimeSub::Unit(e) => self.expr(*e),
+            ir::TimeSub::Sym { l, r } => self.time(*l)?.sub(&self.time(*r)?),
+        }
+    }
+
+    /// Difference between the sides of a comparison
+    fn cmp<T>(
+        &mut self,
+        cmp: &ir::CmpOp<T>,
+        mut term: impl FnMut(&mut Self, &T) -> Option<Term>,
+    ) -> Option<(ir::Cmp, Term)> {
+        let l = term(self, &cmp.lhs)?;
+        let r = term(self, &cmp.rhs)?;
+        Some((cmp.op.clone(), l.sub(&r)?))
+    }
+
+    /// Normal form of the comparison `d op 0` or its negation
+    fn cmp_dnf(op: ir::Cmp, d: Term, positive: bool) -> Option<Dnf> {
+        let one = Term::constant(1);
+        let neg = d.scale(-1)?;
+        Some(match (op, positive) {
+            (ir::Cmp::Gt, true) => vec![vec![d.sub(&one)?]],
+            (ir::Cmp::Gte, true) => vec![vec![d]],
+            (ir::Cmp::Eq, true) => vec![vec![d, neg]],
+            (ir::Cmp::Gt, false) => vec![vec![neg]],
+            (ir::Cmp::Gte, false) => vec![vec![neg.sub(&one)?]],
+            (ir::Cmp::Eq, false) => {
+                vec![vec![d.sub(&one)?], vec![neg.sub(&one)?]]
+            }
+        })
+    }
+
+    fn union(mut l: Dnf, r: Dnf) -> Option<Dnf> {
+        l.extend(r);
+        (l.len() <= MAX_CLAUSES).then_some(l)
+    }
+
+    fn product(l: Dnf, r: Dnf) -> Option<Dnf> {
+        if l.len() * r.len() > MAX_CLAUSES {
+            return None;
+        }
+        Some(
+            l.iter()
+                .flat_map(|a| {
+                    r.iter().map(move |b| a.iter().chain(b).cloned().collect())
+                })
+                .collect(),
+        )
+    }
+
+    /// Disjunctive normal form of the proposition if `positive` is set and
+    /// of its negation otherwise.
+    fn dnf(&mut self, prop: ir::PropIdx, positive: bool) -> Option<Dnf> {
+        match self.comp.get(prop).clone() {
+            ir::Prop::True if positive => Some(vec![vec![]]),
+            ir::Prop::False if !positive => Some(vec![vec![]]),
+            ir::Prop::True | ir::Prop::False => Some(vec![]),
+            ir::Prop::Cmp(c) => {
+                let (op, d) = self.cmp(&c, |s, e| s.expr(*e))?;
+                Self::cmp_dnf(op, d, positive)
+            }
+            ir::Prop::TimeCmp(c) => {
+                let (op, d) = self.cmp(&c, |s, t| s.time(*t))?;
+                Self::cmp_dnf(op, d, positive)
+            }
+            ir::Prop::TimeSubCmp(c) => {
+                let (op, d) = self.cmp(&c, |s, ts| s.time_sub(ts))?;
+                Self::cmp_dnf(op, d, positive)
+            }
+            ir::Prop::Not(p) => self.dnf(p, !positive),
+            ir::Prop::And(l, r) if positive => {
+                Self::product(self.dnf(l, true)?, self.dnf(r, true)?)
+            }
+            ir::Prop::And(l, r) => {
+                Self::union(self.dnf(l, false)?, self.dnf(r, false)?)
+            }
+            ir::Prop::Or(l, r) if positive => {
+                Self::union(self.dnf(l, true)?, self.dnf(r, true)?)
+            }
+            ir::Prop::Or(l, r) => {
+                Self::product(self.dnf(l, false)?, self.dnf(r, false)?)
+            }
+            ir::Prop::Implies(l, r) if positive => {
+                Self::union(self.dnf(l, false)?, self.dnf(r, true)?)
+            }
+            ir::Prop::Implies(l, r) => {
+                Self::product(self.dnf(l, true)?, self.dnf(r, false)?)
+            }
+        }
+    }
+
+    /// Evaluate the proposition using the model
+    fn holds(
+        &mut self,
+        prop: ir::PropIdx,
+        model: &HashMap<Var, i64>,
+    ) -> Option<bool> {
+        let cmp = |op: ir::Cmp, d: Term| {
+            let d = d.eval(model)?;
+            Some(match op {
+                ir::Cmp::Gt => d > 0,
+                ir::Cmp::Gte => d >= 0,
+                ir::Cmp::Eq => d == 0,
+            })
+        };
+        match self.comp.get(prop).clone() {
+            ir::Prop::True => Some(true),
+            ir::Prop::False => Some(false),
+            ir::Prop::Cmp(c) => {
+                let (op, d) = self.cmp(&c, |s, e| s.expr(*e))?;
+                cmp(op, d)
+            }
+            ir::Prop::TimeCmp(c) => {
+                let (op, d) = self.cmp(&c, |s, t| s.time(*t))?;
+                cmp(op, d)
+            }
+            ir::Prop::TimeSubCmp(c) => {
+                let (op, d) = self.cmp(&c, |s, ts| s.time_sub(ts))?;
+                cmp(op, d)
+            }
+            ir::Prop::Not(p) => self.holds(p, model).map(|b| !b),
+            ir::Prop::And(l, r) => {
+                Some(self.holds(l, model)? && self.holds(r, model)?)
+            }
+            ir::Prop::Or(l, r) => {
+                Some(self.holds(l, model)? || self.holds(r, model)?)
+            }
+            ir::Prop::Implies(l, r) => {
+                Some(!self.holds(l, model)? || self.holds(r, model)?)
+            }
+        }
+    }
+
+    /// Drop constant constraints and tighten the others. Returns `None` if
+    /// one of the constant constraints is false.
+    fn prune(atoms: impl IntoIterator<Item = Term>) -> Option<Vec<Term>> {
+        let mut seen = HashSet::new();
+        let mut out = Vec::new();
+        for a in atoms {
+            let a = a.tighten();
+            match a.as_constant() {
+                Some(c) if c < 0 => return None,
+                Some(_) => (),
+                None => {
+                    if seen.insert(a.clone()) {
+                        out.push(a);
+                    }
+                }
+            }
+        }
+        Some(out)
+    }
+
+    /// Eliminate the variables selected by `elim` from the constraints
+    /// `t >= 0` using Fourier-Motzkin elimination. Returns `Some(None)` if
+    /// the constraints are unsatisfiable. Otherwise, returns the constraints
+    /// over the remaining variables along with the constraints mentioning
+    /// each eliminated variable in the order of elimination. Returns `None`
+    /// if there are too many constraints.
+    fn eliminate(
+        atoms: Vec<Term>,
+        elim: impl Fn(&Var) -> bool,
+    ) -> Option<Option<(Vec<Term>, Stages)>> {
+        let Some(mut cur) = Self::prune(atoms) else {
+            return Some(None);
+        };
+        let mut stages: Stages = Vec::new();
+        while let Some(var) = cur
+            .iter()
+            .flat_map(|a| a.coeffs.keys().copied())
+            .filter(|v| elim(v))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .min_by_key(|v| {
+                let pos = cur.iter().filter(|a| a.coeff(*v) > 0).count();
+                let neg = cur.iter().filter(|a| a.coeff(*v) < 0).count();
+                (pos * neg, *v)
+            })
+        {
+            let (with, without): (Vec<_>, Vec<_>) =
+                cur.into_iter().partition(|a| a.coeff(var) != 0);
+            let mut next = without;
+            for p in with.iter().filter(|a| a.coeff(var) > 0) {
+                for n in with.iter().filter(|a| a.coeff(var) < 0) {
+                    let comb =
+                        p.scale(-n.coeff(var))?.add(&n.scale(p.coeff(var))?)?;
+                    next.push(comb);
+                }
+            }
+            let Some(next) = Self::prune(next) else {
+                return Some(None);
+            };
+            if next.len() > MAX_ATOMS {
+                return None;
+            }
+            stages.push((var, with));
+            cur = next;
+        }
+        Some(Some((cur, stages)))
+    }
+
+    /// Check whether the constraints `t >= 0` have an integer solution.
+    /// Returns `Some(None)` if they do not, `Some(Some(model))` with a
+    /// solution if they do, and `None` if neither could be shown.
+    fn solve(atoms: Vec<Term>) -> Option<Option<HashMap<Var, i64>>> {
+        let Some((_, stages)) = Self::eliminate(atoms, |_| true)? else {
+            return Some(None);
+        };
+
+        // The constraints have a rational solution. Try to build an integer
+        // solution by assigning the variables in the reverse order of their
+        // elimination.
+        let mut model = HashMap::new();
+        for (var, atoms) in stages.into_iter().rev() {
+            let (mut lo, mut hi) = (None::<i64>, None::<i64>);
+            for a in atoms {
+                let c = a.coeff(var);
+                let mut rest = a.clone();
+                rest.coeffs.remove(&var);
+                let rest = rest.eval(&model)?;
+                if c > 0 {
+                    // c*x + rest >= 0
+                    let b = rest.div_euclid(c).checked_neg()?;
+                    lo = Some(lo.map_or(b, |l| l.max(b)));
+                } else {
+                    // -d*x + rest >= 0
+                    let b = rest.div_euclid(-c);
+                    hi = Some(hi.map_or(b, |h| h.min(b)));
+                }
+            }
+            let x = match (lo, hi) {
+                (Some(l), Some(h)) if l > h => return None,
+                (Some(l), _) if l > 0 => l,
+                (_, Some(h)) if h < 0 => h,
+                _ => 0,
+            };
+            model.insert(var, x);
+        }
+        Some(Some(model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decision, Linear, Var};
+    use crate::ir::{self, Ctx};
+    use crate::utils::GPosIdx;
+    use std::collections::HashMap;
+
+    /// A component with `n` signature parameters
+    fn component(n: usize) -> (ir::Component, Vec<ir::ExprIdx>) {
+        let mut comp = ir::Component::new(ir::CompIdx::new(0), false);
+        let params = (0..n)
+            .map(|i| {
+                let info = comp.add(ir::Info::param(
+                    format!("p{i}").into(),
+                    GPosIdx::UNKNOWN,
+                ));
+                let param =
+                    comp.add(ir::Param::new(ir::ParamOwner::Sig, info, None));
+                param.expr(&mut comp)
+            })
+            .collect();
+        (comp, params)
+    }
+
+    /// Conjunction of the propositions
+    fn all(props: Vec<ir::PropIdx>, comp: &mut ir::Component) -> ir::PropIdx {
+        let t = comp.add(ir::Prop::True);
+        props.into_iter().fold(t, |acc, p| acc.and(p, comp))
+    }
+
+    #[test]
+    fn valid() {
+        let (mut comp, ps) = component(3);
+        let (a, b, c) = (ps[0], ps[1], ps[2]);
+        let ab = a.gte(b, &mut comp);
+        let bc = b.gt(c, &mut comp);
+        let ac = a.gt(c, &mut comp);
+        let prop = ab.and(bc, &mut comp).implies(ac, &mut comp);
+        assert!(matches!(Linear::decide(prop, &comp), Decision::Valid));
+    }
+
+    #[test]
+    fn invalid_with_model() {
+        let (mut comp, ps) = component(2);
+        let (a, b) = (ps[0], ps[1]);
+        let two = comp.num(2);
+        let ab = a.gte(b, &mut comp);
+        let b2 = b.add(two, &mut comp);
+        let cons = a.gte(b2, &mut comp);
+        let prop = ab.implies(cons, &mut comp);
+        let Decision::Invalid(params, events) = Linear::decide(prop, &comp)
+        else {
+            panic!("expected a counterexample")
+        };
+        // The counterexample falsifies the proposition
+        let model: HashMap<Var, i64> = params
+            .into_iter()
+            .map(|(p, v)| (Var::Param(p), v))
+            .chain(events.into_iter().map(|(e, v)| (Var::Event(e), v)))
+            .collect();
+        let mut lin = Linear {
+            comp: &comp,
+            exprs: HashMap::new(),
+        };
+        assert_eq!(lin.holds(prop, &model), Some(false));
+    }
+
+    #[test]
+    fn unknown_pow2() {
+        let (mut comp, ps) = component(1);
+        let a = ps[0];
+        let pow = a.pow2(&mut comp);
+        let prop = pow.gt(a, &mut comp);
+        assert!(matches!(Linear::decide(prop, &comp), Decision::Unknown));
+    }
+
+    #[test]
+    fn unknown_nonlinear_mul() {
+        let (mut comp, ps) = component(2);
+        let (a, b) = (ps[0], ps[1]);
+        let ab = a.mul(b, &mut comp);
+        let prop = ab.gte(a, &mut comp);
+        assert!(matches!(Linear::decide(prop, &comp), Decision::Unknown));
+    }
+
+    #[test]
+    fn unknown_too_many_clauses() {
+        // The negation of a disjunction of n equalities has 2^n clauses
+        let disjunction = |n: u64| {
+            let (mut comp, ps) = component(1);
+            let f = comp.add(ir::Prop::False);
+            let prop = (1..=n).fold(f, |acc, k| {
+                let k = comp.num(k);
+                let eq = ps[0].equal(k, &mut comp);
+                acc.or(eq, &mut comp)
+            });
+            matches!(Linear::decide(prop, &comp), Decision::Unknown)
+        };
+        assert!(!disjunction(super::MAX_CLAUSES.ilog2() as u64));
+        assert!(disjunction(super::MAX_CLAUSES.ilog2() as u64 + 1));
+    }
+
+    #[test]
+    fn unknown_too_many_atoms() {
+        // Constraints `±x ± k*y ± (k+1)*z + c >= 0` for every combination of
+        // signs. Eliminating any variable combines every lower bound with
+        // every upper bound.
+        let bounds = |n: u64| {
+            let (mut comp, ps) = component(3);
+            let mut atoms = vec![];
+            for k in 1..=n {
+                for signs in 0..8u64 {
+                    let terms = [
+                        (ps[0], 1),
+                        (ps[1], k),
+                        (ps[2], k + 1),
+                        (comp.num(1), 100 * k + signs),
+                    ];
+                    let (mut pos, mut neg) = (comp.num(0), comp.num(0));
+                    for (i, (e, c)) in terms.into_iter().enumerate() {
+                        let c = comp.num(c);
+                        let t = e.mul(c, &mut comp);
+                        if i < 3 && signs & (1 << i) != 0 {
+                            neg = neg.add(t, &mut comp);
+                        } else {
+                            pos = pos.add(t, &mut comp);
+                        }
+                    }
+                    atoms.push(pos.gte(neg, &mut comp));
+                }
+            }
+            let prop = all(atoms, &mut comp).not(&mut comp);
+            matches!(Linear::decide(prop, &comp), Decision::Unknown)
+        };
+        assert!(!bounds(1));
+        assert!(bounds(3));
+    }
+}
@@ -0,0 +1,441 @@
+use super::Fsm;
+use crate::backend;
+use crate::ir::{self, CompIdx, Ctx, EventIdx, InstIdx, PortIdx};
+use crate::{ast, utils::Idx};
+use calyx_ir::{self as calyx, RRC};
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// Compile a program into Calyx.
+/// The program must be monomorphized ([super::super::Monomorphize]) and
+/// must not contain any bundles ([super::super::BundleElim]).
+///
+/// Each event with an interface port is tracked using an FSM which is used to
+/// generate the guards for invocation arguments and to trigger the interface
+/// ports of invoked components.
+pub struct Compile<'a> {
+    ctx: &'a ir::Context,
+    /// Library signatures
+    lib: &'a calyx::LibrarySignatures,
+    /// Signatures of the compiled components
+    sigs: HashMap<CompIdx, Vec<calyx::PortDef<u64>>>,
+    /// Mapping to the component representing FSM with particular number of states
+    fsm_comps: HashMap<u64, calyx::Component>,
+}
+
+impl Compile<'_> {
+    /// Name of a component
+    fn comp_name(comp: &ir::Component) -> ast::Id {
+        let Some(src) = &comp.src_info else {
+            unreachable!("Component {} has no source information", comp.idx())
+        };
+        src.name
+    }
+
+    /// Name of a port
+    fn port_name(comp: &ir::Component, port: PortIdx) -> ast::Id {
+        let ir::Info::Port { name, .. } = comp.get(comp.get(port).info) else {
+            unreachable!("Expected port info")
+        };
+        *name
+    }
+
+    /// Interface ports of the component ordered by their events
+    fn interface_ports(comp: &ir::Component) -> Vec<(EventIdx, ast::Id)> {
+        comp.src_info
+            .iter()
+            .flat_map(|src| src.interface_ports.iter())
+            .map(|(ev, name)| (*ev, *name))
+            .sorted()
+            .collect()
+    }
+
+    fn concrete_transform<CW>(
+        width: impl Fn(u64) -> CW,
+    ) -> impl Fn(&ast::Id, u64) -> calyx::PortDef<CW> {
+        move |name, w| calyx::PortDef {
+            name: calyx::Id::from(name.as_ref()),
+            width: width(w),
+            direction: calyx::Direction::Input,
+            attributes: calyx::Attributes::default(),
+        }
+    }
+
+    /// Generate the Calyx signature of a component. `width` is used to
+    /// transform the bitwidth of ports with data.
+    fn port_defs<CW, F>(
+        comp: &ir::Component,
+        width: F,
+        const_width: impl Fn(u64) -> CW,
+    ) -> Vec<calyx::PortDef<CW>>
+    where
+        F: Fn(ir::ExprIdx) -> CW,
+    {
+        let data_port = |p: PortIdx, dir: calyx::Direction| {
+            let mut attributes = calyx::Attributes::default();
+            attributes.insert(calyx::BoolAttr::Data, 1);
+            calyx::PortDef {
+                name: calyx::Id::from(Self::port_name(comp, p).as_ref()),
+                width: width(comp.get(p).width),
+                direction: dir,
+                attributes,
+            }
+        };
+        let concrete = Self::concrete_transform(const_width);
+        let interface_ports = Self::interface_ports(comp);
+
+        // Interface signals read as data are defined as interface ports
+        let inputs = comp
+            .ports()
+            .iter()
+            .filter(|(idx, p)| {
+                let name = Self::port_name(comp, *idx);
+                p.is_sig_in() && interface_ports.iter().all(|(_, n)| *n != name)
+            })
+            .map(|(idx, _)| data_port(idx, calyx::Direction::Input));
+        let interface = interface_ports.iter().map(|(_, name)| {
+            let mut pd = concrete(name, 1);
+            pd.attributes
+                .insert(calyx::Attribute::Unknown("fil_event".into()), 1);
+            pd
+        });
+        let outputs = comp
+            .ports()
+            .iter()
+            .filter(|(_, p)| p.is_sig_out())
+            .map(|(idx, _)| data_port(idx, calyx::Direction::Output));
+        let unannotated = comp
+            .src_info
+            .iter()
+            .flat_map(|src| src.unannotated_ports.iter())
+            .map(|(name, w)| concrete(name, *w));
+
+        let mut ports = inputs
+            .chain(interface)
+            .chain(outputs)
+            .chain(unannotated)
+            .collect_vec();
+        backend::add_interface_attrs(&mut ports, &concrete, !comp.is_ext);
+        ports
+    }
+
+    /// Generate the Calyx primitive for an external component
+    fn primitive(comp: &ir::Component) -> calyx::Primitive {
+        let param_name = |p: ir::ParamIdx| {
+            let ir::Info::Param { name, .. } = comp.get(comp.get(p).info)
+            else {
+                unreachable!("Expected param info")
+            };
+            calyx::Id::from(name.as_ref())
+        };
+        let width = |w: ir::ExprIdx| match comp.get(w) {
+            ir::Expr::Concrete(n) => calyx::Width::Const { value: *n },
+            ir::Expr::Param(p) => calyx::Width::Param {
+                value: param_name(*p),
+            },
+            _ => panic!(
+                "Cannot compile complex width expression in external `{}'",
+                Self::comp_name(comp)
+            ),
+        };
+        calyx::Primitive {
+            name: calyx::Id::from(Self::comp_name(comp).as_ref()),
+            params: comp
+                .params()
+                .iter()
+                .filter(|(_, p)| p.is_sig_owned())
+                .map(|(idx, _)| param_name(idx))
+                .collect(),
+            signature: Self::port_defs(comp, width, |value| {
+                calyx::Width::Const { value }
+            }),
+            is_comb: false,
+            attributes: calyx::Attributes::default(),
+            body: None,
+        }
+    }
+
+    /// Compile the component and all the components it instantiates.
+    fn comp(&mut self, idx: CompIdx, out: &mut Vec<calyx::Component>) {
+        if self.sigs.contains_key(&idx) {
+            return;
+        }
+        let comp = self.ctx.get(idx);
+        // Compile all instantiated components first
+        for (_, inst) in comp.instances().iter() {
+            if !self.ctx.get(inst.comp).is_ext {
+                self.comp(inst.comp, out);
+            }
+        }
+
+        let width = |w: ir::ExprIdx| {
+            w.as_concrete(comp)
+                .unwrap_or_else(|| unreachable!("Port width is not concrete"))
+        };
+        let ports = Self::port_defs(comp, width, |w| w);
+        let mut component =
+            calyx::Component::new(Self::comp_name(comp).as_ref(), ports, false);
+        component.attributes.insert(calyx::BoolAttr::NoInterface, 1);
+
+        // Define the FSM components needed by this component
+        let states = Fsm::max_states(comp);
+        for (ev, _) in Self::interface_ports(comp) {
+            let n = states.get(&ev).copied().unwrap_or(1);
+            self.fsm_comps
+                .entry(n)
+                .or_insert_with(|| backend::define_fsm_component(n, self.lib));
+        }
+
+        let builder =
+            calyx::Builder::new(&mut component, self.lib).not_generated();
+        let mut ctx = CompCtx {
+            comp,
+            builder,
+            cells: HashMap::new(),
+            fsms: HashMap::new(),
+        };
+        ctx.compile(self.ctx, &self.sigs, &self.fsm_comps, &states);
+
+        let sig = backend::Binding::cell_to_port_def(&component.signature);
+        self.sigs.insert(idx, sig);
+        out.push(component);
+    }
+
+    /// Compile the program into Calyx and print it to stdout
+    pub fn compile(ctx: &ir::Context) {
+        let externs = ctx
+            .externals
+            .iter()
+            .map(|(file, comps)| {
+                let prims = comps
+                    .iter()
+                    .map(|idx| Self::primitive(ctx.get(*idx)))
+                    .collect();
+                (file.clone(), prims)
+            })
+            .collect();
+        let mut calyx_ctx = backend::init_calyx(externs).unwrap_or_else(|e| {
+            panic!("Error initializing calyx context: {:?}", e);
+        });
+
+        let mut comps = vec![];
+        let mut fsm_comps = HashMap::new();
+        if let Some(entry) = ctx.entrypoint {
+            let mut compile = Compile {
+                ctx,
+                lib: &calyx_ctx.lib,
+                sigs: HashMap::new(),
+                fsm_comps: HashMap::new(),
+            };
+            compile.comp(entry, &mut comps);
+            fsm_comps = compile.fsm_comps;
+        }
+        calyx_ctx.components.extend(comps);
+        calyx_ctx.components.extend(fsm_comps.into_values());
+
+        let mut out = &mut std::io::stdout();
+        calyx::Printer::write_context(&calyx_ctx, false, &mut out).unwrap();
+    }
+}
+
+/// Context for compiling a single component
+struct CompCtx<'a> {
+    comp: &'a ir::Component,
+    builder: calyx::Builder<'a>,
+    /// Cells for instances
+    cells: HashMap<InstIdx, RRC<calyx::Cell>>,
+    /// FSMs for events
+    fsms: HashMap<EventIdx, Fsm>,
+}
+
+impl CompCtx<'_> {
+    fn compile(
+        &mut self,
+        ctx: &ir::Context,
+        sigs: &HashMap<CompIdx, Vec<calyx::PortDef<u64>>>,
+        fsm_comps: &HashMap<u64, calyx::Component>,
+        states: &HashMap<EventIdx, u64>,
+    ) {
+        // Construct the FSMs for each event with an interface port
+        for (ev, name) in Compile::interface_ports(self.comp) {
+            let n = states.get(&ev).copied().unwrap_or(1);
+            let ir::Info::Event { name: ev_name, .. } =
+                self.comp.get(self.comp.get(ev).info)
+            else {
+                unreachable!("Expected event info")
+            };
+            let trigger =
+                self.builder.component.signature.borrow().get(name.as_ref());
+            let fsm = Fsm::new(
+                &format!("{ev_name}_fsm"),
+                trigger,
+                &fsm_comps[&n],
+                &mut self.builder,
+            );
+            self.fsms.insert(ev, fsm);
+        }
+
+        // Construct the cells for each instance
+        for (idx, inst) in self.comp.instances().iter() {
+            let callee = ctx.get(inst.comp);
+            let name = Compile::comp_name(callee);
+            let ir::Info::Instance {
+                name: inst_name, ..
+            } = self.comp.get(inst.info)
+            else {
+                unreachable!("Expected instance info")
+            };
+            let cell = if callee.is_ext {
+                let params = inst
+                    .params
+                    .iter()
+                    .map(|p| {
+                        p.as_concrete(self.comp).unwrap_or_else(|| {
+                            unreachable!("Parameter is not concrete")
+                        })
+                    })
+                    .collect_vec();
+                self.builder.add_primitive(
+                    inst_name.to_string(),
+                    name.as_ref(),
+                    &params,
+                )
+            } else {
+                self.builder.add_component(
+                    inst_name.to_string(),
+                    name.to_string(),
+                    sigs[&inst.comp].clone(),
+                )
+            };
+            cell.borrow_mut()
+                .attributes
+                .insert(calyx::BoolAttr::Data, 1);
+            self.cells.insert(idx, cell);
+        }
+
+        for cmd in &self.comp.cmds {
+            match cmd {
+                ir::Command::Connect(con) => self.connect(con),
+                ir::Command::EventBind(eb) => self.event_bind(eb, ctx),
+                ir::Command::Instance(_) | ir::Command::Invoke(_) => (),
+                ir::Command::Fact(_) => {
+                    unreachable!("Facts should have been compiled away")
+                }
+                ir::Command::BundleDef(_) => {
+                    unreachable!("Bundles should have been compiled away")
+                }
+                ir::Command::ForLoop(_) | ir::Command::If(_) => {
+                    unreachable!("Control flow should have been compiled away")
+                }
+            }
+        }
+    }
+
+    /// The concrete offset of a time expression
+    fn offset(&self, time: ir::TimeIdx) -> (EventIdx, u64) {
+        let ir::Time { event, offset } = self.comp.get(time);
+        let offset = offset
+            .as_concrete(self.comp)
+            .unwrap_or_else(|| unreachable!("Time offset is not concrete"));
+        (*event, offset)
+    }
+
+    fn port(&mut self, port: PortIdx) -> RRC<calyx::Port> {
+        let name = Compile::port_name(self.comp, port);
+        match &self.comp.get(port).owner {
+            ir::PortOwner::Const { value } => {
+                let width = self.comp.get(port).width.as_concrete(self.comp);
+                let width = width.unwrap_or_else(|| {
+                    unreachable!("Port width is not concrete")
+                });
+                let c = self.builder.add_constant(*value, width);
+                let out = c.borrow().get("out");
+                out
+            }
+            ir::PortOwner::Sig { .. } => {
+                self.builder.component.signature.borrow().get(name.as_ref())
+            }
+            ir::PortOwner::Inv { inv, .. } => {
+                let inst = self.comp.get(*inv).inst;
+                self.cells[&inst].borrow().get(name.as_ref())
+            }
+            ir::PortOwner::Local => {
+                unreachable!("Local ports should have been compiled away")
+            }
+        }
+    }
+
+    fn connect(&mut self, con: &ir::Connect) {
+        let ir::Connect { src, dst, .. } = con;
+        let dst_port = self.comp.get(dst.port);
+        // Arguments to invocations are only valid during their liveness
+        let guard = match dst_port.owner {
+            ir::PortOwner::Inv {
+                dir: ir::Direction::In,
+                ..
+            } => {
+                let ir::Range { start, end } = dst_port.live.range;
+                let (ev, st) = self.offset(start);
+                let (_, end) = self.offset(end);
+                self.fsms
+                    .get(&ev)
+                    .map(|fsm| fsm.range(st, end))
+                    .unwrap_or(calyx::Guard::True)
+            }
+            _ => calyx::Guard::True,
+        };
+        let guard = match con.guard.as_ref() {
+            Some(g) => guard & self.guard(g),
+            None => guard,
+        };
+        let dst = self.port(dst.port);
+        let src = self.port(src.port);
+        let assign = self.builder.build_assignment(dst, src, guard);
+        self.builder.component.continuous_assignments.push(assign);
+    }
+
+    fn guard(&mut self, g: &ir::Guard) -> calyx::Guard<calyx::Nothing> {
+        match g {
+            ir::Guard::Or(l, r) => self.guard(l) | self.guard(r),
+            ir::Guard::And(l, r) => self.guard(l) & self.guard(r),
+            ir::Guard::Not(g) => !self.guard(g),
+            ir::Guard::Port(p) => self.port(p.port).into(),
+        }
+    }
+
+    /// Trigger the interface port of an invocation using the FSM of the event
+    /// it is scheduled with.
+    fn event_bind(&mut self, eb: &ir::EventBind, ctx: &ir::Context) {
+        let ir::EventBind {
+            event, arg, base, ..
+        } = eb;
+        let ir::EventOwner::Inv { inv } = self.comp.get(*event).owner else {
+            unreachable!("Event binding for a non-invocation event")
+        };
+        let inst = self.comp.get(inv).inst;
+        let callee = ctx.get(self.comp.get(inst).comp);
+        let Some(name) = callee
+            .src_info
+            .as_ref()
+            .and_then(|src| src.interface_ports.get(base))
+        else {
+            // The event does not have an interface port
+            return;
+        };
+        let (ev, st) = self.offset(*arg);
+        let Some(fsm) = self.fsms.get(&ev) else {
+            panic!(
+                "No FSM for event `{}'. The event must have an interface port",
+                Idx::get(ev)
+            )
+        };
+        let guard = fsm.state(st);
+        let one = self.builder.add_constant(1, 1);
+        let assign = self.builder.build_assignment(
+            self.cells[&inst].borrow().get(name.as_ref()),
+            one.borrow().get("out"),
+            guard,
+        );
+        self.builder.component.continuous_assignments.push(assign);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::ir::{self, Ctx, EventIdx};
+use calyx_ir::{self as calyx, RRC};
+use std::collections::HashMap;
+
+/// A Calyx FSM that tracks the states of an event. The FSM is implemented as
+/// a shift register triggered by the interface port of the event.
+pub struct Fsm {
+    cell: RRC<calyx::Cell>,
+}
+
+impl Fsm {
+    /// Instantiate an FSM using the `fsm_comp` component and trigger it using
+    /// the `trigger` port.
+    pub fn new(
+        name: &str,
+        trigger: RRC<calyx::Port>,
+        fsm_comp: &calyx::Component,
+        builder: &mut calyx::Builder,
+    ) -> Self {
+        let cell = builder.add_component(
+            name.to_string(),
+            fsm_comp.name.to_string(),
+            crate::backend::Binding::cell_to_port_def(&fsm_comp.signature),
+        );
+        let go_assign = builder.build_assignment(
+            cell.borrow().get("go"),
+            trigger,
+            calyx::Guard::True,
+        );
+        builder.component.continuous_assignments.push(go_assign);
+        Fsm { cell }
+    }
+
+    /// Guard that is true when the FSM is in the given state
+    pub fn state(&self, st: u64) -> calyx::Guard<calyx::Nothing> {
+        self.cell.borrow().get(format!("_{st}")).into()
+    }
+
+    /// Guard that is true when the FSM is in any state in `[start, end)`
+    pub fn range(&self, start: u64, end: u64) -> calyx::Guard<calyx::Nothing> {
+        (start..end)
+            .map(|st| self.state(st))
+            .reduce(|a, b| a | b)
+            .unwrap_or(calyx::Guard::True)
+    }
+
+    /// Compute the number of states needed to track each event in the
+    /// component. Mirrors the legacy computation: an event needs enough states
+    /// to cover the offsets of the ports the component reads, i.e., the inputs
+    /// of the signature and the outputs of invocations.
+    pub fn max_states(comp: &ir::Component) -> HashMap<EventIdx, u64> {
+        let mut states: HashMap<EventIdx, u64> =
+            comp.events().idx_iter().map(|ev| (ev, 0)).collect();
+        let readable = comp.ports().iter().filter(|(_, p)| {
+            matches!(
+                p.owner,
+                ir::PortOwner::Sig {
+                    dir: ir::Direction::Out
+                } | ir::PortOwner::Inv {
+                    dir: ir::Direction::Out,
+                    ..
+                }
+            )
+        });
+        for (_, port) in readable {
+            let ir::Range { start, end } = port.live.range;
+            for time in [start, end] {
+                let ir::Time { event, offset } = comp.get(time);
+                let offset = offset.as_concrete(comp).unwrap_or_else(|| {
+                    unreachable!("Time offset is not concrete")
+                });
+                let st = states.entry(*event).or_insert(0);
+                *st = (*st).max(offset);
+            }
+        }
+        states
+    }
+}
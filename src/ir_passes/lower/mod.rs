@@ -0,0 +1,5 @@
+mod compile;
+mod fsm;
+
+pub use compile::Compile;
+use fsm::Fsm;
@@ -1,13 +1,21 @@
 mod assume;
+mod bundle_elim;
 mod discharge;
 mod hoist_facts;
 mod interval_check;
+mod linear;
+mod lower;
+mod mono;
 mod prop_simplify;
+mod timeline;
 mod type_check;
 
 pub use assume::Assume;
+pub use bundle_elim::BundleElim;
 pub use discharge::Discharge;
 pub use hoist_facts::HoistFacts;
 pub use interval_check::IntervalCheck;
+pub use lower::Compile;
+pub use mono::Monomorphize;
 pub use prop_simplify::Simplify;
 pub use type_check::TypeCheck;
@@ -0,0 +1,641 @@
+use crate::ir::{
+    self, CompIdx, Ctx, DisplayCtx, EventIdx, ExprIdx, InfoIdx, InstIdx,
+    InvIdx, MutCtx, ParamIdx, PortIdx, PropIdx, TimeIdx,
+};
+use crate::{ast, cmdline, diagnostics, errors::Error};
+use itertools::Itertools;
+use std::collections::{HashMap, VecDeque};
+
+/// Monomorphize the program by generating a copy of each component for every
+/// set of parameters it is instantiated with. The traversal starts from the
+/// entrypoint of the program and only generates components reachable from it.
+///
+/// In the generated program:
+/// * All loops are unrolled and all `if` statements are resolved.
+/// * Parameters defined using `let` are replaced with their definitions.
+/// * All facts are removed since they have already been discharged.
+/// * The only parameters left are those bound by bundles. Externals retain
+///   their parameters which are provided by each instance.
+pub struct Monomorphize<'a> {
+    /// The original program
+    old: &'a ir::Context,
+    /// The monomorphized program
+    ctx: ir::Context,
+    /// Mapping from external components to their copy in the new program
+    ext_map: HashMap<CompIdx, CompIdx>,
+    /// Mapping from instantiated components and their parameters to the
+    /// monomorphized component
+    inst_map: HashMap<(CompIdx, Vec<u64>), CompIdx>,
+    /// Components that still need to be monomorphized
+    queue: VecDeque<(CompIdx, Vec<u64>, CompIdx)>,
+    /// Index for the next monomorphized component
+    next: usize,
+    /// Errors in the values of parameters
+    diag: diagnostics::Diagnostics,
+}
+
+impl<'a> Monomorphize<'a> {
+    /// Generate a monomorphic program from the entrypoint of the program.
+    /// Returns the number of errors if the value of a parameter cannot be
+    /// computed.
+    pub fn transform(
+        opts: &cmdline::Opts,
+        old: &'a ir::Context,
+    ) -> Result<ir::Context, u64> {
+        let mut mono = Monomorphize {
+            old,
+            ctx: ir::Context {
+                funcs: old.funcs.clone(),
+                ..Default::default()
+            },
+            ext_map: HashMap::default(),
+            inst_map: HashMap::default(),
+            queue: VecDeque::default(),
+            next: 0,
+            diag: diagnostics::Diagnostics::new(opts.diagnostics_format),
+        };
+
+        // Externals are copied over without changing their parameters.
+        for (file, exts) in &old.externals {
+            let idxs = exts
+                .iter()
+                .map(|ext| {
+                    let idx = CompIdx::new(mono.next);
+                    mono.next += 1;
+                    let comp = MonoComp::new(&mut mono, *ext, idx, None).ext();
+                    mono.ctx.comps.add(comp);
+                    mono.ext_map.insert(*ext, idx);
+                    idx
+                })
+                .collect_vec();
+            mono.ctx.externals.push((file.clone(), idxs));
+        }
+
+        if let Some(entry) = old.entrypoint {
+            let vals = mono.sig_params(entry, vec![]);
+            let (idx, _) = mono.instance(entry, vals);
+            mono.ctx.entrypoint = Some(idx);
+        }
+
+        while let Some((comp, vals, idx)) = mono.queue.pop_front() {
+            let comp = MonoComp::new(&mut mono, comp, idx, Some(vals)).comp();
+            let added = mono.ctx.comps.add(comp);
+            assert!(added == idx, "monomorphized component index mismatch");
+        }
+
+        match mono.diag.report_all() {
+            Some(n) => Err(n),
+            None => Ok(mono.ctx),
+        }
+    }
+
+    /// Get the component that represents `comp` instantiated with the given
+    /// parameters and the parameters that the instance should provide.
+    /// Schedules the component for monomorphization if needed.
+    fn instance(
+        &mut self,
+        comp: CompIdx,
+        vals: Vec<u64>,
+    ) -> (CompIdx, Vec<u64>) {
+        if let Some(idx) = self.ext_map.get(&comp) {
+            return (*idx, vals);
+        }
+        let key = (comp, vals);
+        if let Some(idx) = self.inst_map.get(&key) {
+            return (*idx, vec![]);
+        }
+        let idx = CompIdx::new(self.next);
+        self.next += 1;
+        self.inst_map.insert(key.clone(), idx);
+        self.queue.push_back((key.0, key.1, idx));
+        (idx, vec![])
+    }
+
+    /// Compute the values of all the signature parameters of the component
+    /// by filling in the missing arguments with their default values.
+    fn sig_params(&mut self, comp: CompIdx, mut vals: Vec<u64>) -> Vec<u64> {
+        let comp = self.old.get(comp);
+        let mut bind = HashMap::new();
+        for (i, (idx, param)) in comp
+            .params()
+            .iter()
+            .filter(|(_, p)| p.is_sig_owned())
+            .enumerate()
+        {
+            if i >= vals.len() {
+                let Some(default) = param.default else {
+                    unreachable!("No value provided for parameter {idx}")
+                };
+                let val = default.eval(comp, &bind).unwrap_or_else(|| {
+                    let ir::Info::Param { name, bind_loc } =
+                        comp.get(param.info)
+                    else {
+                        unreachable!("Expected param info")
+                    };
+                    let info = self.diag.add_info(
+                        format!(
+                            "default value `{}' is not a natural number",
+                            comp.display(default)
+                        ),
+                        *bind_loc,
+                    );
+                    let err = Error::malformed(format!(
+                        "cannot compute the value of parameter `#{name}'"
+                    ))
+                    .add_note(info);
+                    self.diag.add_error(err);
+                    // Continue with a placeholder to report all errors
+                    0
+                });
+                vals.push(val);
+            }
+            bind.insert(idx, vals[i]);
+        }
+        vals
+    }
+}
+
+/// Builds the monomorphic version of a component.
+struct MonoComp<'a, 'p> {
+    /// The monomorphization pass
+    pass: &'a mut Monomorphize<'p>,
+    /// The component being monomorphized
+    old: &'p ir::Component,
+    /// The component being constructed
+    comp: ir::Component,
+    /// Values of the parameters in the old component
+    params: HashMap<ParamIdx, ExprIdx>,
+    // Mapping from the entities in the old component to the new component.
+    // Definitions inside loops are overwritten by each iteration.
+    events: HashMap<EventIdx, EventIdx>,
+    ports: HashMap<PortIdx, PortIdx>,
+    insts: HashMap<InstIdx, InstIdx>,
+    invs: HashMap<InvIdx, InvIdx>,
+    /// Events defined by each invocation in the old component
+    inv_events: HashMap<InvIdx, Vec<EventIdx>>,
+    /// Values of the enclosing loop indices, innermost first, used to name
+    /// the instances defined in loops
+    suffix: String,
+}
+
+impl<'a, 'p> MonoComp<'a, 'p> {
+    fn new(
+        pass: &'a mut Monomorphize<'p>,
+        comp: CompIdx,
+        idx: CompIdx,
+        vals: Option<Vec<u64>>,
+    ) -> Self {
+        let old = pass.old.get(comp);
+        let mut inv_events: HashMap<InvIdx, Vec<EventIdx>> = HashMap::new();
+        for (idx, ev) in old.events().iter() {
+            if let ir::EventOwner::Inv { inv } = ev.owner {
+                inv_events.entry(inv).or_default().push(idx);
+            }
+        }
+        let mut mono = Self {
+            pass,
+            old,
+            comp: ir::Component::new(idx, old.is_ext),
+            params: HashMap::new(),
+            events: HashMap::new(),
+            ports: HashMap::new(),
+            insts: HashMap::new(),
+            invs: HashMap::new(),
+            inv_events,
+            suffix: String::new(),
+        };
+        mono.sig(vals);
+        mono
+    }
+
+    /// Monomorphize an external component
+    fn ext(self) -> ir::Component {
+        self.comp
+    }
+
+    /// Monomorphize the body of the component
+    fn comp(mut self) -> ir::Component {
+        let cmds = self.old.cmds.clone();
+        self.comp.cmds = self.scope(&cmds);
+        self.comp
+    }
+
+    /// Define the signature of the component. If `vals` is `None`, the
+    /// parameters of the signature are retained.
+    fn sig(&mut self, vals: Option<Vec<u64>>) {
+        let sig_params = self
+            .old
+            .params()
+            .iter()
+            .filter(|(_, p)| p.is_sig_owned())
+            .map(|(idx, _)| idx)
+            .collect_vec();
+        match &vals {
+            Some(vals) => {
+                for (idx, v) in sig_params.into_iter().zip(vals) {
+                    let e = self.comp.num(*v);
+                    self.params.insert(idx, e);
+                }
+            }
+            None => {
+                for idx in sig_params {
+                    self.param(idx, ir::ParamOwner::Sig);
+                }
+            }
+        }
+
+        // Declare the events before defining their delays because delays may
+        // mention the events.
+        let events = self
+            .old
+            .events()
+            .iter()
+            .filter(|(_, ev)| ev.owner.is_sig())
+            .map(|(idx, _)| idx)
+            .collect_vec();
+        for &ev in &events {
+            let info = self.info(self.old.get(ev).info);
+            let delay = self.comp.num(0).into();
+            let idx = self.comp.add(ir::Event {
+                delay,
+                owner: ir::EventOwner::Sig,
+                info,
+            });
+            self.events.insert(ev, idx);
+        }
+        for ev in events {
+            let delay = self.timesub(self.old.get(ev).delay.clone());
+            let idx = self.events[&ev];
+            self.comp.get_mut(idx).delay = delay;
+        }
+
+        let ports = self
+            .old
+            .ports()
+            .iter()
+            .filter(|(_, p)| matches!(p.owner, ir::PortOwner::Sig { .. }))
+            .map(|(idx, p)| (idx, p.owner.clone()))
+            .collect_vec();
+        for (idx, owner) in ports {
+            self.port(idx, owner);
+        }
+
+        if let Some(src) = &self.old.src_info {
+            let name = match &vals {
+                Some(vals) => {
+                    let suf = vals.iter().map(|v| format!("_{v}")).join("");
+                    ast::Id::from(format!("{}{suf}", src.name.mangle()))
+                }
+                None => src.name.mangle(),
+            };
+            let mut info = ir::InterfaceSrc::new(name);
+            info.interface_ports = src
+                .interface_ports
+                .iter()
+                .map(|(ev, name)| (self.events[ev], *name))
+                .collect();
+            info.unannotated_ports = src.unannotated_ports.clone();
+            self.comp.src_info = Some(info);
+        }
+    }
+
+    fn info(&mut self, info: InfoIdx) -> InfoIdx {
+        let info = self.old.get(info).clone();
+        self.comp.add(info)
+    }
+
+    /// Define a new parameter in the component
+    fn param(&mut self, param: ParamIdx, owner: ir::ParamOwner) -> ParamIdx {
+        let info = self.info(self.old.get(param).info);
+        let idx = self.comp.add(ir::Param::new(owner, info, None));
+        let e = idx.expr(&mut self.comp);
+        self.params.insert(param, e);
+        idx
+    }
+
+    fn expr(&mut self, expr: ExprIdx) -> ExprIdx {
+        match self.old.get(expr).clone() {
+            ir::Expr::Param(p) => {
+                match (self.params.get(&p), &self.old.get(p).owner) {
+                    (Some(e), _) => *e,
+                    // Parameters defined by `let` are substituted with their
+                    // definition which may mention loop indices.
+                    (None, ir::ParamOwner::Let { bind }) => self.expr(*bind),
+                    (None, _) => unreachable!("Parameter {p} is not bound"),
+                }
+            }
+            ir::Expr::Concrete(n) => self.comp.num(n),
+            ir::Expr::Bin { op, lhs, rhs } => {
+                let l = self.expr(lhs);
+                let r = self.expr(rhs);
+                let ctx = &mut self.comp;
+                match op {
+                    ast::Op::Add => l.add(r, ctx),
+                    ast::Op::Sub => l.sub(r, ctx),
+                    ast::Op::Mul => l.mul(r, ctx),
+                    ast::Op::Div => l.div(r, ctx),
+                    ast::Op::Mod => l.rem(r, ctx),
+                }
+            }
+            ir::Expr::Fn { op, args } => {
+                let args = args.iter().map(|a| self.expr(*a)).collect();
+                ExprIdx::func(op, args, &mut self.comp)
+            }
+        }
+    }
+
+    /// Evaluate an expression which must be concrete after monomorphization
+    fn concrete(&mut self, expr: ExprIdx) -> u64 {
+        let e = self.expr(expr);
+        e.as_concrete(&self.comp).unwrap_or_else(|| {
+            unreachable!(
+                "Expression {} is not concrete after monomorphization",
+                self.comp.display(e)
+            )
+        })
+    }
+
+    /// Evaluate a proposition which must be concrete after monomorphization
+    fn prop(&mut self, prop: PropIdx) -> bool {
+        match self.old.get(prop).clone() {
+            ir::Prop::True => true,
+            ir::Prop::False => false,
+            ir::Prop::Cmp(ir::CmpOp { op, lhs, rhs }) => {
+                let l = self.concrete(lhs);
+                let r = self.concrete(rhs);
+                match op {
+                    ir::Cmp::Gt => l > r,
+                    ir::Cmp::Gte => l >= r,
+                    ir::Cmp::Eq => l == r,
+                }
+            }
+            ir::Prop::Not(p) => !self.prop(p),
+            ir::Prop::And(l, r) => self.prop(l) && self.prop(r),
+            ir::Prop::Or(l, r) => self.prop(l) || self.prop(r),
+            ir::Prop::Implies(l, r) => !self.prop(l) || self.prop(r),
+            ir::Prop::TimeCmp(_) | ir::Prop::TimeSubCmp(_) => {
+                unreachable!("Cannot evaluate propositions over time")
+            }
+        }
+    }
+
+    fn time(&mut self, time: TimeIdx) -> TimeIdx {
+        let ir::Time { event, offset } = self.old.get(time).clone();
+        let event = *self
+            .events
+            .get(&event)
+            .unwrap_or_else(|| unreachable!("Event {event} is not bound"));
+        let offset = self.expr(offset);
+        self.comp.add(ir::Time { event, offset })
+    }
+
+    fn timesub(&mut self, ts: ir::TimeSub) -> ir::TimeSub {
+        match ts {
+            ir::TimeSub::Unit(e) => ir::TimeSub::Unit(self.expr(e)),
+            ir::TimeSub::Sym { l, r } => {
+                let l = self.time(l);
+                let r = self.time(r);
+                l.sub(r, &mut self.comp)
+            }
+        }
+    }
+
+    fn range(&mut self, range: ir::Range) -> ir::Range {
+        let ir::Range { start, end } = range;
+        ir::Range {
+            start: self.time(start),
+            end: self.time(end),
+        }
+    }
+
+    /// Define a new port in the component using the definition of `port`
+    fn port(&mut self, port: PortIdx, owner: ir::PortOwner) -> PortIdx {
+        let ir::Port {
+            width,
+            repr,
+            live,
+            info,
+            ..
+        } = self.old.get(port).clone();
+        let ir::Liveness { idxs, lens, range } = live;
+        // The owners are updated after the port is constructed
+        let idxs = idxs
+            .into_iter()
+            .map(|idx| {
+                self.param(idx, ir::ParamOwner::bundle(ir::PortIdx::UNKNOWN))
+            })
+            .collect_vec();
+        let live = ir::Liveness {
+            idxs: idxs.clone(),
+            lens: lens.into_iter().map(|len| self.expr(len)).collect(),
+            range: self.range(range),
+        };
+        let repr = match repr {
+            ir::NumRepr::Bits => ir::NumRepr::Bits,
+            ir::NumRepr::Num { signed, frac } => ir::NumRepr::Num {
+                signed,
+                frac: self.expr(frac),
+            },
+        };
+        let p = ir::Port {
+            owner,
+            width: self.expr(width),
+            repr,
+            live,
+            info: self.info(info),
+        };
+        let pidx = self.comp.add(p);
+        for idx in idxs {
+            self.comp.get_mut(idx).owner = ir::ParamOwner::bundle(pidx);
+        }
+        self.ports.insert(port, pidx);
+        pidx
+    }
+
+    fn access(&mut self, access: ir::Access) -> ir::Access {
+        let ir::Access { port, ranges } = access;
+        let port = match &self.old.get(port).owner {
+            // Constants are used by exactly one connection and are defined
+            // again in each iteration of a loop.
+            owner @ ir::PortOwner::Const { .. } => {
+                self.port(port, owner.clone())
+            }
+            _ => *self
+                .ports
+                .get(&port)
+                .unwrap_or_else(|| unreachable!("Port {port} is not bound")),
+        };
+        ir::Access {
+            port,
+            ranges: ranges
+                .into_iter()
+                .map(|(start, end)| (self.expr(start), self.expr(end)))
+                .collect(),
+        }
+    }
+
+    /// Forward declare the entities defined by a command. Invocation ports
+    /// can be used before the invocation is defined.
+    fn declare(&mut self, cmd: &ir::Command) {
+        match cmd {
+            ir::Command::Instance(idx) => {
+                let ir::Instance { comp, params, info } =
+                    self.old.get(*idx).clone();
+                let args =
+                    params.iter().map(|p| self.concrete(*p)).collect_vec();
+                let vals = self.pass.sig_params(comp, args);
+                let (comp, vals) = self.pass.instance(comp, vals);
+                let params =
+                    vals.into_iter().map(|v| self.comp.num(v)).collect();
+                // Instances defined in loops are suffixed with the values of
+                // the loop indices.
+                let info = match self.old.get(info).clone() {
+                    ir::Info::Instance {
+                        name,
+                        name_loc,
+                        comp_loc,
+                        bind_loc,
+                    } => self.comp.add(ir::Info::instance(
+                        format!("{name}{}", self.suffix).into(),
+                        name_loc,
+                        comp_loc,
+                        bind_loc,
+                    )),
+                    info => self.comp.add(info),
+                };
+                let inst = self.comp.add(ir::Instance { comp, params, info });
+                self.insts.insert(*idx, inst);
+            }
+            ir::Command::Invoke(idx) => {
+                let ir::Invoke { inst, ports, info } =
+                    self.old.get(*idx).clone();
+                let inst = *self.insts.get(&inst).unwrap_or_else(|| {
+                    unreachable!("Instance {inst} is not bound")
+                });
+                let info = self.info(info);
+                let inv = self.comp.add(ir::Invoke {
+                    inst,
+                    ports: vec![],
+                    info,
+                });
+                self.invs.insert(*idx, inv);
+
+                // Define the events of the invocation
+                let events =
+                    self.inv_events.get(idx).cloned().unwrap_or_default();
+                for ev in events {
+                    let ir::Event { delay, info, .. } =
+                        self.old.get(ev).clone();
+                    let delay = self.timesub(delay);
+                    let info = self.info(info);
+                    let new = self.comp.add(ir::Event {
+                        delay,
+                        owner: ir::EventOwner::Inv { inv },
+                        info,
+                    });
+                    self.events.insert(ev, new);
+                }
+
+                // Define the ports of the invocation
+                let ports = ports
+                    .into_iter()
+                    .map(|p| {
+                        let owner = match self.old.get(p).owner {
+                            ir::PortOwner::Inv { ref dir, .. } => {
+                                ir::PortOwner::Inv {
+                                    inv,
+                                    dir: dir.clone(),
+                                }
+                            }
+                            _ => unreachable!("Expected invocation port"),
+                        };
+                        self.port(p, owner)
+                    })
+                    .collect_vec();
+                self.comp.get_mut(inv).ports = ports;
+            }
+            ir::Command::BundleDef(p) => {
+                self.port(*p, ir::PortOwner::Local);
+            }
+            ir::Command::Connect(_)
+            | ir::Command::ForLoop(_)
+            | ir::Command::If(_)
+            | ir::Command::Fact(_)
+            | ir::Command::EventBind(_) => {}
+        }
+    }
+
+    /// Monomorphize a sequence of commands that represents a scope.
+    fn scope(&mut self, cmds: &[ir::Command]) -> Vec<ir::Command> {
+        for cmd in cmds {
+            self.declare(cmd);
+        }
+        cmds.iter().flat_map(|cmd| self.command(cmd)).collect()
+    }
+
+    fn command(&mut self, cmd: &ir::Command) -> Vec<ir::Command> {
+        match cmd {
+            ir::Command::Instance(idx) => vec![self.insts[idx].into()],
+            ir::Command::Invoke(idx) => vec![self.invs[idx].into()],
+            ir::Command::BundleDef(p) => vec![self.ports[p].into()],
+            ir::Command::Connect(ir::Connect {
+                src,
+                dst,
+                guard,
+                info,
+            }) => {
+                let con = ir::Connect {
+                    src: self.access(src.clone()),
+                    dst: self.access(dst.clone()),
+                    guard: guard
+                        .clone()
+                        .map(|g| g.map_ports(&mut |a| self.access(a))),
+                    info: self.info(*info),
+                };
+                vec![con.into()]
+            }
+            ir::Command::EventBind(ir::EventBind {
+                event,
+                arg,
+                info,
+                base,
+            }) => {
+                let eb = ir::EventBind::new(
+                    self.events[event],
+                    self.time(*arg),
+                    self.info(*info),
+                    *base,
+                );
+                vec![eb.into()]
+            }
+            ir::Command::ForLoop(ir::Loop {
+                index,
+                start,
+                end,
+                body,
+            }) => {
+                let start = self.concrete(*start);
+                let end = self.concrete(*end);
+                let mut cmds = vec![];
+                let suffix = self.suffix.clone();
+                for i in start..end {
+                    self.suffix = format!("{i}{suffix}");
+                    let i = self.comp.num(i);
+                    self.params.insert(*index, i);
+                    cmds.extend(self.scope(body));
+                }
+                self.suffix = suffix;
+                cmds
+            }
+            ir::Command::If(ir::If { cond, then, alt }) => {
+                if self.prop(*cond) {
+                    self.scope(then)
+                } else {
+                    self.scope(alt)
+                }
+            }
+            // Facts have already been discharged
+            ir::Command::Fact(_) => vec![],
+        }
+    }
+}
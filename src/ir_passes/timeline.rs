@@ -0,0 +1,129 @@
+use itertools::Itertools;
+use std::fmt;
+
+/// Maximum number of cycles shown in a timeline
+const MAX_CYCLES: i64 = 32;
+
+/// ASCII timeline comparing the cycles in which a value is required with the
+/// cycles in which it is available in a counterexample:
+/// ```text
+/// Timeline for G = 0:
+/// cycle      0  1  2  3
+/// required   ======
+/// available        ======
+///            ^ required in cycle 0 but not available
+/// ```
+/// Windows are half-open: `[s, e)` covers the cycles `s` to `e-1`.
+pub struct Timeline {
+    /// Values of the events that the windows are relative to
+    events: Vec<(String, i64)>,
+    /// Cycles in which the value is required
+    required: (i64, i64),
+    /// Cycles in which the value is available
+    available: (i64, i64),
+}
+
+impl Timeline {
+    /// Windows only depend on the differences between events so the events
+    /// are shifted to make the earliest one occur in cycle 0. Returns `None`
+    /// if the timeline spans too many cycles to be shown.
+    pub fn new(
+        events: Vec<(String, i64)>,
+        required: (i64, i64),
+        available: (i64, i64),
+    ) -> Option<Self> {
+        let base = events.iter().map(|(_, v)| *v).min().unwrap_or(0);
+        let shift = |(s, e): (i64, i64)| {
+            Some((s.checked_sub(base)?, e.checked_sub(base)?))
+        };
+        let t = Self {
+            required: shift(required)?,
+            available: shift(available)?,
+            events: events
+                .into_iter()
+                .map(|(ev, v)| Some((ev, v.checked_sub(base)?)))
+                .collect::<Option<_>>()?,
+        };
+        let (lo, hi) = t.span();
+        (hi - lo <= MAX_CYCLES).then_some(t)
+    }
+
+    /// The first and one past the last cycle shown
+    fn span(&self) -> (i64, i64) {
+        let (rs, re) = self.required;
+        let (avs, ave) = self.available;
+        let lo = rs.min(re).min(avs).min(ave).min(0);
+        let hi = rs.max(re).max(avs).max(ave);
+        let hi = match self.violation() {
+            Some((c, _)) => hi.max(c + 1),
+            None => hi,
+        };
+        (lo, hi)
+    }
+
+    /// The cycle where the requirement is broken along with a description
+    fn violation(&self) -> Option<(i64, String)> {
+        let (rs, re) = self.required;
+        let (avs, ave) = self.available;
+        // The first required cycle outside the availability
+        let c = if rs < avs || rs >= ave { rs } else { ave };
+        if c < re {
+            return Some((
+                c,
+                format!("required in cycle {c} but not available"),
+            ));
+        }
+        // The requirement is empty but does not fit in the availability
+        if rs < avs {
+            Some((
+                rs,
+                format!(
+                    "required from cycle {rs} but available from cycle {avs}"
+                ),
+            ))
+        } else if re > ave {
+            Some((
+                re,
+                format!(
+                    "required until cycle {re} but available until cycle {ave}"
+                ),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const LABEL: usize = 11;
+        let (lo, hi) = self.span();
+        let width = (lo..hi)
+            .map(|c| c.to_string().len() + 1)
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        let window = |(s, e): (i64, i64)| {
+            (lo..hi)
+                .map(|c| if s <= c && c < e { "=" } else { " " }.repeat(width))
+                .join("")
+        };
+        let events = self
+            .events
+            .iter()
+            .map(|(ev, v)| format!("{ev} = {v}"))
+            .join(", ");
+        let cycles = (lo..hi).map(|c| format!("{c:<width$}")).join("");
+        let mut lines = vec![
+            format!("Timeline for {events}:"),
+            format!("{:LABEL$}{cycles}", "cycle"),
+            format!("{:LABEL$}{}", "required", window(self.required)),
+            format!("{:LABEL$}{}", "available", window(self.available)),
+        ];
+        if let Some((c, msg)) = self.violation() {
+            let col = LABEL + (c - lo) as usize * width;
+            lines.push(format!("{:col$}^ {msg}", ""));
+        }
+        write!(f, "{}", lines.iter().map(|l| l.trim_end()).join("\n"))
+    }
+}
@@ -10,6 +10,7 @@ use crate::{
 /// * Port accesses are in-bounds
 /// * Connections are between ports of same size
 /// * Connected ports have the same bitwidths
+/// * Connected ports have compatible numeric representations
 pub struct TypeCheck;
 
 impl TypeCheck {
@@ -20,12 +21,13 @@ impl TypeCheck {
         loc: GPosIdx,
         comp: &mut ir::Component,
     ) -> impl Iterator<Item = ir::Command> {
-        let &ir::Access { port, start, end } = access;
-        let &ir::Port {
-            live: ir::Liveness { len, .. },
+        let ir::Access { port, ranges } = access;
+        let ir::Port {
+            live: ir::Liveness { lens, .. },
             info,
             ..
-        } = comp.get(port);
+        } = comp.get(*port);
+        let (lens, info) = (lens.clone(), *info);
 
         let &ir::Info::Port {
             bind_loc, ..
@@ -41,33 +43,95 @@ impl TypeCheck {
             .into(),
         );
 
-        let wf_prop = end.gt(start, comp);
-        let within_bounds =
-            comp.add(ir::Reason::in_bounds_access(bind_loc, loc, len).into());
-        let start = start.lt(len, comp);
-        let end = end.lte(len, comp);
-        let in_range = start.and(end, comp);
-        vec![
-            comp.assert(wf_prop, wf),
-            comp.assert(in_range, within_bounds),
-        ]
-        .into_iter()
-        .flatten()
+        // Check the access on each dimension of the port
+        let mut cmds = Vec::with_capacity(ranges.len() * 2);
+        for (&(start, end), len) in ranges.iter().zip(lens) {
+            let wf_prop = end.gt(start, comp);
+            let within_bounds = comp
+                .add(ir::Reason::in_bounds_access(bind_loc, loc, len).into());
+            let start = start.lt(len, comp);
+            let end = end.lte(len, comp);
+            let in_range = start.and(end, comp);
+            cmds.extend(comp.assert(wf_prop, wf));
+            cmds.extend(comp.assert(in_range, within_bounds));
+        }
+        cmds.into_iter()
+    }
+
+    /// The size of each dimension of the bundles produced by the source and
+    /// the destination access. If the accesses have a different number of
+    /// dimensions, dimensions that are syntactically indices are dropped.
+    /// Returns `None` if the dimensions still cannot be matched up.
+    fn access_sizes(
+        src: &ir::Access,
+        dst: &ir::Access,
+        comp: &mut ir::Component,
+    ) -> Option<Vec<(ir::ExprIdx, ir::ExprIdx)>> {
+        let mut src_ranges = src.ranges.clone();
+        let mut dst_ranges = dst.ranges.clone();
+        if src_ranges.len() != dst_ranges.len() {
+            let src_idx = src.index_dims(comp);
+            let dst_idx = dst.index_dims(comp);
+            src_ranges = ranges_without(src_ranges, &src_idx);
+            dst_ranges = ranges_without(dst_ranges, &dst_idx);
+            if src_ranges.len() != dst_ranges.len() {
+                return None;
+            }
+        }
+        Some(
+            src_ranges
+                .into_iter()
+                .zip(dst_ranges)
+                .map(|((ss, se), (ds, de))| {
+                    (se.sub(ss, comp), de.sub(ds, comp))
+                })
+                .collect(),
+        )
     }
 }
 
+/// The ranges of the dimensions that are not marked in `drop`
+fn ranges_without(
+    ranges: Vec<(ir::ExprIdx, ir::ExprIdx)>,
+    drop: &[bool],
+) -> Vec<(ir::ExprIdx, ir::ExprIdx)> {
+    ranges
+        .into_iter()
+        .zip(drop)
+        .filter_map(|(r, d)| (!d).then_some(r))
+        .collect()
+}
+
 impl Visitor for TypeCheck {
     fn connect(
         &mut self,
         c: &mut ir::Connect,
         comp: &mut ir::Component,
     ) -> Action {
-        let ir::Connect { src, dst, info } = &c;
+        let ir::Connect {
+            src,
+            dst,
+            guard,
+            info,
+        } = &c;
         let &ir::Info::Connect { dst_loc, src_loc } = comp.get(*info) else {
             unreachable!("Expected connect info")
         };
         let mut cons = vec![];
 
+        // Ports used in guards are 1-bit signals
+        for port in guard.iter().flat_map(|g| g.ports()) {
+            cons.extend(self.port_access(port, dst_loc, comp));
+            let reason = comp.add(
+                ir::Reason::misc("ports used in guards must be 1-bit", dst_loc)
+                    .into(),
+            );
+            let one = comp.num(1);
+            let width = comp.get(port.port).width;
+            let prop = width.equal(one, comp);
+            cons.extend(comp.assert(prop, reason));
+        }
+
         // Range accesses are well-formed
         cons.extend(self.port_access(src, src_loc, comp));
         cons.extend(self.port_access(dst, dst_loc, comp));
@@ -82,15 +146,55 @@ impl Visitor for TypeCheck {
         let prop = src_w.equal(dst_w, comp);
         cons.extend(comp.assert(prop, reason));
 
-        // Ensure that the sizes are the same
-        let src_size = src.end.sub(src.start, comp);
-        let dst_size = dst.end.sub(dst.start, comp);
-        let reason = comp.add(
-            ir::Reason::bundle_len_match(dst_loc, src_loc, dst_size, src_size)
+        // Ensure that the numeric representations of the ports match.
+        // Uninterpreted bits can be connected to any representation.
+        let src_r = comp.get(src.port).repr.clone();
+        let dst_r = comp.get(dst.port).repr.clone();
+        if let (
+            ir::NumRepr::Num {
+                signed: src_s,
+                frac: src_f,
+            },
+            ir::NumRepr::Num {
+                signed: dst_s,
+                frac: dst_f,
+            },
+        ) = (&src_r, &dst_r)
+        {
+            let prop = if src_s == dst_s {
+                src_f.equal(*dst_f, comp)
+            } else {
+                comp.add(ir::Prop::False)
+            };
+            let reason = comp.add(
+                ir::Reason::repr_match(dst_loc, src_loc, dst_r, src_r).into(),
+            );
+            cons.extend(comp.assert(prop, reason));
+        }
+
+        // Ensure that the sizes are the same in each dimension
+        if let Some(sizes) = Self::access_sizes(src, dst, comp) {
+            for (src_size, dst_size) in sizes {
+                let reason = comp.add(
+                    ir::Reason::bundle_len_match(
+                        dst_loc, src_loc, dst_size, src_size,
+                    )
+                    .into(),
+                );
+                let prop = src_size.equal(dst_size, comp);
+                cons.extend(comp.assert(prop, reason));
+            }
+        } else {
+            let reason = comp.add(
+                ir::Reason::misc(
+                    "source and destination have different dimensions",
+                    dst_loc,
+                )
                 .into(),
-        );
-        let prop = src_size.equal(dst_size, comp);
-        cons.extend(comp.assert(prop, reason));
+            );
+            let prop = comp.add(ir::Prop::False);
+            cons.extend(comp.assert(prop, reason));
+        }
 
         Action::AddBefore(cons)
     }
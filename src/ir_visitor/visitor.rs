@@ -79,6 +79,14 @@ where
         Action::Continue
     }
 
+    fn bundle_def(
+        &mut self,
+        _: ir::PortIdx,
+        _comp: &mut ir::Component,
+    ) -> Action {
+        Action::Continue
+    }
+
     fn connect(
         &mut self,
         _: &mut ir::Connect,
@@ -156,6 +164,7 @@ where
         match cmd {
             ir::Command::Instance(idx) => self.instance(*idx, comp),
             ir::Command::Invoke(idx) => self.invoke(*idx, comp),
+            ir::Command::BundleDef(idx) => self.bundle_def(*idx, comp),
             ir::Command::Connect(con) => self.connect(con, comp),
             ir::Command::ForLoop(l) => self.do_loop(l, comp),
             ir::Command::If(i) => self.do_if(i, comp),
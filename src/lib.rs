@@ -6,8 +6,10 @@ pub mod frontend;
 pub mod ir;
 pub mod ir_passes;
 pub mod ir_visitor;
+pub mod lsp;
 pub mod passes;
 pub mod resolver;
+pub mod sim;
 pub mod utils;
 pub mod visitor;
 
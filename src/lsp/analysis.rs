@@ -0,0 +1,537 @@
+//! Analysis of a single document for the language server.
+use crate::{
+    ast,
+    binding::{CompBinding, ProgBinding},
+    cmdline, diagnostics,
+    frontend::FilamentParser,
+    passes::{self, Pass},
+    resolver::Resolver,
+    utils::{GPosIdx, GlobalPositionTable},
+    visitor::Checker,
+};
+use codespan_reporting::diagnostic::{self as cs, LabelStyle};
+use codespan_reporting::files::Files;
+use itertools::Itertools;
+use lsp_types as lsp;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+/// A use of a name in the document
+struct Symbol {
+    /// Range of the use in the document
+    range: lsp::Range,
+    /// Location of the definition of the name
+    def: Option<lsp::Location>,
+    /// Text shown when hovering over the name
+    hover: Option<String>,
+    /// The port referred to by this symbol along with the component it is
+    /// used in. Used to compute the resolved definition of the port.
+    port: Option<(ast::Id, ast::Port)>,
+}
+
+/// The result of analyzing a document
+#[derive(Default)]
+pub struct Analysis {
+    /// Diagnostics for the document
+    pub diagnostics: Vec<lsp::Diagnostic>,
+    /// Names of all components visible in the document and their signatures
+    pub components: Vec<(String, String)>,
+    /// Uses of names in the document
+    symbols: Vec<Symbol>,
+}
+
+impl Analysis {
+    /// Analyze the document at `path` with contents `source`. Imports are
+    /// resolved against `lib`.
+    pub fn new(path: &Path, source: String, lib: &Path) -> Self {
+        // Positions from the previous analysis are no longer needed
+        GlobalPositionTable::reset();
+        let ns = match FilamentParser::parse_source(
+            path,
+            source,
+            &ast::Functions::default(),
+        ) {
+            Ok(ns) => ns,
+            Err(e) => {
+                let (start, end) = match e.line_col {
+                    pest::error::LineColLocation::Pos(p) => (p, p),
+                    pest::error::LineColLocation::Span(s, e) => (s, e),
+                };
+                return Self {
+                    diagnostics: vec![error(
+                        lsp::Range::new(position(start), position(end)),
+                        e.variant.message().to_string(),
+                    )],
+                    ..Default::default()
+                };
+            }
+        };
+
+        let ns = match Resolver::new(lib.to_path_buf(), path.to_path_buf())
+            .resolve(ns)
+        {
+            Ok(ns) => ns,
+            Err(e) => {
+                return Self {
+                    diagnostics: vec![error(lsp::Range::default(), e.kind)],
+                    ..Default::default()
+                }
+            }
+        };
+
+        let Some(file) = document_file(path) else {
+            return Self::default();
+        };
+        let mut symbols = Index::new(&ns, file).build();
+        let components = ns
+            .signatures()
+            .map(|(_, sig)| (sig.name.to_string(), sig.to_string()))
+            .collect();
+
+        // Run the checks while capturing their diagnostics. The checks are
+        // allowed to panic on malformed programs.
+        let opts = options(path, lib);
+        let (_, diags) = diagnostics::capture(|| {
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                check(&opts, ns, &mut symbols)
+            }))
+        });
+        let diagnostics = diags
+            .into_iter()
+            .map(|d| Self::diagnostic(d, file))
+            .collect();
+
+        Self {
+            diagnostics,
+            components,
+            symbols,
+        }
+    }
+
+    /// Convert a diagnostic into one reported on the document. Diagnostics
+    /// whose primary label is in another file are reported at the start of
+    /// the document.
+    fn diagnostic(diag: cs::Diagnostic<usize>, file: usize) -> lsp::Diagnostic {
+        let files = GlobalPositionTable::as_ref().files();
+        let primary =
+            diag.labels.iter().find(|l| l.style == LabelStyle::Primary);
+        let (range, message) = match primary {
+            Some(l) if l.file_id == file => {
+                (span(l.file_id, l.range.start, l.range.end), diag.message)
+            }
+            Some(l) => {
+                let name = files.name(l.file_id).unwrap_or_default();
+                (None, format!("{}: {}", name, diag.message))
+            }
+            None => (None, diag.message),
+        };
+        let related = diag
+            .labels
+            .iter()
+            .filter(|l| !l.message.is_empty())
+            .filter_map(|l| {
+                Some(lsp::DiagnosticRelatedInformation {
+                    location: location(l.file_id, l.range.start, l.range.end)?,
+                    message: l.message.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut message = message;
+        for note in &diag.notes {
+            message.push('\n');
+            message.push_str(note);
+        }
+        lsp::Diagnostic {
+            related_information: (!related.is_empty()).then_some(related),
+            ..error(range.unwrap_or_default(), message)
+        }
+    }
+
+    /// The innermost symbol at the given position
+    fn symbol(&self, pos: lsp::Position) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.range.start <= pos && pos <= s.range.end)
+            .min_by_key(|s| {
+                (
+                    s.range.end.line - s.range.start.line,
+                    s.range.end.character.wrapping_sub(s.range.start.character),
+                )
+            })
+    }
+
+    /// Location of the definition of the name at the given position
+    pub fn definition(&self, pos: lsp::Position) -> Option<lsp::Location> {
+        self.symbol(pos).and_then(|s| s.def.clone())
+    }
+
+    /// Hover text for the name at the given position
+    pub fn hover(&self, pos: lsp::Position) -> Option<String> {
+        self.symbol(pos).and_then(|s| s.hover.clone())
+    }
+}
+
+/// Run the checks on the namespace and record the resolved definitions of
+/// ports used in the document.
+fn check(opts: &cmdline::Opts, ns: ast::Namespace, symbols: &mut [Symbol]) {
+    let Ok(ns) = passes::StructElim::transform(opts, ns) else {
+        return;
+    };
+    let Ok(bind) = ProgBinding::try_from(&ns) else {
+        return;
+    };
+    if passes::BindCheck::check(opts, &ns, &bind).is_err() {
+        return;
+    }
+    // Bindings are only constructed for commands outside of loops and
+    // conditionals so only ports used there have a resolved definition.
+    for sym in symbols.iter_mut() {
+        if let Some((comp, port)) = &sym.port {
+            let ctx = CompBinding::new(&bind, comp);
+            if let Some(pd) = ctx.get_resolved_port(port) {
+                sym.hover = Some(format!("```filament\n{pd}\n```"));
+            }
+        }
+    }
+    drop(bind);
+
+    let ns = passes::Assume::transform(ns);
+    let Ok(bind) = ProgBinding::try_from(&ns) else {
+        return;
+    };
+    let _ = passes::IntervalCheck::check(opts, &ns, &bind);
+}
+
+/// Command line options used to run the checks on the document
+fn options(path: &Path, lib: &Path) -> cmdline::Opts {
+    let (path, lib) = (path.to_string_lossy(), lib.to_string_lossy());
+    <cmdline::Opts as argh::FromArgs>::from_args(
+        &["filament"],
+        &[&path, "--library", &lib],
+    )
+    .expect("default options should be valid")
+}
+
+/// Index of the file with the given path in the global position table
+fn document_file(path: &Path) -> Option<usize> {
+    let files = GlobalPositionTable::as_ref().files();
+    let name = path.to_string_lossy();
+    (1..)
+        .map_while(|i| files.name(i).ok().map(|n| (i, n)))
+        .find_map(|(i, n)| if n == name { Some(i) } else { None })
+}
+
+/// Convert a 1-indexed line and column into an LSP position
+fn position((line, col): (usize, usize)) -> lsp::Position {
+    lsp::Position::new(line as u32 - 1, col as u32 - 1)
+}
+
+/// LSP range for a span in a file
+fn span(file: usize, start: usize, end: usize) -> Option<lsp::Range> {
+    let files = GlobalPositionTable::as_ref().files();
+    let pos = |idx| {
+        files
+            .location(file, idx)
+            .ok()
+            .map(|l| position((l.line_number, l.column_number)))
+    };
+    Some(lsp::Range::new(pos(start)?, pos(end)?))
+}
+
+/// LSP location for a span in a file
+fn location(file: usize, start: usize, end: usize) -> Option<lsp::Location> {
+    let files = GlobalPositionTable::as_ref().files();
+    let name = files.name(file).ok()?;
+    let path = Path::new(&name);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let uri = lsp::Url::from_file_path(path).ok()?;
+    Some(lsp::Location::new(uri, span(file, start, end)?))
+}
+
+/// LSP location for a position
+fn pos_location(pos: GPosIdx) -> Option<lsp::Location> {
+    let pos = pos.into_option()?;
+    let data = GlobalPositionTable::as_ref().get_pos(pos.0);
+    location(data.file.get(), data.start, data.end)
+}
+
+/// An error diagnostic
+fn error(range: lsp::Range, message: String) -> lsp::Diagnostic {
+    lsp::Diagnostic {
+        range,
+        severity: Some(lsp::DiagnosticSeverity::ERROR),
+        source: Some("filament".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Builds the uses of names in the document
+struct Index<'a> {
+    ns: &'a ast::Namespace,
+    /// Index of the document in the global position table
+    file: usize,
+    /// Signatures visible in the document
+    sigs: HashMap<ast::Id, &'a ast::Signature>,
+    /// Definitions of names in the current scope
+    defs: HashMap<ast::Id, GPosIdx>,
+    /// Component instantiated by each instance in the current component
+    instances: HashMap<ast::Id, ast::Id>,
+    /// Instance invoked by each invocation in the current component
+    invokes: HashMap<ast::Id, ast::Id>,
+    /// Signature of the current component
+    this: Option<&'a ast::Signature>,
+    /// Are we inside a loop or conditional?
+    nested: bool,
+    symbols: Vec<Symbol>,
+}
+
+impl<'a> Index<'a> {
+    fn new(ns: &'a ast::Namespace, file: usize) -> Self {
+        Self {
+            ns,
+            file,
+            sigs: ns.signatures().map(|(_, s)| (*s.name.inner(), s)).collect(),
+            defs: HashMap::new(),
+            instances: HashMap::new(),
+            invokes: HashMap::new(),
+            this: None,
+            nested: false,
+            symbols: vec![],
+        }
+    }
+
+    /// Range of the position if it is in the document
+    fn range(&self, pos: GPosIdx) -> Option<lsp::Range> {
+        let pos = pos.into_option()?;
+        let data = GlobalPositionTable::as_ref().get_pos(pos.0);
+        if data.file.get() != self.file {
+            return None;
+        }
+        span(self.file, data.start, data.end)
+    }
+
+    /// Record a use of a name at `pos` defined at `def`
+    fn add(&mut self, pos: GPosIdx, def: GPosIdx, hover: Option<String>) {
+        if let Some(range) = self.range(pos) {
+            self.symbols.push(Symbol {
+                range,
+                def: pos_location(def),
+                hover,
+                port: None,
+            });
+        }
+    }
+
+    /// Record a use of a name defined in the current scope
+    fn add_use(&mut self, name: &ast::Id, pos: GPosIdx) {
+        if let Some(&def) = self.defs.get(name) {
+            self.add(pos, def, None);
+        }
+    }
+
+    /// Record the definition of a name in the current scope
+    fn define(&mut self, name: &ast::Loc<ast::Id>) {
+        self.defs.insert(*name.inner(), name.pos());
+    }
+
+    /// Record an expression that refers to exactly one name
+    fn expr(&mut self, e: &ast::Loc<ast::Expr>) {
+        if let Ok(name) = e.exprs().unique().exactly_one() {
+            self.add_use(name, e.pos());
+        }
+    }
+
+    /// Record a time expression
+    fn time(&mut self, t: &ast::Loc<ast::Time>) {
+        self.add_use(&t.event, t.pos());
+    }
+
+    fn build(mut self) -> Vec<Symbol> {
+        for comp in &self.ns.components {
+            self.component(comp);
+        }
+        self.symbols
+    }
+
+    fn signature(&mut self, sig: &'a ast::Signature) {
+        self.defs.clear();
+        for p in sig.params() {
+            self.define(&p);
+        }
+        for e in sig.events() {
+            self.define(&e);
+        }
+        for pd in sig.ports() {
+            self.define(pd.name());
+            match pd.inner() {
+                ast::PortDef::Port { bitwidth, .. } => self.expr(bitwidth),
+                ast::PortDef::Bundle(b) => {
+                    self.expr(&b.typ.bitwidth);
+                    for len in &b.typ.lens {
+                        self.expr(len);
+                    }
+                }
+                ast::PortDef::Struct { typ, .. } => {
+                    for arg in &typ.args {
+                        self.expr(arg);
+                    }
+                }
+            }
+        }
+        for id in &sig.interface_signals {
+            self.define(&id.name);
+        }
+    }
+
+    fn component(&mut self, comp: &'a ast::Component) {
+        self.signature(&comp.sig);
+        self.this = Some(&comp.sig);
+        self.instances.clear();
+        self.invokes.clear();
+        self.nested = false;
+        self.commands(&comp.body);
+    }
+
+    fn commands(&mut self, cmds: &[ast::Command]) {
+        // Definitions are visible in the entire scope
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Instance(inst) => {
+                    self.define(&inst.name);
+                    self.instances.insert(*inst.name, *inst.component);
+                }
+                ast::Command::Invoke(inv) => {
+                    self.define(&inv.name);
+                    self.invokes.insert(*inv.name, *inv.instance);
+                }
+                ast::Command::Bundle(bun) => self.define(&bun.name),
+                _ => (),
+            }
+        }
+        for cmd in cmds {
+            self.command(cmd);
+        }
+    }
+
+    fn command(&mut self, cmd: &ast::Command) {
+        match cmd {
+            ast::Command::Instance(inst) => {
+                if let Some(sig) = self.sigs.get(inst.component.inner()) {
+                    let hover = format!("```filament\n{sig}\n```");
+                    self.add(inst.component.pos(), sig.name.pos(), Some(hover));
+                }
+                for b in &inst.bindings {
+                    self.expr(b);
+                }
+            }
+            ast::Command::Invoke(inv) => {
+                self.add_use(&inv.instance, inv.instance.pos());
+                for t in &inv.abstract_vars {
+                    self.time(t);
+                }
+                for p in inv.ports.iter().flatten() {
+                    self.port(p);
+                }
+            }
+            ast::Command::Connect(con) => {
+                self.port(&con.dst);
+                self.port(&con.src);
+                if let Some(g) = &con.guard {
+                    self.guard(g);
+                }
+            }
+            ast::Command::Bundle(bun) => {
+                for len in &bun.typ.lens {
+                    self.expr(len);
+                }
+                self.expr(&bun.typ.bitwidth);
+            }
+            ast::Command::Fact(_) => (),
+            ast::Command::Let(l) => {
+                self.expr(&l.expr);
+                self.define(&l.name);
+            }
+            ast::Command::ForLoop(l) => {
+                let (defs, nested) = (self.defs.clone(), self.nested);
+                self.define(&l.idx);
+                self.nested = true;
+                self.commands(&l.body);
+                (self.defs, self.nested) = (defs, nested);
+            }
+            ast::Command::If(i) => {
+                let (defs, nested) = (self.defs.clone(), self.nested);
+                self.nested = true;
+                self.commands(&i.then);
+                self.defs = defs.clone();
+                self.commands(&i.alt);
+                (self.defs, self.nested) = (defs, nested);
+            }
+        }
+    }
+
+    fn guard(&mut self, g: &ast::Guard) {
+        for p in g.ports() {
+            self.port(p);
+        }
+    }
+
+    /// Definition of the port `name` on the invocation `invoke`
+    fn inv_port_def(
+        &self,
+        invoke: &ast::Id,
+        name: &ast::Id,
+    ) -> Option<ast::Loc<ast::PortDef>> {
+        let inst = self.invokes.get(invoke)?;
+        let comp = self.instances.get(inst)?;
+        self.sigs.get(comp)?.find_port(name)
+    }
+
+    fn port(&mut self, port: &ast::Loc<ast::Port>) {
+        let (def, name_pos) = match port.inner() {
+            ast::Port::This(name) => {
+                self.add_use(name, name.pos());
+                let def = self.this.and_then(|s| s.find_port(name));
+                (def, name.pos())
+            }
+            ast::Port::InvPort { invoke, name }
+            | ast::Port::InvBundle {
+                invoke, port: name, ..
+            } => {
+                self.add_use(invoke, invoke.pos());
+                (self.inv_port_def(invoke, name), name.pos())
+            }
+            ast::Port::Bundle { name, .. } => {
+                self.add_use(name, name.pos());
+                (None, name.pos())
+            }
+            ast::Port::Constant(_) => (None, GPosIdx::UNKNOWN),
+        };
+        let Some(range) = self.range(name_pos) else {
+            return;
+        };
+        let resolve = (!self.nested)
+            .then(|| self.this.map(|s| (*s.name.inner(), port.inner().clone())))
+            .flatten();
+        match def {
+            Some(def) => {
+                // Replace the use of the name recorded above
+                self.symbols.retain(|s| s.range != range);
+                self.symbols.push(Symbol {
+                    range,
+                    def: pos_location(def.name().pos()),
+                    hover: Some(format!("```filament\n{}\n```", def.inner())),
+                    port: resolve,
+                });
+            }
+            None => {
+                if let Some(s) =
+                    self.symbols.iter_mut().find(|s| s.range == range)
+                {
+                    s.port = resolve;
+                }
+            }
+        }
+    }
+}
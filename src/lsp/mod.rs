@@ -0,0 +1,207 @@
+//! A language server for Filament.
+//!
+//! The server analyzes each open document on every change and provides:
+//! - Diagnostics from the binding and interval checks.
+//! - Go-to-definition for instances, invocations, ports, events, and
+//!   parameters.
+//! - The interval and width of ports on hover.
+//! - Completion of the names of components visible in the document.
+mod analysis;
+
+use analysis::Analysis;
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    self as lsp,
+    notification::{self as notif, Notification as _},
+    request::{self as req, Request as _},
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// State of the language server
+struct Server {
+    conn: Connection,
+    /// Path to search for imports
+    lib: PathBuf,
+    /// Analysis of each open document
+    docs: HashMap<lsp::Url, Analysis>,
+}
+
+impl Server {
+    /// Analyze the document and publish its diagnostics
+    fn analyze(&mut self, uri: lsp::Url, text: String) -> Result<()> {
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(());
+        };
+        let mut analysis = Analysis::new(&path, text, &self.lib);
+        // Keep completing names while the document does not parse
+        if let Some(prev) = self.docs.remove(&uri) {
+            if analysis.components.is_empty() {
+                analysis.components = prev.components;
+            }
+        }
+        self.publish(uri.clone(), analysis.diagnostics.clone())?;
+        self.docs.insert(uri, analysis);
+        Ok(())
+    }
+
+    fn publish(
+        &self,
+        uri: lsp::Url,
+        diagnostics: Vec<lsp::Diagnostic>,
+    ) -> Result<()> {
+        let params = lsp::PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.conn
+            .sender
+            .send(Message::Notification(Notification::new(
+                notif::PublishDiagnostics::METHOD.to_string(),
+                params,
+            )))?;
+        Ok(())
+    }
+
+    fn notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            notif::DidOpenTextDocument::METHOD => {
+                let params: lsp::DidOpenTextDocumentParams =
+                    serde_json::from_value(not.params)?;
+                let doc = params.text_document;
+                self.analyze(doc.uri, doc.text)
+            }
+            notif::DidChangeTextDocument::METHOD => {
+                let params: lsp::DidChangeTextDocumentParams =
+                    serde_json::from_value(not.params)?;
+                // The server only asks for full document synchronization
+                match params.content_changes.into_iter().last() {
+                    Some(change) => {
+                        self.analyze(params.text_document.uri, change.text)
+                    }
+                    None => Ok(()),
+                }
+            }
+            notif::DidCloseTextDocument::METHOD => {
+                let params: lsp::DidCloseTextDocumentParams =
+                    serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                self.docs.remove(&uri);
+                self.publish(uri, vec![])
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn request(&mut self, request: Request) -> Result<()> {
+        let Request { id, method, params } = request;
+        let result = match method.as_str() {
+            req::GotoDefinition::METHOD => {
+                let params: lsp::GotoDefinitionParams =
+                    serde_json::from_value(params)?;
+                let pos = params.text_document_position_params;
+                let loc = self
+                    .docs
+                    .get(&pos.text_document.uri)
+                    .and_then(|a| a.definition(pos.position))
+                    .map(lsp::GotoDefinitionResponse::Scalar);
+                serde_json::to_value(loc)?
+            }
+            req::HoverRequest::METHOD => {
+                let params: lsp::HoverParams = serde_json::from_value(params)?;
+                let pos = params.text_document_position_params;
+                let hover = self
+                    .docs
+                    .get(&pos.text_document.uri)
+                    .and_then(|a| a.hover(pos.position))
+                    .map(|value| lsp::Hover {
+                        contents: lsp::HoverContents::Markup(
+                            lsp::MarkupContent {
+                                kind: lsp::MarkupKind::Markdown,
+                                value,
+                            },
+                        ),
+                        range: None,
+                    });
+                serde_json::to_value(hover)?
+            }
+            req::Completion::METHOD => {
+                let params: lsp::CompletionParams =
+                    serde_json::from_value(params)?;
+                let uri = params.text_document_position.text_document.uri;
+                let items = self
+                    .docs
+                    .get(&uri)
+                    .map(|a| {
+                        a.components
+                            .iter()
+                            .map(|(name, sig)| lsp::CompletionItem {
+                                label: name.clone(),
+                                kind: Some(lsp::CompletionItemKind::MODULE),
+                                detail: Some(sig.clone()),
+                                ..Default::default()
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                serde_json::to_value(lsp::CompletionResponse::Array(items))?
+            }
+            _ => {
+                let resp = Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request: {method}"),
+                );
+                self.conn.sender.send(Message::Response(resp))?;
+                return Ok(());
+            }
+        };
+        let resp = Response {
+            id,
+            result: Some(result),
+            error: None,
+        };
+        self.conn.sender.send(Message::Response(resp))?;
+        Ok(())
+    }
+
+    fn main_loop(mut self) -> Result<()> {
+        while let Ok(msg) = self.conn.receiver.recv() {
+            match msg {
+                Message::Request(req) => {
+                    if self.conn.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    self.request(req)?;
+                }
+                Message::Notification(not) => self.notification(not)?,
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run the language server over stdin and stdout. Imports are resolved
+/// against `lib`.
+pub fn run(lib: PathBuf) -> Result<()> {
+    let (conn, io) = Connection::stdio();
+    let caps = lsp::ServerCapabilities {
+        text_document_sync: Some(lsp::TextDocumentSyncCapability::Kind(
+            lsp::TextDocumentSyncKind::FULL,
+        )),
+        definition_provider: Some(lsp::OneOf::Left(true)),
+        hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+        completion_provider: Some(lsp::CompletionOptions::default()),
+        ..Default::default()
+    };
+    conn.initialize(serde_json::to_value(caps)?)?;
+    Server {
+        conn,
+        lib,
+        docs: HashMap::new(),
+    }
+    .main_loop()?;
+    io.join()?;
+    Ok(())
+}
@@ -1,10 +1,15 @@
 use filament::{
-    backend, binding, cmdline, ir, ir_passes,
+    backend, binding,
+    cmdline::{self, Backend, DiagnosticsFormat},
+    frontend, ir, ir_passes,
     ir_visitor::Visitor,
     passes::{self, Pass},
     resolver::Resolver,
+    sim, utils,
     visitor::{Checker, Transform},
 };
+use std::io::{Read, Write};
+use std::path::Path;
 use std::time::Instant;
 
 // Prints out the interface for main component in the input program.
@@ -18,7 +23,8 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
         .target(env_logger::Target::Stderr)
         .init();
 
-    let ns = match Resolver::from(opts).parse_namespace() {
+    let mut resolver = Resolver::from(opts);
+    let ns = match resolver.parse_namespace() {
         Ok(mut ns) => {
             ns.toplevel = opts.toplevel.clone();
             ns
@@ -28,10 +34,17 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
             return Err(1);
         }
     };
+
+    // Struct elimination
+    let ns = passes::StructElim::transform(opts, ns)?;
+    log::debug!("{ns}");
+
+    // Select signed primitives for signed values
+    let ns = passes::SignedPrims::transform(ns);
     log::debug!("{ns}");
 
     // Construct a binding
-    let bind = binding::ProgBinding::try_from(&ns)?;
+    let bind = binding::ProgBinding::new(&ns, opts.diagnostics_format)?;
 
     // Bind check
     let t = Instant::now();
@@ -39,7 +52,25 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
     log::info!("Parameteric Bind check: {}ms", t.elapsed().as_millis());
     drop(bind);
 
+    // The solver must be able to discharge the axioms of user functions
+    if !opts.unsafe_skip_discharge {
+        utils::SolverConf::from(opts)
+            .check_funcs(&ns.funcs, opts.diagnostics_format)?;
+    }
+
     if opts.ir {
+        // Flags of the AST pipeline that the IR pipeline does not support
+        let unsupported = [
+            (opts.dump_interface, "--dump-interface"),
+            (opts.dump_all_interfaces, "--dump-all-interfaces"),
+            (opts.simulate.is_some(), "--simulate"),
+            (opts.vcd.is_some(), "--vcd"),
+        ];
+        if let Some((_, flag)) = unsupported.iter().find(|(set, _)| *set) {
+            eprintln!("Error: the IR pipeline does not support {flag}");
+            return Err(1);
+        }
+
         let mut ir = ir::transform(ns);
         ir_passes::TypeCheck::do_pass(opts, &mut ir)?;
         ir_passes::IntervalCheck::do_pass(opts, &mut ir)?;
@@ -49,7 +80,29 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
         if opts.show_ir {
             ir::Printer::context(&ir, &mut std::io::stdout()).unwrap();
         }
-        ir_passes::Discharge::do_pass(opts, &mut ir)?;
+        if !opts.unsafe_skip_discharge {
+            ir_passes::Discharge::do_pass(opts, &mut ir)?;
+        }
+        if opts.check {
+            resolver.save_interfaces();
+            return Ok(());
+        }
+        if opts.backend != Backend::Calyx {
+            eprintln!("Error: the IR pipeline only supports the calyx backend");
+            return Err(1);
+        }
+
+        let t = Instant::now();
+        let mut ir = ir_passes::Monomorphize::transform(opts, &ir)?;
+        log::info!("Monomorphize: {}ms", t.elapsed().as_millis());
+
+        let t = Instant::now();
+        ir_passes::BundleElim::do_pass(opts, &mut ir)?;
+        log::info!("Bundle elimination: {}ms", t.elapsed().as_millis());
+
+        let t = Instant::now();
+        ir_passes::Compile::compile(&ir);
+        log::info!("Compilation: {}ms", t.elapsed().as_millis());
         return Ok(());
     }
 
@@ -60,18 +113,24 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
     log::debug!("{ns}");
 
     // Construct a binding
-    let bind = binding::ProgBinding::try_from(&ns)?;
+    let bind = binding::ProgBinding::new(&ns, opts.diagnostics_format)?;
 
     // Interval checking
-    let t = Instant::now();
-    passes::IntervalCheck::check(opts, &ns, &bind)?;
-    log::info!("Interval check: {}ms", t.elapsed().as_millis());
+    if !opts.unsafe_skip_discharge {
+        let t = Instant::now();
+        passes::IntervalCheck::check(opts, &ns, &bind)?;
+        log::info!("Interval check: {}ms", t.elapsed().as_millis());
+    }
 
     // User-level @phantom ports
     let t = Instant::now();
     passes::PhantomCheck::check(opts, &ns, &bind)?;
     log::info!("Phantom check: {}ms", t.elapsed().as_millis());
 
+    if opts.dump_interface || opts.dump_all_interfaces {
+        return passes::DumpInterface::print(opts, &ns);
+    }
+
     // Monomorphize the program.
     let t = Instant::now();
     let ns = passes::Monomorphize::transform(ns);
@@ -85,7 +144,7 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
     log::debug!("{ns}");
 
     // Rebuild the binding
-    let bind = binding::ProgBinding::try_from(&ns)?;
+    let bind = binding::ProgBinding::new(&ns, opts.diagnostics_format)?;
 
     // Monomorphic Bind check
     let t = Instant::now();
@@ -93,43 +152,159 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
     log::info!("Monomorphoic Bind check: {}ms", t.elapsed().as_millis());
 
     // Monomorphic Interval checking
-    let t = Instant::now();
-    passes::IntervalCheck::check(opts, &ns, &bind)?;
-    log::info!("Monomorphoic Interval check: {}ms", t.elapsed().as_millis());
+    if !opts.unsafe_skip_discharge {
+        let t = Instant::now();
+        passes::IntervalCheck::check(opts, &ns, &bind)?;
+        log::info!(
+            "Monomorphoic Interval check: {}ms",
+            t.elapsed().as_millis()
+        );
+    }
 
     // Max state calculation
     let states = passes::MaxStates::check(opts, &ns, &bind)?;
 
-    if opts.dump_interface {
-        passes::DumpInterface::print(&ns, &states.max_states);
-        return Ok(());
-    }
-
     // Return early if we're asked to dump the interface
     if opts.check {
+        resolver.save_interfaces();
         return Ok(());
     }
     // Lowering
     let t = Instant::now();
     let Some(ns) =
-        passes::Lower::transform_unwrap(ns, states.max_states) else {
+        passes::Lower::transform_unwrap(ns, states.max_states.clone()) else {
             return Err(1);
         };
     log::info!("Lowering: {}ms", t.elapsed().as_millis());
     log::debug!("{ns}");
 
+    // Simulation
+    if let Some(data) = &opts.simulate {
+        let t = Instant::now();
+        if let Err(e) = sim::simulate(
+            &ns,
+            &states.max_states,
+            data,
+            opts.vcd.as_deref(),
+        ) {
+            eprintln!("Error: {}", e.kind);
+            return Err(1);
+        }
+        log::info!("Simulation: {}ms", t.elapsed().as_millis());
+        return Ok(());
+    }
+
     // Compilation
     let t = Instant::now();
-    backend::compile(ns);
+    match opts.backend {
+        Backend::Calyx => backend::compile(ns),
+        Backend::Verilog => {
+            backend::verilog::compile(ns, opts.diagnostics_format)?
+        }
+    }
     log::info!("Compilation: {}ms", t.elapsed().as_millis());
 
     Ok(())
 }
 
+// Formats the given programs. Returns the number of files that failed to
+// parse or, in check mode, are not formatted.
+fn fmt(opts: &cmdline::FmtOpts) -> Result<(), u64> {
+    let mut errors = 0;
+    if opts.files.is_empty() {
+        let mut src = String::new();
+        std::io::stdin().read_to_string(&mut src).unwrap();
+        let out = match frontend::fmt::format(Path::new("<stdin>"), &src) {
+            Ok(out) => out,
+            Err(e) => {
+                eprintln!("{e}");
+                return Err(1);
+            }
+        };
+        if opts.check {
+            if src != out {
+                eprintln!("error: <stdin> is not formatted");
+                return Err(1);
+            }
+        } else {
+            std::io::stdout().write_all(out.as_bytes()).unwrap();
+        }
+        return Ok(());
+    }
+
+    for file in &opts.files {
+        let src = match std::fs::read_to_string(file) {
+            Ok(src) => src,
+            Err(e) => {
+                eprintln!("error: failed to read {}: {e}", file.display());
+                errors += 1;
+                continue;
+            }
+        };
+        let out = match frontend::fmt::format(file, &src) {
+            Ok(out) => out,
+            Err(e) => {
+                eprintln!("{e}");
+                errors += 1;
+                continue;
+            }
+        };
+        if src == out {
+            continue;
+        }
+        if opts.check {
+            let line = src
+                .lines()
+                .zip(out.lines())
+                .position(|(l, r)| l != r)
+                .unwrap_or_else(|| {
+                    src.lines().count().min(out.lines().count())
+                });
+            eprintln!(
+                "error: {} is not formatted (first difference on line {})",
+                file.display(),
+                line + 1
+            );
+            errors += 1;
+        } else if let Err(e) = std::fs::write(file, out) {
+            eprintln!("error: failed to write {}: {e}", file.display());
+            errors += 1;
+        }
+    }
+    if errors > 0 {
+        Err(errors)
+    } else {
+        Ok(())
+    }
+}
+
 fn main() {
+    // `filament fmt` formats programs instead of compiling them
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some("fmt") {
+        let rest = args[2..].iter().map(String::as_str).collect::<Vec<_>>();
+        let opts = match <cmdline::FmtOpts as argh::FromArgs>::from_args(
+            &["filament", "fmt"],
+            &rest,
+        ) {
+            Ok(opts) => opts,
+            Err(early) => {
+                println!("{}", early.output);
+                std::process::exit(early.status.map_or(1, |_| 0));
+            }
+        };
+        if fmt(&opts).is_err() {
+            std::process::exit(1)
+        }
+        return;
+    }
+
     let opts: cmdline::Opts = argh::from_env();
     match run(&opts) {
         Ok(_) => (),
+        Err(_) if opts.diagnostics_format == DiagnosticsFormat::Json => {
+            std::process::exit(1)
+        }
         Err(err) => {
             eprintln!("Compilation failed with {err} errors.");
             if !opts.show_models {
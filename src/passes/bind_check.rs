@@ -80,12 +80,39 @@ impl BindCheck {
                 self.diag.add_error(err);
             }
         }
+        for (func, args) in expr.funcs() {
+            match (&func, func.arity()) {
+                (ast::UnFn::User(f), None) => {
+                    let err = Error::undefined(f.name(), "function").add_note(
+                        self.diag.add_info(
+                            format!("function `{func}' is not defined"),
+                            pos,
+                        ),
+                    );
+                    self.diag.add_error(err);
+                }
+                (_, Some(arity)) if arity != args => {
+                    let err = Error::malformed(format!(
+                        "function `{func}' takes {arity} arguments but {args} were provided"
+                    ))
+                    .add_note(self.diag.add_info(
+                        format!("`{func}' applied to {args} arguments"),
+                        pos,
+                    ));
+                    self.diag.add_error(err);
+                }
+                _ => (),
+            }
+        }
     }
 }
 
 impl visitor::Checker for BindCheck {
-    fn new(_: &cmdline::Opts, _: &ast::Namespace) -> Self {
-        Self::default()
+    fn new(opts: &cmdline::Opts, _: &ast::Namespace) -> Self {
+        Self {
+            diag: diagnostics::Diagnostics::new(opts.diagnostics_format),
+            ..Default::default()
+        }
     }
 
     fn clear_data(&mut self) {
@@ -112,22 +139,37 @@ impl visitor::Checker for BindCheck {
         _bind: &binding::CompBinding,
     ) -> Traverse {
         let ast::BundleType {
-            idx,
-            len,
+            idxs,
+            lens,
             liveness,
             bitwidth,
+            repr,
         } = &bun.typ;
-        let n = self.push_bundle_params(iter::once(idx.clone()));
+        let n = self.push_bundle_params(idxs.iter().cloned());
         for time in liveness.time_exprs() {
             self.time(time, liveness.pos());
         }
-        for expr in &[bitwidth, len] {
+        for expr in iter::once(bitwidth).chain(lens) {
             self.expr(expr, expr.pos());
         }
+        if let ast::NumRepr::Fixed { frac, .. } = repr {
+            self.expr(frac, bitwidth.pos());
+        }
         self.pop_bundle_params(n);
         Traverse::Continue(())
     }
 
+    fn connect(
+        &mut self,
+        con: &ast::Connect,
+        ctx: &binding::CompBinding,
+    ) -> Traverse {
+        for port in [&con.dst, &con.src] {
+            self.bundle_access(port, ctx);
+        }
+        Traverse::Continue(())
+    }
+
     /// Check the binding of a component
     fn signature(&mut self, sig: &ast::Signature) -> Traverse {
         let events = sig.events().collect_vec();
@@ -142,6 +184,9 @@ impl visitor::Checker for BindCheck {
                 }
             }
             self.expr(pd.bitwidth(), pd.bitwidth().pos());
+            if let ast::NumRepr::Fixed { frac, .. } = pd.repr() {
+                self.expr(frac, pd.bitwidth().pos());
+            }
         }
         // Check that interface ports use only bound events
         for id in &sig.interface_signals {
@@ -202,6 +247,16 @@ impl visitor::Checker for BindCheck {
         Traverse::Continue(())
     }
 
+    fn param_let(
+        &mut self,
+        l: &ast::ParamLet,
+        _ctx: &binding::CompBinding,
+    ) -> Traverse {
+        self.expr(&l.expr, l.expr.pos());
+        self.add_global_params(iter::once(l.name.clone()));
+        Traverse::Continue(())
+    }
+
     fn instance(
         &mut self,
         inst: &ast::Instance,
@@ -325,6 +380,46 @@ impl visitor::Checker for BindCheck {
 }
 
 impl BindCheck {
+    /// Check that an access into a bundle provides an access for every
+    /// dimension of the bundle
+    fn bundle_access(
+        &mut self,
+        port: &Loc<ast::Port>,
+        ctx: &binding::CompBinding,
+    ) {
+        let (name, dims, access) = match port.inner() {
+            ast::Port::Bundle { name, access } => {
+                let bi = ctx.get_bundle_idx(name);
+                (name, ctx[bi].typ.dims(), access)
+            }
+            ast::Port::InvBundle {
+                invoke,
+                port,
+                access,
+            } => {
+                let sig = ctx.get_invoke_idx(invoke).unresolved_signature(ctx);
+                let Some(ast::PortDef::Bundle(bun)) =
+                    ctx.prog[sig].find_port(port).map(|p| p.take())
+                else {
+                    return;
+                };
+                (port, bun.typ.dims(), access)
+            }
+            _ => return,
+        };
+        if dims != access.len() {
+            let err = Error::malformed(format!(
+                "bundle `{name}' has {dims} dimensions but is accessed with {} indices",
+                access.len()
+            ))
+            .add_note(self.diag.add_info(
+                format!("bundle accessed with {} indices", access.len()),
+                port.pos(),
+            ));
+            self.diag.add_error(err);
+        }
+    }
+
     /// Check that an invoke's instance is bound and and bind its signature
     fn bind_invoke(
         &mut self,
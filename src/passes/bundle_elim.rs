@@ -15,8 +15,9 @@ use std::collections::HashMap;
 /// ([ast::Port::Bundle] or [ast::Port::InvBundle]) with explicit ports.
 pub struct BundleElim {
     /// Mapping from name of a monomorphized instance and bundle name to the
-    /// ports generated by the monomorphization of the bundle.
-    sig_bundle_map: HashMap<(ast::Id, ast::Id), Vec<ast::Id>>,
+    /// lengths of the bundle's dimensions and the ports generated by the
+    /// monomorphization of the bundle in row-major order.
+    sig_bundle_map: HashMap<(ast::Id, ast::Id), (Vec<u64>, Vec<ast::Id>)>,
     /// Mapping from names of instances to components
     inst_map: HashMap<ast::Id, ast::Id>,
     /// Mapping from invocations to instances
@@ -33,6 +34,31 @@ impl BundleElim {
         })
     }
 
+    /// All the indices mentioned by an access into a bundle. Each index has
+    /// a value for every dimension of the bundle.
+    fn access_indices(access: Vec<Loc<ast::Access>>) -> Vec<Vec<u64>> {
+        access
+            .into_iter()
+            .map(|a| match a.take() {
+                ast::Access::Index(idx) => {
+                    let i: u64 = idx.try_into().unwrap();
+                    i..i + 1
+                }
+                ast::Access::Range { start, end } => {
+                    let s: u64 = start.try_into().unwrap();
+                    let e: u64 = end.try_into().unwrap();
+                    s..e
+                }
+            })
+            .multi_cartesian_product()
+            .collect_vec()
+    }
+
+    /// Position of an index in the row-major order of the bundle's ports
+    fn flat_index(lens: &[u64], idxs: &[u64]) -> usize {
+        lens.iter().zip(idxs).fold(0, |acc, (len, i)| acc * len + i) as usize
+    }
+
     /// Compile bundles mentioned in the signature of a component:
     /// - IO bundles are moved into the component body.
     /// - Input bundles generate assignments from the bundle to the port
@@ -50,56 +76,68 @@ impl BundleElim {
             name: bundle_name,
             typ:
                 ast::BundleType {
-                    idx,
-                    len,
+                    idxs,
+                    lens,
                     liveness,
                     bitwidth,
+                    repr,
                 },
         } = p;
-        let len: u64 = len.take().try_into().unwrap();
         // For each index in the bundle, generate a corresponding port
-        let ports = (0..len)
-            .map(|i| {
-                let bind =
-                    Binding::new(vec![(*idx.inner(), ast::Expr::concrete(i))]);
-                let liveness =
-                    liveness.clone().take().resolve_exprs(&bind).into();
-                // Name of the new port is the bundle name with the index appended
-                let name = Loc::unknown(ast::Id::from(format!(
-                    "{}_{i}",
-                    bundle_name.clone()
-                )));
-                // Generate connection associated with this bundle port's creation.
-                let this_port = ast::Port::This(name.clone()).into();
-                let bundle_port = ast::Port::bundle(
-                    bundle_name.clone(),
-                    Loc::unknown(ast::Expr::concrete(i).into()),
-                )
-                .into();
-                // Generate assignment for the bundle
-                if is_input {
-                    // bundle{i} = this.p
-                    pre_cmds.push(ast::Command::Connect(ast::Connect::new(
-                        bundle_port,
-                        this_port,
-                        None,
-                    )))
-                } else {
-                    // this.p = bundle{i}
-                    post_cmds.push(ast::Command::Connect(ast::Connect::new(
-                        this_port,
-                        bundle_port,
-                        None,
-                    )))
-                };
-                let port = ast::PortDef::Port {
-                    name,
-                    liveness,
-                    bitwidth: bitwidth.clone(),
-                };
-                port.into()
-            })
-            .collect_vec();
+        let ports =
+            lens.into_iter()
+                .map(|len| {
+                    let len: u64 = len.take().try_into().unwrap();
+                    0..len
+                })
+                .multi_cartesian_product()
+                .map(|is| {
+                    let bind =
+                        Binding::new(idxs.iter().zip(&is).map(|(idx, i)| {
+                            (idx.copy(), ast::Expr::concrete(*i))
+                        }));
+                    let liveness =
+                        liveness.clone().take().resolve_exprs(&bind).into();
+                    // Name of the new port is the bundle name with the indices appended
+                    let name = Loc::unknown(ast::Id::from(format!(
+                        "{}_{}",
+                        bundle_name.clone(),
+                        is.iter().join("_")
+                    )));
+                    // Generate connection associated with this bundle port's creation.
+                    let this_port = ast::Port::This(name.clone()).into();
+                    let bundle_port = ast::Port::bundle(
+                        bundle_name.clone(),
+                        is.iter()
+                            .map(|i| {
+                                Loc::unknown(ast::Expr::concrete(*i).into())
+                            })
+                            .collect(),
+                    )
+                    .into();
+                    // Generate assignment for the bundle
+                    if is_input {
+                        // bundle{i} = this.p
+                        pre_cmds.push(ast::Command::Connect(ast::Connect::new(
+                            bundle_port,
+                            this_port,
+                            None,
+                        )))
+                    } else {
+                        // this.p = bundle{i}
+                        post_cmds.push(ast::Command::Connect(
+                            ast::Connect::new(this_port, bundle_port, None),
+                        ))
+                    };
+                    let port = ast::PortDef::Port {
+                        name,
+                        liveness,
+                        bitwidth: bitwidth.clone(),
+                        repr: repr.clone().resolve(&bind),
+                    };
+                    port.into()
+                })
+                .collect_vec();
         (ports, is_input)
     }
 
@@ -119,8 +157,17 @@ impl BundleElim {
                 p @ ast::PortDef::Port { .. } => {
                     (vec![Loc::new(p, pos)], is_input)
                 }
+                ast::PortDef::Struct { .. } => {
+                    unreachable!("struct ports should have been eliminated")
+                }
                 ast::PortDef::Bundle(b) => {
                     let b_name = *b.name;
+                    let lens = b
+                        .typ
+                        .lens
+                        .iter()
+                        .map(|l| l.inner().try_into().unwrap())
+                        .collect_vec();
                     let (ports, is_input) = Self::compile_sig_port(
                         b,
                         is_input,
@@ -130,10 +177,13 @@ impl BundleElim {
                     // Add the transformed signature to the bundle map.
                     self.sig_bundle_map.insert(
                         (name, b_name),
-                        ports
-                            .iter()
-                            .map(|p| *p.inner().name().inner())
-                            .collect(),
+                        (
+                            lens,
+                            ports
+                                .iter()
+                                .map(|p| *p.inner().name().inner())
+                                .collect(),
+                        ),
                     );
                     (ports, is_input)
                 }
@@ -142,22 +192,22 @@ impl BundleElim {
         (sig, pre_cmds, post_cmds)
     }
 
+    /// The ports generated for the given indices of a signature bundle
     fn bundle_splat_ports(
         &self,
         comp: ast::Id,
         bundle: ast::Id,
-        (start, end): (ast::Expr, ast::Expr),
+        idxs: Vec<Vec<u64>>,
     ) -> impl Iterator<Item = Loc<ast::Id>> + '_ {
-        let renamed =
+        let (lens, renamed) =
             &self.sig_bundle_map.get(&(comp, bundle)).unwrap_or_else(|| {
                 unreachable!(
                     "Bundle `{}' not found in component `{}'",
                     bundle, comp
                 )
             });
-        let start: u64 = start.try_into().unwrap();
-        let end: u64 = end.try_into().unwrap();
-        (start..end).map(|i| Loc::unknown(renamed[i as usize]))
+        idxs.into_iter()
+            .map(|is| Loc::unknown(renamed[Self::flat_index(lens, &is)]))
     }
 
     fn port(&self, p: Loc<ast::Port>) -> Vec<Loc<ast::Port>> {
@@ -166,24 +216,27 @@ impl BundleElim {
             ast::Port::Bundle { name, access } => {
                 // We don't need to rewrite index accesses on signature bundles
                 // because they are going to be bound in the body.
-                if matches!(access.inner(), ast::Access::Index(_)) {
+                if access
+                    .iter()
+                    .all(|a| matches!(a.inner(), ast::Access::Index(_)))
+                {
                     return vec![Loc::new(
                         ast::Port::bundle(name, access),
                         pos,
                     )];
                 }
-                let ast::Access::Range { start, end } = access.take() else {
-                    unreachable!();
-                };
 
                 // This is a locally bound bundle
-                let s: u64 = start.try_into().unwrap();
-                let e: u64 = end.try_into().unwrap();
-                (s..e)
-                    .map(|idx| {
+                Self::access_indices(access)
+                    .into_iter()
+                    .map(|is| {
                         ast::Port::bundle(
                             name.clone(),
-                            Loc::unknown(ast::Expr::concrete(idx).into()),
+                            is.into_iter()
+                                .map(|i| {
+                                    Loc::unknown(ast::Expr::concrete(i).into())
+                                })
+                                .collect(),
                         )
                         .into()
                     })
@@ -194,41 +247,25 @@ impl BundleElim {
                 port,
                 access,
             } => {
-                if let ast::Access::Index(idx) = access.inner() {
-                    let comp = self.sig_from_invoke(invoke.copy());
-                    let ports = &self
-                        .sig_bundle_map
-                        .get(&(comp, port.copy()))
-                        .unwrap_or_else(|| {
-                            unreachable!(
-                                "Bundle `{}' not found in component `{}'",
-                                port, invoke
-                            )
-                        });
-                    let conc: u64 = idx.try_into().unwrap();
-                    let port = ports[conc as usize];
-                    return vec![Loc::new(
-                        ast::Port::inv_port(invoke, port.into()),
-                        pos,
-                    )];
-                }
-                let ast::Access::Range { start, end } = access.take() else {
-                    unreachable!();
-                };
-
-                self.bundle_splat_ports(
-                    self.sig_from_invoke(*invoke),
-                    *port,
-                    (start, end),
-                )
-                .map(|name| {
-                    ast::Port::InvPort {
+                let is_index = access
+                    .iter()
+                    .all(|a| matches!(a.inner(), ast::Access::Index(_)));
+                let ports = self
+                    .bundle_splat_ports(
+                        self.sig_from_invoke(*invoke),
+                        *port,
+                        Self::access_indices(access),
+                    )
+                    .map(|name| ast::Port::InvPort {
                         invoke: invoke.clone(),
                         name,
-                    }
-                    .into()
-                })
-                .collect_vec()
+                    });
+                if is_index {
+                    // Index accesses keep the position of the original port
+                    ports.map(|p| Loc::new(p, pos)).collect_vec()
+                } else {
+                    ports.map(|p| p.into()).collect_vec()
+                }
             }
             p => vec![Loc::new(p, pos)],
         }
@@ -299,9 +336,9 @@ impl BundleElim {
                     let alt = self.commands(alt);
                     vec![ast::If { cond, then, alt }.into()]
                 }
-                c @ (ast::Command::Bundle(_) | ast::Command::Fact(_)) => {
-                    vec![c]
-                }
+                c @ (ast::Command::Bundle(_)
+                | ast::Command::Fact(_)
+                | ast::Command::Let(_)) => vec![c],
             })
             .collect_vec()
     }
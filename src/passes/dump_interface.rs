@@ -1,82 +1,231 @@
-use crate::ast;
-use itertools::Itertools;
+use super::{BundleElim, MaxStates, Monomorphize, Pass};
+use crate::{ast, binding, cmdline, visitor::Checker};
+use serde_json::{json, Value};
 use std::collections::HashMap;
 
+/// Print the interfaces of components in JSON format. Interfaces are
+/// described using the signatures of the components before
+/// monomorphization so parameters, constraints and bundles are kept
+/// symbolic.
+///
+/// Expressions are numbers when they are concrete and otherwise objects:
+/// ```json
+/// {"param": "W"}
+/// {"op": "+", "left": ..., "right": ...}
+/// {"fn": "pow2", "args": [...]}
+/// ```
+/// and times are objects of the form `{"event": "G", "offset": ...}`.
 pub struct DumpInterface;
 
-// Mapping from component -> event -> max state
-type States = HashMap<ast::Id, HashMap<ast::Id, u64>>;
-
 impl DumpInterface {
-    /// Print out the interface of the main component in JSON format
-    pub fn print(ns: &ast::Namespace, max_states: &States) {
-        // Search for the main component
-        let main = ns
-            .components
+    /// Print the interface of the top-level component or of all the
+    /// components if `--dump-all-interfaces` is provided.
+    pub fn print(opts: &cmdline::Opts, ns: &ast::Namespace) -> Result<(), u64> {
+        let comps = if opts.dump_all_interfaces {
+            ns.components.iter().collect::<Vec<_>>()
+        } else {
+            let Some(idx) = ns.main_idx() else {
+                eprintln!("Error: component `{}' not found", ns.toplevel);
+                return Err(1);
+            };
+            vec![&ns.components[idx]]
+        };
+        let mut states = Self::max_states(opts, ns, &comps)?;
+        let mut comps = comps
+            .into_iter()
+            .map(|comp| {
+                let name = comp.sig.name.inner().mangle();
+                Self::component(comp, states.remove(&name))
+            })
+            .collect::<Vec<_>>();
+        let out = if opts.dump_all_interfaces {
+            Value::Array(comps)
+        } else {
+            comps.remove(0)
+        };
+        match serde_json::to_string_pretty(&out) {
+            Ok(out) => {
+                println!("{out}");
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Error: failed to serialize interface: {e}");
+                Err(1)
+            }
+        }
+    }
+
+    /// The number of states for each event of the given components that do
+    /// not have parameters, indexed by the name of the monomorphized
+    /// component. The components are monomorphized together so components
+    /// they share are only monomorphized once.
+    fn max_states(
+        opts: &cmdline::Opts,
+        ns: &ast::Namespace,
+        comps: &[&ast::Component],
+    ) -> Result<HashMap<ast::Id, HashMap<ast::Id, u64>>, u64> {
+        let roots = comps
+            .iter()
+            .filter(|c| c.sig.params.is_empty())
+            .map(|c| *c.sig.name.inner())
+            .collect::<Vec<_>>();
+        if roots.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mono = ast::Namespace {
+            externs: ns.externs.clone(),
+            components: ns.components.clone(),
+            funcs: ns.funcs.clone(),
+            ..Default::default()
+        };
+        let mono =
+            BundleElim::transform(Monomorphize::transform_roots(mono, &roots));
+        let bind = binding::ProgBinding::new(&mono, opts.diagnostics_format)?;
+        Ok(MaxStates::check(opts, &mono, &bind)?.max_states)
+    }
+
+    /// JSON description of the interface of a component:
+    /// ```json
+    /// {
+    ///   "name": "main",
+    ///   "params": [{"name": "W", "default": null}],
+    ///   "constraints": [{"op": ">", "left": ..., "right": ...}],
+    ///   "interfaces": [{"name": "go", "event": "G", "delay": 1,
+    ///                   "states": 2, "phantom": false, "default": null}],
+    ///   "inputs": [{"name": "left", "width": 32, "event": "G",
+    ///               "start": 0, "end": 1, "end_event": "G",
+    ///               "bundle": null}],
+    ///   "outputs": [...]
+    /// }
+    /// ```
+    /// `states` is only computed for components without parameters and is
+    /// `null` otherwise. For bundles, `bundle` contains the names of the
+    /// index parameters and the length of each dimension.
+    fn component(
+        comp: &ast::Component,
+        states: Option<HashMap<ast::Id, u64>>,
+    ) -> Value {
+        let sig = &comp.sig;
+
+        let params = sig
+            .params
             .iter()
-            .find(|c| c.sig.name.inner() == "main")
-            .unwrap_or_else(|| panic!("Component named `main' not found"));
+            .map(|p| {
+                json!({
+                    "name": p.name().to_string(),
+                    "default": p.default.as_ref().map(Self::expr),
+                })
+            })
+            .collect::<Vec<_>>();
 
-        let sig = &main.sig;
+        let constraints = sig
+            .param_constraints
+            .iter()
+            .map(|c| Self::constraint(c, Self::expr))
+            .chain(
+                sig.event_constraints
+                    .iter()
+                    .map(|c| Self::constraint(c, Self::time)),
+            )
+            .collect::<Vec<_>>();
 
-        // For an interface port like this:
-        //      @interface[G, G+5] go_G
-        // Generate the JSON information:
-        // {
-        //   "name": "go_G",
-        //   "event": "G",
-        //   "delay": 5,
-        //   "states": 2,
-        //   "phantom": false
-        // }
-        let events = &max_states[&sig.name];
         let interfaces = sig
             .events
             .iter()
             .map(|eb| {
-                let id = sig.get_interface(&eb.event);
-                let phantom = id.is_none();
-                let delay = eb.delay .concrete() .unwrap();
-                format!(
-                    "{{\"name\": {}, \"event\": \"{}\", \"delay\": {}, \"states\": {}, \"phantom\": {} }}",
-                    id.map(|i| format!("\"{}\"", i.name.as_ref())).unwrap_or_else(|| "null".to_string()),
-                    eb.event,
-                    delay,
-                    events[&eb.event],
-                    phantom
-                )
+                let event = eb.event.inner();
+                let id = sig.get_interface(event);
+                let delay = match eb.delay.inner() {
+                    ast::TimeSub::Unit(e) => Self::expr(e),
+                    ast::TimeSub::Sym { l, r } => json!({
+                        "op": "-",
+                        "left": Self::time(l),
+                        "right": Self::time(r),
+                    }),
+                };
+                json!({
+                    "name": id.map(|i| i.name.to_string()),
+                    "event": event.to_string(),
+                    "delay": delay,
+                    "states": states.as_ref().and_then(|s| s.get(event)),
+                    "phantom": id.is_none(),
+                    "default": eb.default.as_ref().map(Self::time),
+                })
             })
-            .collect_vec().join(",\n");
+            .collect::<Vec<_>>();
 
-        // For each input and output that looks like:
-        // @[G+n, G+m] left: 32
-        // Generate the JSON information:
-        // {
-        //   "event": "G",
-        //   "name": "left",
-        //   "width": 32,
-        //   "start": n,
-        //   "end": m
-        // },
-        let pd_to_info = |pd: &ast::Loc<ast::PortDef>| {
-            let w = &pd.bitwidth();
-            let (event, st, end) = pd.liveness()
-            .as_offset()
-            .unwrap_or_else(|| unreachable!("Cannot covert delay into concrete start and end. Resolved delays should use the same event."));
-            format!(
-                "{{ \"event\": \"{event}\", \"name\": \"{name}\", \"width\": {w} , \"start\": {st}, \"end\": {end} }}",
-                name = pd.name(),
-                st = u64::try_from(&st).unwrap(),
-                end = u64::try_from(&end).unwrap(),
-            )
+        let inputs = sig.inputs().map(|pd| Self::port(pd)).collect::<Vec<_>>();
+        let outputs =
+            sig.outputs().map(|pd| Self::port(pd)).collect::<Vec<_>>();
+
+        json!({
+            "name": sig.name.to_string(),
+            "params": params,
+            "constraints": constraints,
+            "interfaces": interfaces,
+            "inputs": inputs,
+            "outputs": outputs,
+        })
+    }
+
+    /// JSON description of a port. Bundles are not split into their ports.
+    fn port(pd: &ast::PortDef) -> Value {
+        let live = pd.liveness();
+        let bundle = match pd {
+            ast::PortDef::Bundle(b) => {
+                let idxs = b.typ.idxs.iter().map(|i| i.to_string());
+                let lens = b.typ.lens.iter().map(|l| Self::expr(l));
+                json!({
+                    "idxs": idxs.collect::<Vec<_>>(),
+                    "lens": lens.collect::<Vec<_>>(),
+                })
+            }
+            ast::PortDef::Port { .. } | ast::PortDef::Struct { .. } => {
+                Value::Null
+            }
         };
+        json!({
+            "name": pd.name().to_string(),
+            "width": Self::expr(pd.bitwidth()),
+            "event": live.start.event.to_string(),
+            "start": Self::expr(&live.start.offset),
+            "end": Self::expr(&live.end.offset),
+            "end_event": live.end.event.to_string(),
+            "bundle": bundle,
+        })
+    }
 
-        let inputs = sig.inputs().map(pd_to_info).collect_vec().join(",\n");
-        let outputs = sig.outputs().map(pd_to_info).collect_vec().join(",\n");
+    fn constraint<T>(
+        c: &ast::OrderConstraint<T>,
+        f: impl Fn(&T) -> Value,
+    ) -> Value {
+        json!({
+            "op": c.op.to_string(),
+            "left": f(&c.left),
+            "right": f(&c.right),
+        })
+    }
+
+    fn time(t: &ast::Time) -> Value {
+        json!({
+            "event": t.event.to_string(),
+            "offset": Self::expr(&t.offset),
+        })
+    }
 
-        // Look ma, a JSON serializer!
-        println!(
-            "{{\n\"interfaces\": [\n{interfaces}\n],\n\"inputs\": [\n{inputs}\n],\n\"outputs\": [\n{outputs}\n]\n}}",
-        );
+    fn expr(e: &ast::Expr) -> Value {
+        match e {
+            ast::Expr::Concrete(n) => json!(n),
+            ast::Expr::Abstract(p) => json!({ "param": p.to_string() }),
+            ast::Expr::App { func, args } => json!({
+                "fn": func.to_string(),
+                "args": args.iter().map(Self::expr).collect::<Vec<_>>(),
+            }),
+            ast::Expr::Op { op, left, right } => json!({
+                "op": op.to_string(),
+                "left": Self::expr(left),
+                "right": Self::expr(right),
+            }),
+        }
     }
 }
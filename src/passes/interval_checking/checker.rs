@@ -48,8 +48,14 @@ impl IntervalCheck {
             return
         };
         let bun_idx = ctx.get_bundle_idx(name);
-        let len = ctx[bun_idx].typ.len.inner().clone();
+        let lens = ctx[bun_idx].typ.lens.clone();
+        for (access, len) in access.iter().zip(lens) {
+            self.access_index(access, len.take());
+        }
+    }
 
+    /// Check that an access into one dimension of a bundle is within bounds
+    fn access_index(&mut self, access: &ast::Loc<ast::Access>, len: ast::Expr) {
         let pos = access.pos();
         match access.inner() {
             ast::Access::Index(idx) => {
@@ -120,6 +126,83 @@ impl IntervalCheck {
         self.add_obligations(Some(cons));
     }
 
+    /// Check that the numeric representations of the ports match.
+    /// Uninterpreted bits can be connected to any representation.
+    fn check_repr(
+        &mut self,
+        con: &ast::Connect,
+        // Resolved ports
+        src: &Option<ast::PortDef>,
+        dst: &Option<ast::PortDef>,
+    ) {
+        let (Some(src), Some(dst)) = (src, dst) else {
+            return;
+        };
+        let (src_r, dst_r) = (src.repr(), dst.repr());
+        match (src_r, dst_r) {
+            (ast::NumRepr::Bits, _) | (_, ast::NumRepr::Bits) => (),
+            _ if src_r.is_signed() != dst_r.is_signed() => {
+                let (sr, dr) = (src_r.describe(), dst_r.describe());
+                let err =
+                    Error::malformed(format!(
+                        "required `{dr}' value but found `{sr}' value"
+                    ))
+                    .add_note(
+                        self.diag
+                            .add_info(format!("source is {sr}"), con.src.pos()),
+                    )
+                    .add_note(self.diag.add_info(
+                        format!("destination is {dr}"),
+                        con.dst.pos(),
+                    ));
+                self.diag.add_error(err);
+            }
+            (ast::NumRepr::Int { .. }, ast::NumRepr::Int { .. }) => (),
+            _ => {
+                let cons = ast::Constraint::sub(ast::OrderConstraint::eq(
+                    dst_r.frac().into(),
+                    src_r.frac().into(),
+                ))
+                .obligation(
+                    "source and destination must have the same number of fractional bits",
+                )
+                .add_note(self.diag.add_info(
+                    format!("source is {}", src_r.describe()),
+                    con.src.pos(),
+                ))
+                .add_note(self.diag.add_info(
+                    format!("destination is {}", dst_r.describe()),
+                    con.dst.pos(),
+                ));
+                self.add_obligations(Some(cons));
+            }
+        }
+    }
+
+    /// Check that the ports used in a guard are 1-bit signals
+    fn check_guard(&mut self, guard: &ast::Guard, ctx: &CompBinding) {
+        for port in guard.ports() {
+            let Some(ast::PortDef::Port { bitwidth, .. }) =
+                ctx.get_resolved_port(port.inner())
+            else {
+                let err = Error::malformed("guards can only use ports")
+                    .add_note(self.diag.add_info("is not a port", port.pos()));
+                self.diag.add_error(err);
+                continue;
+            };
+            let cons = ast::Constraint::sub(ast::OrderConstraint::eq(
+                bitwidth.inner().clone().into(),
+                ast::Expr::from(1).into(),
+            ))
+            .obligation("ports used in guards must be 1-bit")
+            .add_note(self.diag.add_info(
+                format!("port has width {}", bitwidth.inner()),
+                port.pos(),
+            ));
+            self.add_obligations(Some(cons));
+        }
+    }
+
     /// Checks that the availabilities of the left bundle are a subset of the
     /// availabilities of the right bundle.
     fn bundle_inclusion(
@@ -127,36 +210,62 @@ impl IntervalCheck {
         left: ast::Loc<ast::BundleType>,
         right: ast::Loc<ast::BundleType>,
     ) {
-        // Check that the bundle's lengths are equal
-        let left_len = &left.len;
-        let right_len = &right.len;
-        let len_eq = ast::OrderConstraint::eq(
-            left_len.inner().clone(),
-            right_len.inner().clone(),
-        );
-        let len_cons = len_eq
-            .clone()
-            .obligation("bundle lengths must be equal")
-            .add_note(self.diag.add_info(
-                format!("length of bundle is {}", left.len),
-                left.pos(),
-            ))
-            .add_note(self.diag.add_info(
-                format!("length of bundle is {}", right.len),
-                right.pos(),
-            ));
+        if left.dims() != right.dims() {
+            let err = Error::malformed("bundle dimensions must be equal")
+                .add_note(self.diag.add_info(
+                    format!("bundle has {} dimensions", left.dims()),
+                    left.pos(),
+                ))
+                .add_note(self.diag.add_info(
+                    format!("bundle has {} dimensions", right.dims()),
+                    right.pos(),
+                ));
+            self.diag.add_error(err);
+            return;
+        }
+
+        // Check that the bundle's lengths are equal in every dimension
+        let len_eqs = left
+            .lens
+            .iter()
+            .zip(&right.lens)
+            .map(|(l, r)| {
+                ast::OrderConstraint::eq(l.inner().clone(), r.inner().clone())
+            })
+            .collect_vec();
+        let len_cons = left
+            .lens
+            .iter()
+            .zip(&right.lens)
+            .zip(&len_eqs)
+            .map(|((left_len, right_len), len_eq)| {
+                len_eq
+                    .clone()
+                    .obligation("bundle lengths must be equal")
+                    .add_note(self.diag.add_info(
+                        format!("length of bundle is {left_len}"),
+                        left.pos(),
+                    ))
+                    .add_note(self.diag.add_info(
+                        format!("length of bundle is {right_len}"),
+                        right.pos(),
+                    ))
+            })
+            .collect_vec();
 
         // Check that for each index, the left bundle's availability is a subset
         // of the right bundle's availability
-        // Canonicalize left to use the same index as right
+        // Canonicalize left to use the same indices as right
         let l_live =
             left.liveness
                 .inner()
                 .clone()
-                .resolve_exprs(&utils::Binding::new(Some((
-                    left.idx.copy(),
-                    right.idx.copy().into(),
-                ))));
+                .resolve_exprs(&utils::Binding::new(
+                    left.idxs
+                        .iter()
+                        .zip(&right.idxs)
+                        .map(|(l, r)| (l.copy(), r.copy().into())),
+                ));
 
         let r_live = right.liveness.inner().clone();
         let incl = OrderConstraint::subset(l_live, r_live).map(|c| {
@@ -169,10 +278,10 @@ impl IntervalCheck {
                  format!("bundle's wires are required for {}", left.liveness),
                  left.liveness.pos(),
              ))
-             // Only define the right bundle's index
-             .with_defines(vec![right.idx.copy()])
-             .with_path_cond(vec![len_eq.clone()])
-        }).chain(iter::once(len_cons)).collect_vec();
+             // Only define the right bundle's indices
+             .with_defines(right.idxs.iter().map(|i| i.copy()))
+             .with_path_cond(len_eqs.clone())
+        }).chain(len_cons).collect_vec();
 
         self.add_obligations(incl)
     }
@@ -255,14 +364,22 @@ impl IntervalCheck {
                 {
                     // Generate a complete range splat for the invocation bundle implied
                     // by this port.
+                    let access = b
+                        .typ
+                        .lens
+                        .iter()
+                        .map(|len| {
+                            ast::Access::range(
+                                ast::Expr::concrete(0),
+                                len.inner().clone(),
+                            )
+                            .into()
+                        })
+                        .collect();
                     ast::Port::InvBundle {
                         invoke: invoke.name.clone(),
                         port: formal.clone(),
-                        access: ast::Access::range(
-                            ast::Expr::concrete(0),
-                            b.typ.len.inner().clone(),
-                        )
-                        .into(),
+                        access,
                     }
                 } else {
                     ast::Port::inv_port(invoke.name.clone(), formal.clone())
@@ -282,8 +399,15 @@ impl IntervalCheck {
 
 impl visitor::Checker for IntervalCheck {
     fn new(opts: &cmdline::Opts, ns: &ast::Namespace) -> Self {
-        let mut solver = FilSolver::new(opts.show_models).unwrap();
-        let mut diagnostics = diagnostics::Diagnostics::default();
+        let mut solver = FilSolver::new(
+            opts.show_models,
+            &utils::SolverConf::from(opts),
+            opts.dump_smt.as_deref(),
+            &ns.funcs,
+        )
+        .unwrap();
+        let mut diagnostics =
+            diagnostics::Diagnostics::new(opts.diagnostics_format);
 
         // Check that all signatures are well formed
         let t = std::time::Instant::now();
@@ -301,6 +425,7 @@ impl visitor::Checker for IntervalCheck {
                 )
                 .collect_vec();
 
+            solver.component(*sig.name.inner());
             solver.prove(
                 sig.events()
                     .map(|e| e.take())
@@ -399,6 +524,19 @@ impl visitor::Checker for IntervalCheck {
         Traverse::Continue(())
     }
 
+    fn param_let(&mut self, l: &ast::ParamLet, _ctx: &CompBinding) -> Traverse {
+        // Parameters defined by `let` are unique within a component so their
+        // definitions can be added as facts.
+        let name = *l.name.inner();
+        self.add_var(name);
+        let def = ast::OrderConstraint::eq(
+            ast::TimeSub::from(ast::Expr::from(name)),
+            ast::TimeSub::from(l.expr.inner().clone()),
+        );
+        self.add_facts(iter::once(def.into()));
+        Traverse::Continue(())
+    }
+
     // Checking a bundle involves checking that the availability of all signals in the bundle is
     // less than the delay of the containing component.
     fn bundle(
@@ -408,20 +546,30 @@ impl visitor::Checker for IntervalCheck {
         ctx: &CompBinding,
     ) -> Traverse {
         let ast::BundleType {
-            idx, len, liveness, ..
+            idxs,
+            lens,
+            liveness,
+            ..
         } = &bundle.typ;
 
-        // The index ranges over the length of the bundle
-        let idx_range = [
-            OrderConstraint::gte(
-                ast::Expr::abs(*idx.inner()),
-                ast::Expr::concrete(0),
-            ),
-            OrderConstraint::lt(
-                ast::Expr::abs(*idx.inner()),
-                len.inner().clone(),
-            ),
-        ];
+        // Each index ranges over the length of its dimension
+        let idx_range = idxs
+            .iter()
+            .zip(lens)
+            .flat_map(|(idx, len)| {
+                [
+                    OrderConstraint::gte(
+                        ast::Expr::abs(*idx.inner()),
+                        ast::Expr::concrete(0),
+                    ),
+                    OrderConstraint::lt(
+                        ast::Expr::abs(*idx.inner()),
+                        len.inner().clone(),
+                    ),
+                ]
+            })
+            .collect_vec();
+        let defines = idxs.iter().map(|i| i.copy()).collect_vec();
 
         // Get the delay associated with the event used in the bundle
         let ev = liveness.inner().start.event();
@@ -430,10 +578,16 @@ impl visitor::Checker for IntervalCheck {
             format!("bundle's liveness is {}", liveness.len()),
             liveness.pos(),
         );
-        let idx_note = self.diag.add_info(
-            format!("parameter ranges from 0 to {}-1", len.inner()),
-            idx.pos(),
-        );
+        let idx_notes = idxs
+            .iter()
+            .zip(lens)
+            .map(|(idx, len)| {
+                self.diag.add_info(
+                    format!("parameter ranges from 0 to {}-1", len.inner()),
+                    idx.pos(),
+                )
+            })
+            .collect_vec();
         // The event's delay must be gte than availability's length
         let delay_obl = OrderConstraint::gte(
             delay.inner().clone(),
@@ -441,15 +595,23 @@ impl visitor::Checker for IntervalCheck {
         ).obligation(
             "length of bundle wire availability must be less than event's delay"
         )
-        .add_note(live_note)
-        .add_note(idx_note)
-        .add_note(self.diag.add_info("event's delay", delay.pos()))
-        .with_path_cond(idx_range.clone()).with_defines(iter::once(*idx.inner()));
+        .add_note(live_note);
+        let delay_obl = idx_notes
+            .iter()
+            .fold(delay_obl, |obl, note| obl.add_note(*note))
+            .add_note(self.diag.add_info("event's delay", delay.pos()))
+            .with_path_cond(idx_range.clone())
+            .with_defines(defines.clone());
 
         // Ensure that the availabilty of each index of bundle is well-formed (end > start)
         let wf = liveness.inner().well_formed().obligation(
             "bundle's liveness interval is malformed: end is not strictly greater than start",
-        ).add_note(live_note).add_note(idx_note).with_defines(iter::once(*idx.inner())).with_path_cond(idx_range);
+        ).add_note(live_note);
+        let wf = idx_notes
+            .into_iter()
+            .fold(wf, |obl, note| obl.add_note(note))
+            .with_defines(defines)
+            .with_path_cond(idx_range);
 
         self.add_obligations(vec![wf, delay_obl]);
         Traverse::Continue(())
@@ -495,6 +657,10 @@ impl visitor::Checker for IntervalCheck {
         let mb_dst = ctx.get_resolved_port(dst);
         let mb_src = ctx.get_resolved_port(src);
         self.check_width(con, &mb_src, &mb_dst);
+        self.check_repr(con, &mb_src, &mb_dst);
+        if let Some(guard) = &con.guard {
+            self.check_guard(guard, ctx);
+        }
 
         // If we have: dst = src. We need:
         // 1. @within(dst) \subsetof @within(src): To ensure that src drives within for long enough.
@@ -558,6 +724,9 @@ impl visitor::Checker for IntervalCheck {
                     self.diag.add_error(err);
                 };
             }
+            ast::PortDef::Struct { .. } => {
+                unreachable!("struct ports should have been eliminated")
+            }
         }
 
         Traverse::Continue(())
@@ -668,6 +837,7 @@ impl visitor::Checker for IntervalCheck {
             .chain(self.vars())
             .collect_vec();
 
+        self.solver.component(*comp.sig.name.inner());
         self.solver
             .prove(vars, self.facts.clone(), obs, share, &mut self.diag);
         log::info!(
@@ -13,8 +13,9 @@ pub struct Lower {
     fsms: HashMap<ast::Id, ast::Fsm>,
     /// Max state map
     max_states: HashMap<ast::Id, HashMap<ast::Id, u64>>,
-    /// Track writes to bundles
-    bundle_writes: HashMap<ast::Id, Vec<Option<ast::Port>>>,
+    /// Track writes to bundles. Stores the lengths of the bundle's dimensions
+    /// and the writes to each index in row-major order.
+    bundle_writes: HashMap<ast::Id, (Vec<u64>, Vec<Option<ast::Port>>)>,
 }
 
 impl Lower {
@@ -43,17 +44,29 @@ impl Lower {
         Some(guard)
     }
 
+    /// Position of the index accessed by a port in the row-major order of
+    /// the bundle's writes.
+    fn bundle_index(&self, port: &ast::Port) -> usize {
+        let ast::Port::Bundle { name, access } = port else {
+            unreachable!("Expected bundle port, found: `{port}'")
+        };
+        let (lens, _) = &self.bundle_writes[name.inner()];
+        lens.iter().zip(access).fold(0, |acc, (len, a)| {
+            let ast::Access::Index(idx) = a.inner() else {
+                unreachable!("Unexpected bundle range access found: `{port}'")
+            };
+            acc * len + u64::try_from(idx).unwrap()
+        }) as usize
+    }
+
     fn port(&self, port: &ast::Port) -> ast::Port {
         match &port {
-            ast::Port::Bundle { name, access } => {
-                let ast::Access::Index(idx) = access.inner() else {
-                    unreachable!("Unexpected bundle range access found: `{port}'")
-                };
-                let idx = u64::try_from(idx).unwrap() as usize;
-                let writes = self.bundle_writes.get(name).unwrap();
+            ast::Port::Bundle { name, .. } => {
+                let idx = self.bundle_index(port);
+                let (_, writes) = self.bundle_writes.get(name).unwrap();
                 writes[idx]
                     .clone()
-                    .unwrap_or_else(|| panic!("No write to {name}{{{idx}}}"))
+                    .unwrap_or_else(|| panic!("No write to {port}"))
             }
             _ => port.clone(),
         }
@@ -82,10 +95,15 @@ impl visitor::Transform for Lower {
         bundle: ast::Bundle,
         _: &CompBinding,
     ) -> FilamentResult<Vec<ast::Command>> {
-        self.bundle_writes.insert(
-            *bundle.name,
-            vec![None; u64::try_from(bundle.typ.len.inner()).unwrap() as usize],
-        );
+        let lens = bundle
+            .typ
+            .lens
+            .iter()
+            .map(|l| u64::try_from(l.inner()).unwrap())
+            .collect_vec();
+        let size = lens.iter().product::<u64>() as usize;
+        self.bundle_writes
+            .insert(*bundle.name, (lens, vec![None; size]));
         Ok(vec![])
     }
 
@@ -95,16 +113,10 @@ impl visitor::Transform for Lower {
         _: &CompBinding,
     ) -> FilamentResult<Vec<ast::Command>> {
         let src = self.port(con.src.inner());
-        if let ast::Port::Bundle { name, access } = con.dst.inner() {
-            let ast::Access::Index(idx) = access.inner() else {
-                unreachable!("Unexpected bundle range access found: `{}'", con.dst)
-            };
-            let idx = u64::try_from(idx).unwrap() as usize;
-            assert!(
-                self.bundle_writes[name.inner()][idx].is_none(),
-                "multiple writes to {name}{{{idx}}}"
-            );
-            let writes = self.bundle_writes.get_mut(name).unwrap();
+        if let ast::Port::Bundle { name, .. } = con.dst.inner() {
+            let idx = self.bundle_index(con.dst.inner());
+            let (_, writes) = self.bundle_writes.get_mut(name).unwrap();
+            assert!(writes[idx].is_none(), "multiple writes to {}", con.dst);
             writes[idx] = Some(src);
             // Remove assignment to bundle port
             Ok(vec![])
@@ -36,8 +36,11 @@ impl MaxStates {
 }
 
 impl visitor::Checker for MaxStates {
-    fn new(_opts: &cmdline::Opts, _: &ast::Namespace) -> Self {
-        Self::default()
+    fn new(opts: &cmdline::Opts, _: &ast::Namespace) -> Self {
+        Self {
+            diag: crate::diagnostics::Diagnostics::new(opts.diagnostics_format),
+            ..Default::default()
+        }
     }
     fn clear_data(&mut self) {
         self.cur_states.clear();
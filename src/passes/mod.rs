@@ -8,6 +8,8 @@ mod max_states;
 mod monomorphize;
 mod pass;
 mod phantom_check;
+mod signed_prims;
+mod struct_elim;
 
 pub use assume::Assume;
 pub use bind_check::BindCheck;
@@ -19,3 +21,5 @@ pub use max_states::MaxStates;
 pub use monomorphize::Monomorphize;
 pub use pass::Pass;
 pub use phantom_check::PhantomCheck;
+pub use signed_prims::SignedPrims;
+pub use struct_elim::StructElim;
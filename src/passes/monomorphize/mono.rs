@@ -40,7 +40,7 @@ impl<'e> Monomorphize<'e> {
     /// Gnerate name for a monomorphized component based on the binding parameters.
     fn generate_mono_name(comp: &ast::Id, params: &[u64]) -> ast::Id {
         let suf = params.iter().map(|p| format!("_{}", p)).join("");
-        format!("{comp}{suf}").into()
+        format!("{}{suf}", comp.mangle()).into()
     }
 
     /// Coerce a list of expressions into a list of concrete values.
@@ -134,11 +134,26 @@ impl<'e> Monomorphize<'e> {
         // Current suffix
         suffix: &str,
     ) -> Vec<ast::Command> {
+        // Extended with the parameters defined using `let`
+        let mut param_binding = param_binding.clone();
         let mut n_cmds = Vec::new();
         for cmd in commands {
             match cmd {
+                ast::Command::Let(ast::ParamLet { name, expr }) => {
+                    let v: u64 = expr
+                        .take()
+                        .resolve(&param_binding)
+                        .try_into()
+                        .unwrap_or_else(|e: Error| {
+                            panic!(
+                                "parameter `{}' must be concrete but was {}",
+                                name, e.kind
+                            )
+                        });
+                    param_binding.insert(name.take(), v.into());
+                }
                 ast::Command::Fact(ast::Fact { cons, .. }) => {
-                    match cons.clone().take().resolve_bool(param_binding) {
+                    match cons.clone().take().resolve_bool(&param_binding) {
                         Ok(true) => (),
                         Ok(false) => {
                             panic!("Assumption `{}' violated during elaboration. Bindings: {:?}", cons.inner(), param_binding)
@@ -155,7 +170,7 @@ impl<'e> Monomorphize<'e> {
                 }
                 ast::Command::Bundle(bl) => {
                     prev_names.insert(*bl.name.inner(), *bl.name.inner());
-                    n_cmds.push(bl.resolve_exprs(param_binding).into());
+                    n_cmds.push(bl.resolve_exprs(&param_binding).into());
                 }
                 ast::Command::Invoke(ast::Invoke {
                     name,
@@ -174,14 +189,14 @@ impl<'e> Monomorphize<'e> {
                             abstract_vars
                                 .into_iter()
                                 .map(|t| {
-                                    t.map(|t| t.resolve_expr(param_binding))
+                                    t.map(|t| t.resolve_expr(&param_binding))
                                 })
                                 .collect_vec(),
                             ports.map(|ps| {
                                 ps.into_iter()
                                     .map(|p| {
                                         p.map(|p| {
-                                            p.resolve_exprs(param_binding)
+                                            p.resolve_exprs(&param_binding)
                                         })
                                     })
                                     .collect_vec()
@@ -191,7 +206,7 @@ impl<'e> Monomorphize<'e> {
                     );
                 }
                 ast::Command::Connect(con) => {
-                    n_cmds.push(Self::connect(con, param_binding).into());
+                    n_cmds.push(Self::connect(con, &param_binding).into());
                 }
                 ast::Command::Instance(inst) => {
                     let ast::Instance {
@@ -205,7 +220,7 @@ impl<'e> Monomorphize<'e> {
 
                     let resolved = bindings
                         .into_iter()
-                        .map(|p| p.map(|p| p.resolve(param_binding)));
+                        .map(|p| p.map(|p| p.resolve(&param_binding)));
 
                     match self.externals.get(&component) {
                         Some(sig) => {
@@ -251,11 +266,11 @@ impl<'e> Monomorphize<'e> {
                     }
                 }
                 ast::Command::If(ast::If { cond, then, alt }) => {
-                    let cond = cond.resolve_bool(param_binding).unwrap();
+                    let cond = cond.resolve_bool(&param_binding).unwrap();
                     let cmds = if cond { then } else { alt };
                     n_cmds.extend(self.commands(
                         cmds.into_iter(),
-                        param_binding,
+                        &param_binding,
                         prev_names.clone(),
                         suffix,
                     ));
@@ -269,7 +284,7 @@ impl<'e> Monomorphize<'e> {
                 }) => {
                     // Compute the start and end values of the loop
                     let s: u64 = start
-                        .resolve(param_binding)
+                        .resolve(&param_binding)
                         .try_into()
                         .unwrap_or_else(|e: Error| {
                             panic!(
@@ -278,7 +293,7 @@ impl<'e> Monomorphize<'e> {
                             )
                         });
                     let e = end
-                        .resolve(param_binding)
+                        .resolve(&param_binding)
                         .try_into()
                         .unwrap_or_else(|e: Error| {
                             panic!(
@@ -288,7 +303,7 @@ impl<'e> Monomorphize<'e> {
                         });
 
                     for i in s..e {
-                        let mut new_binding = (*param_binding).clone();
+                        let mut new_binding = param_binding.clone();
                         new_binding.insert(idx.copy(), i.into());
                         // Recur on the body of the loop
                         let ncmds = self.commands(
@@ -310,6 +325,56 @@ impl<'e> Monomorphize<'e> {
         n_cmds
     }
 
+    /// Generate the instances in the queue along with the instances they use
+    fn generate_queued(
+        &mut self,
+        components: &[ast::Component],
+    ) -> Vec<ast::Component> {
+        let mut comps = vec![];
+        while let Some((name, params)) = self.process_instance() {
+            log::trace!(
+                "processing {}[{}]",
+                name,
+                params.iter().map(|p| p.to_string()).join(", "),
+            );
+            // Get the component associated with the instance
+            let comp = components
+                .iter()
+                .find(|c| *c.sig.name.inner() == name)
+                .unwrap();
+            // Generate binding for the component
+            let binding = Binding::new(
+                comp.sig
+                    .params()
+                    .map(|p| p.take())
+                    .zip(params.into_iter().map(|v| v.into())),
+            );
+            comps.push(self.generate_comp(comp, &binding));
+        }
+        comps
+    }
+
+    /// Monomorphize the components without parameters in `roots` and the
+    /// components they instantiate. Components used by several roots are only
+    /// generated once.
+    pub fn transform_roots(
+        mut ns: ast::Namespace,
+        roots: &[ast::Id],
+    ) -> ast::Namespace {
+        let externals = ns.externals().collect();
+        let signatures = ns.signatures().collect();
+        let mut mono = Monomorphize::new(&externals, &signatures);
+        for root in roots {
+            mono.add_instance(*root, iter::empty());
+        }
+        let mut comps = mono.generate_queued(&ns.components);
+        drop(mono);
+
+        comps.reverse();
+        ns.components = comps;
+        ns
+    }
+
     /// Generate a new component using the binding parameters.
     fn generate_comp(
         &mut self,
@@ -351,28 +416,7 @@ impl Pass for Monomorphize<'_> {
         let signatures = ns.signatures().collect();
         let mut mono = Monomorphize::new(&externals, &signatures);
         let mut comps = vec![mono.generate_comp(&main, &Binding::new(None))];
-
-        while let Some((name, params)) = mono.process_instance() {
-            log::trace!(
-                "processing {}[{}]",
-                name,
-                params.iter().map(|p| p.to_string()).join(", "),
-            );
-            // Get the component associated with the instance
-            let comp = ns
-                .components
-                .iter()
-                .find(|c| *c.sig.name.inner() == name)
-                .unwrap();
-            // Generate binding for the component
-            let binding = Binding::new(
-                comp.sig
-                    .params()
-                    .map(|p| p.take())
-                    .zip(params.into_iter().map(|v| v.into())),
-            );
-            comps.push(mono.generate_comp(comp, &binding));
-        }
+        comps.extend(mono.generate_queued(&ns.components));
         drop(mono);
 
         comps.reverse();
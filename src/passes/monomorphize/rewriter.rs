@@ -67,6 +67,9 @@ impl Rewriter {
                         "If statements should be monomorphized already"
                     )
                 }
+                ast::Command::Let(_) => {
+                    unreachable!("Let bindings should be monomorphized already")
+                }
                 ast::Command::Connect(_) | ast::Command::Fact(_) => {}
             }
         }
@@ -126,6 +129,7 @@ impl Rewriter {
                 }
                 ast::Command::If(_)
                 | ast::Command::ForLoop(_)
+                | ast::Command::Let(_)
                 | ast::Command::Fact(_) => unreachable!(),
             };
             n_cmds.push(out);
@@ -20,11 +20,11 @@ pub struct PhantomCheck {
 }
 
 impl visitor::Checker for PhantomCheck {
-    fn new(_opts: &cmdline::Opts, _: &ast::Namespace) -> Self {
+    fn new(opts: &cmdline::Opts, _: &ast::Namespace) -> Self {
         Self {
             instance_used: HashSet::new(),
             phantom_events: Vec::new(),
-            diag: diagnostics::Diagnostics::default(),
+            diag: diagnostics::Diagnostics::new(opts.diagnostics_format),
         }
     }
 
@@ -0,0 +1,244 @@
+use crate::{
+    ast::{self, Loc},
+    passes::Pass,
+};
+use std::collections::HashMap;
+
+/// Primitives in `comb.fil` that interpret their inputs as unsigned integers
+/// and their counterparts in `signed.fil`.
+const SIGNED_PRIMS: &[(&str, &str)] = &[
+    ("Add", "SAdd"),
+    ("Sub", "SSub"),
+    ("Gt", "SGt"),
+    ("Lt", "SLt"),
+    ("Gte", "SGte"),
+    ("Lte", "SLte"),
+];
+
+/// Select the signed primitive for instances of unsigned arithmetic and
+/// comparison primitives that are only used with signed values.
+/// An instance `a := new Add[#W]` whose invocations receive `signed #W` or
+/// `Q<#F> #W` values becomes `a := new SAdd[#W]` or `a := new SAdd[#W, #F]`.
+/// Instances are left unchanged if their arguments mix representations, if
+/// the unsigned primitive is used to change the width, or if `signed.fil` is
+/// not imported. The type checker reports the mismatches in these cases.
+pub struct SignedPrims<'a> {
+    /// Signatures of all components and externals
+    sigs: &'a HashMap<ast::Id, ast::Signature>,
+    /// Signature of the current component
+    sig: &'a ast::Signature,
+    /// Instances in the current component: component and bindings
+    instances: HashMap<ast::Id, (ast::Id, Vec<ast::Expr>)>,
+    /// Mapping from invocations to instances
+    invokes: HashMap<ast::Id, ast::Id>,
+    /// Arguments passed to each instance
+    args: HashMap<ast::Id, Vec<ast::Port>>,
+    /// Representation of the bundles defined in the body
+    bundles: HashMap<ast::Id, ast::NumRepr>,
+}
+
+impl<'a> SignedPrims<'a> {
+    fn new(
+        sigs: &'a HashMap<ast::Id, ast::Signature>,
+        sig: &'a ast::Signature,
+    ) -> Self {
+        Self {
+            sigs,
+            sig,
+            instances: HashMap::new(),
+            invokes: HashMap::new(),
+            args: HashMap::new(),
+            bundles: HashMap::new(),
+        }
+    }
+
+    /// Record the instances, invocations, and bundles in the commands
+    fn collect(&mut self, cmds: &[ast::Command]) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Instance(inst) => {
+                    let binds = inst.bindings.iter().map(|b| b.inner().clone());
+                    self.instances.insert(
+                        *inst.name.inner(),
+                        (*inst.component.inner(), binds.collect()),
+                    );
+                }
+                ast::Command::Invoke(inv) => {
+                    let inst = *inv.instance.inner();
+                    self.invokes.insert(*inv.name.inner(), inst);
+                    let ports = inv.ports.iter().flatten();
+                    self.args
+                        .entry(inst)
+                        .or_default()
+                        .extend(ports.map(|p| p.inner().clone()));
+                }
+                ast::Command::Bundle(bl) => {
+                    self.bundles.insert(*bl.name.inner(), bl.typ.repr.clone());
+                }
+                ast::Command::ForLoop(l) => self.collect(&l.body),
+                ast::Command::If(i) => {
+                    self.collect(&i.then);
+                    self.collect(&i.alt);
+                }
+                ast::Command::Connect(_)
+                | ast::Command::Fact(_)
+                | ast::Command::Let(_) => (),
+            }
+        }
+    }
+
+    /// Representation of a port of an invocation with the parameters of the
+    /// instance substituted in.
+    fn inv_repr(&self, invoke: &ast::Id, port: &ast::Id) -> ast::NumRepr {
+        let Some((comp, binds)) =
+            self.invokes.get(invoke).and_then(|i| self.instances.get(i))
+        else {
+            return ast::NumRepr::Bits;
+        };
+        let Some(sig) = self.sigs.get(comp) else {
+            return ast::NumRepr::Bits;
+        };
+        let required = sig.params.iter().filter(|p| p.default.is_none());
+        let (Some(pd), true) =
+            (sig.find_port(port), required.count() <= binds.len())
+        else {
+            return ast::NumRepr::Bits;
+        };
+        let bind = sig.param_binding(binds.clone());
+        pd.repr().clone().resolve(&bind)
+    }
+
+    /// Representation of the values carried by a port
+    fn repr(&self, port: &ast::Port) -> ast::NumRepr {
+        let this = |name: &ast::Id| {
+            self.sig
+                .find_port(name)
+                .map_or(ast::NumRepr::Bits, |pd| pd.repr().clone())
+        };
+        match port {
+            ast::Port::This(p) => this(p.inner()),
+            ast::Port::Bundle { name, .. } => self
+                .bundles
+                .get(name.inner())
+                .cloned()
+                .unwrap_or_else(|| this(name.inner())),
+            ast::Port::InvPort { invoke, name } => {
+                self.inv_repr(invoke.inner(), name.inner())
+            }
+            ast::Port::InvBundle { invoke, port, .. } => {
+                self.inv_repr(invoke.inner(), port.inner())
+            }
+            ast::Port::Constant(_) => ast::NumRepr::Bits,
+        }
+    }
+
+    /// The signed primitive and its bindings for an instance if the instance
+    /// should use one.
+    fn select(&self, inst: &ast::Id) -> Option<(ast::Id, Vec<ast::Expr>)> {
+        let (comp, binds) = &self.instances[inst];
+        // Keep the qualifier of imported primitives
+        let name = comp.as_ref();
+        let (prefix, base) = name
+            .rsplit_once("::")
+            .map_or(("", name), |(q, n)| (&name[..q.len() + 2], n));
+        let (_, signed) = SIGNED_PRIMS.iter().find(|(u, _)| *u == base)?;
+        let target = ast::Id::from(format!("{prefix}{signed}"));
+        if !self.sigs.contains_key(&target) {
+            return None;
+        }
+
+        // The signed primitives do not change the width
+        let width = match binds.as_slice() {
+            [w] => w.clone(),
+            [w, out] if w == out => w.clone(),
+            _ => return None,
+        };
+
+        // All interpreted arguments must be signed and agree on the position
+        // of the binary point.
+        let reprs = self
+            .args
+            .get(inst)?
+            .iter()
+            .map(|p| self.repr(p))
+            .filter(|r| !matches!(r, ast::NumRepr::Bits))
+            .collect::<Vec<_>>();
+        let frac = reprs.first()?.frac();
+        if !reprs.iter().all(|r| r.is_signed() && r.frac() == frac) {
+            return None;
+        }
+
+        let mut binds = vec![width];
+        if frac != ast::Expr::concrete(0) {
+            binds.push(frac);
+        }
+        Some((target, binds))
+    }
+
+    /// Replace the instances with the selected primitives
+    fn rewrite(
+        cmds: &mut [ast::Command],
+        selected: &HashMap<ast::Id, (ast::Id, Vec<ast::Expr>)>,
+    ) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Instance(inst) => {
+                    let Some((comp, binds)) = selected.get(inst.name.inner())
+                    else {
+                        continue;
+                    };
+                    let pos = inst.component.pos();
+                    inst.component = Loc::new(*comp, pos);
+                    inst.bindings = binds
+                        .iter()
+                        .map(|b| Loc::new(b.clone(), pos))
+                        .collect();
+                }
+                ast::Command::ForLoop(l) => {
+                    Self::rewrite(&mut l.body, selected)
+                }
+                ast::Command::If(i) => {
+                    Self::rewrite(&mut i.then, selected);
+                    Self::rewrite(&mut i.alt, selected);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn component(
+        sigs: &HashMap<ast::Id, ast::Signature>,
+        mut comp: ast::Component,
+    ) -> ast::Component {
+        // Selecting a primitive changes the representation of its outputs so
+        // repeat until the instances that use them are selected as well.
+        loop {
+            let mut pass = SignedPrims::new(sigs, &comp.sig);
+            pass.collect(&comp.body);
+            let selected: HashMap<_, _> = pass
+                .instances
+                .keys()
+                .filter_map(|inst| Some((*inst, pass.select(inst)?)))
+                .collect();
+            if selected.is_empty() {
+                return comp;
+            }
+            Self::rewrite(&mut comp.body, &selected);
+        }
+    }
+}
+
+impl Pass for SignedPrims<'_> {
+    fn transform(ns: ast::Namespace) -> ast::Namespace {
+        let sigs: HashMap<_, _> =
+            ns.signatures().map(|(n, s)| (n, s.clone())).collect();
+        ast::Namespace {
+            components: ns
+                .components
+                .into_iter()
+                .map(|c| SignedPrims::component(&sigs, c))
+                .collect(),
+            ..ns
+        }
+    }
+}
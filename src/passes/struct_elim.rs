@@ -0,0 +1,498 @@
+use crate::{
+    ast::{self, Loc},
+    cmdline,
+    diagnostics::{self, errors::Error},
+    utils::GPosIdx,
+};
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// Struct ports of a signature
+#[derive(Default)]
+struct SigStructs {
+    /// Struct type of each input port in order. `None` for other ports.
+    inputs: Vec<Option<ast::Id>>,
+    /// Mapping from struct ports to the name of their struct
+    ports: HashMap<ast::Id, ast::Id>,
+}
+
+/// Name of the scalar port generated for a field of a struct port
+fn field_port(port: ast::Id, field: ast::Id) -> ast::Id {
+    ast::Id::from(format!("{port}_{field}"))
+}
+
+#[derive(Default)]
+/// Eliminate struct ports by expanding them into one port per field.
+/// A port `p: S` where `S` has fields `a` and `b` becomes the ports `p_a` and
+/// `p_b`. External components with struct ports are expected to use the same
+/// names for the generated ports.
+/// Uses of the struct are rewritten:
+/// - Field accesses `p.a` and `inv.p.a` become uses of the generated ports.
+/// - Connections between structs become a connection for each field.
+/// - Struct arguments to invocations are passed as one argument per field.
+pub struct StructElim {
+    /// Struct definitions in the program
+    structs: HashMap<ast::Id, ast::StructDef>,
+    /// Struct ports of each signature
+    sigs: HashMap<ast::Id, SigStructs>,
+    /// Mapping from invocations in the current component to the component
+    /// they invoke.
+    inv_map: HashMap<ast::Id, ast::Id>,
+    diag: diagnostics::Diagnostics,
+}
+
+impl StructElim {
+    /// Record the struct definitions and report duplicate definitions and
+    /// fields.
+    fn add_structs(&mut self, defs: Vec<ast::StructDef>) {
+        for def in defs {
+            let name = *def.name.inner();
+            if let Some(prev) = self.structs.get(&name) {
+                let err = Error::already_bound(name, "struct")
+                    .add_note(
+                        self.diag.add_info("struct redefined", def.name.pos()),
+                    )
+                    .add_note(
+                        self.diag
+                            .add_info("previous definition", prev.name.pos()),
+                    );
+                self.diag.add_error(err);
+                continue;
+            }
+            for (f, prev) in def
+                .fields
+                .iter()
+                .tuple_combinations()
+                .filter(|(a, b)| a.name.inner() == b.name.inner())
+                .map(|(prev, f)| (f, prev))
+            {
+                let err = Error::already_bound(*f.name.inner(), "field")
+                    .add_note(
+                        self.diag.add_info("field redefined", f.name.pos()),
+                    )
+                    .add_note(
+                        self.diag
+                            .add_info("previous definition", prev.name.pos()),
+                    );
+                self.diag.add_error(err);
+            }
+            self.structs.insert(name, def);
+        }
+    }
+
+    /// Check that a struct type refers to a known struct with the right number
+    /// of parameters.
+    fn check_type(&mut self, typ: &ast::StructType) -> Option<&ast::StructDef> {
+        let Some(def) = self.structs.get(typ.name.inner()) else {
+            let err = Error::undefined(*typ.name.inner(), "struct")
+                .add_note(self.diag.add_info("unknown struct", typ.name.pos()));
+            self.diag.add_error(err);
+            return None;
+        };
+        if def.params.len() != typ.args.len() {
+            let msg = format!(
+                "struct `{}' takes {} parameters but {} were provided",
+                typ.name,
+                def.params.len(),
+                typ.args.len()
+            );
+            let err = Error::malformed(msg.clone())
+                .add_note(self.diag.add_info(msg, typ.name.pos()));
+            self.diag.add_error(err);
+            return None;
+        }
+        Some(def)
+    }
+
+    /// The struct type of a port if it refers to a defined struct. Unknown
+    /// structs are reported when the signature is transformed.
+    fn known_struct(&self, pd: &ast::PortDef) -> Option<ast::Id> {
+        match pd {
+            ast::PortDef::Struct { typ, .. }
+                if self.structs.contains_key(typ.name.inner()) =>
+            {
+                Some(*typ.name.inner())
+            }
+            _ => None,
+        }
+    }
+
+    /// Record the struct ports of a signature
+    fn sig_structs(&mut self, sig: &ast::Signature) {
+        let mut info = SigStructs::default();
+        for pd in sig.ports() {
+            if let Some(strct) = self.known_struct(pd) {
+                info.ports.insert(*pd.name().inner(), strct);
+            }
+        }
+        info.inputs = sig.inputs().map(|pd| self.known_struct(pd)).collect();
+        self.sigs.insert(*sig.name.inner(), info);
+    }
+
+    /// Replace the struct ports in a signature with a port for each field
+    fn sig(&mut self, sig: ast::Signature) -> ast::Signature {
+        sig.replace_ports(&mut |pd, is_input| {
+            let pos = pd.pos();
+            let (name, liveness, typ) = match pd.take() {
+                ast::PortDef::Struct {
+                    name,
+                    liveness,
+                    typ,
+                } => (name, liveness, typ),
+                pd => return (vec![Loc::new(pd, pos)], is_input),
+            };
+            let Some(def) = self.check_type(&typ) else {
+                return (vec![], is_input);
+            };
+            let live_pos = liveness.pos();
+            let ports = def
+                .instantiate(liveness.inner(), &typ.args)
+                .into_iter()
+                .map(|(field, live, width)| {
+                    let port = ast::PortDef::port(
+                        Loc::new(
+                            field_port(*name.inner(), *field.inner()),
+                            pos,
+                        ),
+                        Loc::new(live, live_pos),
+                        Loc::new(width, typ.name.pos()),
+                        ast::NumRepr::Bits,
+                    );
+                    Loc::new(port, pos)
+                })
+                .collect();
+            (ports, is_input)
+        })
+    }
+
+    /// The struct of port `port` on component `comp`
+    fn struct_of(&self, comp: &ast::Id, port: &ast::Id) -> Option<ast::Id> {
+        self.sigs.get(comp).and_then(|s| s.ports.get(port)).copied()
+    }
+
+    /// The name of the port generated for the field `field` of `port`.
+    /// Reports an error if the struct does not have the field.
+    fn field(
+        &mut self,
+        port: ast::Id,
+        strct: ast::Id,
+        field: Loc<ast::Id>,
+    ) -> Loc<ast::Id> {
+        let def = &self.structs[&strct];
+        if def.field(field.inner()).is_none() {
+            let err = Error::undefined(*field.inner(), "field").add_note(
+                self.diag.add_info(
+                    format!("struct `{strct}' has no field `{field}'"),
+                    field.pos(),
+                ),
+            );
+            self.diag.add_error(err);
+        }
+        Loc::new(field_port(port, *field.inner()), field.pos())
+    }
+
+    /// Ports generated for all the fields of a struct port
+    fn fields(
+        &self,
+        strct: ast::Id,
+        port: ast::Id,
+        mk: impl Fn(Loc<ast::Id>) -> ast::Port,
+        pos: GPosIdx,
+    ) -> Vec<Loc<ast::Port>> {
+        self.structs[&strct]
+            .fields
+            .iter()
+            .map(|f| {
+                Loc::new(mk(Loc::new(field_port(port, *f.name), pos)), pos)
+            })
+            .collect()
+    }
+
+    /// Rewrite a port in the component `comp`. Returns the ports it expands to
+    /// and, if the port is an entire struct, the name of the struct.
+    fn port(
+        &mut self,
+        comp: ast::Id,
+        p: Loc<ast::Port>,
+    ) -> (Vec<Loc<ast::Port>>, Option<ast::Id>) {
+        let pos = p.pos();
+        match p.take() {
+            ast::Port::This(name) => match self.struct_of(&comp, &name) {
+                Some(strct) => {
+                    let ports =
+                        self.fields(strct, *name.inner(), ast::Port::This, pos);
+                    (ports, Some(strct))
+                }
+                None => (vec![Loc::new(ast::Port::This(name), pos)], None),
+            },
+            ast::Port::InvPort { invoke, name } => {
+                // Field of a struct port on an invocation: `inv.p.f`
+                if let Some((port, field)) =
+                    name.inner().as_ref().split_once('.')
+                {
+                    let (port, field) =
+                        (ast::Id::from(port), ast::Id::from(field));
+                    let strct = self
+                        .inv_map
+                        .get(invoke.inner())
+                        .and_then(|c| self.struct_of(c, &port));
+                    let Some(strct) = strct else {
+                        let err = Error::malformed(format!(
+                            "`{invoke}.{port}' is not a struct port"
+                        ))
+                        .add_note(
+                            self.diag.add_info("not a struct port", name.pos()),
+                        );
+                        self.diag.add_error(err);
+                        return (vec![], None);
+                    };
+                    let name =
+                        self.field(port, strct, Loc::new(field, name.pos()));
+                    return (
+                        vec![Loc::new(ast::Port::inv_port(invoke, name), pos)],
+                        None,
+                    );
+                }
+                // Field of a struct port on this component: `p.f`
+                if !self.inv_map.contains_key(invoke.inner()) {
+                    if let Some(strct) = self.struct_of(&comp, &invoke) {
+                        let name = self.field(*invoke.inner(), strct, name);
+                        return (
+                            vec![Loc::new(ast::Port::This(name), pos)],
+                            None,
+                        );
+                    }
+                }
+                // Struct port on an invocation: `inv.p`
+                let strct = self
+                    .inv_map
+                    .get(invoke.inner())
+                    .and_then(|c| self.struct_of(c, &name));
+                match strct {
+                    Some(strct) => {
+                        let ports = self.fields(
+                            strct,
+                            *name.inner(),
+                            |n| ast::Port::inv_port(invoke.clone(), n),
+                            pos,
+                        );
+                        (ports, Some(strct))
+                    }
+                    None => (
+                        vec![Loc::new(
+                            ast::Port::InvPort { invoke, name },
+                            pos,
+                        )],
+                        None,
+                    ),
+                }
+            }
+            p => (vec![Loc::new(p, pos)], None),
+        }
+    }
+
+    /// Rewrite a port that must not be an entire struct
+    fn scalar_port(
+        &mut self,
+        comp: ast::Id,
+        p: Loc<ast::Port>,
+        ctx: &str,
+    ) -> Loc<ast::Port> {
+        let pos = p.pos();
+        let (mut ports, strct) = self.port(comp, p.clone());
+        if let Some(strct) = strct {
+            let err = Error::malformed(format!(
+                "struct `{strct}' cannot be used as {ctx}"
+            ))
+            .add_note(self.diag.add_info("struct port", pos));
+            self.diag.add_error(err);
+            return p;
+        }
+        // Malformed field accesses are reported by `port`
+        ports.pop().unwrap_or(p)
+    }
+
+    fn guard(&mut self, comp: ast::Id, g: ast::Guard) -> ast::Guard {
+        match g {
+            ast::Guard::Or(g1, g2, pos) => ast::Guard::Or(
+                Box::new(self.guard(comp, *g1)),
+                Box::new(self.guard(comp, *g2)),
+                pos,
+            ),
+            ast::Guard::And(g1, g2, pos) => ast::Guard::And(
+                Box::new(self.guard(comp, *g1)),
+                Box::new(self.guard(comp, *g2)),
+                pos,
+            ),
+            ast::Guard::Not(g, pos) => {
+                ast::Guard::Not(Box::new(self.guard(comp, *g)), pos)
+            }
+            ast::Guard::Port(p) => {
+                ast::Guard::Port(self.scalar_port(comp, p, "a guard"))
+            }
+        }
+    }
+
+    /// Record the components invoked by the invocations in the commands
+    fn invocations(
+        &mut self,
+        cmds: &[ast::Command],
+        insts: &mut HashMap<ast::Id, ast::Id>,
+    ) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Instance(inst) => {
+                    insts.insert(*inst.name.inner(), *inst.component.inner());
+                }
+                ast::Command::Invoke(inv) => {
+                    if let Some(comp) = insts.get(inv.instance.inner()) {
+                        self.inv_map.insert(*inv.name.inner(), *comp);
+                    }
+                }
+                ast::Command::ForLoop(l) => self.invocations(&l.body, insts),
+                ast::Command::If(i) => {
+                    self.invocations(&i.then, insts);
+                    self.invocations(&i.alt, insts);
+                }
+                ast::Command::Connect(_)
+                | ast::Command::Bundle(_)
+                | ast::Command::Fact(_)
+                | ast::Command::Let(_) => (),
+            }
+        }
+    }
+
+    fn invoke(&mut self, comp: ast::Id, mut inv: ast::Invoke) -> ast::Invoke {
+        let Some(ports) = inv.ports.take() else {
+            return inv;
+        };
+        let formals = self
+            .inv_map
+            .get(inv.name.inner())
+            .and_then(|c| self.sigs.get(c))
+            .map(|s| s.inputs.clone())
+            .unwrap_or_default();
+        let mut args = Vec::with_capacity(ports.len());
+        for (idx, p) in ports.into_iter().enumerate() {
+            let pos = p.pos();
+            let (ports, strct) = self.port(comp, p);
+            let formal = formals.get(idx).copied().flatten();
+            if strct != formal {
+                let msg = match (formal, strct) {
+                    (Some(f), Some(s)) => {
+                        format!("expected struct `{f}' but got struct `{s}'")
+                    }
+                    (Some(f), None) => format!("expected struct `{f}'"),
+                    (None, Some(s)) => format!("unexpected struct `{s}'"),
+                    (None, None) => unreachable!(),
+                };
+                let err = Error::malformed(format!(
+                    "invalid argument to invocation `{}'",
+                    inv.name
+                ))
+                .add_note(self.diag.add_info(msg, pos));
+                self.diag.add_error(err);
+            }
+            args.extend(ports);
+        }
+        inv.ports = Some(args);
+        inv
+    }
+
+    fn connect(
+        &mut self,
+        comp: ast::Id,
+        con: ast::Connect,
+    ) -> Vec<ast::Command> {
+        let (dst_pos, src_pos) = (con.dst.pos(), con.src.pos());
+        let (dst, dst_struct) = self.port(comp, con.dst);
+        let (src, src_struct) = self.port(comp, con.src);
+        let guard = con.guard.map(|g| self.guard(comp, g));
+        if dst_struct != src_struct {
+            let note = |s: Option<ast::Id>| match s {
+                Some(s) => format!("struct `{s}'"),
+                None => "not a struct".to_string(),
+            };
+            let err =
+                Error::malformed("connection between mismatched struct types")
+                    .add_note(self.diag.add_info(note(dst_struct), dst_pos))
+                    .add_note(self.diag.add_info(note(src_struct), src_pos));
+            self.diag.add_error(err);
+            return vec![];
+        }
+        dst.into_iter()
+            .zip(src)
+            .map(|(dst, src)| ast::Connect::new(dst, src, guard.clone()).into())
+            .collect()
+    }
+
+    fn commands(
+        &mut self,
+        comp: ast::Id,
+        cmds: Vec<ast::Command>,
+    ) -> Vec<ast::Command> {
+        cmds.into_iter()
+            .flat_map(|cmd| match cmd {
+                ast::Command::Invoke(inv) => {
+                    vec![self.invoke(comp, inv).into()]
+                }
+                ast::Command::Connect(con) => self.connect(comp, con),
+                ast::Command::ForLoop(l) => {
+                    let body = self.commands(comp, l.body);
+                    vec![ast::ForLoop { body, ..l }.into()]
+                }
+                ast::Command::If(ast::If { cond, then, alt }) => {
+                    let then = self.commands(comp, then);
+                    let alt = self.commands(comp, alt);
+                    vec![ast::If { cond, then, alt }.into()]
+                }
+                c @ (ast::Command::Instance(_)
+                | ast::Command::Bundle(_)
+                | ast::Command::Fact(_)
+                | ast::Command::Let(_)) => vec![c],
+            })
+            .collect()
+    }
+
+    fn component(&mut self, comp: ast::Component) -> ast::Component {
+        let name = *comp.sig.name.inner();
+        let sig = self.sig(comp.sig);
+        self.inv_map.clear();
+        self.invocations(&comp.body, &mut HashMap::new());
+        let body = self.commands(name, comp.body);
+        ast::Component { sig, body, ..comp }
+    }
+
+    /// Eliminate the struct ports in the program. Reports unknown structs and
+    /// fields, and mismatched uses of struct ports.
+    pub fn transform(
+        opts: &cmdline::Opts,
+        ns: ast::Namespace,
+    ) -> Result<ast::Namespace, u64> {
+        let mut pass = Self {
+            diag: diagnostics::Diagnostics::new(opts.diagnostics_format),
+            ..Default::default()
+        };
+        let mut ns = ns;
+        pass.add_structs(std::mem::take(&mut ns.structs));
+        for (_, sig) in ns.signatures() {
+            pass.sig_structs(sig);
+        }
+        ns.components = ns
+            .components
+            .into_iter()
+            .map(|comp| pass.component(comp))
+            .collect();
+        ns.externs = ns
+            .externs
+            .into_iter()
+            .map(|(file, sigs)| {
+                (file, sigs.into_iter().map(|sig| pass.sig(sig)).collect())
+            })
+            .collect();
+        match pass.diag.report_all() {
+            Some(errs) => Err(errs),
+            None => Ok(ns),
+        }
+    }
+}
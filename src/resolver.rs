@@ -3,8 +3,9 @@ use crate::{
     errors::{self, FilamentResult},
     frontend,
 };
+use itertools::Itertools;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
 };
@@ -15,8 +16,10 @@ pub struct Resolver {
     lib: PathBuf,
     // Location of the base file
     input: PathBuf,
-    // Files that have already been imported
-    already_imported: HashSet<PathBuf>,
+    // Files that have already been imported along with their alias
+    already_imported: HashSet<(PathBuf, Option<ast::Id>)>,
+    // Interfaces of imported files that have already been checked
+    cache: Option<frontend::InterfaceCache>,
 }
 
 impl From<&cmdline::Opts> for Resolver {
@@ -25,11 +28,25 @@ impl From<&cmdline::Opts> for Resolver {
             lib: opts.library.clone(),
             input: opts.input.clone(),
             already_imported: HashSet::new(),
+            cache: opts
+                .interface_cache
+                .clone()
+                .filter(|_| opts.check)
+                .map(frontend::InterfaceCache::new),
         }
     }
 }
 
 impl Resolver {
+    pub fn new(lib: PathBuf, input: PathBuf) -> Self {
+        Self {
+            lib,
+            input,
+            already_imported: HashSet::new(),
+            cache: None,
+        }
+    }
+
     /// Resolve import either using opts.library or relative the parent directory of the input file.
     fn resolve_import(
         &self,
@@ -91,31 +108,163 @@ impl Resolver {
         p
     }
 
-    /// Returns the path if it has not already been parsed before
-    fn add_import(&mut self, p: PathBuf) -> Option<PathBuf> {
-        let c = fs::canonicalize(&p).unwrap();
+    /// Returns the path if it has not already been parsed before with the
+    /// same alias
+    fn add_import(
+        &mut self,
+        p: PathBuf,
+        alias: Option<ast::Id>,
+    ) -> Option<(PathBuf, Option<ast::Id>)> {
+        let c = (fs::canonicalize(&p).unwrap(), alias);
         if self.already_imported.contains(&c) {
             None
         } else {
             self.already_imported.insert(c);
-            Some(p)
+            Some((p, alias))
+        }
+    }
+
+    /// Resolve the imports of the file `file` and return the ones that have
+    /// not been parsed yet.
+    fn imports(
+        &mut self,
+        imports: Vec<(String, Option<ast::Loc<ast::Id>>)>,
+        file: &Path,
+    ) -> FilamentResult<Vec<(PathBuf, Option<ast::Id>)>> {
+        let base = Self::parent(file);
+        let imports = imports
+            .into_iter()
+            .map(|(s, alias)| {
+                Ok((self.resolve_import(&s, &base)?, alias.map(|a| a.take())))
+            })
+            .collect::<FilamentResult<Vec<_>>>()?;
+        if let Some(cache) = &mut self.cache {
+            let paths = imports.iter().map(|(p, _)| p.clone()).collect_vec();
+            cache.add_imports(file, &paths);
+        }
+        Ok(imports
+            .into_iter()
+            .filter_map(|(file, alias)| self.add_import(file, alias))
+            .collect())
+    }
+
+    /// Parse an imported file. If the file has a valid cached interface, the
+    /// interface is parsed instead and the returned flag is set.
+    fn parse_import(
+        &mut self,
+        path: &Path,
+        funcs: &ast::Functions,
+    ) -> FilamentResult<(ast::Namespace, bool)> {
+        let Some(cache) = &mut self.cache else {
+            return Ok((
+                frontend::FilamentParser::parse_file(path, funcs)?,
+                false,
+            ));
+        };
+        if let Some(interface) = cache.lookup(path) {
+            let ns =
+                frontend::FilamentParser::parse_source(path, interface, funcs)?;
+            return Ok((ns, true));
+        }
+        let source = fs::read_to_string(path).map_err(|err| {
+            errors::Error::invalid_file(format!(
+                "Failed to read {}: {err}",
+                path.to_string_lossy(),
+            ))
+        })?;
+        let ns = frontend::FilamentParser::parse_source(
+            path,
+            source.clone(),
+            funcs,
+        )?;
+        cache.add_source(path, source);
+        Ok((ns, false))
+    }
+
+    /// Write the interfaces of the imported files. Must only be called once
+    /// the program has been checked.
+    pub fn save_interfaces(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.save();
+        }
+    }
+
+    /// Rename the components used by instances in the commands
+    fn rename_instances(
+        cmds: &mut [ast::Command],
+        rename: &impl Fn(&ast::Id) -> Option<ast::Id>,
+    ) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Instance(inst) => {
+                    if let Some(name) = rename(inst.component.inner()) {
+                        let pos = inst.component.pos();
+                        inst.component = ast::Loc::new(name, pos);
+                    }
+                }
+                ast::Command::ForLoop(l) => {
+                    Self::rename_instances(&mut l.body, rename)
+                }
+                ast::Command::If(i) => {
+                    Self::rename_instances(&mut i.then, rename);
+                    Self::rename_instances(&mut i.alt, rename);
+                }
+                ast::Command::Invoke(_)
+                | ast::Command::Fact(_)
+                | ast::Command::Connect(_)
+                | ast::Command::Bundle(_)
+                | ast::Command::Let(_) => (),
+            }
+        }
+    }
+
+    /// Qualify the names of the components defined in a file imported as
+    /// `alias`. Uses of the components in the file are renamed as well.
+    /// External components keep their names since they refer to modules in
+    /// the generated code. Instead, `externs` maps their qualified names to
+    /// their names.
+    fn qualify(
+        ns: &mut ast::Namespace,
+        alias: ast::Id,
+        externs: &mut HashMap<ast::Id, ast::Id>,
+    ) {
+        let names: HashMap<ast::Id, ast::Id> = ns
+            .components
+            .iter()
+            .map(|c| {
+                let name = *c.sig.name.inner();
+                (name, ast::Id::qualified(alias, name))
+            })
+            .collect();
+        for comp in &mut ns.components {
+            let pos = comp.sig.name.pos();
+            comp.sig.name = ast::Loc::new(names[comp.sig.name.inner()], pos);
+            Self::rename_instances(&mut comp.body, &|n| names.get(n).copied());
+        }
+        for (name, _) in ns.externals() {
+            externs.insert(ast::Id::qualified(alias, name), name);
         }
     }
 
     pub fn parse_namespace(&mut self) -> FilamentResult<ast::Namespace> {
         // Parse the top-level file
-        let mut ns = frontend::FilamentParser::parse_file(&self.input)?;
+        let ns = frontend::FilamentParser::parse_file(
+            &self.input,
+            &ast::Functions::default(),
+        )?;
+        self.resolve(ns)
+    }
 
+    /// Resolve the imports and externs of the already parsed top-level file
+    pub fn resolve(
+        &mut self,
+        mut ns: ast::Namespace,
+    ) -> FilamentResult<ast::Namespace> {
         // Extern are resolved to thier absolute path relative to the input file.
         let base = Self::parent(&self.input);
-        let mut imports: Vec<PathBuf> = ns
-            .imports
-            .drain(..)
-            .filter_map(|s| match self.resolve_import(&s, &base) {
-                Ok(file) => self.add_import(file).map(Ok),
-                f @ Err(_) => Some(f),
-            })
-            .collect::<FilamentResult<_>>()?;
+        let input = self.input.clone();
+        let mut imports =
+            self.imports(std::mem::take(&mut ns.imports), &input)?;
 
         ns.externs = ns
             .externs
@@ -123,26 +272,68 @@ impl Resolver {
             .map(|(p, imps)| (Self::absolute(p, &base), imps))
             .collect();
 
-        while let Some(path) = imports.pop() {
-            let mut imp = frontend::FilamentParser::parse_file(&path)?;
+        // Qualified names of external components defined in aliased imports
+        let mut externs = HashMap::new();
+        // Files that have been parsed with any alias
+        let mut parsed = HashSet::new();
+        // Components whose interface was cached along with their file
+        let mut cached = HashMap::new();
+        while let Some((path, alias)) = imports.pop() {
+            let (mut imp, is_cached) = self.parse_import(&path, &ns.funcs)?;
+            if let Some(alias) = alias {
+                Self::qualify(&mut imp, alias, &mut externs);
+            }
+            if is_cached {
+                let file = path.to_string_lossy().to_string();
+                for comp in &imp.components {
+                    cached.insert(*comp.sig.name.inner(), file.clone());
+                }
+            }
+            // A file imported with different aliases defines a copy of its
+            // components for each alias. All other definitions are shared.
+            if !parsed.insert(fs::canonicalize(&path).unwrap()) {
+                imp.externs.clear();
+                imp.fns.clear();
+                imp.structs.clear();
+                imp.consts.clear();
+            }
             let base = Self::parent(&path);
             imp.components.append(&mut ns.components);
             ns.components = imp.components;
+            ns.fns.append(&mut imp.fns);
+            ns.structs.append(&mut imp.structs);
+            ns.consts.append(&mut imp.consts);
             ns.externs.extend(
                 imp.externs
                     .into_iter()
                     .map(|(p, imps)| (Self::absolute(p, &base), imps)),
             );
-            let base = Self::parent(&path);
-            imports.extend(
-                imp.imports
+            imports.extend(self.imports(imp.imports, &path)?);
+        }
+        for comp in &mut ns.components {
+            Self::rename_instances(&mut comp.body, &|n| {
+                externs.get(n).copied()
+            });
+        }
+
+        ns.funcs.install(&ns.fns)?;
+        ns.resolve_consts()?;
+
+        // The bodies of cached components have already been checked and are
+        // missing from their interface. They are treated as external
+        // components so that only their signatures are used.
+        if !cached.is_empty() {
+            let (comps, others): (Vec<_>, Vec<_>) =
+                std::mem::take(&mut ns.components)
                     .into_iter()
-                    .filter_map(|s| match self.resolve_import(&s, &base) {
-                        Ok(file) => self.add_import(file).map(Ok),
-                        f @ Err(_) => Some(f),
-                    })
-                    .collect::<FilamentResult<Vec<_>>>()?,
-            );
+                    .partition(|c| cached.contains_key(c.sig.name.inner()));
+            ns.components = others;
+            for (file, comps) in
+                &comps.into_iter().group_by(|c| &cached[c.sig.name.inner()])
+            {
+                ns.externs
+                    .push((file.clone(), comps.map(|c| c.sig).collect()));
+            }
         }
 
         log::trace!("Imported: {:#?}", self.already_imported);
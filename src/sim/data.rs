@@ -0,0 +1,202 @@
+use crate::errors::{Error, FilamentResult};
+use itertools::Itertools;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Values in a JSON document. Numbers are restricted to unsigned integers
+/// since those are the only ones that can be provided to a design.
+enum Json {
+    Null,
+    Bool,
+    Num(u64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+/// A minimal JSON parser for the data files
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            chars: src.chars().peekable(),
+        }
+    }
+
+    fn error<T>(msg: impl ToString) -> FilamentResult<T> {
+        Err(Error::malformed(format!(
+            "invalid data file: {}",
+            msg.to_string()
+        )))
+    }
+
+    fn skip_ws(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, ch: char) -> FilamentResult<()> {
+        self.skip_ws();
+        match self.chars.next() {
+            Some(c) if c == ch => Ok(()),
+            Some(c) => Self::error(format!("expected `{ch}', found `{c}'")),
+            None => Self::error(format!("expected `{ch}', found end of file")),
+        }
+    }
+
+    fn keyword(&mut self, kw: &str, val: Json) -> FilamentResult<Json> {
+        for ch in kw.chars() {
+            if self.chars.next() != Some(ch) {
+                return Self::error(format!("expected `{kw}'"));
+            }
+        }
+        Ok(val)
+    }
+
+    fn string(&mut self) -> FilamentResult<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.chars.next() {
+                    Some(c @ ('"' | '\\' | '/')) => s.push(c),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    _ => return Self::error("unsupported escape sequence"),
+                },
+                Some(c) => s.push(c),
+                None => return Self::error("unterminated string"),
+            }
+        }
+    }
+
+    fn value(&mut self) -> FilamentResult<Json> {
+        self.skip_ws();
+        match self.chars.peek() {
+            Some('{') => {
+                self.chars.next();
+                let mut fields = vec![];
+                self.skip_ws();
+                if self.chars.next_if_eq(&'}').is_some() {
+                    return Ok(Json::Obj(fields));
+                }
+                loop {
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    self.skip_ws();
+                    match self.chars.next() {
+                        Some(',') => self.skip_ws(),
+                        Some('}') => return Ok(Json::Obj(fields)),
+                        _ => return Self::error("expected `,' or `}'"),
+                    }
+                }
+            }
+            Some('[') => {
+                self.chars.next();
+                let mut elems = vec![];
+                self.skip_ws();
+                if self.chars.next_if_eq(&']').is_some() {
+                    return Ok(Json::Arr(elems));
+                }
+                loop {
+                    elems.push(self.value()?);
+                    self.skip_ws();
+                    match self.chars.next() {
+                        Some(',') => (),
+                        Some(']') => return Ok(Json::Arr(elems)),
+                        _ => return Self::error("expected `,' or `]'"),
+                    }
+                }
+            }
+            Some('"') => Ok(Json::Str(self.string()?)),
+            Some('t') => self.keyword("true", Json::Bool),
+            Some('f') => self.keyword("false", Json::Bool),
+            Some('n') => self.keyword("null", Json::Null),
+            Some(c) if c.is_ascii_digit() => {
+                let mut n = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit()) {
+                    n.push(c);
+                }
+                n.parse().map(Json::Num).or_else(|_| {
+                    Self::error(format!("number `{n}' is too large"))
+                })
+            }
+            Some(c) => Self::error(format!("unexpected character `{c}'")),
+            None => Self::error("unexpected end of file"),
+        }
+    }
+}
+
+/// Parse a value provided in the data file. Values are either numbers or
+/// strings that represent binary (`0b`) or hexadecimal (`0x`) numbers.
+fn parse_value(name: &str, val: &Json) -> FilamentResult<u64> {
+    let err =
+        || Error::malformed(format!("invalid value for `{name}' in data file"));
+    match val {
+        Json::Num(n) => Ok(*n),
+        Json::Str(s) => {
+            if let Some(bin) = s.strip_prefix("0b") {
+                u64::from_str_radix(bin, 2).map_err(|_| err())
+            } else if let Some(hex) = s.strip_prefix("0x") {
+                u64::from_str_radix(hex, 16).map_err(|_| err())
+            } else {
+                Err(err())
+            }
+        }
+        Json::Null | Json::Bool | Json::Arr(_) | Json::Obj(_) => Err(err()),
+    }
+}
+
+/// Parse a data file that maps each input to the values provided to it in
+/// each transaction:
+/// ```json
+/// { "left": [1, "0x2", "0b11"], "right": [4, 5, 6] }
+/// ```
+pub fn parse_data(src: &str) -> FilamentResult<Vec<(String, Vec<u64>)>> {
+    let mut parser = Parser::new(src);
+    let Json::Obj(fields) = parser.value()? else {
+        return Parser::error("expected an object");
+    };
+    fields
+        .into_iter()
+        .map(|(name, vals)| {
+            let Json::Arr(vals) = vals else {
+                return Parser::error(format!(
+                    "expected an array of values for `{name}'"
+                ));
+            };
+            let vals = vals
+                .iter()
+                .map(|v| parse_value(&name, v))
+                .collect::<FilamentResult<_>>()?;
+            Ok((name, vals))
+        })
+        .collect()
+}
+
+/// Print the outputs in the same format as the cocotb harness:
+/// ```json
+/// {"out": {"0": [10], "1": [257]}, "cycles": 4}
+/// ```
+pub fn format_outputs(
+    outputs: &[(String, Vec<Vec<String>>)],
+    cycles: u64,
+) -> String {
+    let outs = outputs
+        .iter()
+        .map(|(name, txns)| {
+            let txns = txns
+                .iter()
+                .enumerate()
+                .map(|(idx, vals)| format!("\"{idx}\": [{}]", vals.join(", ")))
+                .join(", ");
+            format!("\"{name}\": {{{txns}}}")
+        })
+        .chain(std::iter::once(format!("\"cycles\": {cycles}")))
+        .join(", ");
+    format!("{{{outs}}}")
+}
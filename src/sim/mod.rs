@@ -0,0 +1,467 @@
+//! Cycle-accurate simulator for lowered Filament programs.
+//!
+//! The simulator flattens the lowered program into a netlist and drives it
+//! using the same protocol as the cocotb harness in `fud/harness`: the design
+//! is reset, transactions are started every `delay` cycles, inputs are only
+//! provided during their availability interval and are `'x` otherwise, and
+//! outputs are sampled during their availability interval.
+//!
+//! Reads of ports outside their availability interval are reported as
+//! warnings and flagged in the VCD trace if one is requested.
+mod data;
+mod netlist;
+mod prims;
+mod vcd;
+
+use crate::ast;
+use crate::errors::{Error, FilamentResult};
+use itertools::Itertools;
+use netlist::{Elaborate, Netlist, SigIdx, Signal, Src};
+use prims::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// A port on the interface of the simulated component
+struct PortInfo {
+    name: ast::Id,
+    sig: SigIdx,
+    width: u64,
+    start: u64,
+    end: u64,
+}
+
+/// The interface of the simulated component
+struct Interface {
+    /// The event of the component
+    event: ast::Id,
+    /// The interface port of the event if it is not phantom
+    go: Option<SigIdx>,
+    /// Delay between two transactions
+    delay: u64,
+    /// Number of states required to execute a transaction
+    states: u64,
+    inputs: Vec<PortInfo>,
+    outputs: Vec<PortInfo>,
+}
+
+impl Interface {
+    fn new(
+        sig: &ast::Signature,
+        states: &HashMap<ast::Id, u64>,
+        ports: &HashMap<ast::Id, SigIdx>,
+    ) -> FilamentResult<Self> {
+        let [eb] = sig.events.as_slice() else {
+            return Err(Error::misc(format!(
+                "simulator only supports components with exactly one event but `{}' has {}",
+                sig.name,
+                sig.events.len()
+            )));
+        };
+        let event = eb.event.inner();
+        let delay = eb.delay.concrete().ok_or_else(|| {
+            Error::malformed(format!(
+                "delay of event `{event}' is not concrete"
+            ))
+        })?;
+        let go = sig.get_interface(event).map(|id| ports[id.name.inner()]);
+
+        let info = |pd: &ast::Loc<ast::PortDef>| -> FilamentResult<PortInfo> {
+            let name = *pd.name().inner();
+            let err = || {
+                Error::malformed(format!(
+                    "availability of port `{name}' is not a concrete offset of `{event}'"
+                ))
+            };
+            let (ev, start, end) = pd.liveness().as_offset().ok_or_else(err)?;
+            if ev != *event {
+                return Err(err());
+            }
+            Ok(PortInfo {
+                name,
+                sig: ports[&name],
+                width: pd.bitwidth().inner().try_into()?,
+                start: (&start).try_into()?,
+                end: (&end).try_into()?,
+            })
+        };
+
+        Ok(Self {
+            event: *event,
+            go,
+            delay,
+            states: states[event],
+            inputs: sig.inputs().map(info).collect::<FilamentResult<_>>()?,
+            outputs: sig.outputs().map(info).collect::<FilamentResult<_>>()?,
+        })
+    }
+}
+
+/// Simulates a netlist
+struct Simulator {
+    net: Netlist,
+    /// Current value of each signal
+    values: Vec<Value>,
+    /// State of each primitive
+    prim_state: Vec<Value>,
+    /// Registers in each FSM
+    fsm_state: Vec<Vec<Value>>,
+    /// Signals driven by the testbench
+    inputs: Vec<SigIdx>,
+    /// Signals without any driver
+    undriven: Vec<SigIdx>,
+}
+
+impl Simulator {
+    fn new(net: Netlist, inputs: Vec<SigIdx>) -> Self {
+        let mut driven = vec![false; net.signals.len()];
+        let mut mark = |s: SigIdx| driven[s] = true;
+        inputs.iter().for_each(|s| mark(*s));
+        net.drivers.iter().for_each(|(s, _)| mark(*s));
+        net.fsms
+            .iter()
+            .for_each(|fsm| fsm.states.iter().for_each(|s| mark(*s)));
+        net.prims.iter().for_each(|p| mark(p.port(p.prim.output())));
+        let undriven = (0..net.signals.len()).filter(|s| !driven[*s]).collect();
+
+        Self {
+            values: vec![Value::X; net.signals.len()],
+            prim_state: vec![Value::X; net.prims.len()],
+            fsm_state: net
+                .fsms
+                .iter()
+                .map(|fsm| vec![Value::X; fsm.states.len() - 1])
+                .collect(),
+            net,
+            inputs,
+            undriven,
+        }
+    }
+
+    /// Reset all the stateful elements in the design
+    fn reset(&mut self) {
+        for (st, p) in self.prim_state.iter_mut().zip(&self.net.prims) {
+            *st = p.prim.reset();
+        }
+        for regs in &mut self.fsm_state {
+            regs.iter_mut().for_each(|r| *r = Value::Bits(0));
+        }
+    }
+
+    fn set(&mut self, sig: SigIdx, val: Value) -> bool {
+        let val = val.mask(self.net.signals[sig].width);
+        let changed = self.values[sig] != val;
+        self.values[sig] = val;
+        changed
+    }
+
+    /// Compute the values of all the signals in the current cycle given the
+    /// values provided by the testbench.
+    fn eval(&mut self, tb: &HashMap<SigIdx, Value>) -> FilamentResult<()> {
+        self.values.iter_mut().for_each(|v| *v = Value::X);
+        for sig in &self.inputs {
+            self.values[*sig] = tb.get(sig).copied().unwrap_or(Value::X);
+        }
+        for sig in self.undriven.clone() {
+            self.set(sig, Value::Bits(0));
+        }
+        // Outputs of stateful elements are fixed for the cycle
+        for fsm in 0..self.net.fsms.len() {
+            for (st, reg) in self.fsm_state[fsm].clone().into_iter().enumerate()
+            {
+                self.set(self.net.fsms[fsm].states[st + 1], reg);
+            }
+        }
+        for idx in 0..self.net.prims.len() {
+            let p = &self.net.prims[idx];
+            if p.prim.is_stateful() {
+                self.set(p.port(p.prim.output()), self.prim_state[idx]);
+            }
+        }
+
+        // Propagate combinational values till a fixed point
+        let mut last = None;
+        for _ in 0..=self.net.signals.len() {
+            let mut changed = false;
+            for fsm in 0..self.net.fsms.len() {
+                let fsm = &self.net.fsms[fsm];
+                let (go, st) = (fsm.go, fsm.states[0]);
+                changed |= self.set(st, self.values[go]);
+            }
+            for idx in 0..self.net.prims.len() {
+                let p = &self.net.prims[idx];
+                if !p.prim.is_stateful() {
+                    let out = p.port(p.prim.output());
+                    let val = p.prim.comb(|port| self.values[p.port(port)]);
+                    changed |= self.set(out, val);
+                }
+            }
+            for idx in 0..self.net.drivers.len() {
+                let (dst, drivers) = &self.net.drivers[idx];
+                let (dst, val) = (*dst, self.drive(drivers));
+                if self.set(dst, val) {
+                    changed = true;
+                    last = Some(dst);
+                }
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+        let sig = last.map_or("<unknown>", |s| &self.net.signals[s].name);
+        Err(Error::misc(format!(
+            "simulation did not converge: signal `{sig}' is part of a combinational loop"
+        )))
+    }
+
+    /// Is the guard active? Returns `None` if the guard is unknown.
+    fn active(&self, guard: &Option<netlist::Guard>) -> Option<bool> {
+        guard.as_ref().map_or(Some(true), |g| self.holds(g))
+    }
+
+    /// Evaluate a guard. An unknown operand only makes the result unknown if
+    /// the other operand does not decide it.
+    fn holds(&self, guard: &netlist::Guard) -> Option<bool> {
+        match guard {
+            netlist::Guard::Sig(s) => self.values[*s].is_true(),
+            netlist::Guard::Or(g1, g2) => {
+                match (self.holds(g1), self.holds(g2)) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }
+            }
+            netlist::Guard::And(g1, g2) => {
+                match (self.holds(g1), self.holds(g2)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            }
+            netlist::Guard::Not(g) => self.holds(g).map(|v| !v),
+        }
+    }
+
+    /// Value produced by the first active assignment. If no assignment is
+    /// active, the signal is driven to zero.
+    fn drive(&self, drivers: &[netlist::Driver]) -> Value {
+        for netlist::Driver { guard, src } in drivers {
+            match self.active(guard) {
+                Some(true) => {
+                    return match src {
+                        Src::Sig(s) => self.values[*s],
+                        Src::Const(c) => Value::Bits(*c),
+                    }
+                }
+                Some(false) => (),
+                None => return Value::X,
+            }
+        }
+        Value::Bits(0)
+    }
+
+    /// Ports read by an active assignment while they are not available even
+    /// though the destination of the assignment is.
+    fn violations(&self, live: impl Fn(&Signal) -> bool) -> Vec<SigIdx> {
+        let signals = &self.net.signals;
+        self.net
+            .drivers
+            .iter()
+            .filter(|(dst, _)| live(&signals[*dst]))
+            .filter_map(|(_, drivers)| {
+                let src = drivers
+                    .iter()
+                    .map(|d| (self.active(&d.guard), &d.src))
+                    .find(|(active, _)| *active != Some(false))
+                    .and_then(|(active, src)| active.map(|_| src));
+                match src {
+                    Some(Src::Sig(s)) if !live(&signals[*s]) => Some(*s),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Update the stateful elements at the end of the cycle
+    fn clock(&mut self) {
+        for (idx, fsm) in self.net.fsms.iter().enumerate() {
+            let regs = &mut self.fsm_state[idx];
+            for (st, reg) in regs.iter_mut().enumerate() {
+                *reg = self.values[fsm.states[st]];
+            }
+        }
+        for (idx, p) in self.net.prims.iter().enumerate() {
+            if p.prim.is_stateful() {
+                self.prim_state[idx] =
+                    p.prim.next(self.prim_state[idx], |port| {
+                        self.values[p.port(port)]
+                    });
+            }
+        }
+    }
+}
+
+/// Simulate the top-level component of a lowered namespace using the inputs
+/// in `data` and print the outputs in the same format as the cocotb harness.
+/// If `vcd` is provided, a waveform of the simulation is written to it.
+pub fn simulate(
+    ns: &ast::Namespace,
+    max_states: &HashMap<ast::Id, HashMap<ast::Id, u64>>,
+    data: &Path,
+    vcd: Option<&Path>,
+) -> FilamentResult<()> {
+    let Some(idx) = ns.main_idx() else {
+        return Err(Error::misc(format!(
+            "top-level component `{}' not found",
+            ns.toplevel
+        )));
+    };
+    let main = &ns.components[idx];
+    let src = std::fs::read_to_string(data)
+        .map_err(|e| Error::invalid_file(format!("{}: {e}", data.display())))?;
+    let data: HashMap<_, _> = data::parse_data(&src)?.into_iter().collect();
+
+    let mut elab = Elaborate::new(ns);
+    let ports = elab.net.add_ports(main.sig.name.as_ref(), &main.sig)?;
+    let iface = Interface::new(&main.sig, &max_states[&main.sig.name], &ports)?;
+    // The top-level event occurs at the start of each transaction
+    let events = vec![HashMap::from([(iface.event, 0)])];
+    elab.net.add_liveness(&main.sig, &ports, &events)?;
+    elab.component(main, main.sig.name.as_ref(), &ports, &events)?;
+
+    // Validate the data
+    let txns = iface
+        .inputs
+        .iter()
+        .map(|inp| {
+            let vals = data.get(inp.name.as_ref()).ok_or_else(|| {
+                Error::misc(format!(
+                    "no data provided for input `{}'",
+                    inp.name
+                ))
+            })?;
+            if let Some(v) = vals
+                .iter()
+                .find(|v| Value::Bits(**v).mask(inp.width) != Value::Bits(**v))
+            {
+                return Err(Error::misc(format!(
+                    "value {v} is not representable in {} bits for input `{}'",
+                    inp.width, inp.name
+                )));
+            }
+            Ok(vals.len())
+        })
+        .collect::<FilamentResult<Vec<_>>>()?;
+    if !txns.iter().all_equal() {
+        return Err(Error::misc(
+            "mismatched number of values for inputs".to_string(),
+        ));
+    }
+    let txns = txns
+        .first()
+        .copied()
+        .or_else(|| data.values().next().map(|v| v.len()))
+        .unwrap_or(0) as u64;
+
+    let tb_signals = iface
+        .go
+        .iter()
+        .copied()
+        .chain(iface.inputs.iter().map(|i| i.sig))
+        .collect_vec();
+    let mut sim = Simulator::new(elab.net, tb_signals);
+    let mut trace = vcd.map(|_| vcd::Vcd::new(&sim.net, &iface.event));
+    sim.reset();
+    // The harness waits for a cycle after reset before starting transactions.
+    // The interface port is held low while the inputs are left undefined.
+    let idle = iface.go.iter().map(|go| (*go, Value::Bits(0))).collect();
+    sim.eval(&idle)?;
+    sim.clock();
+
+    let cycles = if txns == 0 {
+        0
+    } else {
+        (txns * iface.delay).max((txns - 1) * iface.delay + iface.states)
+    };
+    let mut outputs = iface
+        .outputs
+        .iter()
+        .map(|_| vec![vec![]; txns as usize])
+        .collect_vec();
+    // Cycles in which a port was read outside its availability interval
+    let mut reads: BTreeMap<SigIdx, Vec<u64>> = BTreeMap::new();
+    for cycle in 0..cycles {
+        // Transactions active in this cycle and their current state
+        let active = (0..txns)
+            .filter_map(|txn| {
+                let st = cycle.checked_sub(txn * iface.delay)?;
+                (st < iface.states).then_some((txn as usize, st))
+            })
+            .collect_vec();
+
+        let mut tb = HashMap::new();
+        if let Some(go) = iface.go {
+            let start = active.iter().any(|(_, st)| *st == 0);
+            tb.insert(go, Value::Bits(start as u64));
+        }
+        for inp in &iface.inputs {
+            let mut writes = active
+                .iter()
+                .filter(|(_, st)| inp.start <= *st && *st < inp.end);
+            if let Some((txn, _)) = writes.next() {
+                if writes.next().is_some() {
+                    return Err(Error::misc(format!(
+                        "multiple transactions write to input `{}' in cycle {cycle}",
+                        inp.name
+                    )));
+                }
+                let v = data[inp.name.as_ref()][*txn];
+                tb.insert(inp.sig, Value::Bits(v));
+            }
+        }
+
+        sim.eval(&tb)?;
+        // A signal is live if it is live for any of the started transactions
+        let live = |sig: &Signal| {
+            (0..txns)
+                .filter_map(|txn| cycle.checked_sub(txn * iface.delay))
+                .any(|c| sig.is_live(c))
+        };
+        let violations = sim.violations(live);
+        for sig in &violations {
+            reads.entry(*sig).or_default().push(cycle);
+        }
+        if let Some(trace) = &mut trace {
+            trace.sample(&sim.values, &violations);
+        }
+        for (out, vals) in iface.outputs.iter().zip(outputs.iter_mut()) {
+            for (txn, st) in &active {
+                if out.start <= *st && *st < out.end {
+                    vals[*txn].push(sim.values[out.sig].format(out.width));
+                }
+            }
+        }
+        sim.clock();
+    }
+
+    for (sig, cycles) in reads {
+        let sig = &sim.net.signals[sig];
+        log::warn!(
+            "`{}' read outside its availability {} in cycle(s) {}",
+            sig.name,
+            sig.availability(&iface.event).unwrap_or_default(),
+            cycles.iter().join(", ")
+        );
+    }
+    if let (Some(trace), Some(path)) = (trace, vcd) {
+        trace.write(path)?;
+    }
+
+    let outputs = iface
+        .outputs
+        .iter()
+        .map(|o| o.name.to_string())
+        .zip(outputs)
+        .collect_vec();
+    println!("{}", data::format_outputs(&outputs, cycles));
+    Ok(())
+}
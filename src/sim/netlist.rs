@@ -0,0 +1,443 @@
+use super::prims::Prim;
+use crate::ast;
+use crate::errors::{Error, FilamentResult};
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// Index of a signal in the netlist
+pub type SigIdx = usize;
+
+/// Offsets of the events of a component relative to the event of the
+/// top-level component for each of its activations. A component has multiple
+/// activations when it is invoked multiple times.
+pub type EventOffsets = Vec<HashMap<ast::Id, u64>>;
+
+/// Offset of a time expression in an activation
+fn offset(
+    time: &ast::Time,
+    act: &HashMap<ast::Id, u64>,
+) -> FilamentResult<u64> {
+    let offset: u64 = time.offset().try_into()?;
+    Ok(act[&time.event()] + offset)
+}
+
+/// Activations of an instance with the signature `sig` given the events
+/// provided by each invocation and the activations of the parent.
+fn activations(
+    sig: &ast::Signature,
+    invokes: &[&Vec<ast::Loc<ast::Time>>],
+    parent: &EventOffsets,
+) -> FilamentResult<EventOffsets> {
+    let mut acts = vec![];
+    for inv in invokes {
+        let binding = sig.event_binding(inv.iter().map(|t| t.inner().clone()));
+        for act in parent {
+            acts.push(
+                sig.events()
+                    .map(|ev| Ok((*ev.inner(), offset(binding.get(&ev), act)?)))
+                    .collect::<FilamentResult<_>>()?,
+            );
+        }
+    }
+    Ok(acts)
+}
+
+/// A signal in the flattened design
+pub struct Signal {
+    /// Hierarchical name of the signal
+    pub name: String,
+    pub width: u64,
+    /// Availability intervals of the port relative to the event of the
+    /// top-level component. Signals that do not represent ports do not have
+    /// an availability.
+    pub live: Option<Vec<(u64, u64)>>,
+}
+
+impl Signal {
+    /// Is the signal available `cycle` cycles after the top-level event?
+    pub fn is_live(&self, cycle: u64) -> bool {
+        let Some(live) = &self.live else {
+            return true;
+        };
+        live.iter().any(|(s, e)| *s <= cycle && cycle < *e)
+    }
+
+    /// Availability of the port in terms of the top-level `event`
+    pub fn availability(&self, event: &ast::Id) -> Option<String> {
+        let time =
+            |o: u64| ast::Time::new(*event, ast::Expr::concrete(o)).to_string();
+        self.live.as_ref().map(|live| {
+            live.iter()
+                .map(|(s, e)| format!("@[{},{}]", time(*s), time(*e)))
+                .collect()
+        })
+    }
+}
+
+/// Source of a guarded assignment
+pub enum Src {
+    Sig(SigIdx),
+    Const(u64),
+}
+
+/// A guard over 1-bit signals
+pub enum Guard {
+    Sig(SigIdx),
+    Or(Box<Guard>, Box<Guard>),
+    And(Box<Guard>, Box<Guard>),
+    Not(Box<Guard>),
+}
+
+/// A guarded assignment to a signal. An assignment without a guard is always
+/// active.
+pub struct Driver {
+    pub guard: Option<Guard>,
+    pub src: Src,
+}
+
+/// A shift-register FSM. State `_0` is the trigger and every other state is
+/// the previous one delayed by a cycle.
+pub struct Fsm {
+    pub go: SigIdx,
+    pub states: Vec<SigIdx>,
+}
+
+/// An instance of a primitive
+pub struct PrimInst {
+    pub prim: Prim,
+    pub ports: HashMap<ast::Id, SigIdx>,
+}
+
+impl PrimInst {
+    /// Signal connected to the given port
+    pub fn port(&self, port: &str) -> SigIdx {
+        self.ports[&ast::Id::from(port)]
+    }
+}
+
+#[derive(Default)]
+/// A flattened design containing all the signals, assignments, FSMs, and
+/// primitives instantiated by a component.
+pub struct Netlist {
+    pub signals: Vec<Signal>,
+    /// Assignments to each signal in the order they were defined
+    pub drivers: Vec<(SigIdx, Vec<Driver>)>,
+    pub fsms: Vec<Fsm>,
+    pub prims: Vec<PrimInst>,
+}
+
+impl Netlist {
+    fn add_signal(
+        &mut self,
+        name: String,
+        width: u64,
+    ) -> FilamentResult<SigIdx> {
+        // Values are represented using `u64`
+        if width > 64 {
+            return Err(Error::misc(format!(
+                "simulator only supports signals up to 64 bits but `{name}' has {width} bits"
+            )));
+        }
+        self.signals.push(Signal {
+            name,
+            width,
+            live: None,
+        });
+        Ok(self.signals.len() - 1)
+    }
+
+    /// Add signals for all the ports in the signature except the unannotated
+    /// ones since they represent the clock and reset signals.
+    pub fn add_ports(
+        &mut self,
+        prefix: &str,
+        sig: &ast::Signature,
+    ) -> FilamentResult<HashMap<ast::Id, SigIdx>> {
+        let ports = sig
+            .ports()
+            .iter()
+            .map(|pd| {
+                let w = pd.bitwidth().inner().try_into().map_err(|_| {
+                    Error::malformed(format!(
+                        "width of port `{}' is not concrete",
+                        pd.name()
+                    ))
+                })?;
+                Ok((*pd.name().inner(), w))
+            })
+            .chain(
+                sig.interface_signals
+                    .iter()
+                    .map(|id| Ok((*id.name.inner(), 1))),
+            )
+            .collect::<FilamentResult<Vec<_>>>()?;
+        ports
+            .into_iter()
+            .map(|(name, w)| {
+                Ok((name, self.add_signal(format!("{prefix}.{name}"), w)?))
+            })
+            .collect()
+    }
+
+    /// Record the availability of the ports in the signature given the offsets
+    /// of its events.
+    pub fn add_liveness(
+        &mut self,
+        sig: &ast::Signature,
+        ports: &HashMap<ast::Id, SigIdx>,
+        events: &EventOffsets,
+    ) -> FilamentResult<()> {
+        for pd in sig.ports() {
+            let range = pd.liveness();
+            let live = events
+                .iter()
+                .map(|act| {
+                    Ok((offset(&range.start, act)?, offset(&range.end, act)?))
+                })
+                .collect::<FilamentResult<Vec<_>>>()?;
+            self.signals[ports[pd.name().inner()]]
+                .live
+                .get_or_insert_with(Vec::new)
+                .extend(live);
+        }
+        Ok(())
+    }
+
+    fn add_driver(&mut self, dst: SigIdx, driver: Driver) {
+        if let Some((_, drivers)) =
+            self.drivers.iter_mut().find(|(d, _)| *d == dst)
+        {
+            drivers.push(driver);
+        } else {
+            self.drivers.push((dst, vec![driver]));
+        }
+    }
+}
+
+/// Flattens a lowered namespace into a [Netlist]
+pub struct Elaborate<'a> {
+    comps: HashMap<ast::Id, &'a ast::Component>,
+    externs: HashMap<ast::Id, &'a ast::Signature>,
+    pub net: Netlist,
+}
+
+impl<'a> Elaborate<'a> {
+    pub fn new(ns: &'a ast::Namespace) -> Self {
+        Self {
+            comps: ns
+                .components
+                .iter()
+                .map(|c| (*c.sig.name.inner(), c))
+                .collect(),
+            externs: ns.externals().collect(),
+            net: Netlist::default(),
+        }
+    }
+
+    /// Elaborate the component whose ports are represented by `this` and
+    /// whose events occur at the given offsets.
+    pub fn component(
+        &mut self,
+        comp: &ast::Component,
+        prefix: &str,
+        this: &HashMap<ast::Id, SigIdx>,
+        events: &EventOffsets,
+    ) -> FilamentResult<()> {
+        let mut ctx = CompCtx {
+            this,
+            fsms: HashMap::new(),
+            insts: HashMap::new(),
+            invokes: HashMap::new(),
+        };
+
+        // Define the states of each FSM
+        for fsm in &comp.fsms {
+            let states = (0..fsm.states)
+                .map(|st| {
+                    self.net
+                        .add_signal(format!("{prefix}.{}._{st}", fsm.name), 1)
+                })
+                .collect::<FilamentResult<Vec<_>>>()?;
+            ctx.fsms.insert(fsm.name, states);
+        }
+
+        let mut cons = vec![];
+        let mut insts = vec![];
+        let mut invokes = vec![];
+        for cmd in &comp.body {
+            match cmd {
+                ast::Command::Instance(inst) => insts.push(inst),
+                ast::Command::Invoke(inv) => {
+                    assert!(
+                        inv.ports.is_none(),
+                        "Cannot simulate high-level invoke statements"
+                    );
+                    ctx.invokes
+                        .insert(*inv.name.inner(), *inv.instance.inner());
+                    invokes.push(inv);
+                }
+                ast::Command::Connect(con) => cons.push(con),
+                ast::Command::ForLoop(_)
+                | ast::Command::If(_)
+                | ast::Command::Bundle(_)
+                | ast::Command::Let(_)
+                | ast::Command::Fact(_) => {
+                    unreachable!("Program should be lowered before simulation")
+                }
+            }
+        }
+
+        // Elaborate each instance using the invocations that use it
+        for inst in insts {
+            let name = *inst.name.inner();
+            let invs = invokes
+                .iter()
+                .filter(|inv| *inv.instance.inner() == name)
+                .map(|inv| &inv.abstract_vars)
+                .collect_vec();
+            let ports = self.instance(inst, prefix, &invs, events)?;
+            ctx.insts.insert(name, ports);
+        }
+
+        for fsm in &comp.fsms {
+            let (go, g) = ctx.port(&fsm.trigger);
+            assert!(g.is_none(), "Trigger port implies guard");
+            let Src::Sig(go) = go else {
+                unreachable!("FSM trigger is a constant")
+            };
+            let states = ctx.fsms[&fsm.name].clone();
+            self.net.fsms.push(Fsm { go, states });
+        }
+
+        for con in cons {
+            let ast::Connect {
+                dst, src, guard, ..
+            } = con;
+            let (Src::Sig(dst), None) = ctx.port(dst) else {
+                unreachable!("Invalid destination `{dst}'")
+            };
+            let (src, g) = ctx.port(src);
+            let guard = match (guard, g) {
+                (None, g) => g.map(Guard::Sig),
+                (Some(g), None) => Some(ctx.guard(g)),
+                (Some(_), Some(_)) => {
+                    panic!("Source implies guard and is guarded")
+                }
+            };
+            self.net.add_driver(dst, Driver { guard, src });
+        }
+        Ok(())
+    }
+
+    /// Define the signals for an instance and elaborate it. `invokes` contains
+    /// the events provided by each invocation of the instance.
+    fn instance(
+        &mut self,
+        inst: &ast::Instance,
+        prefix: &str,
+        invokes: &[&Vec<ast::Loc<ast::Time>>],
+        events: &EventOffsets,
+    ) -> FilamentResult<HashMap<ast::Id, SigIdx>> {
+        let ast::Instance {
+            name,
+            component,
+            bindings,
+        } = inst;
+        let prefix = format!("{prefix}.{name}");
+        if let Some(comp) = self.comps.get(component.inner()).copied() {
+            let events = activations(&comp.sig, invokes, events)?;
+            let ports = self.net.add_ports(&prefix, &comp.sig)?;
+            self.net.add_liveness(&comp.sig, &ports, &events)?;
+            self.component(comp, &prefix, &ports, &events)?;
+            Ok(ports)
+        } else {
+            let sig = self.externs[component.inner()];
+            let args = bindings.iter().map(|b| b.inner().clone()).collect();
+            let bind = sig.param_binding(args);
+            let params = sig
+                .params()
+                .map(|p| bind.get(p.inner()).try_into())
+                .collect::<FilamentResult<Vec<u64>>>()?;
+            let prim = Prim::new(component.inner(), &params)?;
+            let sig = sig.clone().resolve_exprs(
+                params.iter().map(|v| ast::Expr::concrete(*v)).collect(),
+            );
+            let events = activations(&sig, invokes, events)?;
+            let ports = self.net.add_ports(&prefix, &sig)?;
+            self.net.add_liveness(&sig, &ports, &events)?;
+            self.net.prims.push(PrimInst {
+                prim,
+                ports: ports.clone(),
+            });
+            Ok(ports)
+        }
+    }
+}
+
+/// Signals defined in the component being elaborated
+struct CompCtx<'a> {
+    this: &'a HashMap<ast::Id, SigIdx>,
+    fsms: HashMap<ast::Id, Vec<SigIdx>>,
+    insts: HashMap<ast::Id, HashMap<ast::Id, SigIdx>>,
+    invokes: HashMap<ast::Id, ast::Id>,
+}
+
+impl CompCtx<'_> {
+    /// Signal for a port on an FSM or an invocation
+    fn inv_port(&self, invoke: &ast::Id, name: &ast::Id) -> (SigIdx, bool) {
+        if let Some(states) = self.fsms.get(invoke) {
+            let st = ast::Fsm::state(name).unwrap_or_else(|_| {
+                unreachable!("Invalid FSM port `{invoke}.{name}'")
+            });
+            (states[st as usize], true)
+        } else {
+            let inst = self
+                .invokes
+                .get(invoke)
+                .unwrap_or_else(|| panic!("Unknown invocation: {invoke}"));
+            (self.insts[inst][name], false)
+        }
+    }
+
+    /// Compile a port into a source. If the port is a state of an FSM, returns
+    /// the guard implied by it.
+    fn port(&self, port: &ast::Port) -> (Src, Option<SigIdx>) {
+        match port {
+            ast::Port::This(p) => (Src::Sig(self.this[p.inner()]), None),
+            ast::Port::InvPort { invoke, name } => {
+                match self.inv_port(invoke, name) {
+                    (st, true) => (Src::Const(1), Some(st)),
+                    (sig, false) => (Src::Sig(sig), None),
+                }
+            }
+            ast::Port::Constant(c) => (Src::Const(*c), None),
+            ast::Port::Bundle { .. } | ast::Port::InvBundle { .. } => {
+                unreachable!("Bundles should be compiled away")
+            }
+        }
+    }
+
+    fn guard(&self, guard: &ast::Guard) -> Guard {
+        match guard {
+            ast::Guard::Or(g1, g2, _) => {
+                Guard::Or(Box::new(self.guard(g1)), Box::new(self.guard(g2)))
+            }
+            ast::Guard::And(g1, g2, _) => {
+                Guard::And(Box::new(self.guard(g1)), Box::new(self.guard(g2)))
+            }
+            ast::Guard::Not(g, _) => Guard::Not(Box::new(self.guard(g))),
+            ast::Guard::Port(p) => match p.inner() {
+                ast::Port::This(p) => Guard::Sig(self.this[p.inner()]),
+                ast::Port::InvPort { invoke, name } => {
+                    Guard::Sig(self.inv_port(invoke, name).0)
+                }
+                ast::Port::Constant(_) => {
+                    unreachable!("Constants cannot be in guards")
+                }
+                ast::Port::Bundle { .. } | ast::Port::InvBundle { .. } => {
+                    unreachable!("Bundles should be compiled away")
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use crate::ast;
+use crate::errors::{Error, FilamentResult};
+use std::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// A value on a signal. The simulator does not track individual unknown bits:
+/// a value is either fully known or fully unknown.
+pub enum Value {
+    X,
+    Bits(u64),
+}
+
+impl Value {
+    /// Truncate the value to `width` bits
+    pub fn mask(self, width: u64) -> Self {
+        match self {
+            Value::X => Value::X,
+            Value::Bits(v) => Value::Bits(v & mask(width)),
+        }
+    }
+
+    /// Is this value known to be true?
+    pub fn is_true(self) -> Option<bool> {
+        match self {
+            Value::X => None,
+            Value::Bits(v) => Some(v != 0),
+        }
+    }
+
+    /// Format the value in the same way as cocotb: known values are printed
+    /// as integers and unknown values as a string of `x` characters.
+    pub fn format(self, width: u64) -> String {
+        match self {
+            Value::X => format!("\"{}\"", "x".repeat(width as usize)),
+            Value::Bits(v) => v.to_string(),
+        }
+    }
+
+    fn map(self, f: impl FnOnce(u64) -> u64) -> Self {
+        match self {
+            Value::X => Value::X,
+            Value::Bits(v) => Value::Bits(f(v)),
+        }
+    }
+
+    fn zip(self, other: Self, f: impl FnOnce(u64, u64) -> u64) -> Self {
+        match (self, other) {
+            (Value::Bits(l), Value::Bits(r)) => Value::Bits(f(l, r)),
+            _ => Value::X,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::X => write!(f, "'x"),
+            Value::Bits(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// Mask with the lower `width` bits set
+fn mask(width: u64) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// Interpret the lower `width` bits of `v` as a two's complement number
+fn signed(v: u64, width: u64) -> i64 {
+    let shift = 64 - width.min(64);
+    ((v << shift) as i64) >> shift
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+/// Behavioral models of the primitives defined in `comb.fil`, `signed.fil`,
+/// and `state.fil`
+pub enum Prim {
+    Const { value: u64 },
+    NegConst { value: u64 },
+    Add,
+    Sub,
+    MultComb,
+    And,
+    Or,
+    Xor,
+    Not,
+    Eq,
+    Neq,
+    Gt,
+    Lt,
+    Lte,
+    Gte,
+    SGt { width: u64 },
+    SLt { width: u64 },
+    SLte { width: u64 },
+    SGte { width: u64 },
+    SignExtend { in_width: u64 },
+    ZeroExtend,
+    Extend { in_width: u64 },
+    Concat { right: u64 },
+    Select { pos: u64 },
+    Slice { lsb: u64 },
+    ReduceAnd { width: u64 },
+    ReduceOr,
+    ShiftLeft,
+    ShiftRight,
+    ArithShiftRight { width: u64 },
+    Mux,
+    Register,
+    Delay,
+    Prev { safe: bool },
+    ContPrev { safe: bool },
+}
+
+impl Prim {
+    /// Construct the model for the primitive `name` with the given parameters
+    pub fn new(name: &ast::Id, params: &[u64]) -> FilamentResult<Self> {
+        let p = |i: usize| params[i];
+        let prim = match name.as_ref() {
+            "Const" => Prim::Const { value: p(1) },
+            "NegConst" => Prim::NegConst { value: p(1) },
+            // Two's complement addition and subtraction do not depend on the
+            // signedness of the inputs
+            "Add" | "SAdd" => Prim::Add,
+            "Sub" | "SSub" => Prim::Sub,
+            "MultComb" => Prim::MultComb,
+            "And" => Prim::And,
+            "Or" => Prim::Or,
+            "Xor" => Prim::Xor,
+            "Not" => Prim::Not,
+            "Eq" => Prim::Eq,
+            "Neq" => Prim::Neq,
+            "Gt" => Prim::Gt,
+            "Lt" => Prim::Lt,
+            "Lte" => Prim::Lte,
+            "Gte" => Prim::Gte,
+            "SGt" => Prim::SGt { width: p(0) },
+            "SLt" => Prim::SLt { width: p(0) },
+            "SLte" => Prim::SLte { width: p(0) },
+            "SGte" => Prim::SGte { width: p(0) },
+            "SignExtend" => Prim::SignExtend { in_width: p(0) },
+            "ZeroExtend" => Prim::ZeroExtend,
+            "Extend" => Prim::Extend { in_width: p(0) },
+            "Concat" => Prim::Concat { right: p(1) },
+            "Select" => Prim::Select { pos: p(1) },
+            "Slice" => Prim::Slice { lsb: p(2) },
+            "ReduceAnd" => Prim::ReduceAnd { width: p(0) },
+            "ReduceOr" => Prim::ReduceOr,
+            "ShiftLeft" => Prim::ShiftLeft,
+            "ShiftRight" => Prim::ShiftRight,
+            "ArithShiftRight" => Prim::ArithShiftRight { width: p(0) },
+            "Mux" => Prim::Mux,
+            "Register" => Prim::Register,
+            "Delay" => Prim::Delay,
+            "Prev" => Prim::Prev { safe: p(1) != 0 },
+            "ContPrev" => Prim::ContPrev { safe: p(1) != 0 },
+            _ => {
+                return Err(Error::misc(format!(
+                    "no behavioral model for primitive `{name}'"
+                )))
+            }
+        };
+        Ok(prim)
+    }
+
+    /// Name of the output port of the primitive
+    pub fn output(&self) -> &'static str {
+        match self {
+            Prim::Prev { .. } | Prim::ContPrev { .. } => "prev",
+            _ => "out",
+        }
+    }
+
+    /// Is the primitive stateful?
+    pub fn is_stateful(&self) -> bool {
+        matches!(
+            self,
+            Prim::Register
+                | Prim::Delay
+                | Prim::Prev { .. }
+                | Prim::ContPrev { .. }
+        )
+    }
+
+    /// Value of the state after reset
+    pub fn reset(&self) -> Value {
+        match self {
+            Prim::Prev { safe: false } | Prim::ContPrev { safe: false } => {
+                Value::X
+            }
+            _ => Value::Bits(0),
+        }
+    }
+
+    /// Compute the next state of a stateful primitive at the clock edge
+    pub fn next(&self, state: Value, get: impl Fn(&str) -> Value) -> Value {
+        match self {
+            Prim::Register | Prim::Prev { .. } => {
+                match get("write_en").is_true() {
+                    Some(true) => get("in"),
+                    Some(false) => state,
+                    None => Value::X,
+                }
+            }
+            Prim::Delay | Prim::ContPrev { .. } => get("in"),
+            _ => unreachable!("primitive is not stateful"),
+        }
+    }
+
+    /// Compute the output of a combinational primitive
+    pub fn comb(&self, get: impl Fn(&str) -> Value) -> Value {
+        let bin =
+            |f: &dyn Fn(u64, u64) -> u64| get("left").zip(get("right"), f);
+        let un = |f: &dyn Fn(u64) -> u64| get("in").map(f);
+        match *self {
+            Prim::Const { value } => Value::Bits(value),
+            Prim::NegConst { value } => Value::Bits(value.wrapping_neg()),
+            Prim::Add => bin(&u64::wrapping_add),
+            Prim::Sub => bin(&u64::wrapping_sub),
+            Prim::MultComb => bin(&u64::wrapping_mul),
+            Prim::And => bin(&|l, r| l & r),
+            Prim::Or => bin(&|l, r| l | r),
+            Prim::Xor => bin(&|l, r| l ^ r),
+            Prim::Not => un(&|v| !v),
+            Prim::Eq => bin(&|l, r| (l == r) as u64),
+            Prim::Neq => bin(&|l, r| (l != r) as u64),
+            Prim::Gt => bin(&|l, r| (l > r) as u64),
+            Prim::Lt => bin(&|l, r| (l < r) as u64),
+            Prim::Lte => bin(&|l, r| (l <= r) as u64),
+            Prim::Gte => bin(&|l, r| (l >= r) as u64),
+            Prim::SGt { width } => {
+                bin(&|l, r| (signed(l, width) > signed(r, width)) as u64)
+            }
+            Prim::SLt { width } => {
+                bin(&|l, r| (signed(l, width) < signed(r, width)) as u64)
+            }
+            Prim::SLte { width } => {
+                bin(&|l, r| (signed(l, width) <= signed(r, width)) as u64)
+            }
+            Prim::SGte { width } => {
+                bin(&|l, r| (signed(l, width) >= signed(r, width)) as u64)
+            }
+            Prim::SignExtend { in_width } => un(&|v| {
+                if v >> (in_width - 1) & 1 == 1 {
+                    v | !mask(in_width)
+                } else {
+                    v
+                }
+            }),
+            Prim::ZeroExtend => un(&|v| v),
+            Prim::Extend { in_width } => un(&|v| {
+                // Replicate the input to fill the output
+                (0..64 / in_width).fold(0, |acc, i| acc | v << (i * in_width))
+            }),
+            Prim::Concat { right } => bin(&|l, r| {
+                if right >= 64 {
+                    r
+                } else {
+                    l << right | r
+                }
+            }),
+            Prim::Select { pos } => un(&|v| v >> pos & 1),
+            Prim::Slice { lsb } => un(&|v| v >> lsb),
+            Prim::ReduceAnd { width } => {
+                un(&|v| (v & mask(width) == mask(width)) as u64)
+            }
+            Prim::ReduceOr => un(&|v| (v != 0) as u64),
+            Prim::ShiftLeft => get("in")
+                .zip(get("shift"), |v, s| v.checked_shl(s as u32).unwrap_or(0)),
+            Prim::ShiftRight => get("in")
+                .zip(get("shift"), |v, s| v.checked_shr(s as u32).unwrap_or(0)),
+            Prim::ArithShiftRight { width } => {
+                get("in").zip(get("shift"), |v, s| {
+                    // Sign extend to 64 bits before shifting
+                    (signed(v, width) >> s.min(63)) as u64
+                })
+            }
+            Prim::Mux => match get("sel").is_true() {
+                Some(true) => get("in0"),
+                Some(false) => get("in1"),
+                None => Value::X,
+            },
+            Prim::Register
+            | Prim::Delay
+            | Prim::Prev { .. }
+            | Prim::ContPrev { .. } => {
+                unreachable!("primitive is not combinational")
+            }
+        }
+    }
+}
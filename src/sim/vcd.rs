@@ -0,0 +1,195 @@
+use super::netlist::{Netlist, SigIdx};
+use super::prims::Value;
+use crate::ast;
+use crate::errors::{Error, FilamentResult};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Number of time units in a clock cycle
+const PERIOD: u64 = 10;
+
+/// A variable in the VCD file
+struct Var {
+    /// Scopes containing the variable
+    scope: Vec<String>,
+    /// Name of the variable
+    name: String,
+    width: u64,
+}
+
+/// Records the values of the signals in a netlist and writes them out as a
+/// VCD file. Signals that represent Filament ports are labelled with their
+/// availability interval relative to the top-level event and reads outside
+/// that interval are flagged using an additional `<port>!violation` signal.
+pub struct Vcd {
+    vars: Vec<Var>,
+    /// Variable for the violations of each signal
+    violations: HashMap<SigIdx, usize>,
+    /// Last value dumped for each variable
+    last: Vec<Option<Value>>,
+    /// Value changes in the trace
+    body: String,
+    /// Number of cycles recorded
+    cycles: u64,
+}
+
+impl Vcd {
+    pub fn new(net: &Netlist, event: &ast::Id) -> Self {
+        let mut vars = net
+            .signals
+            .iter()
+            .map(|sig| {
+                let mut path =
+                    sig.name.split('.').map(String::from).collect_vec();
+                let mut name = path.pop().unwrap();
+                if let Some(live) = sig.availability(event) {
+                    name.push_str(&live);
+                }
+                Var {
+                    scope: path,
+                    name,
+                    width: sig.width,
+                }
+            })
+            .collect_vec();
+        vars.push(Var {
+            scope: vec![],
+            name: "clk".to_string(),
+            width: 1,
+        });
+        let last = vec![None; vars.len()];
+        Self {
+            vars,
+            violations: HashMap::new(),
+            last,
+            body: String::new(),
+            cycles: 0,
+        }
+    }
+
+    /// Identifier code of a variable
+    fn code(mut idx: usize) -> String {
+        let mut code = String::new();
+        loop {
+            code.push((b'!' + (idx % 94) as u8) as char);
+            idx /= 94;
+            if idx == 0 {
+                return code;
+            }
+            idx -= 1;
+        }
+    }
+
+    fn change(&mut self, var: usize, val: Value) {
+        if self.last[var] == Some(val) {
+            return;
+        }
+        self.last[var] = Some(val);
+        let code = Self::code(var);
+        let width = self.vars[var].width;
+        let out = &mut self.body;
+        match val {
+            Value::X if width == 1 => writeln!(out, "x{code}"),
+            Value::X => writeln!(out, "bx {code}"),
+            Value::Bits(v) if width == 1 => writeln!(out, "{v}{code}"),
+            Value::Bits(v) => writeln!(out, "b{v:b} {code}"),
+        }
+        .unwrap();
+    }
+
+    /// Record the values of the signals in the next cycle along with the
+    /// ports that were read outside their availability interval.
+    pub fn sample(&mut self, values: &[Value], violations: &[SigIdx]) {
+        let clk = self.vars.len() - 1;
+        writeln!(self.body, "#{}", self.cycles * PERIOD).unwrap();
+        self.change(clk, Value::Bits(1));
+        for (idx, val) in values.iter().enumerate() {
+            self.change(idx, *val);
+        }
+
+        for sig in violations {
+            if !self.violations.contains_key(sig) {
+                let mut var = Var {
+                    scope: self.vars[*sig].scope.clone(),
+                    name: self.vars[*sig].name.clone(),
+                    width: 1,
+                };
+                // Drop the availability from the name
+                var.name
+                    .truncate(var.name.find('@').unwrap_or(var.name.len()));
+                var.name.push_str("!violation");
+                self.vars.push(var);
+                // The violation flag is low until the first violation
+                self.last.push(Some(Value::Bits(0)));
+                self.violations.insert(*sig, self.vars.len() - 1);
+            }
+        }
+        let flags = self
+            .violations
+            .iter()
+            .map(|(s, v)| (*v, violations.contains(s)))
+            .sorted()
+            .collect_vec();
+        for (var, active) in flags {
+            self.change(var, Value::Bits(active as u64));
+        }
+
+        writeln!(self.body, "#{}", self.cycles * PERIOD + PERIOD / 2).unwrap();
+        self.change(clk, Value::Bits(0));
+        self.cycles += 1;
+    }
+
+    /// Write the trace to the file at `path`
+    pub fn write(self, path: &Path) -> FilamentResult<()> {
+        let mut out = String::new();
+        writeln!(out, "$timescale 1ns $end").unwrap();
+
+        // Define the variables grouped by their scopes
+        let order = (0..self.vars.len())
+            .sorted_by(|a, b| self.vars[*a].scope.cmp(&self.vars[*b].scope))
+            .collect_vec();
+        let mut scope: &[String] = &[];
+        for idx in order {
+            let var = &self.vars[idx];
+            let common = scope
+                .iter()
+                .zip(&var.scope)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for _ in common..scope.len() {
+                writeln!(out, "$upscope $end").unwrap();
+            }
+            for s in &var.scope[common..] {
+                writeln!(out, "$scope module {s} $end").unwrap();
+            }
+            scope = &var.scope;
+            writeln!(
+                out,
+                "$var wire {} {} {} $end",
+                var.width,
+                Self::code(idx),
+                var.name
+            )
+            .unwrap();
+        }
+        for _ in scope {
+            writeln!(out, "$upscope $end").unwrap();
+        }
+        writeln!(out, "$enddefinitions $end").unwrap();
+
+        // Violation flags are defined after the first cycle and start low
+        writeln!(out, "$dumpvars").unwrap();
+        for var in self.violations.values().sorted() {
+            writeln!(out, "0{}", Self::code(*var)).unwrap();
+        }
+        writeln!(out, "$end").unwrap();
+        out.push_str(&self.body);
+        writeln!(out, "#{}", self.cycles * PERIOD).unwrap();
+
+        std::fs::write(path, out).map_err(|e| {
+            Error::invalid_file(format!("{}: {e}", path.display()))
+        })
+    }
+}
@@ -0,0 +1,24 @@
+use std::hash::Hasher;
+
+/// 64-bit FNV-1a hasher. Unlike [std::collections::hash_map::DefaultHasher],
+/// its output is specified and does not change between Rust releases, so
+/// hashes written to caches remain valid.
+pub struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod bind_map;
+mod fnv;
 mod global_sym;
 mod gsym;
 mod idx;
@@ -7,8 +8,10 @@ mod obligation;
 mod position;
 mod post_order;
 mod solver;
+mod solver_conf;
 
 pub use bind_map::Binding;
+pub use fnv::Fnv;
 pub use gsym::GSym;
 pub use idx::Idx;
 pub use namegenerator::NameGenerator;
@@ -16,3 +19,4 @@ pub use obligation::Obligation;
 pub use position::{FileIdx, GPosIdx, GlobalPositionTable, PosData};
 pub use post_order::Traversal;
 pub use solver::{FilSolver, SExp, ShareConstraint};
+pub use solver_conf::SolverConf;
@@ -78,7 +78,7 @@ impl Obligation {
 
 impl From<Obligation> for Error {
     fn from(v: Obligation) -> Self {
-        let mut e = Error::misc(v.reason);
+        let mut e = Error::constraint(v.reason);
         for i in v.info {
             e = e.add_note(i);
         }
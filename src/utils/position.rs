@@ -126,6 +126,12 @@ impl GlobalPositionTable {
     pub fn as_ref() -> &'static PositionTable {
         Self::as_mut()
     }
+
+    /// Remove all the files and positions from the global position table.
+    /// Positions created before the reset must not be used afterwards.
+    pub fn reset() {
+        *Self::as_mut() = PositionTable::new();
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
@@ -78,6 +78,7 @@ fn process_cmd(
         ast::Command::Connect(_)
         | ast::Command::Invoke(_)
         | ast::Command::Bundle(_)
+        | ast::Command::Let(_)
         | ast::Command::Fact(_) => (),
     }
 }
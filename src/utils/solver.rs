@@ -1,9 +1,12 @@
 use super::Obligation;
+use super::SolverConf;
 use crate::ast::{self, Time, TimeSub};
 use crate::diagnostics::{self, Diagnostics, InfoIdx};
 use crate::errors::{Error, FilamentResult};
 use itertools::Itertools;
-use rsmt2::{SmtConf, Solver};
+use rsmt2::Solver;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 /// Represents the sum of a time and a time sub
@@ -84,7 +87,7 @@ impl ShareConstraint {
     /// Transform the share constraint into an error
     fn error(self, diag: &mut diagnostics::Diagnostics) -> Error {
         let msg = format!("Cannot prove constraint {}", self);
-        let mut err = Error::malformed(msg);
+        let mut err = Error::constraint(msg);
         err = err.add_note(diag.add_info(
             "event's delay must be longer than the difference between minimum start time and maximum end time of all invocations",
             self.event_bind.pos())
@@ -157,7 +160,10 @@ impl std::ops::Not for SExp {
     }
 }
 
-fn define_prelude<P>(solver: &mut Solver<P>) -> FilamentResult<()> {
+fn define_prelude<P>(
+    solver: &mut Solver<P>,
+    funcs: &ast::Functions,
+) -> FilamentResult<()> {
     solver.define_fun(
         "max",
         &[("x", "Int"), ("y", "Int")],
@@ -178,34 +184,110 @@ fn define_prelude<P>(solver: &mut Solver<P>) -> FilamentResult<()> {
     )?;
     solver.declare_fun("pow2", &["Int"], "Int")?;
     solver.declare_fun("log2", &["Int"], "Int")?;
+    // Functions defined by the program
+    for def in funcs.all() {
+        let name = funcs.call(*def.name.inner()).smt_name();
+        match def.smt_body() {
+            Some(body) => {
+                let args = def
+                    .params
+                    .iter()
+                    .map(|p| (p.to_string(), "Int"))
+                    .collect_vec();
+                solver.define_fun(&name, &args, "Int", body.to_string())?
+            }
+            None => {
+                let args = vec!["Int"; def.arity()];
+                solver.declare_fun(&name, &args, "Int")?;
+                for axiom in def.smt_axioms(funcs) {
+                    solver.assert(axiom.to_string())?;
+                }
+            }
+        }
+    }
     Ok(())
 }
 
+/// Directory where the queries of each component are written
+struct Dump {
+    dir: PathBuf,
+    /// Configuration used to spawn a solver for each component
+    conf: SolverConf,
+    /// Functions defined in each solver
+    funcs: ast::Functions,
+    /// Component whose queries are written by the next call to `prove`
+    component: Option<ast::Id>,
+}
+
 /// An instance of a SMT solver for Filament constraints
 pub struct FilSolver {
     /// Generate models for failing queries
     show_models: bool,
     /// Underlying solver
     s: Solver<()>,
+    /// Write the queries of each component to a separate file
+    dump: Option<Dump>,
 }
 
 impl FilSolver {
-    pub fn new(show_models: bool) -> FilamentResult<Self> {
-        let mut conf = SmtConf::default_z3();
-        // Queries should time out about 30 seconds
-        conf.option("-t:30000");
-
-        let mut solver = conf.spawn(())?;
-        solver.produce_models()?;
-        solver.path_tee(std::path::PathBuf::from("./model.smt"))?;
-
-        define_prelude(&mut solver)?;
+    /// Spawn a new solver. If `dump` is provided, the queries sent to the
+    /// solver for each component are written to `<component>.smt2` in that
+    /// directory. The functions defined by the program are defined in the
+    /// solver.
+    pub fn new(
+        show_models: bool,
+        conf: &SolverConf,
+        dump: Option<&Path>,
+        funcs: &ast::Functions,
+    ) -> FilamentResult<Self> {
+        let dump = match dump {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                Some(Dump {
+                    dir: dir.to_path_buf(),
+                    conf: conf.clone(),
+                    funcs: funcs.clone(),
+                    component: None,
+                })
+            }
+            None => None,
+        };
         Ok(Self {
             show_models,
-            s: solver,
+            s: Self::spawn(conf, funcs, None)?,
+            dump,
         })
     }
 
+    /// Spawn a solver with the functions of the program defined and write
+    /// the commands sent to it to `tee`.
+    fn spawn(
+        conf: &SolverConf,
+        funcs: &ast::Functions,
+        tee: Option<PathBuf>,
+    ) -> FilamentResult<Solver<()>> {
+        let mut solver = conf.rsmt2().spawn(())?;
+        if let Some(path) = tee {
+            solver.path_tee(path)?;
+        }
+        solver.produce_models()?;
+        if let Some(logic) = conf.logic() {
+            solver.set_custom_logic(logic)?;
+        }
+        define_prelude(&mut solver, funcs)?;
+        Ok(solver)
+    }
+
+    /// Mark the start of the queries for a component. When dumping queries,
+    /// the next obligations are proven using a fresh solver whose commands
+    /// are written to `<component>.smt2`. Components without obligations do
+    /// not get a file.
+    pub fn component(&mut self, name: ast::Id) {
+        if let Some(dump) = &mut self.dump {
+            dump.component = Some(name);
+        }
+    }
+
     pub fn declare_var(&mut self, var: ast::Id) {
         log::trace!("Declaring {}", var);
         self.s.declare_const(var.to_string(), "Int").unwrap();
@@ -237,6 +319,13 @@ impl FilSolver {
         if to_prove.is_empty() {
             return;
         }
+        if let Some(dump) = &mut self.dump {
+            if let Some(name) = dump.component.take() {
+                let path = dump.dir.join(format!("{name}.smt2"));
+                self.s =
+                    Self::spawn(&dump.conf, &dump.funcs, Some(path)).unwrap();
+            }
+        }
 
         let asserts = to_prove.into_iter().collect_vec();
         self.s.push(1).unwrap();
@@ -255,7 +344,7 @@ impl FilSolver {
             if let Some(model) = self.check_fact(&fact, &vars) {
                 let mut err = Error::from(fact);
                 if self.show_models {
-                    let info = diag.add_message(model);
+                    let info = diag.add_model(model);
                     err = err.add_note(info)
                 }
                 diag.add_error(err);
@@ -266,7 +355,7 @@ impl FilSolver {
             if let Some(model) = self.check_fact(&obs, &vars) {
                 let mut err = share.error(diag);
                 if self.show_models {
-                    let info = diag.add_message(model);
+                    let info = diag.add_model(model);
                     err = err.add_note(info)
                 }
                 diag.add_error(err);
@@ -0,0 +1,194 @@
+use crate::{
+    ast,
+    cmdline::{self, DiagnosticsFormat, Solver},
+    diagnostics::Diagnostics,
+    errors::Error,
+};
+use rsmt2::SmtConf;
+
+#[derive(Debug, Clone)]
+/// Configuration used to spawn the SMT solver that discharges constraints.
+pub struct SolverConf {
+    solver: Solver,
+    /// Timeout for each query in milliseconds
+    timeout: u64,
+    /// Additional command-line options passed to the solver
+    options: Vec<String>,
+}
+
+impl From<&cmdline::Opts> for SolverConf {
+    fn from(opts: &cmdline::Opts) -> Self {
+        Self {
+            solver: opts.solver,
+            timeout: opts.solver_timeout,
+            options: opts.solver_opts.clone(),
+        }
+    }
+}
+
+impl SolverConf {
+    /// The executable used to run the solver
+    pub fn program(&self) -> &'static str {
+        match self.solver {
+            Solver::Z3 => "z3",
+            Solver::Cvc5 => "cvc5",
+            Solver::Yices => "yices-smt2",
+        }
+    }
+
+    /// Arguments passed to the solver. They make the solver read SMT-LIB 2
+    /// commands from stdin, set the per-query timeout, and add the
+    /// user-provided options.
+    pub fn args(&self) -> Vec<String> {
+        let mut args: Vec<String> = match self.solver {
+            Solver::Z3 => vec!["-smt2".into(), "-in".into()],
+            Solver::Cvc5 => vec!["--lang=smt2".into(), "--incremental".into()],
+            Solver::Yices => vec!["--incremental".into()],
+        };
+        args.extend(self.query_args());
+        args
+    }
+
+    /// Arguments that set the per-query timeout and the user-provided
+    /// options
+    fn query_args(&self) -> Vec<String> {
+        let mut args = vec![match self.solver {
+            Solver::Z3 => format!("-t:{}", self.timeout),
+            Solver::Cvc5 => format!("--tlimit-per={}", self.timeout),
+            // Yices only supports timeouts in seconds
+            Solver::Yices => {
+                format!("--timeout={}", self.timeout.div_ceil(1000))
+            }
+        }];
+        args.extend(self.options.iter().cloned());
+        args
+    }
+
+    /// The logic that needs to be set before any declarations. Yices cannot
+    /// infer the logic from the commands and rejects declarations without
+    /// one.
+    pub fn logic(&self) -> Option<&'static str> {
+        match self.solver {
+            Solver::Z3 | Solver::Cvc5 => None,
+            Solver::Yices => Some("QF_UFNIA"),
+        }
+    }
+
+    /// Does the solver support the quantified axioms of user-defined
+    /// functions?
+    fn quantifiers(&self) -> bool {
+        match self.solver {
+            Solver::Z3 | Solver::Cvc5 => true,
+            Solver::Yices => false,
+        }
+    }
+
+    /// Report an error for each function whose axioms cannot be discharged by
+    /// the solver. Returns the number of errors.
+    pub fn check_funcs(
+        &self,
+        funcs: &ast::Functions,
+        format: DiagnosticsFormat,
+    ) -> Result<(), u64> {
+        if self.quantifiers() || !funcs.quantified() {
+            return Ok(());
+        }
+        let mut diag = Diagnostics::new(format);
+        for def in funcs.all().iter().filter(|def| def.quantified()) {
+            let msg = format!(
+                "{} cannot discharge the axioms of user-defined functions",
+                self.solver
+            );
+            let info = diag.add_info(
+                "function is defined using axioms which require quantifiers",
+                def.name.pos(),
+            );
+            let help = diag.add_message(
+                "use a solver that supports quantifiers such as z3 or cvc5",
+            );
+            diag.add_error(Error::malformed(msg).add_note(info).add_note(help));
+        }
+        diag.report_all().map_or(Ok(()), Err)
+    }
+
+    /// Configuration for solvers spawned using `rsmt2`. The style of each
+    /// solver adds the arguments needed to read SMT-LIB 2 commands from
+    /// stdin.
+    pub fn rsmt2(&self) -> SmtConf {
+        let mut conf = match self.solver {
+            Solver::Z3 => SmtConf::z3(self.program()),
+            Solver::Cvc5 => SmtConf::cvc4(self.program()),
+            Solver::Yices => SmtConf::yices_2(self.program()),
+        };
+        conf.incremental();
+        for arg in self.query_args() {
+            conf.option(arg);
+        }
+        conf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SolverConf;
+    use crate::cmdline::Solver;
+
+    fn conf(solver: Solver) -> SolverConf {
+        SolverConf {
+            solver,
+            timeout: 1500,
+            options: vec!["--opt".into()],
+        }
+    }
+
+    #[test]
+    fn z3() {
+        let conf = conf(Solver::Z3);
+        assert_eq!(conf.program(), "z3");
+        assert_eq!(conf.args(), ["-smt2", "-in", "-t:1500", "--opt"]);
+        assert_eq!(
+            conf.rsmt2().get_options(),
+            ["-in", "-smt2", "-t:1500", "--opt"]
+        );
+        assert_eq!(conf.logic(), None);
+        assert!(conf.quantifiers());
+    }
+
+    #[test]
+    fn cvc5() {
+        let conf = conf(Solver::Cvc5);
+        assert_eq!(conf.program(), "cvc5");
+        assert_eq!(
+            conf.args(),
+            ["--lang=smt2", "--incremental", "--tlimit-per=1500", "--opt"]
+        );
+        assert_eq!(
+            conf.rsmt2().get_options(),
+            [
+                "-q",
+                "--no-interactive",
+                "--lang",
+                "smt2",
+                "--incremental",
+                "--tlimit-per=1500",
+                "--opt"
+            ]
+        );
+        assert_eq!(conf.logic(), None);
+        assert!(conf.quantifiers());
+    }
+
+    #[test]
+    fn yices() {
+        let conf = conf(Solver::Yices);
+        assert_eq!(conf.program(), "yices-smt2");
+        // Timeouts are rounded up to seconds
+        assert_eq!(conf.args(), ["--incremental", "--timeout=2", "--opt"]);
+        assert_eq!(
+            conf.rsmt2().get_options(),
+            ["--incremental", "--timeout=2", "--opt"]
+        );
+        assert_eq!(conf.logic(), Some("QF_UFNIA"));
+        assert!(!conf.quantifiers());
+    }
+}
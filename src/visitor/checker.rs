@@ -100,6 +100,11 @@ where
         Traverse::Continue(())
     }
 
+    #[inline]
+    fn param_let(&mut self, _: &ast::ParamLet, _ctx: &CompBinding) -> Traverse {
+        Traverse::Continue(())
+    }
+
     fn command(&mut self, cmd: &ast::Command, ctx: &CompBinding) -> Traverse {
         match cmd {
             ast::Command::Fact(a) => self.fact(a, ctx),
@@ -109,6 +114,7 @@ where
             ast::Command::Connect(con) => self.connect(con, ctx),
             ast::Command::ForLoop(l) => self.forloop(l, ctx),
             ast::Command::If(i) => self.if_(i, ctx),
+            ast::Command::Let(l) => self.param_let(l, ctx),
         }
     }
 
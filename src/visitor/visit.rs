@@ -144,6 +144,9 @@ where
                     ast::Command::If(_) => unreachable!(
                         "Visitor does not support transforming if statements"
                     ),
+                    ast::Command::Let(_) => unreachable!(
+                        "Visitor does not support transforming let bindings"
+                    ),
                 };
                 n_cmds.extend(cmds);
             }
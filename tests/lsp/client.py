@@ -0,0 +1,132 @@
+#!/usr/bin/env python3
+"""
+Minimal language server client for testing filament-ls.
+
+Opens the given file in the server and runs the queries in its header. Each
+query is a comment of the form:
+    // lsp: <definition|hover|completion> <line>:<column>
+where lines and columns start at 1. Prints the diagnostics published for the
+file followed by the result of each query.
+"""
+
+import json
+import os
+import re
+import subprocess
+import sys
+
+QUERY = re.compile(r"//\s*lsp:\s*(\w+)\s+(\d+):(\d+)")
+METHODS = {
+    "definition": "textDocument/definition",
+    "hover": "textDocument/hover",
+    "completion": "textDocument/completion",
+}
+
+
+class Client:
+    def __init__(self, cmd):
+        self.proc = subprocess.Popen(
+            cmd, stdin=subprocess.PIPE, stdout=subprocess.PIPE
+        )
+        self.id = 0
+        self.notifications = []
+
+    def send(self, msg):
+        msg["jsonrpc"] = "2.0"
+        body = json.dumps(msg).encode()
+        self.proc.stdin.write(b"Content-Length: %d\r\n\r\n" % len(body))
+        self.proc.stdin.write(body)
+        self.proc.stdin.flush()
+
+    def recv(self):
+        length = None
+        while True:
+            line = self.proc.stdout.readline().decode().strip()
+            if not line:
+                break
+            key, val = line.split(":", 1)
+            if key.lower() == "content-length":
+                length = int(val)
+        return json.loads(self.proc.stdout.read(length))
+
+    def notify(self, method, params):
+        self.send({"method": method, "params": params})
+
+    def request(self, method, params):
+        self.id += 1
+        self.send({"id": self.id, "method": method, "params": params})
+        while True:
+            msg = self.recv()
+            if msg.get("id") == self.id:
+                return msg.get("result")
+            self.notifications.append(msg)
+
+    def wait(self, method):
+        while True:
+            for msg in self.notifications:
+                if msg["method"] == method:
+                    self.notifications.remove(msg)
+                    return msg["params"]
+            self.notifications.append(self.recv())
+
+
+def pos(p):
+    return "{}:{}".format(p["line"] + 1, p["character"] + 1)
+
+
+def span(r):
+    return "{}-{}".format(pos(r["start"]), pos(r["end"]))
+
+
+def uri_path(uri):
+    return os.path.relpath(uri[len("file://") :])
+
+
+def main(server, path):
+    path = os.path.abspath(path)
+    uri = "file://" + path
+    with open(path) as f:
+        text = f.read()
+
+    client = Client(server)
+    client.request(
+        "initialize", {"processId": None, "rootUri": None, "capabilities": {}}
+    )
+    client.notify("initialized", {})
+    client.notify(
+        "textDocument/didOpen",
+        {
+            "textDocument": {
+                "uri": uri,
+                "languageId": "filament",
+                "version": 0,
+                "text": text,
+            }
+        },
+    )
+    for diag in client.wait("textDocument/publishDiagnostics")["diagnostics"]:
+        print("diagnostic {}: {}".format(span(diag["range"]), diag["message"]))
+
+    for method, line, col in QUERY.findall(text):
+        params = {
+            "textDocument": {"uri": uri},
+            "position": {"line": int(line) - 1, "character": int(col) - 1},
+        }
+        res = client.request(METHODS[method], params)
+        print("{} {}:{} ->".format(method, line, col), end=" ")
+        if res is None:
+            print("none")
+        elif method == "definition":
+            print("{}:{}".format(uri_path(res["uri"]), span(res["range"])))
+        elif method == "hover":
+            print(res["contents"]["value"].strip("`\n").replace("filament\n", ""))
+        else:
+            print(" ".join(sorted(item["label"] for item in res)))
+
+    client.request("shutdown", None)
+    client.notify("exit", None)
+    client.proc.wait()
+
+
+if __name__ == "__main__":
+    main(sys.argv[1:-1], sys.argv[-1])